@@ -0,0 +1,130 @@
+use encryption::StorageKey;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    error::HolochainError,
+};
+use uuid::Uuid;
+
+/// Wraps any ContentAddressableStorage and encrypts all content before it gets handed
+/// down to the wrapped storage.
+/// Addresses stay the same, i.e. content is still stored under the address of its plaintext,
+/// so that the encryption is transparent to everything that uses this storage.
+#[derive(Clone, Debug)]
+pub struct EncryptedStorage<S: ContentAddressableStorage + Clone> {
+    storage: S,
+    key: StorageKey,
+}
+
+impl<S: ContentAddressableStorage + Clone> EncryptedStorage<S> {
+    pub fn new(storage: S, key: StorageKey) -> EncryptedStorage<S> {
+        EncryptedStorage { storage, key }
+    }
+}
+
+/// Encrypted content that keeps the address of the plaintext it was created from.
+struct EncryptedContent {
+    address: Address,
+    cipher: Content,
+}
+
+impl AddressableContent for EncryptedContent {
+    fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    fn content(&self) -> Content {
+        self.cipher.clone()
+    }
+
+    fn try_from_content(_: &Content) -> Result<Self, HolochainError> {
+        Err(HolochainError::NotImplemented(
+            "EncryptedContent can only be created from plaintext content".to_string(),
+        ))
+    }
+}
+
+impl<S: ContentAddressableStorage + Clone> ContentAddressableStorage for EncryptedStorage<S> {
+    fn add(&mut self, content: &AddressableContent) -> Result<(), HolochainError> {
+        let encrypted = EncryptedContent {
+            address: content.address(),
+            cipher: self.key.encrypt(&content.content().to_string())?.into(),
+        };
+        self.storage.add(&encrypted)
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        self.storage.contains(address)
+    }
+
+    fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
+        match self.storage.fetch(address)? {
+            Some(cipher) => Ok(Some(self.key.decrypt(&cipher.to_string())?.into())),
+            None => Ok(None),
+        }
+    }
+
+    fn addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        self.storage.addresses()
+    }
+
+    fn get_id(&self) -> Uuid {
+        self.storage.get_id()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use cas::{encrypted::EncryptedStorage, file::FilesystemStorage, memory::MemoryStorage};
+    use encryption::tests::test_storage_key;
+    use holochain_core_types::{
+        cas::{
+            content::{
+                AddressableContent, ExampleAddressableContent, OtherExampleAddressableContent,
+            },
+            storage::{ContentAddressableStorage, StorageTestSuite},
+        },
+        json::RawString,
+    };
+    use std::fs::read_to_string;
+
+    #[test]
+    fn encrypted_memory_round_trip() {
+        let test_suite = StorageTestSuite::new(EncryptedStorage::new(
+            MemoryStorage::new(),
+            test_storage_key(),
+        ));
+        test_suite.round_trip_test::<ExampleAddressableContent, OtherExampleAddressableContent>(
+            RawString::from("foo").into(),
+            RawString::from("bar").into(),
+        );
+    }
+
+    #[test]
+    fn encrypted_file_stores_no_plaintext() {
+        let temp = tempdir().expect("test was supposed to create temp dir");
+        let mut storage = EncryptedStorage::new(
+            FilesystemStorage::new(temp.path()).unwrap(),
+            test_storage_key(),
+        );
+        let content =
+            ExampleAddressableContent::try_from_content(&RawString::from("secret").into()).unwrap();
+        storage.add(&content).unwrap();
+
+        let on_disk = read_to_string(
+            temp.path()
+                .join(content.address().to_string())
+                .with_extension("txt"),
+        )
+        .unwrap();
+        assert!(!on_disk.contains("secret"));
+        assert_eq!(
+            storage.fetch(&content.address()).unwrap(),
+            Some(content.content())
+        );
+    }
+}
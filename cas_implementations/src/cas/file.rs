@@ -6,7 +6,7 @@ use holochain_core_types::{
     error::HolochainError,
 };
 use std::{
-    fs::{create_dir_all, read_to_string, write},
+    fs::{create_dir_all, read_dir, read_to_string, write},
     path::{Path, PathBuf},
     sync::{Arc, RwLock},
 };
@@ -77,6 +77,23 @@ impl ContentAddressableStorage for FilesystemStorage {
         }
     }
 
+    fn addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        let _guard = self.lock.read()?;
+        if !self.dir_path.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut addresses = Vec::new();
+        for dir_entry in read_dir(&self.dir_path)? {
+            let path = dir_entry?.path();
+            if path.extension().map(|ext| ext == "txt").unwrap_or(false) {
+                if let Some(stem) = path.file_stem() {
+                    addresses.push(Address::from(stem.to_string_lossy().to_string()));
+                }
+            }
+        }
+        Ok(addresses)
+    }
+
     fn get_id(&self) -> Uuid {
         self.id
     }
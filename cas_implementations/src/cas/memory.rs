@@ -49,6 +49,11 @@ impl ContentAddressableStorage for MemoryStorage {
         Ok(map.get(address).cloned())
     }
 
+    fn addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        let map = self.storage.read()?;
+        Ok(map.keys().cloned().collect())
+    }
+
     fn get_id(&self) -> Uuid {
         self.id
     }
@@ -0,0 +1,204 @@
+use encryption::StorageKey;
+use holochain_core_types::{
+    eav::{
+        increment_key_till_no_collision, Attribute, EavFilter, EaviQuery,
+        EntityAttributeValueIndex, EntityAttributeValueStorage, IndexFilter,
+    },
+    error::{HcResult, HolochainError},
+};
+use std::collections::BTreeSet;
+
+/// Wraps any EntityAttributeValueStorage and encrypts the free form parts of attributes
+/// (i.e. link tags) before they get handed down to the wrapped storage.
+/// Entities and values are addresses of content that gets stored in an
+/// [EncryptedStorage](../../cas/encrypted/struct.EncryptedStorage.html) and are stored as is.
+///
+/// Since encrypted tags can't be compared in the wrapped storage, attribute and index
+/// filters get applied here after decryption.
+#[derive(Clone, Debug)]
+pub struct EncryptedEavStorage<S: EntityAttributeValueStorage + Clone> {
+    storage: S,
+    key: StorageKey,
+}
+
+impl<S: EntityAttributeValueStorage + Clone> EncryptedEavStorage<S> {
+    pub fn new(storage: S, key: StorageKey) -> EncryptedEavStorage<S> {
+        EncryptedEavStorage { storage, key }
+    }
+
+    fn encrypt_attribute(&self, attribute: Attribute) -> HcResult<Attribute> {
+        Ok(match attribute {
+            Attribute::LinkTag(tag) => Attribute::LinkTag(self.key.encrypt(&tag)?),
+            Attribute::RemovedLink(tag) => Attribute::RemovedLink(self.key.encrypt(&tag)?),
+            attribute => attribute,
+        })
+    }
+
+    fn decrypt_attribute(&self, attribute: Attribute) -> HcResult<Attribute> {
+        Ok(match attribute {
+            Attribute::LinkTag(tag) => Attribute::LinkTag(self.key.decrypt(&tag)?),
+            Attribute::RemovedLink(tag) => Attribute::RemovedLink(self.key.decrypt(&tag)?),
+            attribute => attribute,
+        })
+    }
+
+    fn with_attribute(
+        eavi: &EntityAttributeValueIndex,
+        attribute: Attribute,
+    ) -> HcResult<EntityAttributeValueIndex> {
+        EntityAttributeValueIndex::new_with_index(
+            &eavi.entity(),
+            &attribute,
+            &eavi.value(),
+            eavi.index(),
+        )
+    }
+}
+
+impl<S: EntityAttributeValueStorage + Clone> EntityAttributeValueStorage
+    for EncryptedEavStorage<S>
+{
+    fn add_eavi(
+        &mut self,
+        eav: &EntityAttributeValueIndex,
+    ) -> Result<Option<EntityAttributeValueIndex>, HolochainError> {
+        // Collisions have to be detected on plaintext since every encryption of the
+        // same tag looks different:
+        let fetched = self.fetch_eavi(&EaviQuery::new(
+            Some(eav.entity()).into(),
+            Some(eav.attribute()).into(),
+            Some(eav.value()).into(),
+            IndexFilter::LatestByAttribute,
+        ))?;
+        let new_eav = increment_key_till_no_collision(eav.clone(), fetched)?;
+        let encrypted =
+            Self::with_attribute(&new_eav, self.encrypt_attribute(new_eav.attribute())?)?;
+        match self.storage.add_eavi(&encrypted)? {
+            Some(stored) => Ok(Some(Self::with_attribute(
+                &stored,
+                self.decrypt_attribute(stored.attribute())?,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn fetch_eavi(
+        &self,
+        query: &EaviQuery,
+    ) -> Result<BTreeSet<EntityAttributeValueIndex>, HolochainError> {
+        let inner_query = EaviQuery::new(
+            EavFilter::predicate(|entity| query.entity().check(entity)),
+            Default::default(),
+            EavFilter::predicate(|value| query.value().check(value)),
+            IndexFilter::Range(None, None),
+        );
+        let eavis = self
+            .storage
+            .fetch_eavi(&inner_query)?
+            .iter()
+            .map(|eavi| Self::with_attribute(eavi, self.decrypt_attribute(eavi.attribute())?))
+            .collect::<HcResult<BTreeSet<_>>>()?;
+        Ok(query.run(eavis.iter().cloned()))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use eav::{encrypted::EncryptedEavStorage, file::EavFileStorage, memory::EavMemoryStorage};
+    use encryption::tests::test_storage_key;
+    use holochain_core_types::{
+        cas::{
+            content::{AddressableContent, ExampleAddressableContent},
+            storage::EavTestSuite,
+        },
+        eav::{
+            Attribute, EaviQuery, EntityAttributeValueIndex, EntityAttributeValueStorage,
+            IndexFilter,
+        },
+        json::RawString,
+    };
+    use std::fs::read_dir;
+
+    fn test_encrypted_eav_storage() -> EncryptedEavStorage<EavMemoryStorage> {
+        EncryptedEavStorage::new(EavMemoryStorage::new(), test_storage_key())
+    }
+
+    #[test]
+    fn encrypted_eav_round_trip() {
+        let entity_content =
+            ExampleAddressableContent::try_from_content(&RawString::from("foo").into()).unwrap();
+        let attribute = "favourite-color".to_string();
+        let value_content =
+            ExampleAddressableContent::try_from_content(&RawString::from("blue").into()).unwrap();
+
+        EavTestSuite::test_round_trip(
+            test_encrypted_eav_storage(),
+            entity_content,
+            attribute,
+            value_content,
+        )
+    }
+
+    #[test]
+    fn encrypted_eav_one_to_many() {
+        EavTestSuite::test_one_to_many::<ExampleAddressableContent, _>(test_encrypted_eav_storage())
+    }
+
+    #[test]
+    fn encrypted_eav_many_to_one() {
+        EavTestSuite::test_many_to_one::<ExampleAddressableContent, _>(test_encrypted_eav_storage())
+    }
+
+    #[test]
+    fn encrypted_eav_range() {
+        EavTestSuite::test_range::<ExampleAddressableContent, _>(test_encrypted_eav_storage());
+    }
+
+    #[test]
+    fn encrypted_eav_prefixes() {
+        EavTestSuite::test_multiple_attributes::<ExampleAddressableContent, _>(
+            test_encrypted_eav_storage(),
+            vec!["a_", "b_", "c_", "d_"]
+                .into_iter()
+                .map(|p| Attribute::LinkTag(p.to_string() + "one_to_many"))
+                .collect(),
+        );
+    }
+
+    #[test]
+    fn encrypted_file_eav_stores_no_plaintext_tags() {
+        let temp = tempdir().expect("test was supposed to create temp dir");
+        let mut eav_storage = EncryptedEavStorage::new(
+            EavFileStorage::new(temp.path()).unwrap(),
+            test_storage_key(),
+        );
+        let entity =
+            ExampleAddressableContent::try_from_content(&RawString::from("foo").into()).unwrap();
+        let eavi = EntityAttributeValueIndex::new(
+            &entity.address(),
+            &Attribute::LinkTag("secret-tag".to_string()),
+            &entity.address(),
+        )
+        .unwrap();
+        eav_storage.add_eavi(&eavi).unwrap();
+
+        let attribute_dirs: Vec<String> = read_dir(temp.path().join("a"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(attribute_dirs.len(), 1);
+        assert!(!attribute_dirs[0].contains("secret-tag"));
+
+        let fetched = eav_storage
+            .fetch_eavi(&EaviQuery::new(
+                Some(entity.address()).into(),
+                Some(Attribute::LinkTag("secret-tag".to_string())).into(),
+                Default::default(),
+                IndexFilter::LatestByAttribute,
+            ))
+            .unwrap();
+        assert_eq!(fetched.into_iter().collect::<Vec<_>>(), vec![eavi]);
+    }
+}
@@ -0,0 +1,219 @@
+//! Symmetric encryption for data at rest.
+//!
+//! A [StorageKey](struct.StorageKey.html) encrypts the values that the
+//! [encrypted CAS](../cas/encrypted/struct.EncryptedStorage.html) and the
+//! [encrypted EAV](../eav/encrypted/struct.EncryptedEavStorage.html) wrappers hand down to
+//! the storage they wrap.
+//! Ciphertexts are URL safe base64 strings of `nonce || cipher` so that they can also be
+//! used as (parts of) file names.
+use base64;
+use holochain_core_types::error::{HcResult, HolochainError};
+use holochain_sodium::{aead, hash, pwhash, secbuf::SecBuf};
+use serde_json;
+use std::{
+    fmt, fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Name of the file that holds salt and key check in the directory of an encrypted storage
+pub const ENCRYPTION_INFO_FILE: &str = "encryption.json";
+
+const KEYBYTES: usize = 32;
+const KEY_CHECK_PLAINTEXT: &str = "holochain encrypted storage";
+
+/// What gets persisted next to an encrypted storage so the key can be derived again
+/// and checked before any data gets decrypted with it.
+#[derive(Serialize, Deserialize)]
+struct EncryptionInfo {
+    salt: String,
+    key_check: String,
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode(encoded: &str) -> HcResult<Vec<u8>> {
+    Ok(base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?)
+}
+
+fn buf_from_bytes(bytes: &[u8]) -> HcResult<SecBuf> {
+    let mut buf = SecBuf::with_insecure(bytes.len());
+    buf.write(0, bytes)?;
+    Ok(buf)
+}
+
+/// Symmetric key used to encrypt and decrypt stored content.
+/// Clones share the same secret which is kept in secure memory.
+#[derive(Clone)]
+pub struct StorageKey {
+    secret: Arc<Mutex<SecBuf>>,
+}
+
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StorageKey(<secret>)")
+    }
+}
+
+impl StorageKey {
+    fn new(secret: SecBuf) -> StorageKey {
+        StorageKey {
+            secret: Arc::new(Mutex::new(secret)),
+        }
+    }
+
+    /// Derives a key from a passphrase and salt with Argon2id.
+    pub fn from_passphrase(passphrase: &mut SecBuf, salt: &mut SecBuf) -> HcResult<StorageKey> {
+        let mut secret = SecBuf::with_secure(KEYBYTES);
+        pwhash::hash(
+            passphrase,
+            pwhash::OPSLIMIT_MODERATE,
+            pwhash::MEMLIMIT_MODERATE,
+            pwhash::ALG_ARGON2ID13,
+            salt,
+            &mut secret,
+        )?;
+        Ok(StorageKey::new(secret))
+    }
+
+    /// Derives a key from high entropy secret material, like a signature that only the
+    /// owner of an agent key can create, by hashing it.
+    pub fn from_secret(secret_material: &mut SecBuf) -> HcResult<StorageKey> {
+        let mut secret = SecBuf::with_secure(KEYBYTES);
+        hash::sha256(secret_material, &mut secret)?;
+        Ok(StorageKey::new(secret))
+    }
+
+    /// Derives the key for the encrypted storage in `dir`.
+    ///
+    /// On first use a random salt gets created and stored in `dir` together with a key check.
+    /// On every later use `derive` gets called with that same salt and the resulting key gets
+    /// compared against the key check, so that a wrong passphrase is reported as such
+    /// instead of producing garbage when reading.
+    pub fn open<P, F>(dir: P, derive: F) -> HcResult<StorageKey>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut SecBuf) -> HcResult<StorageKey>,
+    {
+        let info_path = dir.as_ref().join(ENCRYPTION_INFO_FILE);
+        if info_path.is_file() {
+            let info: EncryptionInfo = serde_json::from_str(&fs::read_to_string(&info_path)?)?;
+            let key = derive(&mut buf_from_bytes(&decode(&info.salt)?)?)?;
+            match key.decrypt(&info.key_check) {
+                Ok(ref plaintext) if plaintext == KEY_CHECK_PLAINTEXT => Ok(key),
+                _ => Err(HolochainError::ErrorGeneric(format!(
+                    "Wrong key for encrypted storage in {}",
+                    dir.as_ref().display()
+                ))),
+            }
+        } else {
+            let mut salt = SecBuf::with_insecure(pwhash::SALTBYTES);
+            salt.randomize();
+            let key = derive(&mut salt)?;
+            let info = EncryptionInfo {
+                salt: encode(&salt.read_lock()),
+                key_check: key.encrypt(KEY_CHECK_PLAINTEXT)?,
+            };
+            fs::create_dir_all(dir.as_ref())?;
+            fs::write(info_path, serde_json::to_string(&info)?)?;
+            Ok(key)
+        }
+    }
+
+    /// Encrypts the given string with a fresh random nonce.
+    pub fn encrypt(&self, plaintext: &str) -> HcResult<String> {
+        let mut message = SecBuf::with_insecure_from_string(plaintext.to_string());
+        let mut nonce = SecBuf::with_insecure(aead::NONCEBYTES);
+        nonce.randomize();
+        let mut cipher = SecBuf::with_insecure(message.len() + aead::ABYTES);
+        {
+            let mut secret = self.secret.lock()?;
+            aead::enc(&mut message, &mut secret, None, &mut nonce, &mut cipher)?;
+        }
+        let mut bytes = nonce.read_lock().to_vec();
+        bytes.extend_from_slice(&cipher.read_lock());
+        Ok(encode(&bytes))
+    }
+
+    /// Decrypts a string created by [encrypt](#method.encrypt).
+    ///
+    /// Note that a wrong key does not necessarily result in an error here but can yield
+    /// garbage. [open](#method.open) makes sure the right key is used.
+    pub fn decrypt(&self, ciphertext: &str) -> HcResult<String> {
+        let bytes = decode(ciphertext)?;
+        if bytes.len() < aead::NONCEBYTES + aead::ABYTES {
+            return Err(HolochainError::ErrorGeneric(
+                "Encrypted data is too short".to_string(),
+            ));
+        }
+        let (nonce_bytes, cipher_bytes) = bytes.split_at(aead::NONCEBYTES);
+        let mut nonce = buf_from_bytes(nonce_bytes)?;
+        let mut cipher = buf_from_bytes(cipher_bytes)?;
+        let mut message = SecBuf::with_insecure(cipher_bytes.len() - aead::ABYTES);
+        {
+            let mut secret = self.secret.lock()?;
+            aead::dec(&mut message, &mut secret, None, &mut nonce, &mut cipher)?;
+        }
+        let plaintext = message.read_lock().to_vec();
+        String::from_utf8(plaintext)
+            .map_err(|_| HolochainError::ErrorGeneric("Could not decrypt stored data".to_string()))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+
+    pub fn test_storage_key() -> StorageKey {
+        StorageKey::from_secret(&mut SecBuf::with_insecure_from_string(
+            "some secret".to_string(),
+        ))
+        .expect("could not create test key")
+    }
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let key = test_storage_key();
+        for plaintext in &["", "hello", "{\"some\":\"json\"}"] {
+            let ciphertext = key.encrypt(plaintext).unwrap();
+            assert_eq!(key.decrypt(&ciphertext).unwrap(), plaintext.to_string());
+        }
+        // fresh nonce every time:
+        assert_ne!(key.encrypt("hello").unwrap(), key.encrypt("hello").unwrap());
+    }
+
+    #[test]
+    fn decrypt_rejects_garbage() {
+        let key = test_storage_key();
+        assert!(key.decrypt("not/base64").is_err());
+        assert!(key.decrypt("c2hvcnQ").is_err());
+    }
+
+    #[test]
+    fn open_checks_passphrase() {
+        let dir = tempdir().unwrap();
+        let passphrase = |p: &str| SecBuf::with_insecure_from_string(p.to_string());
+
+        let key = StorageKey::open(dir.path(), |salt| {
+            StorageKey::from_passphrase(&mut passphrase("right"), salt)
+        })
+        .expect("first open should create encryption info");
+        assert!(dir.path().join(ENCRYPTION_INFO_FILE).is_file());
+        let ciphertext = key.encrypt("hello").unwrap();
+
+        let reopened = StorageKey::open(dir.path(), |salt| {
+            StorageKey::from_passphrase(&mut passphrase("right"), salt)
+        })
+        .expect("same passphrase should open the storage again");
+        assert_eq!(reopened.decrypt(&ciphertext).unwrap(), "hello".to_string());
+
+        assert!(StorageKey::open(dir.path(), |salt| {
+            StorageKey::from_passphrase(&mut passphrase("wrong"), salt)
+        })
+        .is_err());
+    }
+}
@@ -7,10 +7,17 @@
 #![feature(try_from)]
 #![warn(unused_extern_crates)]
 extern crate holochain_core_types;
+extern crate holochain_sodium;
 
+extern crate base64;
 extern crate glob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 extern crate uuid;
 
 pub mod cas;
 pub mod eav;
+pub mod encryption;
 pub mod path;
@@ -0,0 +1,73 @@
+use colored::*;
+use error::DefaultResult;
+use holochain_cas_implementations::cas::file::FilesystemStorage;
+use holochain_core::agent::{
+    chain_integrity::verify_chain,
+    chain_store::ChainStore,
+    state::{AgentStateSnapshot, AGENT_SNAPSHOT_ADDRESS},
+};
+use holochain_core_types::cas::{
+    content::{Address, AddressableContent},
+    storage::ContentAddressableStorage,
+};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+/// Verifies the integrity of the source chain persisted in the given instance storage
+/// directory (the `path` of a conductor's file storage configuration) and reports
+/// the first broken link.
+pub fn chain_verify(path: &PathBuf) -> DefaultResult<()> {
+    let cas_path = path.join("cas");
+    if !cas_path.is_dir() {
+        bail!("{} is not an instance storage directory", path.display());
+    }
+    let storage = FilesystemStorage::new(&cas_path).map_err(|e| format_err!("{}", e))?;
+
+    let top_chain_header = storage
+        .fetch(&Address::from(AGENT_SNAPSHOT_ADDRESS))
+        .map_err(|e| format_err!("{}", e))?
+        .ok_or_else(|| format_err!("No source chain found in {}", path.display()))
+        .and_then(|content| {
+            AgentStateSnapshot::try_from_content(&content).map_err(|e| format_err!("{}", e))
+        })?
+        .top_chain_header()
+        .cloned();
+
+    let chain_store = ChainStore::new(Arc::new(RwLock::new(storage)));
+    let report = verify_chain(&chain_store, &top_chain_header);
+
+    match report.broken_link {
+        None => {
+            println!(
+                "{} Verified {} headers.",
+                "Source chain is intact.".green().bold(),
+                report.verified_headers
+            );
+            Ok(())
+        }
+        Some(broken_link) => {
+            println!(
+                "{} {} headers verified before the first broken link:",
+                "Source chain is broken!".red().bold(),
+                report.verified_headers
+            );
+            println!("{}", broken_link);
+            bail!("Source chain verification failed")
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+
+    #[test]
+    fn chain_verify_fails_without_storage() {
+        let dir = tempdir().unwrap();
+        assert!(chain_verify(&dir.path().to_path_buf()).is_err());
+    }
+}
@@ -105,6 +105,7 @@ fn agent_configuration() -> AgentConfiguration {
         public_address: agent_id.pub_sign_key,
         key_file: agent_name,
         holo_remote_key: None,
+        signer: None,
     }
 }
 
@@ -119,6 +120,8 @@ fn dna_configuration(dna_path: &PathBuf) -> DnaConfiguration {
             .expect("Expected DNA path to be valid unicode")
             .to_string(),
         hash: None,
+        uuid: None,
+        properties: None,
     }
 }
 
@@ -131,6 +134,7 @@ fn storage_configuration(persist: bool) -> DefaultResult<StorageConfiguration> {
 
         Ok(StorageConfiguration::File {
             path: LOCAL_STORAGE_PATH.into(),
+            encryption: None,
         })
     } else {
         Ok(StorageConfiguration::Memory)
@@ -146,6 +150,8 @@ fn instance_configuration(storage: StorageConfiguration) -> InstanceConfiguratio
         dna: DNA_CONFIG_ID.into(),
         agent: AGENT_CONFIG_ID.into(),
         storage,
+        log_rules: None,
+        restart_policy: None,
     }
 }
 
@@ -157,9 +163,17 @@ fn interface_configuration(
     port: u16,
 ) -> DefaultResult<InterfaceConfiguration> {
     let driver = if interface_type == &String::from("websocket") {
-        InterfaceDriver::Websocket { port }
+        InterfaceDriver::Websocket {
+            port,
+            bind_address: None,
+            tls: None,
+        }
     } else if interface_type == &String::from("http") {
-        InterfaceDriver::Http { port }
+        InterfaceDriver::Http {
+            port,
+            bind_address: None,
+            tls: None,
+        }
     } else {
         return Err(format_err!("unknown interface type: {}", interface_type));
     };
@@ -171,6 +185,7 @@ fn interface_configuration(
         instances: vec![InstanceReferenceConfiguration {
             id: INSTANCE_CONFIG_ID.into(),
         }],
+        tokens: Vec::new(),
     })
 }
 
@@ -180,6 +195,8 @@ fn logger_configuration() -> LoggerConfiguration {
     LoggerConfiguration {
         logger_type: "debug".to_string(),
         rules: LogRules::new(),
+        format: Default::default(),
+        file: None,
     }
 }
 
@@ -276,6 +293,7 @@ mod tests {
                     .to_string(),
                 key_file: "testAgent".to_string(),
                 holo_remote_key: None,
+                signer: None,
             },
         );
     }
@@ -290,6 +308,8 @@ mod tests {
                 id: "hc-run-dna".to_string(),
                 file: "/test/path".to_string(),
                 hash: None,
+                uuid: None,
+                properties: None,
             }
         )
     }
@@ -303,7 +323,8 @@ mod tests {
         assert_eq!(
             persist_store,
             StorageConfiguration::File {
-                path: ".hc".to_string()
+                path: ".hc".to_string(),
+                encryption: None,
             }
         );
     }
@@ -319,6 +340,8 @@ mod tests {
                 dna: "hc-run-dna".to_string(),
                 agent: "hc-run-agent".to_string(),
                 storage: StorageConfiguration::Memory,
+                log_rules: None,
+                restart_policy: None,
             }
         )
     }
@@ -330,11 +353,16 @@ mod tests {
             http_interface,
             InterfaceConfiguration {
                 id: "websocket-interface".to_string(),
-                driver: InterfaceDriver::Http { port: 4444 },
+                driver: InterfaceDriver::Http {
+                    port: 4444,
+                    bind_address: None,
+                    tls: None,
+                },
                 admin: true,
                 instances: vec![InstanceReferenceConfiguration {
                     id: "test-instance".to_string(),
                 }],
+                tokens: Vec::new(),
             }
         );
 
@@ -344,11 +372,16 @@ mod tests {
             websocket_interface,
             InterfaceConfiguration {
                 id: "websocket-interface".to_string(),
-                driver: InterfaceDriver::Websocket { port: 5555 },
+                driver: InterfaceDriver::Websocket {
+                    port: 5555,
+                    bind_address: None,
+                    tls: None,
+                },
                 admin: true,
                 instances: vec![InstanceReferenceConfiguration {
                     id: "test-instance".to_string(),
                 }],
+                tokens: Vec::new(),
             }
         );
 
@@ -1,4 +1,5 @@
 #![warn(unused_extern_crates)]
+extern crate holochain_cas_implementations;
 extern crate holochain_common;
 extern crate holochain_conductor_api;
 extern crate holochain_core;
@@ -142,6 +143,29 @@ enum Cli {
         #[structopt(long, short, help = "Don't ask for passphrase")]
         silent: bool,
     },
+    #[structopt(
+        name = "chain",
+        about = "Inspects the source chain persisted in an instance's storage directory"
+    )]
+    Chain {
+        #[structopt(subcommand)]
+        command: ChainCommand,
+    },
+}
+
+#[derive(StructOpt)]
+enum ChainCommand {
+    #[structopt(
+        name = "verify",
+        about = "Checks header links, entries, signatures and timestamps of a persisted source chain and reports the first broken link"
+    )]
+    Verify {
+        #[structopt(
+            help = "The storage directory of the instance (as configured in the conductor's file storage)",
+            parse(from_os_str)
+        )]
+        path: PathBuf,
+    },
 }
 
 fn main() {
@@ -207,6 +231,11 @@ fn run() -> HolochainResult<()> {
             cli::keygen(None, passphrase)
                 .map_err(|e| HolochainError::Default(format_err!("{}", e)))?
         }
+        Cli::Chain { command } => match command {
+            ChainCommand::Verify { path } => {
+                cli::chain_verify(&path).map_err(HolochainError::Default)?
+            }
+        },
     }
 
     Ok(())
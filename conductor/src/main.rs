@@ -15,17 +15,21 @@
 /// If called without arguments, this executable tries to load a configuration from
 /// ~/.holochain/conductor/conductor_config.toml.
 /// A custom config can be provided with the --config, -c flag.
+///
+/// On SIGTERM or SIGINT the conductor shuts down gracefully, i.e. it lets running zome calls
+/// finish and saves the state of all instances before exiting. A second signal exits immediately.
+extern crate ctrlc;
 extern crate holochain_conductor_api;
 extern crate holochain_core_types;
 extern crate holochain_sodium;
 extern crate structopt;
 
 use holochain_conductor_api::{
-    conductor::{mount_conductor_from_config, CONDUCTOR},
+    conductor::{mount_conductor_from_config, CONDUCTOR, DEFAULT_SHUTDOWN_TIMEOUT},
     config::{self, load_configuration, Configuration},
 };
 use holochain_core_types::error::HolochainError;
-use std::{fs::File, io::prelude::*, path::PathBuf};
+use std::{fs::File, io::prelude::*, path::PathBuf, process, sync::mpsc::channel};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -65,13 +69,44 @@ fn main() {
                 conductor
                     .start_all_static_servers()
                     .expect("Could not start UI servers!");
+                conductor
+                    .start_metrics_server()
+                    .expect("Could not start metrics endpoint!");
+                conductor.start_supervisor();
+                println!("Watching {} for changes", config_path_str);
+                conductor.watch_config_file(config_path.clone());
+            }
+            wait_for_termination_signal();
+            println!("Shutting down...");
+            let mut conductor_guard = CONDUCTOR.lock().unwrap();
+            let conductor = conductor_guard.as_mut().expect("Conductor must be mounted");
+            match conductor.shutdown_gracefully(DEFAULT_SHUTDOWN_TIMEOUT) {
+                Ok(()) => println!("Done."),
+                Err(error) => {
+                    println!("Error while shutting down:\n{}", error);
+                    process::exit(1);
+                }
             }
-            loop {}
         }
         Err(error) => println!("Error while trying to boot from config: {:?}", error),
     };
 }
 
+/// Blocks until the process receives SIGTERM or SIGINT (Ctrl-C).
+/// Any further signal kills the process without waiting for the shutdown.
+#[cfg_attr(tarpaulin, skip)]
+fn wait_for_termination_signal() {
+    let (tx, rx) = channel();
+    ctrlc::set_handler(move || {
+        if tx.send(()).is_err() {
+            println!("Received second termination signal, exiting immediately");
+            process::exit(130);
+        }
+    })
+    .expect("Could not set signal handler");
+    let _ = rx.recv();
+}
+
 #[cfg_attr(tarpaulin, skip)]
 fn bootstrap_from_config(path: &str) -> Result<(), HolochainError> {
     let config = load_config_file(&String::from(path))?;
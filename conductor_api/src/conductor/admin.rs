@@ -2,15 +2,39 @@ use crate::{
     conductor::{base::notify, Conductor},
     config::{
         AgentConfiguration, Bridge, DnaConfiguration, InstanceConfiguration,
-        InstanceReferenceConfiguration, InterfaceConfiguration, StorageConfiguration,
+        InstanceReferenceConfiguration, InterfaceConfiguration, InterfaceTokenConfiguration,
+        StorageConfiguration,
     },
     error::HolochainInstanceError,
+    interface_auth::{generate_token, hash_token},
+    keystore::{read_key_blob, unlock_key_blob},
+};
+use holochain_core::{
+    agent::{chain_archive::ChainArchive, chain_integrity::ChainIntegrityReport},
+    introspection::{ChainItem, EavItem, HeldEntry, NetworkRequests, PendingValidationItem},
+    storage_stats::StorageStats,
+    tracing::Span,
 };
 use holochain_core_types::{
-    cas::content::AddressableContent, error::HolochainError, hash::HashString,
+    agent::Base32,
+    cas::content::{Address, AddressableContent},
+    error::HolochainError,
+    hash::HashString,
+};
+use holochain_dpki::key_blob::KeyBlob;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
 };
-use json_patch;
-use std::{fs, path::PathBuf, sync::Arc};
+
+/// A span together with the instance that recorded it
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InstanceSpan {
+    pub instance_id: String,
+    #[serde(flatten)]
+    pub span: Span,
+}
 
 pub trait ConductorAdmin {
     fn install_dna_from_file(
@@ -20,6 +44,7 @@ pub trait ConductorAdmin {
         copy: bool,
         expected_hash: Option<HashString>,
         properties: Option<&serde_json::Value>,
+        uuid: Option<String>,
     ) -> Result<(), HolochainError>;
     fn uninstall_dna(&mut self, id: &String) -> Result<(), HolochainError>;
     fn add_instance(
@@ -31,6 +56,54 @@ pub trait ConductorAdmin {
     fn remove_instance(&mut self, id: &String) -> Result<(), HolochainError>;
     fn start_instance(&mut self, id: &String) -> Result<(), HolochainInstanceError>;
     fn stop_instance(&mut self, id: &String) -> Result<(), HolochainInstanceError>;
+    fn verify_chain(&mut self, id: &String)
+        -> Result<ChainIntegrityReport, HolochainInstanceError>;
+    fn instance_stats(&mut self, id: &String) -> Result<StorageStats, HolochainInstanceError>;
+    fn debug_chain(
+        &mut self,
+        id: &String,
+        start: usize,
+        limit: usize,
+    ) -> Result<Vec<ChainItem>, HolochainInstanceError>;
+    fn debug_held_entries(
+        &mut self,
+        id: &String,
+        start: usize,
+        limit: usize,
+    ) -> Result<Vec<HeldEntry>, HolochainInstanceError>;
+    fn debug_eav(
+        &mut self,
+        id: &String,
+        address: &Address,
+    ) -> Result<Vec<EavItem>, HolochainInstanceError>;
+    fn debug_pending_validations(
+        &mut self,
+        id: &String,
+    ) -> Result<Vec<PendingValidationItem>, HolochainInstanceError>;
+    fn debug_network_requests(
+        &mut self,
+        id: &String,
+    ) -> Result<NetworkRequests, HolochainInstanceError>;
+    fn debug_traces(
+        &mut self,
+        id: &String,
+        limit: usize,
+    ) -> Result<Vec<Span>, HolochainInstanceError>;
+    fn debug_spans(&mut self, id: &String) -> Result<Vec<Span>, HolochainInstanceError>;
+    fn debug_trace(
+        &mut self,
+        trace_id: &String,
+    ) -> Result<Vec<InstanceSpan>, HolochainInstanceError>;
+    fn export_instance(
+        &mut self,
+        id: &String,
+        include_dht_shard: bool,
+    ) -> Result<ChainArchive, HolochainInstanceError>;
+    fn import_instance(
+        &mut self,
+        id: &String,
+        archive: &ChainArchive,
+    ) -> Result<(), HolochainInstanceError>;
     fn add_interface(&mut self, new_instance: InterfaceConfiguration)
         -> Result<(), HolochainError>;
     fn remove_interface(&mut self, id: &String) -> Result<(), HolochainError>;
@@ -44,8 +117,35 @@ pub trait ConductorAdmin {
         interface_id: &String,
         instance_id: &String,
     ) -> Result<(), HolochainError>;
+    fn issue_interface_token(
+        &mut self,
+        interface_id: &String,
+        methods: Option<Vec<String>>,
+        instances: Option<Vec<String>>,
+    ) -> Result<String, HolochainError>;
+    fn revoke_interface_token(
+        &mut self,
+        interface_id: &String,
+        token_hash: &String,
+    ) -> Result<(), HolochainError>;
     fn add_agent(&mut self, new_agent: AgentConfiguration) -> Result<(), HolochainError>;
     fn remove_agent(&mut self, id: &String) -> Result<(), HolochainError>;
+    fn generate_agent_key(
+        &mut self,
+        passphrase: &String,
+    ) -> Result<(Base32, PathBuf), HolochainError>;
+    fn import_agent_key(
+        &mut self,
+        blob: &KeyBlob,
+        passphrase: &String,
+    ) -> Result<(Base32, PathBuf), HolochainError>;
+    fn export_agent_key(&mut self, public_address: &Base32) -> Result<KeyBlob, HolochainError>;
+    fn unlock_agent_key(
+        &mut self,
+        agent_id: &String,
+        passphrase: &String,
+    ) -> Result<(), HolochainError>;
+    fn lock_agent_key(&mut self, agent_id: &String) -> Result<(), HolochainError>;
     fn add_bridge(&mut self, new_bridge: Bridge) -> Result<(), HolochainError>;
     fn remove_bridge(
         &mut self,
@@ -63,6 +163,11 @@ impl ConductorAdmin for Conductor {
     /// and will be injected in the dna package prior to installation. Existing properties will also be kept and
     /// overriden by the passed properties in the case of collisions. This will change the dna hash!
     /// (Note injecting properties requires that copy=true)
+    ///
+    /// Likewise, an optional `uuid` replaces the uuid of the DNA package, which makes it possible
+    /// to run a separate network of the same DNA. This also requires copy=true.
+    ///
+    /// Both overrides get recorded in the DNA's config.
     fn install_dna_from_file(
         &mut self,
         path: PathBuf,
@@ -70,6 +175,7 @@ impl ConductorAdmin for Conductor {
         copy: bool,
         expected_hash: Option<HashString>,
         properties: Option<&serde_json::Value>,
+        uuid: Option<String>,
     ) -> Result<(), HolochainError> {
         let path_string = path
             .to_str()
@@ -89,15 +195,26 @@ impl ConductorAdmin for Conductor {
             }
         }
 
-        if let Some(props) = properties {
-            if !copy {
-                return Err(HolochainError::ConfigError(
-                    "Cannot install DNA with properties unless copy flag is true".into(),
-                ));
-            }
-            json_patch::merge(&mut dna.properties, &props);
+        if properties.is_some() && !copy {
+            return Err(HolochainError::ConfigError(
+                "Cannot install DNA with properties unless copy flag is true".into(),
+            ));
+        }
+        if uuid.is_some() && !copy {
+            return Err(HolochainError::ConfigError(
+                "Cannot install DNA with uuid unless copy flag is true".into(),
+            ));
         }
 
+        let mut new_dna = DnaConfiguration {
+            id: id.clone(),
+            file: String::new(),
+            hash: None,
+            uuid,
+            properties: properties.cloned(),
+        };
+        new_dna.apply_overrides(&mut dna);
+
         let config_path = match copy {
             true => self.save_dna(&dna)?,
             false => PathBuf::from(path_string),
@@ -106,11 +223,8 @@ impl ConductorAdmin for Conductor {
             .to_str()
             .ok_or(HolochainError::ConfigError("invalid path".into()))?;
 
-        let new_dna = DnaConfiguration {
-            id: id.clone(),
-            file: config_path_str.into(),
-            hash: Some(dna.address().to_string()),
-        };
+        new_dna.file = config_path_str.into();
+        new_dna.hash = Some(dna.address().to_string());
 
         let mut new_config = self.config.clone();
         new_config.dnas.push(new_dna.clone());
@@ -185,7 +299,10 @@ impl ConductorAdmin for Conductor {
                         format!("invalid path {:?}", storage_path).into(),
                     ))?
                     .into(),
+                encryption: None,
             },
+            log_rules: None,
+            restart_policy: None,
         };
         new_config.instances.push(new_instance);
         new_config.check_consistency()?;
@@ -234,6 +351,167 @@ impl ConductorAdmin for Conductor {
         instance.write().unwrap().stop()
     }
 
+    /// Walks the source chain of the given instance and reports the first broken link,
+    /// if any.
+    fn verify_chain(
+        &mut self,
+        id: &String,
+    ) -> Result<ChainIntegrityReport, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let report = instance.read().unwrap().verify_chain();
+        match report.broken_link {
+            Some(ref broken_link) => notify(format!(
+                "Source chain of instance \"{}\" is broken at {}",
+                id, broken_link
+            )),
+            None => notify(format!(
+                "Verified {} headers of source chain of instance \"{}\".",
+                report.verified_headers, id
+            )),
+        }
+        Ok(report)
+    }
+
+    /// Collects statistics about what the given instance holds in its storages.
+    fn instance_stats(&mut self, id: &String) -> Result<StorageStats, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let stats = instance.read().unwrap().storage_stats()?;
+        Ok(stats)
+    }
+
+    /// Returns `limit` headers (with their entries) of the instance's source chain,
+    /// skipping the `start` newest ones.
+    fn debug_chain(
+        &mut self,
+        id: &String,
+        start: usize,
+        limit: usize,
+    ) -> Result<Vec<ChainItem>, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let items = instance.read().unwrap().debug_chain(start, limit)?;
+        Ok(items)
+    }
+
+    /// Returns a page of the entries the instance holds for the DHT with their CRUD status.
+    fn debug_held_entries(
+        &mut self,
+        id: &String,
+        start: usize,
+        limit: usize,
+    ) -> Result<Vec<HeldEntry>, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let entries = instance.read().unwrap().debug_held_entries(start, limit)?;
+        Ok(entries)
+    }
+
+    /// Returns the EAV metadata the instance stores about the given address.
+    fn debug_eav(
+        &mut self,
+        id: &String,
+        address: &Address,
+    ) -> Result<Vec<EavItem>, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let items = instance.read().unwrap().debug_eav(address)?;
+        Ok(items)
+    }
+
+    /// Returns the validations of the instance that wait for missing dependencies.
+    fn debug_pending_validations(
+        &mut self,
+        id: &String,
+    ) -> Result<Vec<PendingValidationItem>, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let pending = instance.read().unwrap().debug_pending_validations()?;
+        Ok(pending)
+    }
+
+    /// Returns the network requests of the instance that are still in flight.
+    fn debug_network_requests(
+        &mut self,
+        id: &String,
+    ) -> Result<NetworkRequests, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let requests = instance.read().unwrap().debug_network_requests()?;
+        Ok(requests)
+    }
+
+    /// Returns the outermost span of the most recent traces the instance recorded,
+    /// newest first.
+    fn debug_traces(
+        &mut self,
+        id: &String,
+        limit: usize,
+    ) -> Result<Vec<Span>, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let traces = instance.read().unwrap().debug_traces(limit);
+        Ok(traces)
+    }
+
+    /// Returns all spans the instance recorded, for exporting them.
+    fn debug_spans(&mut self, id: &String) -> Result<Vec<Span>, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        let spans = instance.read().unwrap().debug_spans();
+        Ok(spans)
+    }
+
+    /// Returns the spans of the given trace that any instance of this conductor recorded,
+    /// in the order they started. Traces continue across bridges, so a call that
+    /// bridges into another instance has spans in both of them.
+    fn debug_trace(
+        &mut self,
+        trace_id: &String,
+    ) -> Result<Vec<InstanceSpan>, HolochainInstanceError> {
+        let mut spans: Vec<InstanceSpan> = self
+            .instances
+            .iter()
+            .flat_map(|(instance_id, instance)| {
+                let spans = instance.read().unwrap().debug_trace(trace_id);
+                spans.into_iter().map(move |span| InstanceSpan {
+                    instance_id: instance_id.clone(),
+                    span,
+                })
+            })
+            .collect();
+        spans.sort_by(|a, b| a.span.started_at.cmp(&b.span.started_at));
+        Ok(spans)
+    }
+
+    /// Creates an archive of the source chain (and optionally the held DHT shard)
+    /// of the given instance that can be restored with `import_instance`.
+    fn export_instance(
+        &mut self,
+        id: &String,
+        include_dht_shard: bool,
+    ) -> Result<ChainArchive, HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        notify(format!("Exporting source chain of instance \"{}\"...", id));
+        instance.read().unwrap().export_chain(include_dht_shard)
+    }
+
+    /// Restores the source chain of the given instance from an archive created
+    /// with `export_instance`.
+    /// The archive's header chain and all signatures get verified first.
+    /// The instance has to run the same DNA with the same agent and must not have
+    /// committed anything but its genesis entries yet.
+    fn import_instance(
+        &mut self,
+        id: &String,
+        archive: &ChainArchive,
+    ) -> Result<(), HolochainInstanceError> {
+        let instance = self.instances.get(id)?;
+        notify(format!(
+            "Importing source chain into instance \"{}\"...",
+            id
+        ));
+        instance.write().unwrap().import_chain(archive)?;
+        notify(format!(
+            "Imported {} chain entries into instance \"{}\".",
+            archive.chain.len(),
+            id
+        ));
+        Ok(())
+    }
+
     fn add_interface(&mut self, interface: InterfaceConfiguration) -> Result<(), HolochainError> {
         let mut new_config = self.config.clone();
         if new_config.interfaces.iter().any(|i| i.id == interface.id) {
@@ -374,6 +652,94 @@ impl ConductorAdmin for Conductor {
         Ok(())
     }
 
+    /// Creates a new random token that grants access to the given interface (restricted
+    /// to the given methods and instances, if set).
+    /// Only the hash of the token gets stored in the config, so the returned token can't be
+    /// retrieved again later.
+    /// This restarts the given interface in order to have the change take effect.
+    fn issue_interface_token(
+        &mut self,
+        interface_id: &String,
+        methods: Option<Vec<String>>,
+        instances: Option<Vec<String>>,
+    ) -> Result<String, HolochainError> {
+        let token = generate_token()?;
+        let mut new_config = self.config.clone();
+        new_config
+            .interfaces
+            .iter_mut()
+            .find(|interface| interface.id == *interface_id)
+            .ok_or(HolochainError::ErrorGeneric(format!(
+                "Interface with ID {} not found",
+                interface_id
+            )))?
+            .tokens
+            .push(InterfaceTokenConfiguration {
+                token: None,
+                token_hash: Some(hash_token(&token)?),
+                methods,
+                instances,
+            });
+
+        new_config.check_consistency()?;
+        self.config = new_config;
+        self.save_config()?;
+
+        let _ = self.stop_interface_by_id(interface_id);
+        self.start_interface_by_id(interface_id)?;
+
+        notify(format!("Issued new token for interface {}", interface_id));
+        Ok(token)
+    }
+
+    /// Removes the token with the given hash from the given interface.
+    /// This restarts the given interface in order to have the change take effect.
+    fn revoke_interface_token(
+        &mut self,
+        interface_id: &String,
+        token_hash: &String,
+    ) -> Result<(), HolochainError> {
+        let mut new_config = self.config.clone();
+        {
+            let interface = new_config
+                .interfaces
+                .iter_mut()
+                .find(|interface| interface.id == *interface_id)
+                .ok_or(HolochainError::ErrorGeneric(format!(
+                    "Interface with ID {} not found",
+                    interface_id
+                )))?;
+            let token_count = interface.tokens.len();
+            let mut remaining = Vec::new();
+            for token in interface.tokens.drain(..) {
+                let hash = match token.token {
+                    Some(ref plain) => hash_token(plain)?,
+                    None => token.token_hash.clone().unwrap_or_default().to_lowercase(),
+                };
+                if hash != token_hash.to_lowercase() {
+                    remaining.push(token);
+                }
+            }
+            if remaining.len() == token_count {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "No token with hash {} in interface {}",
+                    token_hash, interface_id
+                )));
+            }
+            interface.tokens = remaining;
+        }
+
+        new_config.check_consistency()?;
+        self.config = new_config;
+        self.save_config()?;
+
+        let _ = self.stop_interface_by_id(interface_id);
+        self.start_interface_by_id(interface_id)?;
+
+        notify(format!("Revoked token of interface {}", interface_id));
+        Ok(())
+    }
+
     fn add_agent(&mut self, new_agent: AgentConfiguration) -> Result<(), HolochainError> {
         let mut new_config = self.config.clone();
         if new_config.agents.iter().any(|i| i.id == new_agent.id) {
@@ -439,6 +805,101 @@ impl ConductorAdmin for Conductor {
         Ok(())
     }
 
+    /// Creates a new agent key in the conductor's keystore, encrypted with the given
+    /// passphrase. Returns the public address and the key file, which can be used to
+    /// add an agent with `add_agent`.
+    fn generate_agent_key(
+        &mut self,
+        passphrase: &String,
+    ) -> Result<(Base32, PathBuf), HolochainError> {
+        let (public_address, path) = self.keystore().generate(passphrase)?;
+        notify(format!("Generated agent key {}", public_address));
+        Ok((public_address, path))
+    }
+
+    /// Stores an encrypted key (as created by `hc keygen` or `export_agent_key`) in the
+    /// conductor's keystore after checking that the passphrase decrypts it.
+    fn import_agent_key(
+        &mut self,
+        blob: &KeyBlob,
+        passphrase: &String,
+    ) -> Result<(Base32, PathBuf), HolochainError> {
+        let (public_address, path) = self.keystore().import(blob, passphrase)?;
+        notify(format!("Imported agent key {}", public_address));
+        Ok((public_address, path))
+    }
+
+    /// Returns the encrypted key with the given public address from the keystore.
+    fn export_agent_key(&mut self, public_address: &Base32) -> Result<KeyBlob, HolochainError> {
+        self.keystore().export(public_address)
+    }
+
+    /// Decrypts the key file of the given agent and keeps the key in memory, so instances
+    /// of this agent can be created without prompting for the passphrase.
+    fn unlock_agent_key(
+        &mut self,
+        agent_id: &String,
+        passphrase: &String,
+    ) -> Result<(), HolochainError> {
+        let agent_config = self.config.agent_by_id(agent_id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Agent '{}' not found", agent_id))
+        })?;
+        if agent_config
+            .remote_signer(&self.config.signing_service_uri)?
+            .is_some()
+        {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Agent '{}' uses a remote key",
+                agent_id
+            )));
+        }
+        let blob = read_key_blob(&PathBuf::from(agent_config.key_file.clone()))?;
+        let keybundle = unlock_key_blob(&blob, passphrase, self.pwhash_config.clone())?;
+        if keybundle.get_id() != agent_config.public_address {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Key from file '{}' ('{}') does not match public address {} mentioned in config!",
+                agent_config.key_file,
+                keybundle.get_id(),
+                agent_config.public_address,
+            )));
+        }
+        self.agent_keys
+            .insert(agent_id.clone(), Arc::new(Mutex::new(keybundle)));
+        notify(format!("Unlocked key of agent \"{}\"", agent_id));
+        Ok(())
+    }
+
+    /// Drops the decrypted key of the given agent from memory.
+    /// Fails if an instance of this agent is running.
+    fn lock_agent_key(&mut self, agent_id: &String) -> Result<(), HolochainError> {
+        if self.config.agent_by_id(agent_id).is_none() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Agent '{}' not found",
+                agent_id
+            )));
+        }
+        let running = self
+            .config
+            .instances
+            .iter()
+            .filter(|instance| instance.agent == *agent_id)
+            .find(|instance| {
+                self.instances
+                    .get(&instance.id)
+                    .map(|holochain| holochain.read().unwrap().active())
+                    .unwrap_or(false)
+            });
+        if let Some(instance) = running {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Cannot lock key of agent '{}' while instance '{}' is running",
+                agent_id, instance.id
+            )));
+        }
+        self.agent_keys.remove(agent_id);
+        notify(format!("Locked key of agent \"{}\"", agent_id));
+        Ok(())
+    }
+
     fn add_bridge(&mut self, new_bridge: Bridge) -> Result<(), HolochainError> {
         let mut new_config = self.config.clone();
         if new_config
@@ -453,6 +914,7 @@ impl ConductorAdmin for Conductor {
         }
         new_config.bridges.push(new_bridge.clone());
         new_config.check_consistency()?;
+        self.check_bridges(&new_config)?;
         self.config = new_config;
         self.save_config()?;
 
@@ -505,7 +967,7 @@ pub mod tests {
     use super::*;
     use crate::{
         conductor::base::{
-            tests::{example_dna_string, test_key_loader, test_keybundle},
+            tests::{example_dna_string, test_key_loader, test_keybundle, test_pwhash_config},
             DnaLoader,
         },
         config::{load_configuration, Configuration, InterfaceConfiguration, InterfaceDriver},
@@ -575,7 +1037,7 @@ public_address = '{}'"#,
     pub fn dna() -> String {
         r#"[[dnas]]
 file = 'app_spec.dna.json'
-hash = 'Qm328wyq38924y'
+hash = 'QmQVLgFxUpd1ExVkBzvwASshpG6fmaJGxDEgf1cFf7S73a'
 id = 'test-dna'"#
             .to_string()
     }
@@ -666,6 +1128,7 @@ pattern = '.*'"#
         let mut conductor = Conductor::from_config(config.clone());
         conductor.dna_loader = test_dna_loader();
         conductor.key_loader = test_key_loader();
+        conductor.pwhash_config = test_pwhash_config();
         conductor.load_config().unwrap();
         conductor
     }
@@ -684,6 +1147,7 @@ pattern = '.*'"#
                 String::from("new-dna"),
                 false,
                 None,
+                None,
                 None
             ),
             Ok(()),
@@ -701,12 +1165,18 @@ pattern = '.*'"#
                 DnaConfiguration {
                     id: String::from("test-dna"),
                     file: String::from("app_spec.dna.json"),
-                    hash: Some(String::from("Qm328wyq38924y")),
+                    hash: Some(String::from(
+                        "QmQVLgFxUpd1ExVkBzvwASshpG6fmaJGxDEgf1cFf7S73a"
+                    )),
+                    uuid: None,
+                    properties: None,
                 },
                 DnaConfiguration {
                     id: String::from("new-dna"),
                     file: String::from("new-dna.dna.json"),
                     hash: Some(String::from(new_dna.address())),
+                    uuid: None,
+                    properties: None,
                 },
             ]
         );
@@ -753,6 +1223,7 @@ id = 'new-dna'"#,
                 String::from("new-dna"),
                 true,
                 None,
+                None,
                 None
             ),
             Ok(()),
@@ -779,12 +1250,18 @@ id = 'new-dna'"#,
                 DnaConfiguration {
                     id: String::from("test-dna"),
                     file: String::from("app_spec.dna.json"),
-                    hash: Some(String::from("Qm328wyq38924y")),
+                    hash: Some(String::from(
+                        "QmQVLgFxUpd1ExVkBzvwASshpG6fmaJGxDEgf1cFf7S73a"
+                    )),
+                    uuid: None,
+                    properties: None,
                 },
                 DnaConfiguration {
                     id: String::from("new-dna"),
                     file: output_dna_file.to_str().unwrap().to_string(),
                     hash: Some(String::from(new_dna.address())),
+                    uuid: None,
+                    properties: None,
                 },
             ]
         );
@@ -805,6 +1282,7 @@ id = 'new-dna'"#,
                 String::from("new-dna"),
                 false,
                 Some(dna.address()),
+                None,
                 None
             ),
             Ok(()),
@@ -816,6 +1294,7 @@ id = 'new-dna'"#,
                 String::from("new-dna"),
                 false,
                 Some("wrong-address".into()),
+                None,
                 None
             ),
             Err(HolochainError::DnaHashMismatch(
@@ -840,7 +1319,8 @@ id = 'new-dna'"#,
                 String::from("new-dna-with-props"),
                 false,
                 None,
-                Some(&new_props)
+                Some(&new_props),
+                None
             ),
             Err(HolochainError::ConfigError(
                 "Cannot install DNA with properties unless copy flag is true".into()
@@ -853,7 +1333,8 @@ id = 'new-dna'"#,
                 String::from("new-dna-with-props"),
                 true,
                 None,
-                Some(&new_props)
+                Some(&new_props),
+                None
             ),
             Ok(()),
         );
@@ -882,18 +1363,70 @@ id = 'new-dna'"#,
                 DnaConfiguration {
                     id: String::from("test-dna"),
                     file: String::from("app_spec.dna.json"),
-                    hash: Some(String::from("Qm328wyq38924y")),
+                    hash: Some(String::from(
+                        "QmQVLgFxUpd1ExVkBzvwASshpG6fmaJGxDEgf1cFf7S73a"
+                    )),
+                    uuid: None,
+                    properties: None,
                 },
                 DnaConfiguration {
                     id: String::from("new-dna-with-props"),
                     file: output_dna_file.to_str().unwrap().to_string(),
                     hash: Some(String::from(new_dna.address())),
+                    uuid: None,
+                    properties: Some(json!({"propertyKey": "value"})),
                 },
             ]
         );
         assert!(output_dna_file.is_file())
     }
 
+    #[test]
+    fn test_install_dna_from_file_with_uuid() {
+        let test_name = "test_install_dna_from_file_with_uuid";
+        let mut conductor = create_test_conductor(test_name, 3000);
+        let new_dna_path = PathBuf::from("new-dna.dna.json");
+
+        assert_eq!(
+            conductor.install_dna_from_file(
+                new_dna_path.clone(),
+                String::from("new-dna-with-uuid"),
+                false,
+                None,
+                None,
+                Some(String::from("network-2")),
+            ),
+            Err(HolochainError::ConfigError(
+                "Cannot install DNA with uuid unless copy flag is true".into()
+            )),
+        );
+
+        for uuid in vec!["network-1", "network-2"] {
+            assert_eq!(
+                conductor.install_dna_from_file(
+                    new_dna_path.clone(),
+                    format!("new-dna-{}", uuid),
+                    true,
+                    None,
+                    None,
+                    Some(String::from(uuid)),
+                ),
+                Ok(()),
+            );
+        }
+
+        let dnas = conductor.config().dnas;
+        assert_eq!(dnas.len(), 3);
+        assert_eq!(dnas[1].uuid, Some(String::from("network-1")));
+        assert_eq!(dnas[2].uuid, Some(String::from("network-2")));
+        assert_ne!(dnas[1].hash, dnas[2].hash);
+        assert_ne!(dnas[1].file, dnas[2].file);
+
+        let saved_dna = Dna::try_from(dnas[2].clone()).unwrap();
+        assert_eq!(saved_dna.uuid, "network-2");
+        assert_eq!(Some(saved_dna.address().to_string()), dnas[2].hash);
+    }
+
     #[test]
     fn test_add_instance() {
         let test_name = "test_add_instance";
@@ -908,6 +1441,7 @@ id = 'new-dna'"#,
                 false,
                 None,
                 None,
+                None,
             )
             .expect("Could not install DNA");
 
@@ -1098,9 +1632,14 @@ type = 'websocket'"#,
 
         let interface_config = InterfaceConfiguration {
             id: String::from("new-interface"),
-            driver: InterfaceDriver::Http { port: 8080 },
+            driver: InterfaceDriver::Http {
+                port: 8080,
+                bind_address: None,
+                tls: None,
+            },
             admin: false,
             instances: Vec::new(),
+            tokens: Vec::new(),
         };
 
         assert_eq!(conductor.add_interface(interface_config), Ok(()),);
@@ -1340,6 +1879,7 @@ type = 'websocket'"#,
             public_address: AgentId::generate_fake("new").address().to_string(),
             key_file: String::from("new-test-path"),
             holo_remote_key: None,
+            signer: None,
         };
 
         assert_eq!(conductor.add_agent(agent_config), Ok(()),);
@@ -1486,4 +2026,189 @@ handle = 'my favourite instance!'"#,
 
         assert_eq!(config_contents, toml,);
     }
+
+    #[test]
+    fn test_verify_chain() {
+        let mut conductor = create_test_conductor("test_verify_chain", 3014);
+        let report = conductor
+            .verify_chain(&String::from("test-instance-1"))
+            .expect("Could not verify chain");
+        assert!(report.is_valid());
+        assert!(report.verified_headers > 0);
+        assert_eq!(
+            conductor.verify_chain(&String::from("non-existant-id")),
+            Err(HolochainInstanceError::NoSuchInstance),
+        );
+    }
+
+    #[test]
+    fn test_instance_stats() {
+        let mut conductor = create_test_conductor("test_instance_stats", 3015);
+        let stats = conductor
+            .instance_stats(&String::from("test-instance-1"))
+            .expect("Could not get instance stats");
+        assert!(stats.chain_length >= 2);
+        assert!(stats.total_bytes > 0);
+        assert_eq!(
+            conductor.instance_stats(&String::from("non-existant-id")),
+            Err(HolochainInstanceError::NoSuchInstance),
+        );
+    }
+
+    #[test]
+    fn test_debug_introspection() {
+        let mut conductor = create_test_conductor("test_debug_introspection", 3017);
+        let id = String::from("test-instance-1");
+        let chain = conductor
+            .debug_chain(&id, 0, 100)
+            .expect("Could not get chain");
+        assert!(chain.len() >= 2);
+        assert_eq!(
+            conductor.debug_chain(&id, 1, 1).unwrap(),
+            vec![chain[1].clone()]
+        );
+
+        let dna_address = chain.last().unwrap().header.entry_address().clone();
+        assert!(conductor.debug_held_entries(&id, 0, 100).is_ok());
+        assert!(conductor.debug_eav(&id, &dna_address).is_ok());
+        assert_eq!(conductor.debug_pending_validations(&id), Ok(Vec::new()));
+        assert!(conductor.debug_network_requests(&id).is_ok());
+        assert_eq!(
+            conductor.debug_network_requests(&String::from("non-existant-id")),
+            Err(HolochainInstanceError::NoSuchInstance),
+        );
+        assert!(conductor.debug_traces(&id, 10).is_ok());
+        assert!(conductor.debug_spans(&id).is_ok());
+        assert_eq!(
+            conductor.debug_traces(&String::from("non-existant-id"), 10),
+            Err(HolochainInstanceError::NoSuchInstance),
+        );
+        assert_eq!(
+            conductor.debug_trace(&String::from("non-existant-trace")),
+            Ok(Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_generate_unlock_and_lock_agent_key() {
+        let mut conductor = create_test_conductor("test_generate_unlock_and_lock_agent_key", 3018);
+        let passphrase = String::from("secret");
+
+        let (public_address, key_file) = conductor
+            .generate_agent_key(&passphrase)
+            .expect("Could not generate key");
+        assert!(key_file.starts_with(conductor.keystore_dir_path()));
+
+        let blob = conductor.export_agent_key(&public_address).unwrap();
+        assert!(conductor.import_agent_key(&blob, &passphrase).is_err());
+
+        conductor
+            .add_agent(AgentConfiguration {
+                id: String::from("new-agent"),
+                name: String::from("Mr. New"),
+                public_address: public_address.clone(),
+                key_file: key_file.to_str().unwrap().to_string(),
+                holo_remote_key: None,
+                signer: None,
+            })
+            .unwrap();
+        assert!(conductor
+            .unlock_agent_key(&String::from("new-agent"), &String::from("wrong"))
+            .is_err());
+        assert_eq!(
+            conductor.unlock_agent_key(&String::from("new-agent"), &passphrase),
+            Ok(())
+        );
+        assert_eq!(
+            conductor.agent_keys[&String::from("new-agent")]
+                .lock()
+                .unwrap()
+                .get_id(),
+            public_address
+        );
+        assert_eq!(conductor.lock_agent_key(&String::from("new-agent")), Ok(()));
+        assert!(!conductor
+            .agent_keys
+            .contains_key(&String::from("new-agent")));
+
+        let instance_id = String::from("test-instance-1");
+        let agent_id = String::from("test-agent-1");
+        conductor.start_instance(&instance_id).unwrap();
+        assert!(conductor.lock_agent_key(&agent_id).is_err());
+        conductor.stop_instance(&instance_id).unwrap();
+        assert_eq!(conductor.lock_agent_key(&agent_id), Ok(()));
+    }
+
+    #[test]
+    fn test_issue_and_revoke_interface_token() {
+        let mut conductor = create_test_conductor("test_issue_and_revoke_interface_token", 3016);
+        let interface_id = String::from("websocket interface");
+
+        let token = conductor
+            .issue_interface_token(
+                &interface_id,
+                Some(vec![String::from("call")]),
+                Some(vec![String::from("test-instance-1")]),
+            )
+            .expect("Could not issue token");
+        let token_hash = hash_token(&token).unwrap();
+        let interface = conductor.config().interface_by_id(&interface_id).unwrap();
+        assert_eq!(
+            interface.tokens,
+            vec![InterfaceTokenConfiguration {
+                token: None,
+                token_hash: Some(token_hash.clone()),
+                methods: Some(vec![String::from("call")]),
+                instances: Some(vec![String::from("test-instance-1")]),
+            }]
+        );
+
+        assert!(conductor
+            .issue_interface_token(&interface_id, None, Some(vec![String::from("no-instance")]))
+            .is_err());
+
+        assert_eq!(
+            conductor.revoke_interface_token(&interface_id, &token_hash),
+            Ok(())
+        );
+        assert!(conductor
+            .config()
+            .interface_by_id(&interface_id)
+            .unwrap()
+            .tokens
+            .is_empty());
+        assert!(conductor
+            .revoke_interface_token(&interface_id, &token_hash)
+            .is_err());
+    }
+
+    #[test]
+    fn test_export_import_instance() {
+        let mut source = create_test_conductor("test_export_import_instance_source", 3012);
+        let mut target = create_test_conductor("test_export_import_instance_target", 3013);
+        let instance_1 = String::from("test-instance-1");
+        let instance_2 = String::from("test-instance-2");
+
+        let archive = source
+            .export_instance(&instance_1, true)
+            .expect("Could not export instance");
+        assert_eq!(
+            archive.agent_address.to_string(),
+            test_keybundle(1).get_id()
+        );
+        assert!(archive.dht_shard.is_some());
+
+        // agent of test-instance-2 is different from the one the chain was signed by:
+        assert!(target.import_instance(&instance_2, &archive).is_err());
+        assert_eq!(
+            target.import_instance(&String::from("non-existant-id"), &archive),
+            Err(HolochainInstanceError::NoSuchInstance),
+        );
+
+        assert_eq!(target.import_instance(&instance_1, &archive), Ok(()));
+        let imported = target
+            .export_instance(&instance_1, false)
+            .expect("Could not export imported instance");
+        assert_eq!(imported.chain, archive.chain);
+    }
 }
@@ -1,13 +1,21 @@
 use crate::{
+    conductor::supervisor::Supervision,
     config::{
         serialize_configuration, Configuration, InterfaceConfiguration, InterfaceDriver,
-        StorageConfiguration,
+        StorageConfiguration, StorageEncryptionConfiguration,
     },
     context_builder::ContextBuilder,
     error::HolochainInstanceError,
+    keystore::Keystore,
     logger::DebugLogger,
+    metrics::{
+        render_prometheus, InstanceMetrics, MetricsServer, MetricsSource, StorageStatsCache,
+        StorageStatsRefresher,
+    },
+    signers::{remote_signer_from_config, LocalSigner, Signer},
     Holochain,
 };
+use holochain_cas_implementations::encryption::StorageKey;
 use holochain_common::paths::DNA_EXTENSION;
 use holochain_core::{
     logger::{ChannelLogger, Logger},
@@ -15,19 +23,21 @@ use holochain_core::{
 };
 use holochain_core_types::{
     agent::AgentId, cas::content::AddressableContent, dna::Dna, error::HolochainError,
-    json::JsonString,
+    hash::HashString, json::JsonString,
 };
 use holochain_dpki::{
     key_blob::{Blobbable, KeyBlob},
     key_bundle::KeyBundle,
+    password_encryption::PwHashConfig,
 };
 use holochain_sodium::secbuf::SecBuf;
 use jsonrpc_ws_server::jsonrpc_core::IoHandler;
 use rpassword;
 use std::{
     clone::Clone,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     convert::TryFrom,
+    env,
     fs::{self, File},
     io::prelude::*,
     option::NoneError,
@@ -37,15 +47,28 @@ use std::{
         Arc, Mutex, RwLock,
     },
     thread,
+    time::{Duration, Instant},
 };
+use toml;
 
 use holochain_net::{
     ipc::spawn::{ipc_spawn, SpawnResult},
     p2p_config::P2pConfig,
 };
 use interface::{ConductorApiBuilder, InstanceMap, Interface};
+use interface_auth::Authorization;
 use static_file_server::StaticServer;
 
+/// Message that gets signed with an agent's key to derive the key for storage encryption
+const STORAGE_KEY_MESSAGE: &str = "holochain storage encryption";
+
+/// Time the conductor binary gives [shutdown_gracefully](struct.Conductor.html#method.shutdown_gracefully)
+/// when it receives SIGTERM or SIGINT
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Time an instance gets to shut down when the conductor replaces or removes it
+const INSTANCE_TEARDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 lazy_static! {
     /// This is a global and mutable Conductor singleton.
     /// (Ok, not really. I've made Conductor::from_config public again so holochain_nodejs
@@ -81,17 +104,27 @@ pub fn mount_conductor_from_config(config: Configuration) {
 /// Dna object for a given path string) has to be injected on creation.
 pub struct Conductor {
     pub(in crate::conductor) instances: InstanceMap,
-    agent_keys: HashMap<String, Arc<Mutex<KeyBundle>>>,
+    pub(in crate::conductor) agent_keys: HashMap<String, Arc<Mutex<KeyBundle>>>,
     pub(in crate::conductor) config: Configuration,
     pub(in crate::conductor) static_servers: HashMap<String, StaticServer>,
     pub(in crate::conductor) interface_threads: HashMap<String, Sender<()>>,
+    interface_factories: HashMap<String, InterfaceFactory>,
     pub key_loader: KeyLoader,
-    pub(in crate::conductor) dna_loader: DnaLoader,
+    pub dna_loader: DnaLoader,
+    /// Password hashing settings for the keystore and for unlocking agent keys.
+    /// `None` means the default (sensitive) settings, which `load_key` expects.
+    pub pwhash_config: Option<PwHashConfig>,
     pub(in crate::conductor) ui_dir_copier: UiDirCopier,
-    signal_tx: Option<SignalSender>,
+    pub(in crate::conductor) signal_tx: Option<SignalSender>,
     logger: DebugLogger,
     p2p_config: Option<P2pConfig>,
     network_spawn: Option<SpawnResult>,
+    pub(in crate::conductor) config_watcher: Option<Sender<()>>,
+    metrics_server: Option<MetricsServer>,
+    storage_stats: StorageStatsCache,
+    storage_stats_refresher: Option<StorageStatsRefresher>,
+    pub(in crate::conductor) supervisor: Option<Sender<()>>,
+    pub(in crate::conductor) supervision: HashMap<String, Supervision>,
 }
 
 impl Drop for Conductor {
@@ -109,6 +142,9 @@ pub type KeyLoader = Arc<Box<FnMut(&PathBuf) -> Result<KeyBundle, HolochainError
 pub type DnaLoader = Arc<Box<FnMut(&PathBuf) -> Result<Dna, HolochainError> + Send + Sync>>;
 pub type UiDirCopier =
     Arc<Box<FnMut(&PathBuf, &PathBuf) -> Result<(), HolochainError> + Send + Sync>>;
+/// Constructs the Interface for a custom interface driver from its TOML table
+pub type InterfaceFactory =
+    Arc<Box<Fn(&toml::Value) -> Result<Box<Interface>, String> + Send + Sync>>;
 
 // preparing for having conductor notifiers go to one of the log streams
 pub fn notify(msg: String) {
@@ -117,21 +153,29 @@ pub fn notify(msg: String) {
 
 impl Conductor {
     pub fn from_config(config: Configuration) -> Self {
-        let rules = config.logger.rules.clone();
+        let logger = DebugLogger::from_config(&config.logger);
         holochain_sodium::check_init();
         Conductor {
             instances: HashMap::new(),
             agent_keys: HashMap::new(),
             interface_threads: HashMap::new(),
+            interface_factories: HashMap::new(),
             static_servers: HashMap::new(),
             config,
             key_loader: Arc::new(Box::new(Self::load_key)),
             dna_loader: Arc::new(Box::new(Self::load_dna)),
+            pwhash_config: None,
             ui_dir_copier: Arc::new(Box::new(Self::copy_ui_dir)),
             signal_tx: None,
-            logger: DebugLogger::new(rules),
+            logger,
             p2p_config: None,
             network_spawn: None,
+            config_watcher: None,
+            metrics_server: None,
+            storage_stats: Arc::new(RwLock::new(BTreeMap::new())),
+            storage_stats_refresher: None,
+            supervisor: None,
+            supervision: HashMap::new(),
         }
     }
 
@@ -143,6 +187,30 @@ impl Conductor {
         self
     }
 
+    /// Makes all instances use the given network config instead of the one derived from
+    /// `config.network`. Conductors that get the same in-memory network config
+    /// (e.g. a clone of `P2pConfig::new_with_unique_memory_backend()`) share one network,
+    /// which lets tests run several conductors in one process.
+    pub fn with_p2p_config(mut self, p2p_config: P2pConfig) -> Self {
+        if !self.instances.is_empty() {
+            panic!("Cannot set a network config after having run load_config()");
+        }
+        self.p2p_config = Some(p2p_config);
+        self
+    }
+
+    /// Registers an Interface implementation for custom interface drivers.
+    /// Interfaces configured with `type = "custom"` and `name = <name>` in their driver
+    /// table get constructed by calling `factory` with that table.
+    /// This has to happen before the interfaces get started to take effect.
+    pub fn register_interface_driver<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&toml::Value) -> Result<Box<Interface>, String> + Send + Sync + 'static,
+    {
+        self.interface_factories
+            .insert(name.to_string(), Arc::new(Box::new(factory)));
+    }
+
     pub fn p2p_bindings(&self) -> Option<Vec<String>> {
         match self.network_spawn {
             None => None,
@@ -203,15 +271,68 @@ impl Conductor {
 
     pub fn start_all_static_servers(&mut self) -> Result<(), String> {
         notify("Starting all servers".into());
-        self.static_servers.iter_mut().for_each(|(id, server)| {
+        for (id, server) in self.static_servers.iter_mut() {
             server
                 .start()
-                .expect(&format!("Couldnt start server {}", id));
+                .map_err(|error| format!("Couldn't start server {}: {}", id, error))?;
             notify(format!("Server started for \"{}\"", id))
-        });
+        }
         Ok(())
     }
 
+    /// Collects the metrics of all instances and renders them in Prometheus text format.
+    /// Storage statistics come from the cache the metrics server keeps up to date.
+    pub fn metrics_text(&self) -> String {
+        let storage_stats = self.storage_stats.read().unwrap();
+        let instances = self
+            .instances
+            .iter()
+            .map(|(id, instance)| {
+                let instance = instance.read().unwrap();
+                let metrics = InstanceMetrics {
+                    active: instance.active(),
+                    metrics: instance.metrics().unwrap_or_default(),
+                    storage: storage_stats.get(id).cloned(),
+                };
+                (id.clone(), metrics)
+            })
+            .collect();
+        render_prometheus(&instances)
+    }
+
+    /// Starts the HTTP endpoint for metrics if one is configured.
+    /// The endpoint reads the metrics from the conductor mounted as `CONDUCTOR`.
+    pub fn start_metrics_server(&mut self) -> Result<(), String> {
+        self.stop_metrics_server();
+        if let Some(config) = self.config.metrics.clone() {
+            let source: MetricsSource = Arc::new(Box::new(|| match *CONDUCTOR.lock().unwrap() {
+                Some(ref conductor) => conductor.metrics_text(),
+                None => String::new(),
+            }));
+            let mut server = MetricsServer::new(config.clone(), source);
+            server.start()?;
+            notify(format!("Serving metrics on port {}", config.port));
+            self.metrics_server = Some(server);
+            self.storage_stats_refresher = Some(StorageStatsRefresher::start(
+                self.storage_stats.clone(),
+                Box::new(|| match *CONDUCTOR.lock().unwrap() {
+                    Some(ref conductor) => conductor.instances.clone(),
+                    None => InstanceMap::new(),
+                }),
+            )?);
+        }
+        Ok(())
+    }
+
+    pub fn stop_metrics_server(&mut self) {
+        if let Some(mut server) = self.metrics_server.take() {
+            server.stop();
+        }
+        if let Some(mut refresher) = self.storage_stats_refresher.take() {
+            refresher.stop();
+        }
+    }
+
     /// Starts all instances
     pub fn start_all_instances(&mut self) -> Result<(), HolochainInstanceError> {
         self.instances
@@ -240,6 +361,26 @@ impl Conductor {
         &self.instances
     }
 
+    /// Shuts the given instance down for good (see [Holochain::shutdown](../../struct.Holochain.html#method.shutdown)),
+    /// so that a replacement can be loaded from the same storage without the old action loop
+    /// and network still running for the same agent. Errors only get reported since the
+    /// instance can't be used anymore either way.
+    pub(crate) fn shutdown_instance(&self, id: &String) {
+        if let Some(instance) = self.instances.get(id) {
+            notify(format!("Shutting down instance \"{}\"...", id));
+            let result = instance
+                .write()
+                .unwrap()
+                .shutdown(INSTANCE_TEARDOWN_TIMEOUT);
+            if let Err(error) = result {
+                notify(format!(
+                    "Error shutting down instance \"{}\": {}",
+                    id, error
+                ));
+            }
+        }
+    }
+
     /// Stop and clear all instances
     pub fn shutdown(&mut self) {
         let _ = self
@@ -249,6 +390,59 @@ impl Conductor {
         self.instances = HashMap::new();
     }
 
+    /// Shuts the conductor down without losing data, bounded by the given timeout:
+    /// stops the background threads and interfaces so that no new zome calls come in,
+    /// then shuts down every instance (see [Holochain::shutdown](../../struct.Holochain.html#method.shutdown)),
+    /// callers before the instances they bridge to, and finally stops all servers.
+    /// Stopped instances get shut down as well, since stopping them leaves their action
+    /// loop and network running.
+    /// Returns the errors of all instances that could not be shut down cleanly.
+    pub fn shutdown_gracefully(&mut self, timeout: Duration) -> Result<(), String> {
+        let deadline = Instant::now() + timeout;
+        self.stop_supervisor();
+        self.stop_watching_config_file();
+        self.stop_all_interfaces();
+
+        let mut ids = self
+            .config
+            .instance_ids_sorted_by_bridge_dependencies()
+            .unwrap_or_else(|_| self.instances.keys().cloned().collect());
+        ids.reverse();
+        let mut errors = Vec::new();
+        for id in ids {
+            let instance = match self.instances.get(&id) {
+                Some(instance) => instance.clone(),
+                None => continue,
+            };
+            let mut instance = instance.write().unwrap();
+            notify(format!("Shutting down instance \"{}\"...", id));
+            let now = Instant::now();
+            let remaining = if deadline > now {
+                deadline - now
+            } else {
+                Duration::from_millis(0)
+            };
+            if let Err(error) = instance.shutdown(remaining) {
+                notify(format!(
+                    "Error shutting down instance \"{}\": {}",
+                    id, error
+                ));
+                errors.push(format!("{}: {}", id, error));
+            }
+        }
+
+        for (_, server) in self.static_servers.iter_mut() {
+            let _ = server.stop();
+        }
+        self.stop_metrics_server();
+        self.instances = HashMap::new();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
     pub fn spawn_network(&mut self) -> Result<SpawnResult, HolochainError> {
         let network_config = self
             .config
@@ -326,7 +520,8 @@ impl Conductor {
     }
 
     /// Tries to create all instances configured in the given Configuration object.
-    /// Calls `Configuration::check_consistency()` first and clears `self.instances`.
+    /// Calls `Configuration::check_consistency()` and [check_bridges](#method.check_bridges)
+    /// first and clears `self.instances`.
     /// The first time we call this, we also initialize the conductor-wide config
     /// for use with all instances
     ///
@@ -338,12 +533,13 @@ impl Conductor {
         signal_tx: Option<SignalSender>,
     ) -> Result<(), String> {
         let _ = self.config.check_consistency()?;
+        let config = self.config.clone();
+        self.check_bridges(&config)?;
 
         if self.p2p_config.is_none() {
             self.p2p_config = Some(self.initialize_p2p_config());
         }
 
-        let config = self.config.clone();
         self.shutdown();
 
         for id in config.instance_ids_sorted_by_bridge_dependencies()? {
@@ -410,11 +606,9 @@ impl Conductor {
 
                 // Agent:
                 let agent_config = config.agent_by_id(&instance_config.agent).unwrap();
-                let agent_id = if Some(true) == agent_config.holo_remote_key {
-                    // !!!!!!!!!!!!!!!!!!!!!!!
-                    // Holo closed-alpha hack:
-                    // !!!!!!!!!!!!!!!!!!!!!!!
-                    AgentId::new(&agent_config.name, agent_config.public_address)
+                let remote_signer = agent_config.remote_signer(&self.config.signing_service_uri)?;
+                let agent_id = if remote_signer.is_some() {
+                    AgentId::new(&agent_config.name, agent_config.public_address.clone())
                 } else {
                     let keybundle_arc = self.get_keybundle_for_agent(&instance_config.agent)?;
                     let keybundle = keybundle_arc.lock().unwrap();
@@ -432,11 +626,21 @@ impl Conductor {
 
                 // Storage:
                 match instance_config.storage {
-                    StorageConfiguration::File { path } => {
-                        context_builder =
-                            context_builder.with_file_storage(path).map_err(|hc_err| {
-                                format!("Error creating context: {}", hc_err.to_string())
-                            })?
+                    StorageConfiguration::File { path, encryption } => {
+                        context_builder = match encryption {
+                            None => context_builder.with_file_storage(path),
+                            Some(encryption) => {
+                                let key = self.get_storage_key(
+                                    &path,
+                                    &encryption,
+                                    &instance_config.agent,
+                                )?;
+                                context_builder.with_encrypted_file_storage(path, key)
+                            }
+                        }
+                        .map_err(|hc_err| {
+                            format!("Error creating context: {}", hc_err.to_string())
+                        })?
                     }
                     StorageConfiguration::Memory => {
                         context_builder = context_builder.with_memory_storage()
@@ -444,6 +648,8 @@ impl Conductor {
                 }
 
                 if config.logger.logger_type == "debug" {
+                    self.logger
+                        .set_instance_rules(&instance_config.id, instance_config.log_rules.clone());
                     context_builder = context_builder.with_logger(Arc::new(Mutex::new(
                         ChannelLogger::new(instance_config.id.clone(), self.logger.get_sender()),
                     )));
@@ -451,23 +657,17 @@ impl Conductor {
 
                 // Conductor API
                 let mut api_builder = ConductorApiBuilder::new();
-                // Signing callback:
-                if Some(true) == agent_config.holo_remote_key {
-                    // !!!!!!!!!!!!!!!!!!!!!!!
-                    // Holo closed-alpha hack:
-                    // !!!!!!!!!!!!!!!!!!!!!!!
-                    api_builder = api_builder.with_outsource_signing_callback(
-                        agent_id.clone(),
-                        self.config
-                            .signing_service_uri
-                            .clone()
-                            .expect("holo_remote_key needs signing_service_uri set"),
-                    );
-                } else {
-                    api_builder = api_builder.with_agent_signature_callback(
+                // Signer:
+                let signer: Arc<Signer> = match remote_signer {
+                    Some(signer_config) => Arc::from(remote_signer_from_config(
+                        &signer_config,
+                        &agent_config.public_address,
+                    )?),
+                    None => Arc::new(LocalSigner::new(
                         self.get_keybundle_for_agent(&instance_config.agent)?,
-                    );
-                }
+                    )),
+                };
+                api_builder = api_builder.with_signer(signer);
 
                 // Bridges:
                 let id = instance_config.id.clone();
@@ -497,14 +697,7 @@ impl Conductor {
                 let context = context_builder.spawn();
 
                 // Get DNA
-                let dna_config = config.dna_by_id(&instance_config.dna).unwrap();
-                let dna_file = PathBuf::from(&dna_config.file);
-                let dna = Arc::get_mut(&mut self.dna_loader).unwrap()(&dna_file).map_err(|_| {
-                    HolochainError::ConfigError(format!(
-                        "Could not load DNA file \"{}\"",
-                        dna_config.file
-                    ))
-                })?;
+                let dna = self.load_instance_dna(&id, config)?;
 
                 let context = Arc::new(context);
                 Holochain::load(context.clone())
@@ -533,22 +726,50 @@ impl Conductor {
             })
     }
 
+    /// Loads the DNA of the given instance through the DNA loader and applies the
+    /// overrides of its DNA configuration.
+    /// Fails if the DNA config has a hash that does not match the DNA's address.
+    pub(in crate::conductor) fn load_instance_dna(
+        &mut self,
+        id: &String,
+        config: &Configuration,
+    ) -> Result<Dna, String> {
+        let dna_config = config
+            .instance_by_id(id)
+            .and_then(|instance_config| config.dna_by_id(&instance_config.dna))
+            .ok_or_else(|| format!("No DNA configured for instance \"{}\"", id))?;
+        let dna_file = PathBuf::from(&dna_config.file);
+        let mut dna = Arc::get_mut(&mut self.dna_loader).unwrap()(&dna_file).map_err(|_| {
+            HolochainError::ConfigError(format!("Could not load DNA file \"{}\"", dna_config.file))
+        })?;
+        dna_config.apply_overrides(&mut dna);
+        if let Some(ref hash) = dna_config.hash {
+            let expected = HashString::from(hash.clone());
+            if dna.address() != expected {
+                return Err(format!(
+                    "Refusing to load DNA \"{}\" of instance \"{}\": {}",
+                    dna_config.id,
+                    id,
+                    HolochainError::DnaHashMismatch(dna.address(), expected)
+                ));
+            }
+        }
+        Ok(dna)
+    }
+
     /// Checks if the key for the given agent can be loaded or was already loaded.
     /// Will trigger loading if key is not loaded yet.
     /// Meant to be used in conductor executable to first try to load all keys (which will trigger
     /// passphrase prompts) before bootstrapping the whole config and have prompts appear
     /// in between other initialization output.
     pub fn check_load_key_for_agent(&mut self, agent_id: &String) -> Result<(), String> {
-        if Some(true)
-            == self
-                .config
-                .agent_by_id(agent_id)
-                .and_then(|a| a.holo_remote_key)
-        {
-            // !!!!!!!!!!!!!!!!!!!!!!!
-            // Holo closed-alpha hack:
-            // !!!!!!!!!!!!!!!!!!!!!!!
-            return Ok(());
+        if let Some(agent_config) = self.config.agent_by_id(agent_id) {
+            if agent_config
+                .remote_signer(&self.config.signing_service_uri)?
+                .is_some()
+            {
+                return Ok(());
+            }
         }
         self.get_keybundle_for_agent(agent_id)?;
         Ok(())
@@ -557,7 +778,7 @@ impl Conductor {
     /// Get reference to key for given agent ID.
     /// If the key was not loaded (into secure memory) yet, this will use the KeyLoader
     /// to do so.
-    fn get_keybundle_for_agent(
+    pub(in crate::conductor) fn get_keybundle_for_agent(
         &mut self,
         agent_id: &String,
     ) -> Result<Arc<Mutex<KeyBundle>>, String> {
@@ -589,6 +810,60 @@ impl Conductor {
         Ok(keybundle_ref.clone())
     }
 
+    /// Derives the key for the encrypted instance storage at `path`, either from a passphrase
+    /// or from the key bundle of the given agent.
+    fn get_storage_key(
+        &mut self,
+        path: &String,
+        encryption: &StorageEncryptionConfiguration,
+        agent_id: &String,
+    ) -> Result<StorageKey, String> {
+        match encryption {
+            StorageEncryptionConfiguration::Passphrase { passphrase_env } => {
+                let mut passphrase = Self::read_storage_passphrase(passphrase_env)
+                    .map_err(|e| format!("Could not read storage passphrase: {}", e))?;
+                StorageKey::open(path, |salt| {
+                    StorageKey::from_passphrase(&mut passphrase, salt)
+                })
+            }
+            StorageEncryptionConfiguration::Agent => {
+                // Ed25519 signatures are deterministic, so signing a constant message
+                // gives secret material only the owner of the agent key can reproduce.
+                let keybundle = self.get_keybundle_for_agent(agent_id)?;
+                let mut message =
+                    SecBuf::with_insecure_from_string(STORAGE_KEY_MESSAGE.to_string());
+                let mut signature = keybundle
+                    .lock()
+                    .unwrap()
+                    .sign(&mut message)
+                    .map_err(|e| e.to_string())?;
+                StorageKey::open(path, |_| StorageKey::from_secret(&mut signature))
+            }
+        }
+        .map_err(|e| format!("Could not open encrypted storage at {}: {}", path, e))
+    }
+
+    /// Reads the passphrase for an encrypted storage from the given environment variable
+    /// or, if that is not set, prompts for it.
+    fn read_storage_passphrase(passphrase_env: &Option<String>) -> Result<SecBuf, HolochainError> {
+        let mut passphrase_string = match passphrase_env.as_ref().and_then(|var| env::var(var).ok())
+        {
+            Some(passphrase) => passphrase,
+            None => rpassword::read_password_from_tty(Some("Storage passphrase: "))?,
+        };
+
+        // Move passphrase in secure memory
+        let passphrase_bytes = unsafe { passphrase_string.as_mut_vec() };
+        let mut passphrase_buf = SecBuf::with_insecure(passphrase_bytes.len());
+        passphrase_buf.write(0, passphrase_bytes.as_slice())?;
+
+        // Overwrite the unsafe passphrase memory with zeros
+        for byte in passphrase_bytes.iter_mut() {
+            *byte = 0u8;
+        }
+        Ok(passphrase_buf)
+    }
+
     fn start_interface(&mut self, config: &InterfaceConfiguration) -> Result<(), String> {
         if self.interface_threads.contains_key(&config.id) {
             return Err(format!("Interface {} already started!", config.id));
@@ -656,7 +931,10 @@ impl Conductor {
         Ok(())
     }
 
-    fn make_interface_handler(&self, interface_config: &InterfaceConfiguration) -> IoHandler {
+    fn make_interface_handler(
+        &self,
+        interface_config: &InterfaceConfiguration,
+    ) -> Result<IoHandler, String> {
         let instance_ids: Vec<String> = interface_config
             .instances
             .iter()
@@ -670,7 +948,11 @@ impl Conductor {
             .map(|(id, val)| (id.clone(), val.clone()))
             .collect();
 
+        let authorization =
+            Authorization::new(&interface_config.tokens).map_err(|e| e.to_string())?;
+
         let mut conductor_api_builder = ConductorApiBuilder::new()
+            .with_authorization(authorization)
             .with_instances(instance_subset)
             .with_instance_configs(self.config.instances.clone());
 
@@ -679,25 +961,30 @@ impl Conductor {
             conductor_api_builder = conductor_api_builder.with_admin_ui_functions();
         }
 
-        conductor_api_builder.spawn()
+        Ok(conductor_api_builder.spawn())
     }
 
     fn spawn_interface_thread(&self, interface_config: InterfaceConfiguration) -> Sender<()> {
         let dispatcher = self.make_interface_handler(&interface_config);
+        let interface_factories = self.interface_factories.clone();
         let log_sender = self.logger.get_sender();
         let (tx, rx) = channel();
         thread::Builder::new()
             .name(format!("conductor-interface: {}", interface_config.id))
             .spawn(move || {
-                let iface = make_interface(&interface_config);
-                iface.run(dispatcher, rx).map_err(|error| {
-                    let message = format!(
-                        "err/conductor: Error running interface '{}': {}",
-                        interface_config.id, error
-                    );
-                    let _ = log_sender.send((String::from("conductor"), message));
-                    error
-                })
+                dispatcher
+                    .and_then(|dispatcher| {
+                        make_interface(&interface_config, &interface_factories)
+                            .and_then(|iface| iface.run(dispatcher, rx))
+                    })
+                    .map_err(|error| {
+                        let message = format!(
+                            "err/conductor: Error running interface '{}': {}",
+                            interface_config.id, error
+                        );
+                        let _ = log_sender.send((String::from("conductor"), message));
+                        error
+                    })
             })
             .expect("Could not spawn thread for interface");
         tx
@@ -715,6 +1002,15 @@ impl Conductor {
         self.config.persistence_dir.join("storage")
     }
 
+    pub fn keystore_dir_path(&self) -> PathBuf {
+        self.config.persistence_dir.join("keys")
+    }
+
+    /// The keystore that holds the agent keys generated or imported via the admin API
+    pub fn keystore(&self) -> Keystore {
+        Keystore::with_config(self.keystore_dir_path(), self.pwhash_config.clone())
+    }
+
     pub fn save_config(&self) -> Result<(), HolochainError> {
         fs::create_dir_all(&self.config.persistence_dir).map_err(|_| {
             HolochainError::ErrorGeneric(
@@ -762,13 +1058,58 @@ impl Conductor {
     }
 }
 
-/// This can eventually be dependency injected for third party Interface definitions
-fn make_interface(interface_config: &InterfaceConfiguration) -> Box<Interface> {
+/// Creates the Interface for the given config.
+/// Custom drivers are looked up by name in the given interface factories.
+fn make_interface(
+    interface_config: &InterfaceConfiguration,
+    interface_factories: &HashMap<String, InterfaceFactory>,
+) -> Result<Box<Interface>, String> {
+    #[cfg(unix)]
+    use crate::{
+        config::socket_permissions, interface_impls::domain_socket::DomainSocketInterface,
+    };
     use interface_impls::{http::HttpInterface, websocket::WebsocketInterface};
     match interface_config.driver {
-        InterfaceDriver::Websocket { port } => Box::new(WebsocketInterface::new(port)),
-        InterfaceDriver::Http { port } => Box::new(HttpInterface::new(port)),
-        _ => unimplemented!(),
+        InterfaceDriver::Websocket {
+            port,
+            ref bind_address,
+            ref tls,
+        } => Ok(Box::new(
+            WebsocketInterface::new(port)
+                .with_bind_address(bind_address.clone())
+                .with_tls(tls.clone()),
+        )),
+        InterfaceDriver::Http {
+            port,
+            ref bind_address,
+            ref tls,
+        } => Ok(Box::new(
+            HttpInterface::new(port)
+                .with_bind_address(bind_address.clone())
+                .with_tls(tls.clone()),
+        )),
+        #[cfg(unix)]
+        InterfaceDriver::DomainSocket {
+            ref file,
+            ref permissions,
+        } => Ok(Box::new(DomainSocketInterface::new(
+            PathBuf::from(file),
+            socket_permissions(permissions)?,
+        ))),
+        #[cfg(not(unix))]
+        InterfaceDriver::DomainSocket { .. } => Err(String::from(
+            "Domain socket interfaces are only supported on Unix",
+        )),
+        InterfaceDriver::Custom(ref custom) => {
+            let name = custom
+                .get("name")
+                .and_then(|name| name.as_str())
+                .ok_or_else(|| String::from("Custom interface driver without a name"))?;
+            let factory = interface_factories
+                .get(name)
+                .ok_or_else(|| format!("No interface driver registered for \"{}\"", name))?;
+            factory.as_ref()(custom)
+        }
     }
 }
 
@@ -785,16 +1126,17 @@ pub mod tests {
     extern crate tempfile;
     use crate::config::load_configuration;
     use holochain_core::{
-        action::Action, nucleus::actions::call_zome_function::make_cap_request_for_call,
-        signal::signal_channel,
+        action::Action, health::Probe,
+        nucleus::actions::call_zome_function::make_cap_request_for_call, signal::signal_channel,
     };
     use holochain_core_types::{cas::content::Address, dna, json::RawString};
     use holochain_dpki::{key_bundle::KeyBundle, SEED_SIZE};
-    use holochain_sodium::secbuf::SecBuf;
+    use holochain_sodium::{pwhash, secbuf::SecBuf};
     use holochain_wasm_utils::wasm_target_dir;
     use std::{
         fs::{File, OpenOptions},
         io::Write,
+        sync::mpsc::Receiver,
     };
 
     use self::tempfile::tempdir;
@@ -812,6 +1154,15 @@ pub mod tests {
         Arc::new(loader)
     }
 
+    /// Fast password hashing so that tests don't spend seconds and a GiB of memory per key
+    pub fn test_pwhash_config() -> Option<PwHashConfig> {
+        Some(PwHashConfig(
+            pwhash::OPSLIMIT_INTERACTIVE,
+            pwhash::MEMLIMIT_INTERACTIVE,
+            pwhash::ALG_ARGON2ID13,
+        ))
+    }
+
     pub fn test_key_loader() -> KeyLoader {
         let loader = Box::new(|path: &PathBuf| match path.to_str().unwrap().as_ref() {
             "holo_tester1.key" => Ok(test_keybundle(1)),
@@ -861,17 +1212,14 @@ pub mod tests {
     [[dnas]]
     id = "test-dna"
     file = "app_spec.dna.json"
-    hash = "Qm328wyq38924y"
 
     [[dnas]]
     id = "bridge-callee"
     file = "bridge/callee.dna"
-    hash = "Qm328wyq38924y"
 
     [[dnas]]
     id = "bridge-caller"
     file = "bridge/caller.dna"
-    hash = "Qm328wyq38924y"
 
     [[instances]]
     id = "test-instance-1"
@@ -1027,6 +1375,34 @@ pub mod tests {
         conductor.stop_all_instances().unwrap();
     }
 
+    #[test]
+    fn test_conductor_shutdown_gracefully() {
+        let mut conductor = test_conductor();
+        conductor.start_all_instances().unwrap();
+        // Stopped instances still have their action loop and network running
+        conductor.instances["test-instance-1"]
+            .write()
+            .unwrap()
+            .stop()
+            .unwrap();
+        let instances: Vec<_> = conductor.instances.values().cloned().collect();
+
+        assert_eq!(
+            conductor.shutdown_gracefully(Duration::from_secs(10)),
+            Ok(())
+        );
+        assert!(conductor.instances.is_empty());
+        for instance in instances {
+            let instance = instance.read().unwrap();
+            assert!(!instance.active());
+            assert!(instance.state().unwrap().network().network.is_none());
+            assert_eq!(
+                instance.probe(Duration::from_secs(60)).action_loop,
+                Probe::Dead
+            );
+        }
+    }
+
     //#[test]
     // Default config path ~/.holochain/conductor/conductor-config.toml won't work in CI
     fn _test_conductor_save_and_load_config_default_location() {
@@ -1252,7 +1628,6 @@ pub mod tests {
                 [[dnas]]
                 id = "test-dna"
                 file = "app_spec.dna.json"
-                hash = "Qm328wyq38924y"
 
                 [[instances]]
                 id = "test-instance-1"
@@ -1271,4 +1646,79 @@ pub mod tests {
                 .to_string()),
         );
     }
+
+    #[test]
+    fn refuses_dna_with_mismatching_hash() {
+        let mut config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        config.dnas[0].hash = Some(String::from("QmWrongHash"));
+        let mut conductor = Conductor::from_config(config.clone());
+        conductor.dna_loader = test_dna_loader();
+        conductor.key_loader = test_key_loader();
+        let dna =
+            Arc::get_mut(&mut conductor.dna_loader).unwrap()(&PathBuf::from("app_spec.dna.json"))
+                .unwrap();
+        assert_eq!(
+            conductor.load_config(),
+            Err(format!(
+                "Refusing to load DNA \"test-dna\" of instance \"test-instance-2\": {}",
+                HolochainError::DnaHashMismatch(dna.address(), "QmWrongHash".into())
+            )),
+        );
+
+        config.dnas[0].hash = Some(dna.address().to_string());
+        conductor.config = config;
+        assert_eq!(conductor.load_config(), Ok(()));
+    }
+
+    struct TestInterface;
+
+    impl Interface for TestInterface {
+        fn run(&self, _: IoHandler, kill_switch: Receiver<()>) -> Result<(), String> {
+            let _ = kill_switch.recv();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn make_interface_uses_registered_custom_drivers() {
+        let mut conductor = Conductor::from_config(Configuration::default());
+        let (table_sender, table_receiver) = channel();
+        let table_sender = Mutex::new(table_sender);
+        conductor.register_interface_driver("queue", move |table| {
+            table_sender.lock().unwrap().send(table.clone()).unwrap();
+            Ok(Box::new(TestInterface))
+        });
+
+        let custom_interface = |toml: &str| InterfaceConfiguration {
+            id: "custom interface".to_string(),
+            driver: InterfaceDriver::Custom(toml::from_str(toml).unwrap()),
+            admin: false,
+            instances: Vec::new(),
+            tokens: Vec::new(),
+        };
+
+        let table = r#"
+            name = "queue"
+            url = "amqp://localhost"
+            "#;
+        assert!(make_interface(&custom_interface(table), &conductor.interface_factories).is_ok());
+        assert_eq!(
+            table_receiver.recv().unwrap(),
+            toml::from_str::<toml::Value>(table).unwrap()
+        );
+
+        assert_eq!(
+            make_interface(
+                &custom_interface("name = \"stdio\""),
+                &conductor.interface_factories
+            )
+            .err(),
+            Some("No interface driver registered for \"stdio\"".to_string())
+        );
+        assert!(make_interface(
+            &custom_interface("url = \"x\""),
+            &conductor.interface_factories
+        )
+        .is_err());
+    }
 }
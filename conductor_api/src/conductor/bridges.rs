@@ -0,0 +1,205 @@
+//! Checks of the configured bridges against the bridges that DNAs declare.
+//!
+//! Zomes declare the bridges they use with a handle, a presence and a reference to the
+//! callee's DNA, either by address or by traits (see `holochain_core_types::dna::bridges`).
+//! Checking these when the config gets loaded lets misconfigured bridges fail early with
+//! a precise error instead of at the first bridge call.
+use crate::{conductor::Conductor, config::Configuration};
+use holochain_core_types::dna::Dna;
+use std::collections::HashMap;
+
+impl Conductor {
+    /// Checks the bridges of the given config against the DNAs of its instances:
+    /// * every bridge a DNA declares as required has to be configured with the same handle
+    ///   for every instance of that DNA
+    /// * the callee of a configured bridge has to match the reference of the caller DNA's
+    ///   bridge declaration with the same handle, i.e. have the referenced DNA address or
+    ///   expose the referenced traits with matching function signatures
+    ///
+    /// Configured bridges with handles the caller DNA does not declare are not checked.
+    pub fn check_bridges(&mut self, config: &Configuration) -> Result<(), String> {
+        let mut dnas: HashMap<String, Dna> = HashMap::new();
+        for instance_id in config.instance_ids() {
+            let dna = self.load_instance_dna(&instance_id, config)?;
+            dnas.insert(instance_id, dna);
+        }
+
+        for instance_id in config.instance_ids() {
+            for (zome_name, zome) in dnas[&instance_id].zomes.iter() {
+                for required in zome.get_required_bridges() {
+                    let configured = config.bridges.iter().any(|bridge| {
+                        bridge.caller_id == instance_id && bridge.handle == required.handle
+                    });
+                    if !configured {
+                        return Err(format!(
+                            "Instance \"{}\" is missing the bridge \"{}\" that zome \"{}\" requires",
+                            instance_id, required.handle, zome_name
+                        ));
+                    }
+                }
+            }
+        }
+
+        for bridge in config.bridges.iter() {
+            let callee_dna = &dnas[&bridge.callee_id];
+            for (zome_name, zome) in dnas[&bridge.caller_id].zomes.iter() {
+                for declared in zome.bridges.iter().filter(|b| b.handle == bridge.handle) {
+                    declared.reference.check_callee(callee_dna).map_err(|error| {
+                        format!(
+                            "Bridge \"{}\" from instance \"{}\" to instance \"{}\" does not match its declaration in zome \"{}\": {}",
+                            bridge.handle, bridge.caller_id, bridge.callee_id, zome_name, error
+                        )
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use conductor::base::{
+        tests::{test_key_loader, test_toml},
+        DnaLoader,
+    };
+    use config::load_configuration;
+    use holochain_core_types::{
+        cas::content::{Address, AddressableContent},
+        dna::{
+            bridges::{Bridge, BridgePresence, BridgeReference},
+            fn_declarations::{FnDeclaration, FnParameter, Trait, TraitFns},
+            zome::Zome,
+        },
+        error::HolochainError,
+    };
+    use std::{path::PathBuf, sync::Arc};
+
+    fn hello() -> FnDeclaration {
+        FnDeclaration {
+            name: String::from("hello"),
+            inputs: vec![],
+            outputs: vec![FnParameter::new("greeting", "String")],
+        }
+    }
+
+    fn callee_dna() -> Dna {
+        let mut zome = Zome::default();
+        zome.fn_declarations.push(hello());
+        zome.traits.insert(
+            String::from("greetings"),
+            TraitFns {
+                functions: vec![String::from("hello")],
+            },
+        );
+        let mut dna = Dna::new();
+        dna.zomes.insert(String::from("greeter"), zome);
+        dna
+    }
+
+    /// Loader for the DNAs of test_toml() in which the "bridge-caller" instance's DNA
+    /// declares the given bridges
+    fn dna_loader_with_caller_bridges(bridges: Vec<Bridge>) -> DnaLoader {
+        let loader = Box::new(move |path: &PathBuf| {
+            Ok(match path.to_str().unwrap() {
+                "bridge/callee.dna" => callee_dna(),
+                "bridge/caller.dna" => {
+                    let mut zome = Zome::default();
+                    zome.bridges = bridges.clone();
+                    let mut dna = Dna::new();
+                    dna.zomes.insert(String::from("caller"), zome);
+                    dna
+                }
+                _ => Dna::new(),
+            })
+        })
+            as Box<FnMut(&PathBuf) -> Result<Dna, HolochainError> + Send + Sync>;
+        Arc::new(loader)
+    }
+
+    fn check_with_caller_bridges(bridges: Vec<Bridge>) -> Result<(), String> {
+        let config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        let mut conductor = Conductor::from_config(config.clone());
+        conductor.dna_loader = dna_loader_with_caller_bridges(bridges);
+        conductor.key_loader = test_key_loader();
+        conductor.check_bridges(&config)
+    }
+
+    fn greetings_bridge(presence: BridgePresence, handle: &str, function: FnDeclaration) -> Bridge {
+        Bridge {
+            presence,
+            handle: String::from(handle),
+            reference: BridgeReference::Trait {
+                traits: btreemap! {
+                    String::from("greetings") => Trait { functions: vec![function] }
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn matching_bridges_pass() {
+        assert_eq!(check_with_caller_bridges(vec![]), Ok(()));
+        assert_eq!(
+            check_with_caller_bridges(vec![
+                greetings_bridge(BridgePresence::Required, "test-callee", hello()),
+                greetings_bridge(BridgePresence::Optional, "not-configured", hello()),
+            ]),
+            Ok(())
+        );
+        assert_eq!(
+            check_with_caller_bridges(vec![Bridge {
+                presence: BridgePresence::Required,
+                handle: String::from("test-callee"),
+                reference: BridgeReference::Address {
+                    dna_address: callee_dna().address(),
+                },
+            }]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn missing_required_bridge_fails() {
+        assert_eq!(
+            check_with_caller_bridges(vec![greetings_bridge(
+                BridgePresence::Required,
+                "greeter",
+                hello()
+            )]),
+            Err(String::from(
+                "Instance \"bridge-caller\" is missing the bridge \"greeter\" that zome \"caller\" requires"
+            ))
+        );
+    }
+
+    #[test]
+    fn mismatching_callee_fails() {
+        let mut hello_with_name = hello();
+        hello_with_name.inputs = vec![FnParameter::new("name", "String")];
+        let result = check_with_caller_bridges(vec![greetings_bridge(
+            BridgePresence::Optional,
+            "test-callee",
+            hello_with_name,
+        )]);
+        assert!(result.unwrap_err().starts_with(
+            "Bridge \"test-callee\" from instance \"bridge-caller\" to instance \"test-instance-1\" does not match its declaration in zome \"caller\": No zome implements trait \"greetings\" (zome \"greeter\": function \"hello\" has inputs []"
+        ));
+
+        let result = check_with_caller_bridges(vec![Bridge {
+            presence: BridgePresence::Required,
+            handle: String::from("test-callee"),
+            reference: BridgeReference::Address {
+                dna_address: Address::from("QmOtherDna"),
+            },
+        }]);
+        assert_eq!(
+            result,
+            Err(format!(
+                "Bridge \"test-callee\" from instance \"bridge-caller\" to instance \"test-instance-1\" does not match its declaration in zome \"caller\": DNA hash {} does not match the referenced DNA address QmOtherDna",
+                callee_dna().address()
+            ))
+        );
+    }
+}
@@ -0,0 +1,560 @@
+//! Applying changes of the config file to a running conductor.
+//!
+//! A new configuration gets diffed against the running one and only the affected parts get
+//! (re)started or stopped:
+//! * instances whose instance, DNA or agent configuration or outgoing bridges changed, plus
+//!   all instances that bridge to one of those (since they hold a reference to the callee),
+//! * interfaces whose configuration changed or that serve a (re)started or removed instance,
+//! * UI interfaces whose configuration, bundle or connected DNA interface changed.
+//!
+//! New interfaces and UI interfaces get started, changed ones only get restarted if they
+//! were running.
+//!
+//! An instance gets shut down completely before its replacement gets instantiated, since both
+//! use the same storage and agent. Its DNA and agent key get loaded first though, so if the
+//! new config breaks those the old instance keeps running and the error gets reported.
+use crate::{
+    conductor::{base::notify, Conductor, CONDUCTOR},
+    config::{load_configuration, Configuration},
+};
+use holochain_core_types::error::HolochainError;
+use serde::Serialize;
+use serde_json;
+use static_file_server::StaticServer;
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How often the config file gets checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// IDs of the items of one kind that got touched by a reload
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReloadedItems {
+    pub started: Vec<String>,
+    pub restarted: Vec<String>,
+    pub stopped: Vec<String>,
+}
+
+impl ReloadedItems {
+    pub fn is_empty(&self) -> bool {
+        self.started.is_empty() && self.restarted.is_empty() && self.stopped.is_empty()
+    }
+}
+
+/// Report of what [Conductor::reload_config](../base/struct.Conductor.html) changed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigReload {
+    pub instances: ReloadedItems,
+    pub interfaces: ReloadedItems,
+    pub ui_interfaces: ReloadedItems,
+    /// Settings that changed but only take effect after restarting the conductor
+    pub needs_restart: Vec<String>,
+    /// Errors that kept parts of the new config from being applied
+    pub errors: Vec<String>,
+}
+
+impl ConfigReload {
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+            && self.interfaces.is_empty()
+            && self.ui_interfaces.is_empty()
+            && self.needs_restart.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+fn differs<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() != serde_json::to_value(b).ok()
+}
+
+/// Did anything change that requires re-creating the given instance,
+/// not counting changes of bridge callees?
+fn instance_changed(old: &Configuration, new: &Configuration, id: &String) -> bool {
+    match (old.instance_by_id(id), new.instance_by_id(id)) {
+        (Some(old_instance), Some(new_instance)) => {
+            old_instance != new_instance
+                || old.dna_by_id(&new_instance.dna) != new.dna_by_id(&new_instance.dna)
+                || old.agent_by_id(&new_instance.agent) != new.agent_by_id(&new_instance.agent)
+                || old.bridge_dependencies(id.clone()) != new.bridge_dependencies(id.clone())
+        }
+        _ => true,
+    }
+}
+
+impl Conductor {
+    /// Loads what the given instance needs from outside its storage, i.e. its DNA and agent key,
+    /// so that broken references in a new config get found before the old instance goes away.
+    fn preload_instance(&mut self, id: &String, config: &Configuration) -> Result<(), String> {
+        let instance_config = config
+            .instance_by_id(id)
+            .ok_or(String::from("Instance not found in config"))?;
+        let agent_config = config
+            .agent_by_id(&instance_config.agent)
+            .ok_or(String::from("Agent not found in config"))?;
+        if agent_config
+            .remote_signer(&config.signing_service_uri)?
+            .is_none()
+        {
+            self.get_keybundle_for_agent(&instance_config.agent)?;
+        }
+        self.load_instance_dna(id, config)?;
+        Ok(())
+    }
+
+    /// Applies the given configuration to the running conductor, only touching the instances,
+    /// interfaces, bridges and UI interfaces that are affected by the changes.
+    /// Fails without changing anything if the new config is not consistent or its bridges
+    /// don't match the DNAs (see [check_bridges](#method.check_bridges)).
+    /// Problems with single items don't abort the reload but get reported in the returned
+    /// [ConfigReload](struct.ConfigReload.html).
+    /// The new config does not get saved, since it is expected to come from the config file.
+    pub fn reload_config(
+        &mut self,
+        new_config: Configuration,
+    ) -> Result<ConfigReload, HolochainError> {
+        new_config.check_consistency()?;
+        self.check_bridges(&new_config)?;
+        let old_config = self.config.clone();
+        let mut report = ConfigReload::default();
+
+        for (name, changed) in vec![
+            ("logger", differs(&old_config.logger, &new_config.logger)),
+            ("network", differs(&old_config.network, &new_config.network)),
+            ("metrics", old_config.metrics != new_config.metrics),
+            (
+                "persistence_dir",
+                old_config.persistence_dir != new_config.persistence_dir,
+            ),
+            (
+                "signing_service_uri",
+                old_config.signing_service_uri != new_config.signing_service_uri,
+            ),
+        ] {
+            if changed {
+                report.needs_restart.push(name.to_string());
+            }
+        }
+
+        // Keys of agents whose config changed have to be loaded again
+        for agent in old_config.agents.iter() {
+            if new_config.agent_by_id(&agent.id).as_ref() != Some(agent) {
+                self.agent_keys.remove(&agent.id);
+            }
+        }
+
+        self.config = new_config.clone();
+
+        // Instances:
+        let mut touched_instances = HashSet::new();
+        for id in old_config.instance_ids() {
+            if new_config.instance_by_id(&id).is_none() {
+                self.shutdown_instance(&id);
+                self.instances.remove(&id);
+                touched_instances.insert(id.clone());
+                report.instances.stopped.push(id);
+            }
+        }
+
+        for id in new_config.instance_ids_sorted_by_bridge_dependencies()? {
+            let callee_touched = new_config
+                .bridge_dependencies(id.clone())
+                .iter()
+                .any(|bridge| touched_instances.contains(&bridge.callee_id));
+            let running = self.instances.contains_key(&id);
+            if running && !callee_touched && !instance_changed(&old_config, &new_config, &id) {
+                continue;
+            }
+            touched_instances.insert(id.clone());
+
+            if let Some(missing) = new_config
+                .bridge_dependencies(id.clone())
+                .iter()
+                .find(|bridge| !self.instances.contains_key(&bridge.callee_id))
+            {
+                report.errors.push(format!(
+                    "Could not create instance \"{}\": bridge callee \"{}\" is not running",
+                    id, missing.callee_id
+                ));
+                continue;
+            }
+
+            if let Err(error) = self.preload_instance(&id, &new_config) {
+                report.errors.push(format!(
+                    "Error while trying to create instance \"{}\": {}",
+                    id, error
+                ));
+                continue;
+            }
+
+            // The replacement uses the same storage and agent, so the old action loop and
+            // network have to be gone before it gets created
+            self.shutdown_instance(&id);
+            let replaced = self.instances.remove(&id).is_some();
+            let instance = match self.instantiate_from_config(&id, &new_config, None) {
+                Ok(instance) => instance,
+                Err(error) => {
+                    report.errors.push(format!(
+                        "Error while trying to create instance \"{}\": {}",
+                        id, error
+                    ));
+                    if replaced {
+                        report.instances.stopped.push(id.clone());
+                    }
+                    continue;
+                }
+            };
+            if replaced {
+                report.instances.restarted.push(id.clone());
+            } else {
+                report.instances.started.push(id.clone());
+            }
+            let instance = Arc::new(RwLock::new(instance));
+            self.instances.insert(id.clone(), instance.clone());
+            notify(format!("Starting instance \"{}\"...", id));
+            if let Err(error) = instance.write().unwrap().start() {
+                report.errors.push(format!(
+                    "Error while trying to start instance \"{}\": {}",
+                    id, error
+                ));
+            }
+        }
+
+        // Interfaces:
+        for interface in old_config.interfaces.iter() {
+            if new_config.interface_by_id(&interface.id).is_none() {
+                let _ = self.stop_interface_by_id(&interface.id);
+                report.interfaces.stopped.push(interface.id.clone());
+            }
+        }
+        for interface in new_config.interfaces.iter() {
+            let serves_touched_instance = interface
+                .instances
+                .iter()
+                .any(|instance| touched_instances.contains(&instance.id));
+            match old_config.interface_by_id(&interface.id) {
+                Some(ref old_interface)
+                    if old_interface == interface && !serves_touched_instance => {}
+                Some(_) => {
+                    if self.stop_interface_by_id(&interface.id).is_err() {
+                        // Not running, so the change will take effect when it gets started
+                        continue;
+                    }
+                    match self.start_interface_by_id(&interface.id) {
+                        Ok(()) => report.interfaces.restarted.push(interface.id.clone()),
+                        Err(error) => report.errors.push(error),
+                    }
+                }
+                None => match self.start_interface_by_id(&interface.id) {
+                    Ok(()) => report.interfaces.started.push(interface.id.clone()),
+                    Err(error) => report.errors.push(error),
+                },
+            }
+        }
+
+        // UI interfaces:
+        for ui_interface in old_config.ui_interfaces.iter() {
+            if !new_config
+                .ui_interfaces
+                .iter()
+                .any(|new_ui_interface| new_ui_interface.id == ui_interface.id)
+            {
+                if let Some(mut server) = self.static_servers.remove(&ui_interface.id) {
+                    let _ = server.stop();
+                }
+                report.ui_interfaces.stopped.push(ui_interface.id.clone());
+            }
+        }
+        for ui_interface in new_config.ui_interfaces.iter() {
+            let bundle = new_config
+                .ui_bundle_by_id(&ui_interface.bundle)
+                .expect("config.check_consistency()? jumps out if config is broken");
+            let dna_interface = ui_interface
+                .dna_interface
+                .as_ref()
+                .and_then(|id| new_config.interface_by_id(id));
+            let old_ui_interface = old_config
+                .ui_interfaces
+                .iter()
+                .find(|old_ui_interface| old_ui_interface.id == ui_interface.id);
+            let unchanged = old_ui_interface == Some(ui_interface)
+                && old_config.ui_bundle_by_id(&ui_interface.bundle) == Some(bundle.clone())
+                && ui_interface
+                    .dna_interface
+                    .as_ref()
+                    .and_then(|id| old_config.interface_by_id(id))
+                    == dna_interface;
+            if unchanged {
+                continue;
+            }
+
+            let was_running = match self.static_servers.remove(&ui_interface.id) {
+                Some(mut server) => server.stop().is_ok(),
+                None => false,
+            };
+            let mut server =
+                StaticServer::from_configs(ui_interface.clone(), bundle, dna_interface);
+            if old_ui_interface.is_some() && !was_running {
+                self.static_servers.insert(ui_interface.id.clone(), server);
+                continue;
+            }
+            match server.start() {
+                Ok(()) if was_running => {
+                    report.ui_interfaces.restarted.push(ui_interface.id.clone())
+                }
+                Ok(()) => report.ui_interfaces.started.push(ui_interface.id.clone()),
+                Err(error) => report.errors.push(format!(
+                    "Error while trying to start UI interface \"{}\": {}",
+                    ui_interface.id, error
+                )),
+            }
+            self.static_servers.insert(ui_interface.id.clone(), server);
+        }
+
+        Ok(report)
+    }
+
+    /// Reads the config file at the given path and applies it with
+    /// [reload_config](#method.reload_config).
+    pub fn reload_config_from_file(
+        &mut self,
+        path: &PathBuf,
+    ) -> Result<ConfigReload, HolochainError> {
+        let contents = fs::read_to_string(path)?;
+        let config = load_configuration::<Configuration>(&contents)?;
+        self.reload_config(config)
+    }
+
+    /// Starts a thread that watches the given config file and reloads the mounted conductor
+    /// (see [CONDUCTOR](../base/struct.CONDUCTOR.html)) whenever the file changes.
+    /// Errors get reported but leave the running instances untouched.
+    pub fn watch_config_file(&mut self, path: PathBuf) {
+        if let Some(kill_switch) = self.config_watcher.take() {
+            let _ = kill_switch.send(());
+        }
+        self.config_watcher = Some(spawn_config_watcher(path));
+    }
+
+    /// Stops the thread started with [watch_config_file](#method.watch_config_file).
+    pub fn stop_watching_config_file(&mut self) {
+        if let Some(kill_switch) = self.config_watcher.take() {
+            let _ = kill_switch.send(());
+        }
+    }
+}
+
+fn notify_reload(path: &PathBuf, reload: &ConfigReload) {
+    let items = vec![
+        ("instances", &reload.instances),
+        ("interfaces", &reload.interfaces),
+        ("UI interfaces", &reload.ui_interfaces),
+    ];
+    for (kind, items) in items {
+        for (action, ids) in vec![
+            ("Started", &items.started),
+            ("Restarted", &items.restarted),
+            ("Stopped", &items.stopped),
+        ] {
+            if !ids.is_empty() {
+                notify(format!("{} {}: {}", action, kind, ids.join(", ")));
+            }
+        }
+    }
+    if !reload.needs_restart.is_empty() {
+        notify(format!(
+            "Changes to {} in {} only take effect after restarting the conductor",
+            reload.needs_restart.join(", "),
+            path.display()
+        ));
+    }
+    for error in reload.errors.iter() {
+        notify(format!("Error while reloading config: {}", error));
+    }
+}
+
+fn spawn_config_watcher(path: PathBuf) -> Sender<()> {
+    let (tx, rx) = channel();
+    thread::Builder::new()
+        .name(format!("conductor-config-watcher: {}", path.display()))
+        .spawn(move || {
+            let mut last_contents = fs::read_to_string(&path).ok();
+            loop {
+                match rx.recv_timeout(CONFIG_POLL_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => (),
+                    _ => break,
+                }
+                let contents = fs::read_to_string(&path).ok();
+                if contents.is_none() || contents == last_contents {
+                    continue;
+                }
+                last_contents = contents;
+
+                let mut conductor_guard = CONDUCTOR.lock().unwrap();
+                let conductor = match conductor_guard.as_mut() {
+                    Some(conductor) => conductor,
+                    None => continue,
+                };
+                match conductor.reload_config_from_file(&path) {
+                    Ok(ref reload) if reload.is_empty() => (),
+                    Ok(reload) => {
+                        notify(format!("Reloaded config from {}", path.display()));
+                        notify_reload(&path, &reload);
+                    }
+                    Err(error) => notify(format!(
+                        "Not applying changed config {}: {}",
+                        path.display(),
+                        error
+                    )),
+                }
+            }
+        })
+        .expect("Could not spawn thread for config watcher");
+    tx
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use conductor::base::tests::{test_conductor, test_toml};
+    use config::load_configuration;
+    use holochain_core::health::Probe;
+
+    fn reload(conductor: &mut Conductor, toml: &str) -> ConfigReload {
+        let config = load_configuration::<Configuration>(toml).unwrap();
+        conductor.reload_config(config).unwrap()
+    }
+
+    #[test]
+    fn reload_without_changes_does_nothing() {
+        let mut conductor = test_conductor();
+        assert_eq!(
+            reload(&mut conductor, &test_toml()),
+            ConfigReload::default()
+        );
+    }
+
+    #[test]
+    fn reload_rejects_inconsistent_config() {
+        let mut conductor = test_conductor();
+        let toml = test_toml().replace("agent = \"test-agent-2\"", "agent = \"no-agent\"");
+        let config = load_configuration::<Configuration>(&toml).unwrap();
+        assert!(conductor.reload_config(config).is_err());
+        assert_eq!(
+            conductor
+                .config()
+                .instance_by_id("test-instance-2")
+                .unwrap()
+                .agent,
+            "test-agent-2"
+        );
+        assert_eq!(conductor.instances().len(), 3);
+    }
+
+    #[test]
+    fn reload_restarts_changed_instances_and_their_bridge_callers() {
+        let mut conductor = test_conductor();
+        let test_instance_1 = conductor
+            .instances()
+            .get("test-instance-1")
+            .unwrap()
+            .clone();
+        let test_instance_2 = conductor
+            .instances()
+            .get("test-instance-2")
+            .unwrap()
+            .clone();
+        let mut config = conductor.config();
+        config
+            .instances
+            .iter_mut()
+            .find(|instance| instance.id == "test-instance-2")
+            .unwrap()
+            .agent = String::from("test-agent-1");
+
+        let reload = conductor.reload_config(config).unwrap();
+
+        assert_eq!(
+            reload.instances.restarted,
+            vec![
+                String::from("test-instance-2"),
+                String::from("bridge-caller")
+            ]
+        );
+        assert!(reload.instances.started.is_empty());
+        assert!(reload.instances.stopped.is_empty());
+        assert_eq!(reload.errors, Vec::<String>::new());
+        // Interfaces of the test conductor are not running so they don't get started
+        assert!(reload.interfaces.is_empty());
+
+        // Replaced instances got shut down
+        let old_probes = test_instance_2
+            .read()
+            .unwrap()
+            .probe(Duration::from_secs(60));
+        assert_eq!(old_probes.action_loop, Probe::Dead);
+
+        // Untouched instances keep running
+        assert!(Arc::ptr_eq(
+            conductor.instances().get("test-instance-1").unwrap(),
+            &test_instance_1
+        ));
+        assert_eq!(
+            conductor
+                .config()
+                .instance_by_id("test-instance-2")
+                .unwrap()
+                .agent,
+            "test-agent-1"
+        );
+    }
+
+    #[test]
+    fn reload_stops_removed_and_keeps_failed_instances() {
+        let mut conductor = test_conductor();
+        let test_instance_1 = conductor
+            .instances()
+            .get("test-instance-1")
+            .unwrap()
+            .clone();
+        let mut config = conductor.config();
+        config
+            .instances
+            .retain(|instance| instance.id != "bridge-caller");
+        config
+            .bridges
+            .retain(|bridge| bridge.caller_id != "bridge-caller");
+        config
+            .agents
+            .iter_mut()
+            .find(|agent| agent.id == "test-agent-1")
+            .unwrap()
+            .key_file = String::from("unknown.key");
+
+        let reload = conductor.reload_config(config).unwrap();
+
+        assert_eq!(
+            reload.instances.stopped,
+            vec![String::from("bridge-caller")]
+        );
+        assert!(conductor.instances().get("bridge-caller").is_none());
+        assert_eq!(reload.errors.len(), 1);
+        assert!(reload.errors[0].contains("test-instance-1"));
+        assert!(Arc::ptr_eq(
+            conductor.instances().get("test-instance-1").unwrap(),
+            &test_instance_1
+        ));
+        // test-instance-2 bridges to the touched test-instance-1
+        assert_eq!(
+            reload.instances.restarted,
+            vec![String::from("test-instance-2")]
+        );
+    }
+}
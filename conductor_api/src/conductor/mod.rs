@@ -1,10 +1,13 @@
 pub mod admin;
 pub mod base;
+pub mod bridges;
+pub mod config_reload;
+pub mod supervisor;
 pub mod ui_admin;
 
 pub use self::{
     admin::ConductorAdmin,
-    base::{mount_conductor_from_config, Conductor, CONDUCTOR},
+    base::{mount_conductor_from_config, Conductor, CONDUCTOR, DEFAULT_SHUTDOWN_TIMEOUT},
     ui_admin::ConductorUiAdmin,
 };
 
@@ -0,0 +1,343 @@
+//! Supervision of running instances.
+//!
+//! A panic in an instance's action loop or network worker does not bring down the conductor,
+//! it just leaves a half-dead instance behind. The supervisor probes all active instances
+//! periodically (see `holochain_core::health`) and restarts those that failed according to
+//! their [RestartPolicy](../../config/struct.RestartPolicy.html). Instances that bridge to a
+//! restarted instance and interfaces that serve one get restarted with it, since they hold a
+//! reference to the old instance.
+//!
+//! Changes of an instance's health status get logged and sent as `Signal::InstanceHealth`.
+use crate::conductor::{base::notify, Conductor, CONDUCTOR};
+use holochain_core::{
+    health::{HealthStatus, InstanceHealth},
+    signal::Signal,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Supervision state of one instance
+pub struct Supervision {
+    health: InstanceHealth,
+    consecutive_failures: u32,
+    restart_at: Option<Instant>,
+    healthy_since: Option<Instant>,
+}
+
+impl Supervision {
+    fn new() -> Self {
+        Supervision {
+            health: InstanceHealth {
+                status: HealthStatus::Healthy,
+                probes: None,
+                restarts: 0,
+                last_error: None,
+            },
+            consecutive_failures: 0,
+            restart_at: None,
+            healthy_since: None,
+        }
+    }
+}
+
+impl Conductor {
+    /// Health of all instances as far as the supervisor knows it.
+    /// Instances that did not get probed yet are reported without probe results.
+    pub fn instance_health(&self) -> BTreeMap<String, InstanceHealth> {
+        self.instances
+            .iter()
+            .map(|(id, instance)| {
+                let health = match self.supervision.get(id) {
+                    Some(supervision) => supervision.health.clone(),
+                    None => {
+                        let mut health = Supervision::new().health;
+                        if !instance.read().unwrap().active() {
+                            health.status = HealthStatus::Stopped;
+                        }
+                        health
+                    }
+                };
+                (id.clone(), health)
+            })
+            .collect()
+    }
+
+    /// Probes all instances once and restarts failed ones whose backoff is over.
+    /// Returns the IDs of the instances whose health status changed.
+    pub fn supervise(&mut self) -> Vec<String> {
+        let stuck_after = Duration::from_millis(self.config.supervisor.stuck_after_ms);
+        let now = Instant::now();
+        let mut ids: Vec<String> = self.instances.keys().cloned().collect();
+        ids.sort();
+        self.supervision.retain(|id, _| ids.contains(id));
+
+        let mut changed = Vec::new();
+        let mut due = Vec::new();
+        for id in ids {
+            let (active, probes) = {
+                let instance = self.instances[&id].read().unwrap();
+                (instance.active(), instance.probe(stuck_after))
+            };
+            let policy = self
+                .config
+                .instance_by_id(&id)
+                .and_then(|instance_config| instance_config.restart_policy)
+                .unwrap_or_default();
+            let supervision = self
+                .supervision
+                .entry(id.clone())
+                .or_insert_with(Supervision::new);
+            let previous_status = supervision.health.status.clone();
+            let alive = probes.is_alive();
+            supervision.health.probes = Some(probes);
+
+            // An instance whose restart failed got shut down but still waits for the next try
+            if !active && supervision.health.last_error.is_none() {
+                supervision.health.status = HealthStatus::Stopped;
+                supervision.restart_at = None;
+            } else if alive {
+                if supervision.health.status != HealthStatus::Healthy
+                    || supervision.healthy_since.is_none()
+                {
+                    supervision.healthy_since = Some(now);
+                }
+                supervision.health.status = HealthStatus::Healthy;
+                supervision.restart_at = None;
+                let healthy_for = now - supervision.healthy_since.unwrap_or(now);
+                if healthy_for >= Duration::from_millis(policy.max_backoff_ms) {
+                    supervision.consecutive_failures = 0;
+                }
+            } else if previous_status == HealthStatus::Failed {
+                // Stays down until it gets restarted by hand
+            } else if !policy.allows_restart(supervision.health.restarts) {
+                supervision.health.status = HealthStatus::Failed;
+                supervision.restart_at = None;
+            } else {
+                supervision.health.status = HealthStatus::Restarting;
+                supervision.healthy_since = None;
+                match supervision.restart_at {
+                    None => {
+                        supervision.restart_at =
+                            Some(now + policy.backoff(supervision.consecutive_failures))
+                    }
+                    Some(restart_at) if restart_at <= now => due.push(id.clone()),
+                    Some(_) => (),
+                }
+            }
+
+            if supervision.health.status != previous_status {
+                changed.push(id);
+            }
+        }
+
+        for id in due {
+            let result = self.restart_with_dependents(&id);
+            let policy = self
+                .config
+                .instance_by_id(&id)
+                .and_then(|instance_config| instance_config.restart_policy)
+                .unwrap_or_default();
+            if let Some(supervision) = self.supervision.get_mut(&id) {
+                supervision.health.restarts += 1;
+                supervision.consecutive_failures += 1;
+                match result {
+                    Ok(_) => {
+                        notify(format!("Restarted failed instance \"{}\"", id));
+                        supervision.health.last_error = None;
+                        supervision.restart_at = None;
+                    }
+                    Err(error) => {
+                        notify(format!(
+                            "Error while trying to restart instance \"{}\": {}",
+                            id, error
+                        ));
+                        supervision.health.last_error = Some(error);
+                        supervision.restart_at =
+                            Some(now + policy.backoff(supervision.consecutive_failures));
+                    }
+                }
+            }
+        }
+
+        for id in changed.iter() {
+            self.report_health_change(id);
+        }
+        changed
+    }
+
+    fn report_health_change(&self, id: &String) {
+        let health = match self.supervision.get(id) {
+            Some(supervision) => supervision.health.clone(),
+            None => return,
+        };
+        notify(format!(
+            "Health of instance \"{}\" changed to {:?} (probes: {:?})",
+            id, health.status, health.probes
+        ));
+        if let Some(ref signal_tx) = self.signal_tx {
+            // Don't block the supervisor if nobody reads the signals
+            let _ = signal_tx.try_send(Signal::InstanceHealth(id.clone(), health));
+        }
+    }
+
+    /// Replaces the given instance with a new one and does the same for all instances that
+    /// bridge to it (transitively) and restarts interfaces that serve one of them.
+    /// Returns the IDs of the restarted instances.
+    fn restart_with_dependents(&mut self, id: &String) -> Result<Vec<String>, String> {
+        let config = self.config.clone();
+        let mut restarted: Vec<String> = Vec::new();
+        for instance_id in config
+            .instance_ids_sorted_by_bridge_dependencies()
+            .map_err(|error| error.to_string())?
+        {
+            let bridges_to_restarted = config
+                .bridge_dependencies(instance_id.clone())
+                .iter()
+                .any(|bridge| restarted.contains(&bridge.callee_id));
+            if (instance_id != *id && !bridges_to_restarted)
+                || !self.instances.contains_key(&instance_id)
+            {
+                continue;
+            }
+            let was_active =
+                instance_id == *id || self.instances[&instance_id].read().unwrap().active();
+            // The replacement uses the same storage and agent, so the old action loop and
+            // network have to be gone before it starts
+            self.shutdown_instance(&instance_id);
+            let instance = self.instantiate_from_config(&instance_id, &config, None)?;
+            let instance = Arc::new(RwLock::new(instance));
+            if was_active {
+                instance
+                    .write()
+                    .unwrap()
+                    .start()
+                    .map_err(|error| error.to_string())?;
+            }
+            self.instances.insert(instance_id.clone(), instance);
+            restarted.push(instance_id);
+        }
+
+        for interface in config.interfaces.iter() {
+            let serves_restarted = interface
+                .instances
+                .iter()
+                .any(|instance| restarted.contains(&instance.id));
+            if serves_restarted && self.stop_interface_by_id(&interface.id).is_ok() {
+                self.start_interface_by_id(&interface.id)?;
+            }
+        }
+        Ok(restarted)
+    }
+
+    /// Starts a thread that calls [supervise](#method.supervise) on the mounted conductor
+    /// (see [CONDUCTOR](../base/struct.CONDUCTOR.html)) every `probe_interval_ms`.
+    pub fn start_supervisor(&mut self) {
+        self.stop_supervisor();
+        self.supervisor = Some(spawn_supervisor());
+    }
+
+    /// Stops the thread started with [start_supervisor](#method.start_supervisor).
+    pub fn stop_supervisor(&mut self) {
+        if let Some(kill_switch) = self.supervisor.take() {
+            let _ = kill_switch.send(());
+        }
+    }
+}
+
+fn spawn_supervisor() -> Sender<()> {
+    let (tx, rx) = channel();
+    thread::Builder::new()
+        .name(String::from("conductor-supervisor"))
+        .spawn(move || {
+            let mut interval = Duration::from_millis(1000);
+            loop {
+                match rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => (),
+                    _ => break,
+                }
+                let mut conductor_guard = CONDUCTOR.lock().unwrap();
+                if let Some(conductor) = conductor_guard.as_mut() {
+                    conductor.supervise();
+                    interval = Duration::from_millis(conductor.config.supervisor.probe_interval_ms);
+                }
+            }
+        })
+        .expect("Could not spawn thread for supervisor");
+    tx
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use conductor::base::tests::test_conductor;
+    use config::{RestartMode, RestartPolicy};
+    use holochain_core::health::Probe;
+
+    #[test]
+    fn healthy_instances_stay_untouched() {
+        let mut conductor = test_conductor();
+        let ids: Vec<String> = conductor.instances.keys().cloned().collect();
+        for (_, health) in conductor.instance_health() {
+            assert_eq!(health.status, HealthStatus::Stopped);
+            assert_eq!(health.probes, None);
+        }
+
+        conductor.start_all_instances().unwrap();
+        conductor.supervise();
+        let health = conductor.instance_health();
+        for id in ids.iter() {
+            let instance_health = &health[id];
+            assert_eq!(instance_health.status, HealthStatus::Healthy);
+            assert!(instance_health.probes.as_ref().unwrap().is_alive());
+            assert_eq!(instance_health.restarts, 0);
+        }
+        let instance = conductor.instances[&ids[0]].clone();
+        conductor.supervise();
+        assert!(Arc::ptr_eq(&instance, &conductor.instances[&ids[0]]));
+    }
+
+    #[test]
+    fn restart_shuts_down_replaced_instance() {
+        let mut conductor = test_conductor();
+        conductor.start_all_instances().unwrap();
+        let id = String::from("test-instance-1");
+        let old_instance = conductor.instances[&id].clone();
+
+        let restarted = conductor.restart_with_dependents(&id).unwrap();
+        assert!(restarted.contains(&id));
+        assert!(!Arc::ptr_eq(&old_instance, &conductor.instances[&id]));
+        assert!(conductor.instances[&id].read().unwrap().active());
+
+        let old_probes = old_instance.read().unwrap().probe(Duration::from_secs(60));
+        assert_eq!(old_probes.action_loop, Probe::Dead);
+        assert_eq!(old_probes.network, Probe::NotReady);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RestartPolicy {
+            mode: RestartMode::OnFailure,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            max_restarts: Some(2),
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(10), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(100), Duration::from_millis(1000));
+        assert!(policy.allows_restart(1));
+        assert!(!policy.allows_restart(2));
+        assert!(!RestartPolicy {
+            mode: RestartMode::Never,
+            ..Default::default()
+        }
+        .allows_restart(0));
+    }
+}
@@ -1,10 +1,10 @@
 use crate::{
     conductor::{base::notify, Conductor},
-    config::{UiBundleConfiguration, UiInterfaceConfiguration},
+    config::{ui_bundle_hash, UiBundleConfiguration, UiInterfaceConfiguration},
     static_file_server::StaticServer,
 };
 use error::HolochainInstanceError;
-use holochain_core_types::error::HolochainError;
+use holochain_core_types::{error::HolochainError, hash::HashString};
 use std::{path::PathBuf, sync::Arc};
 
 pub trait ConductorUiAdmin {
@@ -13,6 +13,7 @@ pub trait ConductorUiAdmin {
         path: PathBuf,
         id: &String,
         copy: bool,
+        expected_hash: Option<HashString>,
     ) -> Result<(), HolochainError>;
     fn uninstall_ui_bundle(&mut self, id: &String) -> Result<(), HolochainError>;
 
@@ -27,12 +28,29 @@ pub trait ConductorUiAdmin {
 }
 
 impl ConductorUiAdmin for Conductor {
+    /// Installs the UI bundle in the given directory, which gets copied to the conductor's
+    /// persistence dir if `copy` is true.
+    ///
+    /// If an expected hash is given, the bundle only gets installed if its
+    /// [ui_bundle_hash](../../config/fn.ui_bundle_hash.html) matches. The hash then gets
+    /// recorded in the bundle's config, so the bundle also gets checked before it is served.
     fn install_ui_bundle_from_file(
         &mut self,
         path: PathBuf,
         id: &String,
         copy: bool,
+        expected_hash: Option<HashString>,
     ) -> Result<(), HolochainError> {
+        if let Some(ref expected_hash) = expected_hash {
+            let hash = ui_bundle_hash(&path)?;
+            if hash != *expected_hash {
+                return Err(HolochainError::ConfigError(format!(
+                    "UI bundle hash does not match expected hash!\n{} != {}",
+                    hash, expected_hash
+                )));
+            }
+        }
+
         let path = match copy {
             true => {
                 let dest = self.config.persistence_dir.join("static").join(id);
@@ -57,7 +75,7 @@ impl ConductorUiAdmin for Conductor {
         let new_bundle = UiBundleConfiguration {
             id: id.to_string(),
             root_dir: path_string.into(),
-            hash: None,
+            hash: expected_hash.map(String::from),
         };
 
         let mut new_config = self.config.clone();
@@ -172,8 +190,13 @@ impl ConductorUiAdmin for Conductor {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    extern crate tempfile;
+    use self::tempfile::tempdir;
     use conductor::{admin::tests::*, base::UiDirCopier};
-    use std::{fs::File, io::Read};
+    use std::{
+        fs::{self, File},
+        io::Read,
+    };
 
     pub fn test_ui_copier() -> UiDirCopier {
         let copier = Box::new(|_source: &PathBuf, _dest: &PathBuf| Ok(()))
@@ -190,7 +213,8 @@ pub mod tests {
             conductor.install_ui_bundle_from_file(
                 bundle_path,
                 &"test-bundle-id".to_string(),
-                false
+                false,
+                None
             ),
             Ok(())
         );
@@ -233,7 +257,12 @@ root_dir = '.'"#,
 
         let bundle_path = PathBuf::from(".");
         assert_eq!(
-            conductor.install_ui_bundle_from_file(bundle_path, &"test-bundle-id".to_string(), true),
+            conductor.install_ui_bundle_from_file(
+                bundle_path,
+                &"test-bundle-id".to_string(),
+                true,
+                None
+            ),
             Ok(())
         );
 
@@ -287,7 +316,8 @@ id = 'test-bundle-id'"#,
             conductor.install_ui_bundle_from_file(
                 bundle_path,
                 &"test-bundle-id".to_string(),
-                false
+                false,
+                None
             ),
             Ok(())
         );
@@ -324,6 +354,7 @@ id = 'test-bundle-id'"#,
                 port: 4000,
                 bundle: "test-bundle-id".into(),
                 dna_interface: None,
+                server: None,
             }),
             Err(HolochainError::ErrorGeneric(
                 "UI bundle configuration test-bundle-id not found, mentioned in UI interface test-ui-interface-id".into()
@@ -335,7 +366,8 @@ id = 'test-bundle-id'"#,
             conductor.install_ui_bundle_from_file(
                 bundle_path,
                 &"test-bundle-id".to_string(),
-                false
+                false,
+                None
             ),
             Ok(())
         );
@@ -346,6 +378,7 @@ id = 'test-bundle-id'"#,
                 port: 4000,
                 bundle: "test-bundle-id".into(),
                 dna_interface: None,
+                server: None,
             }),
             Ok(())
         );
@@ -399,7 +432,8 @@ port = 4000"#,
             conductor.install_ui_bundle_from_file(
                 bundle_path,
                 &"test-bundle-id".to_string(),
-                false
+                false,
+                None
             ),
             Ok(())
         );
@@ -410,6 +444,7 @@ port = 4000"#,
                 port: 4000,
                 bundle: "test-bundle-id".into(),
                 dna_interface: None,
+                server: None,
             }),
             Ok(())
         );
@@ -458,7 +493,8 @@ root_dir = '.'"#,
             conductor.install_ui_bundle_from_file(
                 bundle_path,
                 &"test-bundle-id".to_string(),
-                false
+                false,
+                None
             ),
             Ok(())
         );
@@ -469,6 +505,7 @@ root_dir = '.'"#,
                 port: 4000,
                 bundle: "test-bundle-id".into(),
                 dna_interface: None,
+                server: None,
             }),
             Ok(())
         );
@@ -489,7 +526,8 @@ root_dir = '.'"#,
             conductor.install_ui_bundle_from_file(
                 bundle_path,
                 &"test-bundle-id".to_string(),
-                false
+                false,
+                None
             ),
             Ok(())
         );
@@ -500,6 +538,7 @@ root_dir = '.'"#,
                 port: 4001,
                 bundle: "test-bundle-id".into(),
                 dna_interface: None,
+                server: None,
             }),
             Ok(())
         );
@@ -521,4 +560,55 @@ root_dir = '.'"#,
             Ok(())
         );
     }
+
+    #[test]
+    fn test_ui_bundle_hash_gets_checked() {
+        let test_name = "test_ui_bundle_hash_gets_checked";
+        let mut conductor = create_test_conductor(test_name, 3006);
+        let bundle_dir = tempdir().unwrap();
+        let index_path = bundle_dir.path().join("index.html");
+        fs::write(&index_path, "<html></html>").unwrap();
+        let hash = ui_bundle_hash(bundle_dir.path()).unwrap();
+
+        assert_eq!(
+            conductor.install_ui_bundle_from_file(
+                bundle_dir.path().to_path_buf(),
+                &"test-bundle-id".to_string(),
+                false,
+                Some("QmWrongHash".into())
+            ),
+            Err(HolochainError::ConfigError(format!(
+                "UI bundle hash does not match expected hash!\n{} != QmWrongHash",
+                hash
+            )))
+        );
+        assert_eq!(
+            conductor.install_ui_bundle_from_file(
+                bundle_dir.path().to_path_buf(),
+                &"test-bundle-id".to_string(),
+                false,
+                Some(hash.clone())
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            conductor.config().ui_bundles[0].hash,
+            Some(hash.to_string())
+        );
+
+        assert_eq!(
+            conductor.add_ui_interface(UiInterfaceConfiguration {
+                id: "test-ui-interface-id".into(),
+                port: 4002,
+                bundle: "test-bundle-id".into(),
+                dna_interface: None,
+                server: None,
+            }),
+            Ok(())
+        );
+        fs::write(&index_path, "<html>changed</html>").unwrap();
+        assert!(conductor
+            .start_ui_interface(&"test-ui-interface-id".to_string())
+            .is_err());
+    }
 }
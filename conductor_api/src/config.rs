@@ -1,4 +1,4 @@
-use crate::logger::LogRules;
+use crate::logger::{LogFileConfiguration, LogFormat, LogRules};
 /// Conductor Configuration
 /// This module provides structs that represent the different aspects of how
 /// a conductor can be configured.
@@ -16,11 +16,23 @@ use holochain_core_types::{
     agent::{AgentId, Base32},
     dna::Dna,
     error::{HcResult, HolochainError},
+    hash::HashString,
     json::JsonString,
 };
+use json_patch;
+use multihash::Hash;
 use petgraph::{algo::toposort, graph::DiGraph, prelude::NodeIndex};
 use serde::Deserialize;
-use std::{collections::HashMap, convert::TryFrom, env, fs::File, io::prelude::*, path::PathBuf};
+use serde_json;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    env,
+    fs::{self, File},
+    io::prelude::*,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use toml;
 
 /// Main conductor configuration struct
@@ -58,14 +70,20 @@ pub struct Configuration {
     /// Configuration options for the network module n3h. Optional.
     #[serde(default)]
     pub network: Option<NetworkConfig>,
+    /// HTTP endpoint that serves metrics of all instances in Prometheus text format. Optional.
+    #[serde(default)]
+    pub metrics: Option<MetricsConfiguration>,
+    /// How the conductor probes its instances for liveness. Optional.
+    #[serde(default)]
+    pub supervisor: SupervisorConfiguration,
     /// where to persist the config file and DNAs. Optional.
     #[serde(default = "default_persistence_dir")]
     pub persistence_dir: PathBuf,
 
-    /// Optional URI for a websocket connection to an outsourced signing service.
+    /// Optional URI of an outsourced HTTP signing service.
     /// Bootstrapping step for Holo closed-alpha.
     /// If set, all agents with holo_remote_key = true will be emulated by asking for signatures
-    /// over this websocket.
+    /// from this service. See AgentConfiguration::signer for other remote signers.
     pub signing_service_uri: Option<String>,
 }
 
@@ -78,6 +96,7 @@ pub fn default_persistence_dir() -> PathBuf {
 
 /// There might be different kinds of loggers in the future.
 /// Currently there is a "debug" and "simple" logger.
+/// The debug logger writes to stdout in the given format and optionally to a log file.
 /// TODO: make this an enum
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct LoggerConfiguration {
@@ -85,7 +104,10 @@ pub struct LoggerConfiguration {
     pub logger_type: String,
     #[serde(default)]
     pub rules: LogRules,
-    //    pub file: Option<String>,
+    #[serde(default)]
+    pub format: LogFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<LogFileConfiguration>,
 }
 
 impl Default for LoggerConfiguration {
@@ -93,6 +115,8 @@ impl Default for LoggerConfiguration {
         LoggerConfiguration {
             logger_type: "debug".into(),
             rules: Default::default(),
+            format: Default::default(),
+            file: None,
         }
     }
 }
@@ -101,6 +125,9 @@ impl Configuration {
     /// This function basically checks if self is a semantically valid configuration.
     /// This mainly means checking for consistency between config structs that reference others.
     pub fn check_consistency(&self) -> Result<(), String> {
+        for ref agent in self.agents.iter() {
+            agent.remote_signer(&self.signing_service_uri)?;
+        }
         for ref instance in self.instances.iter() {
             self.agent_by_id(&instance.agent).is_some().ok_or_else(|| {
                 format!(
@@ -116,6 +143,18 @@ impl Configuration {
             })?;
         }
         for ref interface in self.interfaces.iter() {
+            if let InterfaceDriver::DomainSocket {
+                ref permissions, ..
+            } = interface.driver
+            {
+                if cfg!(not(unix)) {
+                    return Err(format!(
+                        "Interface \"{}\" uses a domain socket, which is only supported on Unix",
+                        interface.id
+                    ));
+                }
+                socket_permissions(permissions)?;
+            }
             for ref instance in interface.instances.iter() {
                 self.instance_by_id(&instance.id).is_some().ok_or_else(|| {
                     format!(
@@ -124,6 +163,22 @@ impl Configuration {
                     )
                 })?;
             }
+            for ref token in interface.tokens.iter() {
+                (token.token.is_some() != token.token_hash.is_some()).ok_or_else(|| {
+                    format!(
+                        "Tokens of interface \"{}\" need either a token or a token_hash",
+                        interface.id
+                    )
+                })?;
+                for instance_id in token.instances.iter().flatten() {
+                    self.instance_by_id(instance_id).is_some().ok_or_else(|| {
+                        format!(
+                            "Instance configuration \"{}\" not found, mentioned in token of interface \"{}\"",
+                            instance_id, interface.id
+                        )
+                    })?;
+                }
+            }
         }
 
         for ref bridge in self.bridges.iter() {
@@ -313,6 +368,84 @@ pub struct AgentConfiguration {
     /// If set to true conductor will ignore key_file and instead use the remote signer
     /// accessible through signing_service_uri to request signatures.
     pub holo_remote_key: Option<bool>,
+    /// Remote signer to use instead of key_file, see
+    /// [SignerConfiguration](enum.SignerConfiguration.html). Takes precedence over
+    /// holo_remote_key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<SignerConfiguration>,
+}
+
+impl AgentConfiguration {
+    /// The remote signer this agent uses, if any. Agents with holo_remote_key = true use the
+    /// HTTP signing service at the given signing_service_uri.
+    pub fn remote_signer(
+        &self,
+        signing_service_uri: &Option<String>,
+    ) -> Result<Option<SignerConfiguration>, HolochainError> {
+        match self.signer {
+            Some(SignerConfiguration::DomainSocket { .. }) if cfg!(not(unix)) => {
+                Err(HolochainError::ConfigError(format!(
+                    "Agent \"{}\" uses a domain socket signer, which is only supported on Unix",
+                    self.id
+                )))
+            }
+            Some(ref signer) => Ok(Some(signer.clone())),
+            None if self.holo_remote_key == Some(true) => signing_service_uri
+                .clone()
+                .map(|url| {
+                    Some(SignerConfiguration::Http {
+                        url,
+                        token: None,
+                        timeout_ms: default_signer_timeout_ms(),
+                    })
+                })
+                .ok_or_else(|| {
+                    HolochainError::ConfigError(format!(
+                        "Agent \"{}\" has holo_remote_key set but there is no signing_service_uri",
+                        self.id
+                    ))
+                }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A signer outside of the conductor that holds the agent's private key.
+/// Requests can be authenticated with a token, which the signer has to check.
+/// `timeout_ms` limits the whole request, from connecting to receiving the signature, and
+/// defaults to 10 seconds.
+/// Signatures get verified against the agent's public_address before they are used.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SignerConfiguration {
+    /// HTTP signing service as used by Holo. Sends the token as bearer token.
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        #[serde(default = "default_signer_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// JSON-RPC over a websocket, `url` is a ws:// or wss:// URL
+    Websocket {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        #[serde(default = "default_signer_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// Newline delimited JSON-RPC over the Unix domain socket at `file`, only on Unix
+    DomainSocket {
+        file: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        #[serde(default = "default_signer_timeout_ms")]
+        timeout_ms: u64,
+    },
+}
+
+pub fn default_signer_timeout_ms() -> u64 {
+    10000
 }
 
 impl From<AgentConfiguration> for AgentId {
@@ -323,14 +456,32 @@ impl From<AgentConfiguration> for AgentId {
 }
 
 /// A DNA is represented by a DNA file.
-/// A hash can optionally be provided, which could be used to validate that the DNA being installed
-/// is the DNA that was intended to be installed.
+/// A hash can optionally be provided, in which case instances of the DNA only get created if the
+/// DNA's address (after applying the overrides below) equals that hash.
+/// `uuid` and `properties` override the values of the DNA file (properties get merged), so the
+/// same DNA file can be used to run separate networks. This changes the DNA's hash.
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct DnaConfiguration {
     pub id: String,
     pub file: String,
     #[serde(default)]
     pub hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<serde_json::Value>,
+}
+
+impl DnaConfiguration {
+    /// Applies the `uuid` and `properties` overrides to the given DNA.
+    pub fn apply_overrides(&self, dna: &mut Dna) {
+        if let Some(ref uuid) = self.uuid {
+            dna.uuid = uuid.clone();
+        }
+        if let Some(ref properties) = self.properties {
+            json_patch::merge(&mut dna.properties, properties);
+        }
+    }
 }
 
 impl TryFrom<DnaConfiguration> for Dna {
@@ -345,12 +496,84 @@ impl TryFrom<DnaConfiguration> for Dna {
 
 /// An instance combines a DNA with an agent.
 /// Each instance has its own storage configuration.
+/// Optional log rules replace the logger's rules for this instance's messages.
+/// The restart policy defaults to restarting failed instances with backoff,
+/// see [RestartPolicy](struct.RestartPolicy.html).
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct InstanceConfiguration {
     pub id: String,
     pub dna: String,
     pub agent: String,
     pub storage: StorageConfiguration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_rules: Option<LogRules>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<RestartPolicy>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartMode {
+    /// Failed instances stay down (but get reported)
+    Never,
+    OnFailure,
+}
+
+/// Decides if and when the supervisor restarts an instance that failed its liveness probes.
+/// The delay before a restart starts at `initial_backoff_ms` and doubles with every
+/// consecutive failure up to `max_backoff_ms`. Failures count as consecutive unless the
+/// instance stayed healthy for `max_backoff_ms` in between.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RestartPolicy {
+    #[serde(default = "default_restart_mode")]
+    pub mode: RestartMode,
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// Give up after this many restarts. Unlimited if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_restarts: Option<u32>,
+}
+
+fn default_restart_mode() -> RestartMode {
+    RestartMode::OnFailure
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_backoff_ms() -> u64 {
+    60000
+}
+
+impl Default for RestartPolicy {
+    fn default() -> RestartPolicy {
+        RestartPolicy {
+            mode: default_restart_mode(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            max_restarts: None,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before the next restart after the given number of consecutive failures
+    pub fn backoff(&self, consecutive_failures: u32) -> Duration {
+        let factor = 1u64 << consecutive_failures.min(32);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+
+    pub fn allows_restart(&self, restarts: u32) -> bool {
+        self.mode == RestartMode::OnFailure
+            && self.max_restarts.map(|max| restarts < max).unwrap_or(true)
+    }
 }
 
 /// This configures the Content Addressable Storage (CAS) that
@@ -359,12 +582,34 @@ pub struct InstanceConfiguration {
 /// * memory
 /// * file
 ///
+/// File storage can optionally be encrypted at rest, see
+/// [StorageEncryptionConfiguration](enum.StorageEncryptionConfiguration.html).
+///
 /// Projected are various DB adapters.
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum StorageConfiguration {
     Memory,
-    File { path: String },
+    File {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encryption: Option<StorageEncryptionConfiguration>,
+    },
+}
+
+/// Where the key for an encrypted storage comes from:
+/// * passphrase: the key gets derived from a passphrase that is read from the environment
+///   variable given as `passphrase_env` or, if that is not set, prompted for on startup
+/// * agent: the key gets derived from the key bundle of the instance's agent, so the storage
+///   is as well protected as the agent's key file
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "key", rename_all = "lowercase")]
+pub enum StorageEncryptionConfiguration {
+    Passphrase {
+        #[serde(default)]
+        passphrase_env: Option<String>,
+    },
+    Agent,
 }
 
 /// Here, interfaces are user facing and make available zome functions to
@@ -372,8 +617,6 @@ pub enum StorageConfiguration {
 /// We currently have:
 /// * websockets
 /// * HTTP
-///
-/// We will also soon develop
 /// * Unix domain sockets
 ///
 /// The instances (referenced by ID) that are to be made available via that interface should be listed.
@@ -387,17 +630,131 @@ pub struct InterfaceConfiguration {
     pub admin: bool,
     #[serde(default)]
     pub instances: Vec<InstanceReferenceConfiguration>,
+    /// Tokens that grant access to this interface.
+    /// If there are any, only calls that pass one of them as `auth_token` param get dispatched.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<InterfaceTokenConfiguration>,
+}
+
+/// A token that grants access to an interface, optionally restricted to some
+/// methods and instances.
+/// The token can either be given in plain text or as (hex encoded) SHA-256 hash.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct InterfaceTokenConfiguration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_hash: Option<String>,
+    /// Methods that can be called with this token. Entries ending with `*` match all methods
+    /// starting with the rest of the entry, e.g. "admin/*". All methods if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub methods: Option<Vec<String>>,
+    /// IDs of the instances that can be called with this token. All instances if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instances: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum InterfaceDriver {
-    Websocket { port: u16 },
-    Http { port: u16 },
-    DomainSocket { file: String },
+    Websocket {
+        port: u16,
+        /// Address (IP or host name) to bind the server to.
+        /// Defaults to "0.0.0.0", i.e. all network interfaces.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bind_address: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tls: Option<TlsConfiguration>,
+    },
+    Http {
+        port: u16,
+        /// Address (IP or host name) to bind the server to.
+        /// Defaults to "0.0.0.0", i.e. all network interfaces.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bind_address: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tls: Option<TlsConfiguration>,
+    },
+    DomainSocket {
+        file: String,
+        /// Unix file mode (as octal string, e.g. "660") the socket file gets.
+        /// Only users that can write to the socket file can connect.
+        /// Defaults to "600", i.e. only the user running the conductor.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        permissions: Option<String>,
+    },
     Custom(toml::value::Value),
 }
 
+/// Makes a websocket or HTTP interface only accept TLS connections.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct TlsConfiguration {
+    /// Path to the PEM encoded certificate chain of the server
+    pub certificate: String,
+    /// Path to the PEM encoded (PKCS#8 or RSA) private key of the server
+    pub key: String,
+    /// Path to PEM encoded CA certificates.
+    /// If set, clients have to present a certificate signed by one of these CAs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ca: Option<String>,
+}
+
+/// Serves metrics at `/metrics` on the given port.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct MetricsConfiguration {
+    pub port: u16,
+    /// Defaults to "0.0.0.0", i.e. all network interfaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<String>,
+}
+
+/// Instances get probed every `probe_interval_ms`. An action loop that is busy with one
+/// action for longer than `stuck_after_ms` counts as stuck.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SupervisorConfiguration {
+    #[serde(default = "default_probe_interval_ms")]
+    pub probe_interval_ms: u64,
+    #[serde(default = "default_stuck_after_ms")]
+    pub stuck_after_ms: u64,
+}
+
+fn default_probe_interval_ms() -> u64 {
+    5000
+}
+
+fn default_stuck_after_ms() -> u64 {
+    60000
+}
+
+impl Default for SupervisorConfiguration {
+    fn default() -> SupervisorConfiguration {
+        SupervisorConfiguration {
+            probe_interval_ms: default_probe_interval_ms(),
+            stuck_after_ms: default_stuck_after_ms(),
+        }
+    }
+}
+
+pub const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0";
+
+pub const DEFAULT_SOCKET_PERMISSIONS: u32 = 0o600;
+
+/// Parses the permissions of a domain socket interface given as octal string.
+pub fn socket_permissions(permissions: &Option<String>) -> Result<u32, String> {
+    match permissions {
+        None => Ok(DEFAULT_SOCKET_PERMISSIONS),
+        Some(ref permissions) => u32::from_str_radix(permissions, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o777)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid domain socket permissions \"{}\", expected an octal mode like \"660\"",
+                    permissions
+                )
+            }),
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct InstanceReferenceConfiguration {
     pub id: String,
@@ -423,8 +780,8 @@ pub struct Bridge {
 }
 
 /// A UI Bundle is a folder containing static assets which can be served as a UI
-/// A hash can optionally be provided, which could be used to validate that the UI being installed
-/// is the UI bundle that was intended to be installed.
+/// A hash can optionally be provided, in which case the bundle only gets served if its
+/// [ui_bundle_hash](fn.ui_bundle_hash.html) equals that hash.
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct UiBundleConfiguration {
     pub id: String,
@@ -433,6 +790,60 @@ pub struct UiBundleConfiguration {
     pub hash: Option<String>,
 }
 
+/// Computes a hash of the given directory that only depends on the paths and contents of the
+/// files in it, not on timestamps, permissions or the order in which the file system lists them.
+/// This is the SHA2-256 multihash (in base58, like DNA addresses) of a manifest with one line
+/// `<content hash> <path>` per file, sorted by path, where `<path>` is relative to `root_dir`
+/// with `/` as separator and `<content hash>` is the multihash of the file's content.
+/// Bundles containing symbolic links can't be hashed.
+pub fn ui_bundle_hash(root_dir: &Path) -> Result<HashString, HolochainError> {
+    let mut files = Vec::new();
+    collect_files(root_dir, root_dir, &mut files)?;
+    files.sort();
+    let mut manifest = String::new();
+    for (relative_path, path) in files {
+        let mut content = Vec::new();
+        File::open(&path)?.read_to_end(&mut content)?;
+        manifest.push_str(&format!(
+            "{} {}\n",
+            HashString::encode_from_bytes(&content, Hash::SHA2256),
+            relative_path
+        ));
+    }
+    Ok(HashString::encode_from_str(&manifest, Hash::SHA2256))
+}
+
+fn collect_files(
+    root_dir: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf)>,
+) -> Result<(), HolochainError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        // Symlinks could point anywhere, including to files that change without changing the
+        // bundle (or to parent directories), so the hash can't vouch for them
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        if file_type.is_symlink() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "UI bundle contains a symbolic link: {}",
+                path.display()
+            )));
+        } else if file_type.is_dir() {
+            collect_files(root_dir, &path, files)?;
+        } else {
+            let relative_path = path
+                .strip_prefix(root_dir)
+                .map_err(|error| HolochainError::ErrorGeneric(error.to_string()))?
+                .iter()
+                .map(|component| component.to_string_lossy().into_owned())
+                .collect::<Vec<String>>()
+                .join("/");
+            files.push((relative_path, path));
+        }
+    }
+    Ok(())
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct UiInterfaceConfiguration {
     pub id: String,
@@ -448,6 +859,66 @@ pub struct UiInterfaceConfiguration {
     /// (Optional)
     #[serde(default)]
     pub dna_interface: Option<String>,
+
+    /// HTTP features of the server, see
+    /// [UiServerConfiguration](struct.UiServerConfiguration.html) for the defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<UiServerConfiguration>,
+}
+
+/// Configures how a UI interface serves its bundle.
+/// Paths that don't match a file and don't look like asset paths (i.e. their last segment
+/// has no file extension) get index.html, so that single page apps can use client side routing.
+/// HTML files are sent with `Cache-Control: no-cache` and all other files may be cached for
+/// `cache_max_age` seconds. All files get an ETag, so browsers can revalidate them cheaply.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct UiServerConfiguration {
+    #[serde(default = "default_true")]
+    pub spa_fallback: bool,
+    /// Compress responses with brotli or gzip if the browser accepts it
+    #[serde(default = "default_true")]
+    pub compression: bool,
+    #[serde(default = "default_cache_max_age")]
+    pub cache_max_age: u64,
+    /// No CORS headers get sent if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfiguration>,
+    /// Path (e.g. "/_dna_interface") on which websocket connections get forwarded to the
+    /// `dna_interface`, so browsers can reach it on the same origin as the UI.
+    /// Only works with websocket interfaces without TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dna_interface_proxy: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_cache_max_age() -> u64 {
+    3600
+}
+
+impl Default for UiServerConfiguration {
+    fn default() -> UiServerConfiguration {
+        UiServerConfiguration {
+            spa_fallback: true,
+            compression: true,
+            cache_max_age: default_cache_max_age(),
+            cors: None,
+            dna_interface_proxy: None,
+        }
+    }
+}
+
+/// Origins in `allowed_origins` (or any origin if it contains "*") get access to the UI
+/// interface's responses. `allowed_headers` and `max_age` are used to answer preflight requests.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct CorsConfiguration {
+    pub allowed_origins: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
@@ -543,12 +1014,46 @@ pub fn serialize_configuration(config: &Configuration) -> HcResult<String> {
 pub mod tests {
     use super::*;
     use crate::config::{load_configuration, Configuration, NetworkConfig};
+    use crate::logger::LogRotation;
     use holochain_net::p2p_config::P2pConfig;
+    extern crate tempfile;
+    use self::tempfile::tempdir;
 
     pub fn example_serialized_network_config() -> String {
         String::from(JsonString::from(P2pConfig::new_with_unique_memory_backend()))
     }
 
+    #[test]
+    fn test_ui_bundle_hash() {
+        let write_bundle = |files: Vec<(&str, &str)>| {
+            let dir = tempdir().unwrap();
+            for (path, content) in files {
+                let path = dir.path().join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+            dir
+        };
+        let bundle = write_bundle(vec![("index.html", "<html/>"), ("js/app.js", "app()")]);
+        let hash = ui_bundle_hash(bundle.path()).unwrap();
+
+        let same_files = write_bundle(vec![("js/app.js", "app()"), ("index.html", "<html/>")]);
+        assert_eq!(ui_bundle_hash(same_files.path()), Ok(hash.clone()));
+        let other_content = write_bundle(vec![("index.html", "<html/>"), ("js/app.js", "app(1)")]);
+        assert_ne!(ui_bundle_hash(other_content.path()), Ok(hash.clone()));
+        let other_path = write_bundle(vec![("index.html", "<html/>"), ("app.js", "app()")]);
+        assert_ne!(ui_bundle_hash(other_path.path()), Ok(hash));
+        assert!(ui_bundle_hash(&bundle.path().join("missing")).is_err());
+
+        #[cfg(unix)]
+        {
+            let outside = write_bundle(vec![("secret.txt", "secret")]);
+            let with_symlink = write_bundle(vec![("index.html", "<html/>")]);
+            std::os::unix::fs::symlink(outside.path(), with_symlink.path().join("js")).unwrap();
+            assert!(ui_bundle_hash(with_symlink.path()).is_err());
+        }
+    }
+
     #[test]
     fn test_agent_load() {
         let toml = r#"
@@ -606,6 +1111,71 @@ pub mod tests {
         assert_eq!(dna_config.hash, Some("Qm328wyq38924y".to_string()));
     }
 
+    #[test]
+    fn test_storage_encryption_load() {
+        let toml = r#"
+    [[agents]]
+    id="agent"
+    name = "Holo Tester 1"
+    public_address = "HoloTester1-------------------------------------------------------------------------AHi1"
+    key_file="whatever"
+
+    [[instances]]
+    id = "plain"
+    dna = "dna"
+    agent = "agent"
+    [instances.storage]
+    type = "file"
+    path = "plain_storage"
+
+    [[instances]]
+    id = "passphrase"
+    dna = "dna"
+    agent = "agent"
+    [instances.storage]
+    type = "file"
+    path = "passphrase_storage"
+    [instances.storage.encryption]
+    key = "passphrase"
+    passphrase_env = "STORAGE_PASSPHRASE"
+
+    [[instances]]
+    id = "agent"
+    dna = "dna"
+    agent = "agent"
+    [instances.storage]
+    type = "file"
+    path = "agent_storage"
+    [instances.storage.encryption]
+    key = "agent"
+    "#;
+        let storages: Vec<StorageConfiguration> = load_configuration::<Configuration>(toml)
+            .unwrap()
+            .instances
+            .into_iter()
+            .map(|instance| instance.storage)
+            .collect();
+        assert_eq!(
+            storages,
+            vec![
+                StorageConfiguration::File {
+                    path: "plain_storage".to_string(),
+                    encryption: None,
+                },
+                StorageConfiguration::File {
+                    path: "passphrase_storage".to_string(),
+                    encryption: Some(StorageEncryptionConfiguration::Passphrase {
+                        passphrase_env: Some("STORAGE_PASSPHRASE".to_string()),
+                    }),
+                },
+                StorageConfiguration::File {
+                    path: "agent_storage".to_string(),
+                    encryption: Some(StorageEncryptionConfiguration::Agent),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_load_complete_config() {
         let toml = r#"
@@ -714,6 +1284,8 @@ pub mod tests {
     [instances.storage]
     type = "file"
     path = "app_spec_storage"
+    [[instances.log_rules.rules]]
+    pattern = "^debug/dna"
 
     [[interfaces]]
     id = "app spec websocket interface"
@@ -744,6 +1316,10 @@ pub mod tests {
     [[logger.rules.rules]]
     pattern = ".*"
     color = "red"
+    [logger.file]
+    path = "/var/log/holochain/conductor.log"
+    [logger.file.rotation]
+    type = "hourly"
 
     [[ui_bundles]]
     id = "bundle1"
@@ -774,6 +1350,12 @@ pub mod tests {
         assert_eq!(instance_config.agent, "test agent");
         assert_eq!(config.logger.logger_type, "debug");
         assert_eq!(config.logger.rules.rules.len(), 1);
+        assert_eq!(config.logger.format, LogFormat::Text);
+        let log_file = config.logger.file.expect("expected log file config");
+        assert_eq!(log_file.format, LogFormat::Json);
+        assert_eq!(log_file.rotation, LogRotation::Hourly);
+        let log_rules = instance_config.log_rules.clone().unwrap();
+        assert!(log_rules.rules[0].pattern.is_match("debug/dna: hello"));
 
         assert_eq!(config.network, None);
     }
@@ -850,6 +1432,79 @@ pub mod tests {
         );
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_domain_socket_interface_permissions() {
+        let toml = r#"
+    [[agents]]
+    id = "test agent"
+    name = "Holo Tester 1"
+    public_address = "HoloTester1-------------------------------------------------------------------------AHi1"
+    key_file = "holo_tester.key"
+
+    [[interfaces]]
+    id = "default permissions"
+    [interfaces.driver]
+    type = "domainsocket"
+    file = "/tmp/holochain-default.sock"
+
+    [[interfaces]]
+    id = "group permissions"
+    [interfaces.driver]
+    type = "domainsocket"
+    file = "/tmp/holochain-group.sock"
+    permissions = "660"
+    "#;
+
+        let mut config = load_configuration::<Configuration>(toml).unwrap();
+        assert_eq!(
+            config.interfaces[0].driver,
+            InterfaceDriver::DomainSocket {
+                file: "/tmp/holochain-default.sock".to_string(),
+                permissions: None,
+            }
+        );
+        assert_eq!(socket_permissions(&None), Ok(0o600));
+        assert_eq!(socket_permissions(&Some("660".to_string())), Ok(0o660));
+        assert_eq!(config.check_consistency(), Ok(()));
+
+        config.interfaces[1].driver = InterfaceDriver::DomainSocket {
+            file: "/tmp/holochain-group.sock".to_string(),
+            permissions: Some("rw-rw----".to_string()),
+        };
+        assert!(config.check_consistency().is_err());
+    }
+
+    #[test]
+    fn test_custom_interface_driver_load() {
+        let toml = r#"
+    [[agents]]
+    id = "test agent"
+    name = "Holo Tester 1"
+    public_address = "HoloTester1-------------------------------------------------------------------------AHi1"
+    key_file = "holo_tester.key"
+
+    [[interfaces]]
+    id = "message queue"
+    [interfaces.driver]
+    type = "custom"
+    name = "amqp"
+    url = "amqp://localhost"
+    "#;
+
+        let config = load_configuration::<Configuration>(toml).unwrap();
+        match config.interfaces[0].driver {
+            InterfaceDriver::Custom(ref table) => {
+                assert_eq!(table.get("name").and_then(|v| v.as_str()), Some("amqp"));
+                assert_eq!(
+                    table.get("url").and_then(|v| v.as_str()),
+                    Some("amqp://localhost")
+                );
+            }
+            ref other => panic!("Expected custom driver, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_invalid_toml_1() {
         let toml = &format!(
@@ -1143,4 +1798,55 @@ pub mod tests {
             Err("DNA Interface configuration \"<not existant>\" not found, mentioned in UI interface \"ui-interface-1\"".to_string())
         );
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_remote_signers() {
+        let toml = r#"
+    [[agents]]
+    id = "holo agent"
+    name = "Holo Tester 1"
+    public_address = "HoloTester1-------------------------------------------------------------------------AHi1"
+    key_file = "holo_tester.key"
+    holo_remote_key = true
+
+    [[agents]]
+    id = "socket agent"
+    name = "Holo Tester 2"
+    public_address = "HoloTester2-----------------------------------------------------------------------AAAGy4WW9e"
+    key_file = "holo_tester2.key"
+    [agents.signer]
+    type = "domainsocket"
+    file = "/run/signer.sock"
+    token = "secret"
+    "#;
+        let mut config = load_configuration::<Configuration>(&toml)
+            .expect("Config should be syntactically correct");
+        assert_eq!(
+            config.agents[1].remote_signer(&None),
+            Ok(Some(SignerConfiguration::DomainSocket {
+                file: String::from("/run/signer.sock"),
+                token: Some(String::from("secret")),
+                timeout_ms: 10000,
+            }))
+        );
+        assert_eq!(
+            config.check_consistency(),
+            Err(
+                "Agent \"holo agent\" has holo_remote_key set but there is no signing_service_uri"
+                    .to_string()
+            )
+        );
+
+        config.signing_service_uri = Some(String::from("http://localhost:8888"));
+        assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(
+            config.agents[0].remote_signer(&config.signing_service_uri),
+            Ok(Some(SignerConfiguration::Http {
+                url: String::from("http://localhost:8888"),
+                token: None,
+                timeout_ms: 10000,
+            }))
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use holochain_cas_implementations::{
-    cas::{file::FilesystemStorage, memory::MemoryStorage},
-    eav::{file::EavFileStorage, memory::EavMemoryStorage},
+    cas::{encrypted::EncryptedStorage, file::FilesystemStorage, memory::MemoryStorage},
+    eav::{encrypted::EncryptedEavStorage, file::EavFileStorage, memory::EavMemoryStorage},
+    encryption::StorageKey,
 };
 
 use holochain_core::{
@@ -92,6 +93,33 @@ impl ContextBuilder {
         Ok(self)
     }
 
+    /// Like [with_file_storage](#method.with_file_storage) but wraps the file storages so that
+    /// content and link tags get encrypted with the given key before being written to disk.
+    pub fn with_encrypted_file_storage<P: AsRef<Path>>(
+        mut self,
+        path: P,
+        key: StorageKey,
+    ) -> Result<Self, HolochainError> {
+        let base_path: PathBuf = path.as_ref().into();
+        let cas_path = base_path.join("cas");
+        let eav_path = base_path.join("eav");
+        fs::create_dir_all(&cas_path)?;
+        fs::create_dir_all(&eav_path)?;
+
+        let file_storage = Arc::new(RwLock::new(EncryptedStorage::new(
+            FilesystemStorage::new(&cas_path)?,
+            key.clone(),
+        )));
+        let eav_storage = Arc::new(RwLock::new(EncryptedEavStorage::new(
+            EavFileStorage::new(eav_path)?,
+            key,
+        )));
+        self.chain_storage = Some(file_storage.clone());
+        self.dht_storage = Some(file_storage);
+        self.eav_storage = Some(eav_storage);
+        Ok(self)
+    }
+
     /// Sets the network config.
     pub fn with_p2p_config(mut self, p2p_config: P2pConfig) -> Self {
         self.p2p_config = Some(p2p_config);
@@ -148,6 +176,7 @@ mod tests {
     extern crate tempfile;
     use self::tempfile::tempdir;
     use holochain_net::p2p_config::P2pBackendKind;
+    use holochain_sodium::secbuf::SecBuf;
     use test_utils::mock_signing::mock_conductor_api;
 
     #[test]
@@ -193,5 +222,15 @@ mod tests {
             .expect("Filestorage should get instantiated with tempdir")
             .with_conductor_api(mock_conductor_api(AgentId::generate_fake("alice")))
             .spawn();
+        let temp = tempdir().expect("test was supposed to create temp dir");
+        let key = StorageKey::open(temp.path(), |_| {
+            StorageKey::from_secret(&mut SecBuf::with_insecure_from_string("secret".to_string()))
+        })
+        .expect("StorageKey should get created in tempdir");
+        let _ = ContextBuilder::new()
+            .with_encrypted_file_storage(temp.path(), key)
+            .expect("Encrypted filestorage should get instantiated with tempdir")
+            .with_conductor_api(mock_conductor_api(AgentId::generate_fake("alice")))
+            .spawn();
     }
 }
@@ -86,8 +86,15 @@
 
 use crate::error::{HolochainInstanceError, HolochainResult};
 use holochain_core::{
+    agent::{
+        chain_archive::ChainArchive,
+        chain_integrity::{verify_chain, ChainIntegrityReport},
+    },
     context::Context,
+    health::{probe_instance, InstanceProbes},
     instance::Instance,
+    introspection::{self, ChainItem, EavItem, HeldEntry, NetworkRequests, PendingValidationItem},
+    metrics::{metrics, MetricsSnapshot},
     nucleus::{
         call_zome_function,
         ribosome::{capabilities::CapabilityRequest, run_dna, WasmCallData},
@@ -95,9 +102,16 @@ use holochain_core::{
     },
     persister::{Persister, SimplePersister},
     state::State,
+    storage_stats::{storage_stats, StorageStats},
+    tracing::Span,
+};
+use holochain_core_types::{
+    cas::content::Address, dna::Dna, error::HolochainError, json::JsonString,
 };
-use holochain_core_types::{dna::Dna, error::HolochainError, json::JsonString};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+
+/// Time the old instance gets to shut down when a chain archive gets imported
+const IMPORT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// contains a Holochain application instance
 pub struct Holochain {
@@ -183,6 +197,14 @@ impl Holochain {
         Ok(())
     }
 
+    /// deactivate the Holochain instance, wait for its running zome calls to finish,
+    /// untrack its DNA from the network, save its state and stop its action loop and
+    /// network (see `Instance::shutdown`). The instance can't be used anymore afterwards.
+    pub fn shutdown(&mut self, timeout: Duration) -> HolochainResult<()> {
+        self.active = false;
+        Ok(self.instance.shutdown(&self.context, timeout)?)
+    }
+
     /// call a function in a zome
     pub fn call(
         &mut self,
@@ -191,12 +213,37 @@ impl Holochain {
         fn_name: &str,
         params: &str,
     ) -> HolochainResult<JsonString> {
+        let context = self.call_context()?;
+        Self::call_in_context(&context, zome, cap, fn_name, params)
+    }
+
+    /// Returns the context zome calls get executed in, if the instance is active.
+    ///
+    /// A zome call does not need the Holochain struct itself since all state changes it
+    /// causes get dispatched to (and serialized by) the instance's action loop.
+    /// Code that shares an instance behind a lock can grab the context, release the lock
+    /// and then use [call_in_context](#method.call_in_context), so that calls to the
+    /// same instance don't block each other.
+    pub fn call_context(&self) -> HolochainResult<Arc<Context>> {
         if !self.active {
             return Err(HolochainInstanceError::InstanceNotActiveYet);
         }
+        Ok(self.context.clone())
+    }
 
-        let zome_call = ZomeFnCall::new(&zome, cap, &fn_name, String::from(params));
-        let context = self.context();
+    /// call a function in a zome with a context obtained through
+    /// [call_context](#method.call_context).
+    /// If the context has a span set (see `Context::with_span`), the call becomes a
+    /// child span of it, otherwise it starts a new trace.
+    pub fn call_in_context(
+        context: &Arc<Context>,
+        zome: &str,
+        cap: CapabilityRequest,
+        fn_name: &str,
+        params: &str,
+    ) -> HolochainResult<JsonString> {
+        let zome_call = ZomeFnCall::new(&zome, cap, &fn_name, String::from(params))
+            .with_span(context.child_span());
         Ok(context.block_on(call_zome_function(zome_call, context))?)
     }
 
@@ -213,6 +260,94 @@ impl Holochain {
     pub fn context(&self) -> &Arc<Context> {
         &self.context
     }
+
+    /// probe whether the action loop and the network worker of this instance are alive
+    pub fn probe(&self, stuck_after: Duration) -> InstanceProbes {
+        probe_instance(&self.instance, stuck_after)
+    }
+
+    /// check the source chain for broken links, entries, signatures and timestamps
+    pub fn verify_chain(&self) -> ChainIntegrityReport {
+        let agent = self.instance.state().agent();
+        verify_chain(&agent.chain_store(), &agent.top_chain_header())
+    }
+
+    /// count what this instance holds in its storages
+    pub fn storage_stats(&self) -> HolochainResult<StorageStats> {
+        Ok(storage_stats(&self.context)?)
+    }
+
+    /// zome call, validation and network counters of this instance
+    pub fn metrics(&self) -> HolochainResult<MetricsSnapshot> {
+        Ok(metrics(&self.context)?)
+    }
+
+    /// page through the source chain, newest header first
+    pub fn debug_chain(&self, start: usize, limit: usize) -> HolochainResult<Vec<ChainItem>> {
+        Ok(introspection::chain(&self.context, start, limit)?)
+    }
+
+    /// page through the entries held for the DHT together with their CRUD status
+    pub fn debug_held_entries(
+        &self,
+        start: usize,
+        limit: usize,
+    ) -> HolochainResult<Vec<HeldEntry>> {
+        Ok(introspection::held_entries(&self.context, start, limit)?)
+    }
+
+    /// all EAV metadata stored about the given address
+    pub fn debug_eav(&self, address: &Address) -> HolochainResult<Vec<EavItem>> {
+        Ok(introspection::eav_for_address(&self.context, address)?)
+    }
+
+    /// validations that wait for missing dependencies
+    pub fn debug_pending_validations(&self) -> HolochainResult<Vec<PendingValidationItem>> {
+        Ok(introspection::pending_validations(&self.context)?)
+    }
+
+    /// network requests that did not get a response yet
+    pub fn debug_network_requests(&self) -> HolochainResult<NetworkRequests> {
+        Ok(introspection::network_requests(&self.context)?)
+    }
+
+    /// the outermost recorded span of the most recent traces, newest first
+    pub fn debug_traces(&self, limit: usize) -> Vec<Span> {
+        self.context.tracer.recent_traces(limit)
+    }
+
+    /// the recorded spans of the given trace in the order they started
+    pub fn debug_trace(&self, trace_id: &str) -> Vec<Span> {
+        self.context.tracer.trace(trace_id)
+    }
+
+    /// all recorded spans, in the order they finished
+    pub fn debug_spans(&self) -> Vec<Span> {
+        self.context.tracer.spans()
+    }
+
+    /// create a portable archive of the source chain, optionally including the held DHT shard
+    pub fn export_chain(&self, include_dht_shard: bool) -> HolochainResult<ChainArchive> {
+        Ok(ChainArchive::from_state(
+            &self.instance.state(),
+            include_dht_shard,
+        )?)
+    }
+
+    /// verify and restore the source chain from the given archive and reload the instance
+    /// from storage so that it continues on top of the restored chain
+    pub fn import_chain(&mut self, archive: &ChainArchive) -> HolochainResult<()> {
+        let active = self.active;
+        // Shutting down first untracks the DNA and stops the old action loop and network,
+        // and makes sure the old instance does not save its state over the restored one.
+        let result = self
+            .shutdown(IMPORT_SHUTDOWN_TIMEOUT)
+            .and_then(|_| Ok(archive.restore(&self.context)?));
+        // Loads the old chain again if restoring failed
+        *self = Holochain::load(self.context.clone())?;
+        self.active = active;
+        result
+    }
 }
 
 #[cfg(test)]
@@ -230,6 +365,7 @@ mod tests {
             ribosome::capabilities::CapabilityRequest,
         },
         signal::{signal_channel, SignalReceiver},
+        tracing::{SpanContext, SpanKind, SpanOutcome},
     };
     use holochain_core_types::{
         cas::content::{Address, AddressableContent},
@@ -237,7 +373,10 @@ mod tests {
         json::RawString,
     };
     use holochain_wasm_utils::wasm_target_dir;
-    use std::sync::{Arc, Mutex};
+    use std::{
+        sync::{Arc, Mutex, RwLock},
+        thread,
+    };
     use test_utils::{
         create_test_defs_with_fn_name, create_test_dna_with_defs, create_test_dna_with_wat,
         create_wasm_from_file, expect_action, hc_setup_and_call_zome_fn,
@@ -496,6 +635,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn records_spans_of_calls() {
+        let wasm = example_api_wasm();
+        let defs = create_test_defs_with_fn_name("round_trip_test");
+        let dna = create_test_dna_with_defs("test_zome", defs, &wasm);
+        let (context, _, _) = test_context("bob");
+        let mut hc = Holochain::new(dna.clone(), context.clone()).unwrap();
+        hc.start().expect("couldn't start");
+
+        // A span of the caller, e.g. a client or the instance on the other side of a bridge
+        let caller_span = SpanContext::root();
+        let traced_context = Arc::new(hc.call_context().unwrap().with_span(caller_span.clone()));
+        let params = r#"{"input_int_val":2,"input_str_val":"fish"}"#;
+        let result = Holochain::call_in_context(
+            &traced_context,
+            "test_zome",
+            cap_call(context.clone(), "round_trip_test", params),
+            "round_trip_test",
+            params,
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let trace = hc.debug_trace(&caller_span.trace_id);
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].kind, SpanKind::ZomeCall);
+        assert_eq!(trace[0].name, "test_zome/round_trip_test");
+        assert_eq!(trace[0].parent_span_id, Some(caller_span.span_id.clone()));
+        assert_eq!(trace[0].outcome, SpanOutcome::Ok);
+        assert_eq!(hc.debug_traces(10), trace);
+        assert_eq!(hc.debug_spans(), trace);
+    }
+
     #[test]
     // TODO #165 - Move test to core/nucleus and use instance directly
     fn can_call_commit() {
@@ -544,6 +715,47 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn can_call_concurrently_without_holding_the_instance() {
+        let wasm = example_api_wasm();
+        let defs = create_test_defs_with_fn_name("commit_test");
+        let dna = create_test_dna_with_defs("test_zome", defs, &wasm);
+        let (context, _, _) = test_context("alex");
+        let mut hc = Holochain::new(dna.clone(), context.clone()).unwrap();
+        hc.start().expect("couldn't start");
+        let hc = Arc::new(RwLock::new(hc));
+
+        let calls: Vec<_> = (0..4)
+            .map(|_| {
+                let context = hc.read().unwrap().call_context().unwrap();
+                thread::spawn(move || {
+                    Holochain::call_in_context(
+                        &context,
+                        "test_zome",
+                        cap_call(context.clone(), "commit_test", r#"{}"#),
+                        "commit_test",
+                        r#"{}"#,
+                    )
+                })
+            })
+            .collect();
+        // The instance can be locked exclusively while calls are running
+        hc.write().unwrap().stop().expect("couldn't stop");
+        for call in calls {
+            let result = call.join().unwrap();
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+
+        // Commits got serialized through the action loop, so the chain is still intact
+        let report = hc.read().unwrap().verify_chain();
+        assert!(report.is_valid(), "report = {:?}", report);
+
+        assert_eq!(
+            hc.read().unwrap().call_context().err(),
+            Some(HolochainInstanceError::InstanceNotActiveYet)
+        );
+    }
+
     #[test]
     // TODO #165 - Move test to core/nucleus and use instance directly
     fn can_call_commit_err() {
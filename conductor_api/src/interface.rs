@@ -1,34 +1,60 @@
-use crate::holo_signing_service::request_signing_service;
-use base64;
 use holochain_core::{
+    agent::chain_archive::ChainArchive,
+    context::Context,
     nucleus::{
         actions::call_zome_function::make_cap_request_for_call,
         ribosome::capabilities::CapabilityRequest,
     },
     state::State,
+    tracing::{SpanContext, TRACE_CONTEXT_PARAM},
 };
 
-use holochain_core_types::{agent::AgentId, cas::content::Address, signature::Provenance};
-use holochain_dpki::key_bundle::KeyBundle;
-use holochain_sodium::secbuf::SecBuf;
+use holochain_core_types::{
+    agent::AgentId, cas::content::Address, dna::Dna, signature::Provenance,
+};
+use holochain_dpki::{key_blob::KeyBlob, key_bundle::KeyBundle};
+use signers::{remote_signer_from_config, LocalSigner, Signer};
+use zome_api;
 use Holochain;
 
-use jsonrpc_ws_server::jsonrpc_core::{self, types::params::Params, IoHandler, Value};
+use jsonrpc_ws_server::jsonrpc_core::{
+    self,
+    futures::{future, future::Either, sync::oneshot, Future, IntoFuture},
+    types::params::Params,
+    IoHandler, Value,
+};
 use serde_json;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     convert::TryFrom,
+    panic::{catch_unwind, AssertUnwindSafe},
     path::PathBuf,
-    sync::{mpsc::Receiver, Arc, Mutex, RwLock},
+    sync::{mpsc::Receiver, Arc, Condvar, Mutex, RwLock},
+    thread,
+    time::Duration,
 };
 
 use conductor::{ConductorAdmin, ConductorUiAdmin, CONDUCTOR};
 use config::{
-    AgentConfiguration, Bridge, DnaConfiguration, InstanceConfiguration, InterfaceConfiguration,
-    InterfaceDriver, UiBundleConfiguration, UiInterfaceConfiguration,
+    default_signer_timeout_ms, socket_permissions, AgentConfiguration, Bridge, DnaConfiguration,
+    InstanceConfiguration, InterfaceConfiguration, InterfaceDriver, SignerConfiguration,
+    UiBundleConfiguration, UiInterfaceConfiguration,
 };
+use interface_auth::{hash_token, unauthorized, Authorization, TokenScope};
 use serde_json::map::Map;
 
+/// Number of items paged debug methods return if no `limit` is given
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Maximum number of threads one handler runs concurrent methods on
+const MAX_CALL_THREADS: usize = 32;
+
+/// Maximum number of concurrent calls waiting for a free thread before new ones get rejected
+const MAX_QUEUED_CALLS: usize = 1024;
+
+/// How long an idle call thread waits for more work before it ends
+const CALL_THREAD_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub type InterfaceError = String;
 pub type InstanceMap = HashMap<String, Arc<RwLock<Holochain>>>;
 
@@ -40,6 +66,100 @@ pub struct PublicInstanceIdentifier(String);
 /// A mapper type between the public identifier and the conductor config identifier string
 pub type PublicInstanceMap = HashMap<PublicInstanceIdentifier, String>;
 
+type CallMethod = Arc<Fn(Params) -> Result<Value, jsonrpc_core::Error> + Send + Sync>;
+
+/// A call of a concurrent method waiting to be executed
+struct PendingCall {
+    method: CallMethod,
+    params: Params,
+    sender: oneshot::Sender<Result<Value, jsonrpc_core::Error>>,
+}
+
+#[derive(Default)]
+struct CallPoolState {
+    queue: VecDeque<PendingCall>,
+    threads: usize,
+    idle: usize,
+}
+
+/// Bounded set of threads that execute the calls of concurrent methods.
+/// Threads get spawned on demand up to MAX_CALL_THREADS and end after being idle for
+/// CALL_THREAD_IDLE_TIMEOUT, so handlers that don't get calls don't hold any threads.
+#[derive(Clone)]
+struct CallPool {
+    shared: Arc<(Mutex<CallPoolState>, Condvar)>,
+}
+
+impl CallPool {
+    fn new() -> Self {
+        CallPool {
+            shared: Arc::new((Mutex::new(CallPoolState::default()), Condvar::new())),
+        }
+    }
+
+    /// Queues the call and makes sure a thread is going to pick it up.
+    /// Fails if too many calls are waiting already.
+    fn submit(&self, call: PendingCall) -> Result<(), String> {
+        let (lock, condvar) = &*self.shared;
+        let mut state = lock.lock().unwrap();
+        if state.queue.len() >= MAX_QUEUED_CALLS {
+            return Err(String::from("Too many concurrent calls, try again later"));
+        }
+        state.queue.push_back(call);
+        if state.idle < state.queue.len() && state.threads < MAX_CALL_THREADS {
+            let pool = self.clone();
+            let spawned = thread::Builder::new()
+                .name(String::from("interface-call"))
+                .spawn(move || pool.work());
+            match spawned {
+                Ok(_) => state.threads += 1,
+                Err(error) => {
+                    if state.threads == 0 {
+                        state.queue.pop_back();
+                        return Err(error.to_string());
+                    }
+                }
+            }
+        }
+        condvar.notify_one();
+        Ok(())
+    }
+
+    fn work(&self) {
+        let (lock, condvar) = &*self.shared;
+        loop {
+            let call = {
+                let mut state = lock.lock().unwrap();
+                loop {
+                    if let Some(call) = state.queue.pop_front() {
+                        break call;
+                    }
+                    state.idle += 1;
+                    let (guard, wait) = condvar
+                        .wait_timeout(state, CALL_THREAD_IDLE_TIMEOUT)
+                        .unwrap();
+                    state = guard;
+                    state.idle -= 1;
+                    if wait.timed_out() && state.queue.is_empty() {
+                        state.threads -= 1;
+                        return;
+                    }
+                }
+            };
+            // A panicking call drops its sender, which the caller sees as an internal error,
+            // but must not take the thread down without it being accounted for
+            let PendingCall {
+                method,
+                params,
+                sender,
+            } = call;
+            if let Ok(result) = catch_unwind(AssertUnwindSafe(|| method(params))) {
+                let _ = sender.send(result);
+            }
+        }
+    }
+}
+
 impl From<String> for PublicInstanceIdentifier {
     fn from(s: String) -> PublicInstanceIdentifier {
         PublicInstanceIdentifier(s)
@@ -86,6 +206,7 @@ macro_rules! conductor_call {
 /// Examples for method names are:
 /// {instance_id}/{zome}/{cap}/{func} -> a zome call
 /// info/list_instances               -> Map of InstanceConfigs, keyed by ID
+/// info/zomes, info/functions        -> Description of an instance's zome API
 /// admin/...                         -> see [with_admin_dna_functions]
 ///
 /// Each interface has their own handler, and each may be configured differently.
@@ -98,6 +219,8 @@ pub struct ConductorApiBuilder {
     instance_ids_map: PublicInstanceMap,
     instance_configs: HashMap<String, InstanceConfiguration>,
     io: Box<IoHandler>,
+    authorization: Arc<RwLock<Authorization>>,
+    call_pool: CallPool,
 }
 
 impl ConductorApiBuilder {
@@ -107,9 +230,98 @@ impl ConductorApiBuilder {
             instance_ids_map: HashMap::new(),
             instance_configs: HashMap::new(),
             io: Box::new(IoHandler::new()),
+            authorization: Arc::new(RwLock::new(Authorization::default())),
+            call_pool: CallPool::new(),
         }
     }
 
+    /// Only dispatch calls that pass a token which is allowed to make them
+    /// (see [interface_auth](../interface_auth/index.html)).
+    /// Applies to all methods, no matter if they get added before or after this call.
+    pub fn with_authorization(self, authorization: Authorization) -> Self {
+        *self.authorization.write().unwrap() = authorization;
+        self
+    }
+
+    /// Registers a method that only gets dispatched if the authorization allows the call.
+    fn add_method<F>(&mut self, name: &str, method: F)
+    where
+        F: Fn(Params) -> Result<Value, jsonrpc_core::Error> + Send + Sync + 'static,
+    {
+        self.add_authorized_method(name, None, method)
+    }
+
+    /// Registers a method that checks the scope of the caller's token itself.
+    /// It gets the scope, or None if the interface has no tokens.
+    fn add_scoped_method<F>(&mut self, name: &str, method: F)
+    where
+        F: Fn(Params, Option<TokenScope>) -> Result<Value, jsonrpc_core::Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let authorization = self.authorization.clone();
+        let method_name = name.to_string();
+        self.io.add_method(name, move |params| {
+            let (params, scope) = authorization
+                .read()
+                .unwrap()
+                .authorize_scoped(&method_name, params)?;
+            method(params, scope)
+        });
+    }
+
+    /// Like add_method, but calls get executed on the handler's call threads so that the
+    /// interface can serve other requests while they are running.
+    /// Used for zome calls, which can take long (e.g. when waiting for the network).
+    /// `instance_id` is set for methods that always call the same instance.
+    fn add_concurrent_method<F>(&mut self, name: &str, instance_id: Option<String>, method: F)
+    where
+        F: Fn(Params) -> Result<Value, jsonrpc_core::Error> + Send + Sync + 'static,
+    {
+        let method: CallMethod = Arc::new(method);
+        let call_pool = self.call_pool.clone();
+        self.add_authorized_method(name, instance_id, move |params| {
+            let (sender, receiver) = oneshot::channel();
+            if let Err(message) = call_pool.submit(PendingCall {
+                method: method.clone(),
+                params,
+                sender,
+            }) {
+                let mut internal_error = jsonrpc_core::Error::internal_error();
+                internal_error.message = message;
+                return Either::A(future::err(internal_error));
+            }
+            Either::B(receiver.then(|result| {
+                result.unwrap_or_else(|_| Err(jsonrpc_core::Error::internal_error()))
+            }))
+        });
+    }
+
+    fn add_authorized_method<F, R>(&mut self, name: &str, instance_id: Option<String>, method: F)
+    where
+        F: Fn(Params) -> R + Send + Sync + 'static,
+        R: IntoFuture<Item = Value, Error = jsonrpc_core::Error> + 'static,
+        R::Future: Send + 'static,
+    {
+        let authorization = self.authorization.clone();
+        let method_name = name.to_string();
+        let method = Arc::new(method);
+        self.io.add_method(name, move |params| {
+            let method = method.clone();
+            authorization
+                .read()
+                .unwrap()
+                .authorize(
+                    &method_name,
+                    instance_id.as_ref().map(|id| id.as_str()),
+                    params,
+                )
+                .into_future()
+                .and_then(move |params| method(params))
+        });
+    }
+
     /// Finish the building and retrieve the populated handler
     pub fn spawn(mut self) -> IoHandler {
         self.setup_info_api();
@@ -117,12 +329,14 @@ impl ConductorApiBuilder {
         *self.io
     }
 
-    /// Adds a "call" method for making zome function calls
+    /// Adds a "call" method for making zome function calls.
+    /// Callers can pass a `trace_context` param with the span (`trace_id`, `span_id`)
+    /// the call should become part of.
     fn setup_call_api(&mut self) {
         let instances = self.instances.clone();
         let instance_ids_map = self.instance_ids_map.clone();
-        self.io.add_method("call", move |params| {
-            let params_map = Self::unwrap_params_map(params)?;
+        self.add_concurrent_method("call", None, move |params| {
+            let mut params_map = Self::unwrap_params_map(params)?;
             let public_id_str = Self::get_as_string("instance_id", &params_map)?;
             let id = instance_ids_map
                 .get(&PublicInstanceIdentifier::from(public_id_str))
@@ -132,9 +346,14 @@ impl ConductorApiBuilder {
             let instance = instances
                 .get(id)
                 .ok_or(jsonrpc_core::Error::invalid_params("unknown instance"))?;
-            let hc_lock = instance.clone();
-            let hc_lock_inner = hc_lock.clone();
-            let mut hc = hc_lock_inner.write().unwrap();
+            // Only hold the lock on the instance for as long as it takes to get its context,
+            // so that other calls to this instance don't have to wait for this one to finish.
+            let context = instance
+                .read()
+                .unwrap()
+                .call_context()
+                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+            let context = Self::take_trace_context(&mut params_map, context)?;
             let call_params = params_map.get("params");
             let params_string = serde_json::to_string(&call_params)
                 .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
@@ -142,7 +361,6 @@ impl ConductorApiBuilder {
             let func_name = Self::get_as_string("function", &params_map)?;
 
             let cap_request = {
-                let context = hc.context();
                 // Get the token from the parameters.  If not there assume public token.
                 let maybe_token = Self::get_as_string("token", &params_map);
                 let token = match maybe_token {
@@ -173,15 +391,27 @@ impl ConductorApiBuilder {
                 }
             };
 
-            let response = hc
-                .call(&zome_name, cap_request, &func_name, &params_string)
-                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+            let response = Holochain::call_in_context(
+                &context,
+                &zome_name,
+                cap_request,
+                &func_name,
+                &params_string,
+            )
+            .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
             Ok(Value::String(response.to_string()))
         });
     }
 
     /// Adds a "info/instances" method that returns a JSON object describing all registered
     /// instances we have a config for.
+    ///
+    /// Also adds methods that describe the zome API of an instance, given by `instance_id`
+    /// (see [zome_api](../zome_api/index.html)):
+    /// * "info/zomes": traits, function declarations and entry types of all zomes
+    /// * "info/functions": the function declarations of the zome given by `zome`,
+    ///   or of all zomes
+    /// * "info/openrpc": an OpenRPC document for calling the zome functions with "call"
     fn setup_info_api(&mut self) {
         let instance_configs = self.instance_configs.clone();
 
@@ -199,8 +429,72 @@ impl ConductorApiBuilder {
             })
             .collect();
 
-        self.io.add_method("info/instances", move |_| {
-            Ok(serde_json::Value::Array(configs.clone()))
+        // Tokens that are restricted to some instances only get to see those
+        self.add_scoped_method("info/instances", move |_, scope| {
+            let visible = configs
+                .iter()
+                .filter(|config| {
+                    config["id"]
+                        .as_str()
+                        .map(|id| Self::in_scope(&scope, id))
+                        .unwrap_or(false)
+                })
+                .cloned()
+                .collect();
+            Ok(serde_json::Value::Array(visible))
+        });
+
+        // The DNA of a running instance doesn't change, so we can read them once
+        let dnas: HashMap<String, Dna> = self
+            .instances
+            .iter()
+            .filter_map(|(name, instance)| {
+                let dna = instance.read().unwrap().state().ok()?.nucleus().dna()?;
+                Some((name.clone(), dna))
+            })
+            .collect();
+        let dnas = Arc::new(dnas);
+        let instance_ids_map = self.instance_ids_map.clone();
+        let dna_of_instance = move |params_map: &Map<String, Value>| {
+            let public_id_str = Self::get_as_string("instance_id", params_map)?;
+            instance_ids_map
+                .get(&PublicInstanceIdentifier::from(public_id_str))
+                .and_then(|id| dnas.get(id))
+                .cloned()
+                .ok_or(jsonrpc_core::Error::invalid_params(
+                    "instance identifier invalid",
+                ))
+        };
+        let dna_of_instance = Arc::new(dna_of_instance);
+
+        let dna_of = dna_of_instance.clone();
+        self.add_method("info/zomes", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let dna = dna_of(&params_map)?;
+            Ok(json!(zome_api::zomes(&dna)))
+        });
+
+        let dna_of = dna_of_instance.clone();
+        self.add_method("info/functions", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let dna = dna_of(&params_map)?;
+            let zome = Self::get_as_string("zome", &params_map).ok();
+            let functions = zome_api::functions(&dna, zome.as_ref().map(|zome| zome.as_str()))
+                .ok_or_else(|| {
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "DNA has no zome \"{}\"",
+                        zome.unwrap_or_default()
+                    ))
+                })?;
+            Ok(json!(functions))
+        });
+
+        let dna_of = dna_of_instance;
+        self.add_method("info/openrpc", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let dna = dna_of(&params_map)?;
+            let instance_id = Self::get_as_string("instance_id", &params_map)?;
+            Ok(zome_api::openrpc_document(&dna, &instance_id))
         });
     }
 
@@ -250,15 +544,19 @@ impl ConductorApiBuilder {
                         let zome_name = zome_name.clone();
                         let method_name = format!("{}/{}/{}", instance_name, zome_name, func_name);
                         let hc_lock_inner = hc_lock.clone();
-                        self.io.add_method(&method_name, move |params| {
-                            let mut hc = hc_lock_inner.write().unwrap();
+                        let instance_id = instance_name.clone();
+                        self.add_concurrent_method(&method_name, Some(instance_id), move |params| {
+                            let context = hc_lock_inner
+                                .read()
+                                .unwrap()
+                                .call_context()
+                                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
                             let params_string = serde_json::to_string(&params)
                                 .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
                             println!("ZOME CALLING USING instance/zome/function ROUTE HAS BEEN DEPRECATED.  USE call INSTEAD");
                             let cap_request = {
                                 // TODO: get the token from the parameters.  If not there assume public token.
                                 // currently we are always getting the public token and signing it ourself
-                                let context = hc.context();
                                 let token = context.get_public_token().ok_or(
                                     jsonrpc_core::Error::invalid_params("public token not found"),
                                 )?;
@@ -270,9 +568,14 @@ impl ConductorApiBuilder {
                                 )
                             };
 
-                            let response = hc
-                                .call(&zome_name, cap_request, &func_name, &params_string)
-                                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                            let response = Holochain::call_in_context(
+                                &context,
+                                &zome_name,
+                                cap_request,
+                                &func_name,
+                                &params_string,
+                            )
+                            .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
                             Ok(Value::String(response.to_string()))
                         })
                     }
@@ -351,6 +654,58 @@ impl ConductorApiBuilder {
             )))?)
     }
 
+    /// Reads the optional `start` and `limit` params of paged methods.
+    fn get_page(params_map: &Map<String, Value>) -> (usize, usize) {
+        let start = Self::get_as_int("start", params_map).unwrap_or(0);
+        let limit = Self::get_as_int("limit", params_map).unwrap_or(DEFAULT_PAGE_SIZE);
+        (start.max(0) as usize, limit.max(0) as usize)
+    }
+
+    /// Returns `None` if the param is not given or null.
+    fn get_as_string_vec_opt<T: Into<String>>(
+        key: T,
+        params_map: &Map<String, Value>,
+    ) -> Result<Option<Vec<String>>, jsonrpc_core::Error> {
+        let key = key.into();
+        match params_map.get(&key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => serde_json::from_value(value.clone())
+                .map(Some)
+                .map_err(|_| {
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "`{}` has to be an array of strings",
+                        &key
+                    ))
+                }),
+        }
+    }
+
+    /// Whether a call with the given token scope may see the given instance
+    fn in_scope(scope: &Option<TokenScope>, instance_id: &str) -> bool {
+        scope
+            .as_ref()
+            .map(|scope| scope.allows_instance(instance_id))
+            .unwrap_or(true)
+    }
+
+    /// Removes the `trace_context` param callers can give to make a zome call part of
+    /// their trace (see `holochain_core::tracing`) and returns the context to run the
+    /// call in: the given one with the caller's span set, or the given one as is.
+    fn take_trace_context(
+        params_map: &mut Map<String, Value>,
+        context: Arc<Context>,
+    ) -> Result<Arc<Context>, jsonrpc_core::Error> {
+        match params_map.remove(TRACE_CONTEXT_PARAM) {
+            None | Some(Value::Null) => Ok(context),
+            Some(value) => {
+                let span: SpanContext = serde_json::from_value(value).map_err(|e| {
+                    jsonrpc_core::Error::invalid_params(format!("invalid trace_context: {}", e))
+                })?;
+                Ok(Arc::new(context.with_span(span)))
+            }
+        }
+    }
+
     /// This adds functions to remotely change any aspect of the conductor config.
     /// After any change the conductor's config file gets saved.
     /// It is guaranteed that the config is either valid after the change or the change
@@ -364,6 +719,9 @@ impl ConductorApiBuilder {
     ///     * `id`: [string] internal handle/name of the newly created DNA config
     ///     * `path`: [string] local file path to DNA file
     ///     * `expected_hash`: [string] (optional) the hash of this DNA. If this does not match the actual hash, installation will fail.
+    ///     * `copy`: [bool] (optional) copy the DNA file to the conductor's DNA dir
+    ///     * `properties`: [object] (optional) properties to merge into the DNA's properties. Requires `copy`.
+    ///     * `uuid`: [string] (optional) uuid that replaces the DNA's uuid, to run a separate network. Requires `copy`.
     ///
     ///  * `admin/dna/uninstall`
     ///     Uninstalls a DNA from the conductor config. Recursively also removes (and stops)
@@ -372,7 +730,7 @@ impl ConductorApiBuilder {
     ///     * `id`: [string] handle of the DNA to be deleted.
     ///
     ///  * `admin/dna/list`
-    ///     Returns an array of all configured DNAs.
+    ///     Returns an array of all configured DNAs with their hash and uuid/properties overrides.
     ///
     ///  * `admin/instance/add`
     ///     Creates a new instance and adds it to the config.
@@ -404,6 +762,98 @@ impl ConductorApiBuilder {
     ///  * `admin/instance/running`
     ///     Returns an array of all instances that are running.
     ///
+    ///  * `admin/instance/stats`
+    ///     Returns statistics about the data an instance holds: chain length, number of
+    ///     stored entries per entry type, number of headers, total bytes of stored content
+    ///     and number of EAV items (e.g. links) per attribute.
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///
+    ///  * `admin/instance/health`
+    ///     Returns the health of all instances by ID as determined by the conductor's
+    ///     supervisor: status (`healthy`, `restarting`, `failed` or `stopped`), results of
+    ///     the last liveness probes of action loop and network, number of restarts and
+    ///     the error of the last failed restart.
+    ///
+    ///  * `admin/debug/chain`
+    ///     Returns headers of an instance's source chain together with their entries,
+    ///     newest first.
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///     * `start`: [number] (optional) How many of the newest headers to skip (default 0)
+    ///     * `limit`: [number] (optional) Maximum number of headers to return (default 100)
+    ///
+    ///  * `admin/debug/dht`
+    ///     Returns the entries an instance holds for the DHT, ordered by address, with their
+    ///     CRUD status and the address of the entry that updated or deleted them.
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///     * `start`: [number] (optional) How many entries to skip (default 0)
+    ///     * `limit`: [number] (optional) Maximum number of entries to return (default 100)
+    ///
+    ///  * `admin/debug/eav`
+    ///     Returns all EAV metadata (links, CRUD status, headers...) an instance stores with
+    ///     the given address as entity.
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///     * `address`: [string] Address of the entry to look up
+    ///
+    ///  * `admin/debug/pending_validations`
+    ///     Returns the entries and links an instance can't validate yet, together with the
+    ///     addresses of the dependencies it is waiting for.
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///
+    ///  * `admin/debug/network_requests`
+    ///     Returns the GET entry, GET links and validation package requests of an instance
+    ///     that did not get a response yet, and its open direct message connections.
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///
+    ///  * `admin/debug/traces`
+    ///     Returns the outermost span of the most recent traces an instance recorded,
+    ///     newest first. Each zome call starts a trace unless its caller passed a
+    ///     `trace_context`.
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///     * `limit`: [number] (optional) Maximum number of traces to return (default 100)
+    ///
+    ///  * `admin/debug/trace`
+    ///     Returns the spans of a trace (zome calls, bridge calls, direct messages and
+    ///     network look-ups) that any instance recorded, in the order they started, with
+    ///     their duration in milliseconds, outcome and the id of the recording instance.
+    ///     Params:
+    ///     * `trace_id`: [string] Which trace to return?
+    ///
+    ///  * `admin/debug/spans`
+    ///     Exports all spans an instance recorded (up to the last 1000).
+    ///     Params:
+    ///     * `id`: [string] Which instance to inspect?
+    ///
+    ///  * `admin/chain/verify`
+    ///     Checks the source chain of an instance for integrity: header links have to resolve,
+    ///     entries have to match their headers, signatures have to verify and timestamps have
+    ///     to be monotonic. Returns the number of verified headers and the first broken link
+    ///     (or null).
+    ///     Params:
+    ///     * `id`: [string] Which instance's chain to verify?
+    ///
+    ///  * `admin/instance/export`
+    ///     Returns a versioned archive of the instance's source chain (headers and entries,
+    ///     starting with the DNA entry) that can be restored with `admin/instance/import`.
+    ///     Params:
+    ///     * `id`: [string] Which instance to export?
+    ///     * `include_dht`: [bool] (optional) Also include the DHT shard held by the instance
+    ///
+    ///  * `admin/instance/import`
+    ///     Restores a source chain from an archive created by `admin/instance/export`.
+    ///     The header chain and all signatures get verified before anything is written.
+    ///     The instance has to run the same DNA and agent as the exported one and must not
+    ///     have anything but genesis entries on its chain.
+    ///     Params:
+    ///     * `id`: [string] Which instance to import into?
+    ///     * `archive`: [object] The archive as returned by `admin/instance/export`
+    ///
     ///  * `admin/interface/add`
     ///     Adds a new DNA / zome / conductor interface (that provides access to zome functions
     ///     of selected instances and conductor functions, depending on the interfaces config).
@@ -412,8 +862,13 @@ impl ConductorApiBuilder {
     ///     Params:
     ///     * `id`: [string] ID for the new interface
     ///     * `admin`: [bool] Grant access to (these) admin functions?
-    ///     * `type`: [string] Either "websocket" or "http"
-    ///     * `port`:  [number] Port to bind the server to.
+    ///     * `type`: [string] Either "websocket", "http" or "domainsocket"
+    ///     * `port`:  [number] Port to bind the server to (websocket and http only).
+    ///     * `bind_address`: [string] Optional address to bind the server to, defaults to
+    ///       "0.0.0.0" (websocket and http only). TLS has to be configured in the config file.
+    ///     * `file`:  [string] Path of the socket file (domainsocket only).
+    ///     * `permissions`: [string] Optional octal mode of the socket file, defaults to "600"
+    ///       (domainsocket only).
     ///
     ///  * `admin/interface/remove`
     ///     Remove an interface from config. This automatically stops the interface as well.
@@ -434,8 +889,29 @@ impl ConductorApiBuilder {
     ///     * `interface_id`: Which interface to remove the instance from?
     ///     * `instance_id`: Which instance to remove?
     ///
+    ///  * `admin/interface/issue_token`
+    ///     Create a new token that clients have to pass as `auth_token` to call methods
+    ///     of the given interface. Only the hash of the token gets stored in the config.
+    ///     This restarts the given interface in order to have the change take effect.
+    ///     Params:
+    ///     * `interface_id`: Which interface should the token grant access to?
+    ///     * `methods`: [array of strings] (optional) Methods the token may call,
+    ///       a trailing `*` matches every method with that prefix.
+    ///     * `instances`: [array of strings] (optional) Instances the token may call.
+    ///     The new token can't grant access to methods or instances the caller's token
+    ///     can't access.
+    ///     Returns `token` and `token_hash`.
+    ///
+    ///  * `admin/interface/revoke_token`
+    ///     Remove a token from a given interface.
+    ///     This restarts the given interface in order to have the change take effect.
+    ///     Params:
+    ///     * `interface_id`: Which interface to remove the token from?
+    ///     * `token_hash`: Hash of the token to remove, as returned by `issue_token`
+    ///
     ///  * `admin/interface/list`
     ///     Returns an array of all DNA/zome interfaces.
+    ///     Their tokens only show the methods and instances they grant access to.
     ///
     ///  * `admin/agent/add`
     ///     Add an agent to the conductor configuration that can be used with instances.
@@ -445,6 +921,8 @@ impl ConductorApiBuilder {
     ///     * `public_address`: Public part of this agents key. Has to match the private key in the
     ///         given key file.
     ///     * `key_file`: Local path to the file that holds this agent configuration's private key
+    ///     * `signer`: [object] Remote signer to use instead of the key file, e.g.
+    ///         `{"type": "domainsocket", "file": "/run/signer.sock", "token": "..."}` (Optional)
     ///
     ///  * `admin/agent/remove`
     ///     Remove an agent from the conductor config.
@@ -454,6 +932,38 @@ impl ConductorApiBuilder {
     ///  * `admin/agent/list`
     ///     Returns an array of all configured agents.
     ///
+    ///  * `admin/keystore/generate`
+    ///     Creates a new agent key in the conductor's keystore (`keys` in the persistence dir),
+    ///     encrypted with the given passphrase. Returns `public_address` and `key_file`, which
+    ///     can be passed to `admin/agent/add`.
+    ///     Params:
+    ///     * `passphrase`: [string] Passphrase to encrypt the key with
+    ///
+    ///  * `admin/keystore/import`
+    ///     Stores an encrypted key (as created by `hc keygen` or `admin/keystore/export`) in
+    ///     the conductor's keystore. Returns `public_address` and `key_file`.
+    ///     Params:
+    ///     * `key`: [object] The encrypted key blob
+    ///     * `passphrase`: [string] Passphrase of the key, used to check that it can be decrypted
+    ///
+    ///  * `admin/keystore/export`
+    ///     Returns the encrypted key blob with the given public address from the keystore.
+    ///     Params:
+    ///     * `public_address`: [string] Public address of the key
+    ///
+    ///  * `admin/agent/unlock`
+    ///     Decrypts the key file of an agent and keeps the key in memory, so instances of this
+    ///     agent can be added and started without prompting for the passphrase on the terminal.
+    ///     Params:
+    ///     * `id`: [string] Which agent's key to unlock?
+    ///     * `passphrase`: [string] Passphrase of the key
+    ///
+    ///  * `admin/agent/lock`
+    ///     Drops the decrypted key of an agent from memory. Fails if an instance of this agent
+    ///     is running.
+    ///     Params:
+    ///     * `id`: [string] Which agent's key to lock?
+    ///
     ///  * `admin/bridge/add`
     ///     Add a bridge between two instances to enable the caller to call the callee's
     ///     zome functions.
@@ -473,53 +983,64 @@ impl ConductorApiBuilder {
     ///     Returns an array of all bridges.
     ///
     pub fn with_admin_dna_functions(mut self) -> Self {
-        self.io
-            .add_method("admin/dna/install_from_file", move |params| {
-                let params_map = Self::unwrap_params_map(params)?;
-                let id = Self::get_as_string("id", &params_map)?;
-                let path = Self::get_as_string("path", &params_map)?;
-                let copy = Self::get_as_bool("copy", &params_map).unwrap_or(false);
-                let expected_hash = match params_map.get("expected_hash") {
-                    Some(value) => Some(
-                        value
-                            .as_str()
-                            .ok_or(jsonrpc_core::Error::invalid_params(format!(
-                                "`{}` is not a valid json string",
-                                &value
-                            )))?
-                            .into(),
-                    ),
-                    None => None,
-                };
-                let properties = params_map.get("properties");
-                conductor_call!(|c| c.install_dna_from_file(
-                    PathBuf::from(path),
-                    id.to_string(),
-                    copy,
-                    expected_hash,
-                    properties
-                ))?;
-                Ok(json!({"success": true}))
-            });
+        self.add_method("admin/dna/install_from_file", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let path = Self::get_as_string("path", &params_map)?;
+            let copy = Self::get_as_bool("copy", &params_map).unwrap_or(false);
+            let expected_hash = match params_map.get("expected_hash") {
+                Some(value) => Some(
+                    value
+                        .as_str()
+                        .ok_or(jsonrpc_core::Error::invalid_params(format!(
+                            "`{}` is not a valid json string",
+                            &value
+                        )))?
+                        .into(),
+                ),
+                None => None,
+            };
+            let properties = params_map.get("properties");
+            let uuid = match params_map.get("uuid") {
+                Some(Value::Null) | None => None,
+                Some(_) => Some(Self::get_as_string("uuid", &params_map)?),
+            };
+            conductor_call!(|c| c.install_dna_from_file(
+                PathBuf::from(path),
+                id.to_string(),
+                copy,
+                expected_hash,
+                properties,
+                uuid
+            ))?;
+            Ok(json!({"success": true}))
+        });
 
-        self.io.add_method("admin/dna/uninstall", move |params| {
+        self.add_method("admin/dna/uninstall", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             conductor_call!(|c| c.uninstall_dna(&id))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/dna/list", move |_params| {
+        self.add_method("admin/dna/list", move |_params| {
             let dnas =
                 conductor_call!(|c| Ok(c.config().dnas) as Result<Vec<DnaConfiguration>, String>)?;
             Ok(serde_json::Value::Array(
                 dnas.iter()
-                    .map(|dna| json!({"id": dna.id, "hash": dna.hash}))
+                    .map(|dna| {
+                        json!({
+                            "id": dna.id,
+                            "hash": dna.hash,
+                            "uuid": dna.uuid,
+                            "properties": dna.properties,
+                        })
+                    })
                     .collect(),
             ))
         });
 
-        self.io.add_method("admin/instance/add", move |params| {
+        self.add_method("admin/instance/add", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
 
             let id = Self::get_as_string("id", &params_map)?;
@@ -529,34 +1050,35 @@ impl ConductorApiBuilder {
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/instance/remove", move |params| {
+        self.add_method("admin/instance/remove", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             conductor_call!(|c| c.remove_instance(&id))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/instance/start", move |params| {
+        self.add_method("admin/instance/start", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             conductor_call!(|c| c.start_instance(&id))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/instance/stop", move |params| {
+        self.add_method("admin/instance/stop", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             conductor_call!(|c| c.stop_instance(&id))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/instance/list", move |_params| {
+        self.add_scoped_method("admin/instance/list", move |_params, scope| {
             let instances = conductor_call!(
                 |c| Ok(c.config().instances) as Result<Vec<InstanceConfiguration>, String>
             )?;
             Ok(serde_json::Value::Array(
                 instances
                     .iter()
+                    .filter(|instance| Self::in_scope(&scope, &instance.id))
                     .map(|instance| {
                         json!({
                             "id": instance.id,
@@ -568,99 +1090,265 @@ impl ConductorApiBuilder {
             ))
         });
 
-        self.io
-            .add_method("admin/instance/running", move |_params| {
-                let active_ids = conductor_call!(|c| Ok(c
-                    .instances()
+        self.add_scoped_method("admin/instance/running", move |_params, scope| {
+            let active_ids = conductor_call!(|c| Ok(c
+                .instances()
+                .iter()
+                .filter(|(_, hc)| hc.read().unwrap().active())
+                .map(|(id, _)| id)
+                .cloned()
+                .collect())
+                as Result<Vec<String>, String>)?;
+            let instances = conductor_call!(
+                |c| Ok(c.config().instances) as Result<Vec<InstanceConfiguration>, String>
+            )?;
+            Ok(serde_json::Value::Array(
+                instances
                     .iter()
-                    .filter(|(_, hc)| hc.read().unwrap().active())
-                    .map(|(id, _)| id)
-                    .cloned()
-                    .collect())
-                    as Result<Vec<String>, String>)?;
-                let instances = conductor_call!(
-                    |c| Ok(c.config().instances) as Result<Vec<InstanceConfiguration>, String>
-                )?;
-                Ok(serde_json::Value::Array(
-                    instances
-                        .iter()
-                        .filter(|instance| active_ids.contains(&instance.id))
-                        .map(|instance| {
-                            json!({
-                                "id": instance.id,
-                                "dna": instance.dna,
-                                "agent": instance.agent,
-                            })
+                    .filter(|instance| active_ids.contains(&instance.id))
+                    .filter(|instance| Self::in_scope(&scope, &instance.id))
+                    .map(|instance| {
+                        json!({
+                            "id": instance.id,
+                            "dna": instance.dna,
+                            "agent": instance.agent,
                         })
-                        .collect(),
+                    })
+                    .collect(),
+            ))
+        });
+
+        self.add_method("admin/instance/stats", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let stats = conductor_call!(|c| c.instance_stats(&id))?;
+            Ok(serde_json::to_value(stats).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/instance/health", move |_params| {
+            let health = conductor_call!(|c| Ok(c.instance_health()) as Result<_, String>)?;
+            Ok(serde_json::to_value(health).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/chain", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let (start, limit) = Self::get_page(&params_map);
+            let items = conductor_call!(|c| c.debug_chain(&id, start, limit))?;
+            Ok(serde_json::to_value(items).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/dht", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let (start, limit) = Self::get_page(&params_map);
+            let entries = conductor_call!(|c| c.debug_held_entries(&id, start, limit))?;
+            Ok(serde_json::to_value(entries).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/eav", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let address = Address::from(Self::get_as_string("address", &params_map)?);
+            let items = conductor_call!(|c| c.debug_eav(&id, &address))?;
+            Ok(serde_json::to_value(items).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/pending_validations", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let pending = conductor_call!(|c| c.debug_pending_validations(&id))?;
+            Ok(serde_json::to_value(pending).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/network_requests", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let pending = conductor_call!(|c| c.debug_network_requests(&id))?;
+            Ok(serde_json::to_value(pending).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/traces", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let (_, limit) = Self::get_page(&params_map);
+            let traces = conductor_call!(|c| c.debug_traces(&id, limit))?;
+            Ok(serde_json::to_value(traces).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/trace", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let trace_id = Self::get_as_string("trace_id", &params_map)?;
+            let spans = conductor_call!(|c| c.debug_trace(&trace_id))?;
+            Ok(serde_json::to_value(spans).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/debug/spans", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let spans = conductor_call!(|c| c.debug_spans(&id))?;
+            Ok(serde_json::to_value(spans).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/chain/verify", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let report = conductor_call!(|c| c.verify_chain(&id))?;
+            Ok(serde_json::to_value(report).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/instance/export", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let include_dht = Self::get_as_bool("include_dht", &params_map).unwrap_or(false);
+            let archive = conductor_call!(|c| c.export_instance(&id, include_dht))?;
+            Ok(serde_json::to_value(archive).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/instance/import", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let archive: ChainArchive = params_map
+                .get("archive")
+                .ok_or(jsonrpc_core::Error::invalid_params(
+                    "`archive` param not provided",
                 ))
-            });
+                .and_then(|value| {
+                    serde_json::from_value(value.clone()).map_err(|e| {
+                        jsonrpc_core::Error::invalid_params(format!(
+                            "`archive` is not a valid chain archive: {}",
+                            e
+                        ))
+                    })
+                })?;
+            conductor_call!(|c| c.import_instance(&id, &archive))?;
+            Ok(json!({"success": true}))
+        });
 
-        self.io.add_method("admin/interface/add", move |params| {
+        self.add_method("admin/interface/add", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
 
             let id = Self::get_as_string("id", &params_map)?;
             let admin = Self::get_as_bool("admin", &params_map)?;
             let driver_type = Self::get_as_string("type", &params_map)?;
-            let port = u16::try_from(Self::get_as_int("port", &params_map)?).map_err(|_| {
-                jsonrpc_core::Error::invalid_params(String::from(
-                    "`port` has to be a 16bit integer",
-                ))
-            })?;
+            let get_port = || {
+                u16::try_from(Self::get_as_int("port", &params_map)?).map_err(|_| {
+                    jsonrpc_core::Error::invalid_params(String::from(
+                        "`port` has to be a 16bit integer",
+                    ))
+                })
+            };
 
             let new_interface = InterfaceConfiguration {
                 id: id.to_string(),
                 admin,
                 driver: match driver_type.as_ref() {
-                    "websocket" => InterfaceDriver::Websocket { port },
-                    "http" => InterfaceDriver::Http { port },
+                    "websocket" => InterfaceDriver::Websocket {
+                        port: get_port()?,
+                        bind_address: Self::get_as_string("bind_address", &params_map).ok(),
+                        tls: None,
+                    },
+                    "http" => InterfaceDriver::Http {
+                        port: get_port()?,
+                        bind_address: Self::get_as_string("bind_address", &params_map).ok(),
+                        tls: None,
+                    },
+                    "domainsocket" => {
+                        let permissions = Self::get_as_string("permissions", &params_map).ok();
+                        socket_permissions(&permissions)
+                            .map_err(|e| jsonrpc_core::Error::invalid_params(e))?;
+                        InterfaceDriver::DomainSocket {
+                            file: Self::get_as_string("file", &params_map)?,
+                            permissions,
+                        }
+                    }
                     _ => {
                         return Err(jsonrpc_core::Error::invalid_params(String::from(
-                            "`type` has to be either `websocket` or `http`",
+                            "`type` has to be either `websocket`, `http` or `domainsocket`",
                         )));
                     }
                 },
                 instances: Vec::new(),
+                tokens: Vec::new(),
             };
 
             conductor_call!(|c| c.add_interface(new_interface))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/interface/remove", move |params| {
+        self.add_method("admin/interface/remove", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             conductor_call!(|c| c.remove_interface(&id))?;
             Ok(json!({"success": true}))
         });
 
-        self.io
-            .add_method("admin/interface/add_instance", move |params| {
-                let params_map = Self::unwrap_params_map(params)?;
-                let interface_id = Self::get_as_string("interface_id", &params_map)?;
-                let instance_id = Self::get_as_string("instance_id", &params_map)?;
-                conductor_call!(|c| c.add_instance_to_interface(&interface_id, &instance_id))?;
-                Ok(json!({"success": true}))
-            });
+        self.add_method("admin/interface/add_instance", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let interface_id = Self::get_as_string("interface_id", &params_map)?;
+            let instance_id = Self::get_as_string("instance_id", &params_map)?;
+            conductor_call!(|c| c.add_instance_to_interface(&interface_id, &instance_id))?;
+            Ok(json!({"success": true}))
+        });
 
-        self.io
-            .add_method("admin/interface/remove_instance", move |params| {
-                let params_map = Self::unwrap_params_map(params)?;
-                let interface_id = Self::get_as_string("interface_id", &params_map)?;
-                let instance_id = Self::get_as_string("instance_id", &params_map)?;
-                conductor_call!(|c| c.remove_instance_from_interface(&interface_id, &instance_id))?;
-                Ok(json!({"success": true}))
-            });
+        self.add_method("admin/interface/remove_instance", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let interface_id = Self::get_as_string("interface_id", &params_map)?;
+            let instance_id = Self::get_as_string("instance_id", &params_map)?;
+            conductor_call!(|c| c.remove_instance_from_interface(&interface_id, &instance_id))?;
+            Ok(json!({"success": true}))
+        });
 
-        self.io.add_method("admin/interface/list", move |_params| {
+        // Tokens can only issue tokens that grant at most what they grant themselves
+        self.add_scoped_method("admin/interface/issue_token", move |params, scope| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let interface_id = Self::get_as_string("interface_id", &params_map)?;
+            let methods = Self::get_as_string_vec_opt("methods", &params_map)?;
+            let instances = Self::get_as_string_vec_opt("instances", &params_map)?;
+            let requested = TokenScope {
+                methods: methods.clone(),
+                instances: instances.clone(),
+            };
+            if !scope.map(|scope| scope.covers(&requested)).unwrap_or(true) {
+                return Err(unauthorized(String::from(
+                    "Token is not allowed to issue a token that grants more than itself",
+                )));
+            }
+            let token =
+                conductor_call!(|c| c.issue_interface_token(&interface_id, methods, instances))?;
+            let token_hash = hash_token(&token)
+                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+            Ok(json!({"token": token, "token_hash": token_hash}))
+        });
+
+        self.add_method("admin/interface/revoke_token", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let interface_id = Self::get_as_string("interface_id", &params_map)?;
+            let token_hash = Self::get_as_string("token_hash", &params_map)?;
+            conductor_call!(|c| c.revoke_interface_token(&interface_id, &token_hash))?;
+            Ok(json!({"success": true}))
+        });
+
+        self.add_method("admin/interface/list", move |_params| {
             let interfaces = conductor_call!(
                 |c| Ok(c.config().interfaces) as Result<Vec<InterfaceConfiguration>, String>
             )?;
+            // Tokens and their hashes are secrets, only show what they grant access to
+            let interfaces: Vec<InterfaceConfiguration> = interfaces
+                .into_iter()
+                .map(|mut interface| {
+                    for token in interface.tokens.iter_mut() {
+                        token.token = None;
+                        token.token_hash = None;
+                    }
+                    interface
+                })
+                .collect();
             Ok(serde_json::to_value(interfaces)
                 .map_err(|_| jsonrpc_core::Error::internal_error())?)
         });
 
-        self.io.add_method("admin/agent/add", move |params| {
+        self.add_method("admin/agent/add", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             let name = Self::get_as_string("name", &params_map)?;
@@ -670,6 +1358,15 @@ impl ConductorApiBuilder {
                 .get("holo_remote_key")
                 .map(|k| k.as_bool())
                 .unwrap_or_default();
+            let signer = match params_map.get("signer") {
+                Some(Value::Null) | None => None,
+                Some(signer) => Some(serde_json::from_value(signer.clone()).map_err(|e| {
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "`signer` is not a valid signer configuration: {}",
+                        e
+                    ))
+                })?),
+            };
 
             let agent = AgentConfiguration {
                 id,
@@ -677,26 +1374,78 @@ impl ConductorApiBuilder {
                 public_address,
                 key_file,
                 holo_remote_key,
+                signer,
             };
             conductor_call!(|c| c.add_agent(agent))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/agent/remove", move |params| {
+        self.add_method("admin/agent/remove", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             conductor_call!(|c| c.remove_agent(&id))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/agent/list", move |_params| {
+        self.add_method("admin/agent/list", move |_params| {
             let agents = conductor_call!(
                 |c| Ok(c.config().agents) as Result<Vec<AgentConfiguration>, String>
             )?;
             Ok(serde_json::to_value(agents).map_err(|_| jsonrpc_core::Error::internal_error())?)
         });
 
-        self.io.add_method("admin/bridge/add", move |params| {
+        self.add_method("admin/keystore/generate", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let passphrase = Self::get_as_string("passphrase", &params_map)?;
+            let (public_address, key_file) =
+                conductor_call!(|c| c.generate_agent_key(&passphrase))?;
+            Ok(json!({"public_address": public_address, "key_file": key_file}))
+        });
+
+        self.add_method("admin/keystore/import", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let passphrase = Self::get_as_string("passphrase", &params_map)?;
+            let blob: KeyBlob = params_map
+                .get("key")
+                .ok_or(jsonrpc_core::Error::invalid_params(
+                    "`key` param not provided",
+                ))
+                .and_then(|value| {
+                    serde_json::from_value(value.clone()).map_err(|e| {
+                        jsonrpc_core::Error::invalid_params(format!(
+                            "`key` is not a valid key blob: {}",
+                            e
+                        ))
+                    })
+                })?;
+            let (public_address, key_file) =
+                conductor_call!(|c| c.import_agent_key(&blob, &passphrase))?;
+            Ok(json!({"public_address": public_address, "key_file": key_file}))
+        });
+
+        self.add_method("admin/keystore/export", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let public_address = Self::get_as_string("public_address", &params_map)?;
+            let blob = conductor_call!(|c| c.export_agent_key(&public_address))?;
+            Ok(serde_json::to_value(blob).map_err(|_| jsonrpc_core::Error::internal_error())?)
+        });
+
+        self.add_method("admin/agent/unlock", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let passphrase = Self::get_as_string("passphrase", &params_map)?;
+            conductor_call!(|c| c.unlock_agent_key(&id, &passphrase))?;
+            Ok(json!({"success": true}))
+        });
+
+        self.add_method("admin/agent/lock", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            conductor_call!(|c| c.lock_agent_key(&id))?;
+            Ok(json!({"success": true}))
+        });
+
+        self.add_method("admin/bridge/add", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let caller_id = Self::get_as_string("caller_id", &params_map)?;
             let callee_id = Self::get_as_string("callee_id", &params_map)?;
@@ -711,7 +1460,7 @@ impl ConductorApiBuilder {
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/bridge/remove", move |params| {
+        self.add_method("admin/bridge/remove", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let caller_id = Self::get_as_string("caller_id", &params_map)?;
             let callee_id = Self::get_as_string("callee_id", &params_map)?;
@@ -719,7 +1468,7 @@ impl ConductorApiBuilder {
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/bridge/list", move |_params| {
+        self.add_method("admin/bridge/list", move |_params| {
             let bridges =
                 conductor_call!(|c| Ok(c.config().bridges) as Result<Vec<Bridge>, String>)?;
             Ok(serde_json::to_value(bridges).map_err(|_| jsonrpc_core::Error::internal_error())?)
@@ -737,6 +1486,9 @@ impl ConductorApiBuilder {
     ///     Params:
     ///     - `id` ID used to refer to this bundle
     ///     - `root_dir` Directory to host on the HTTP server
+    ///     - `expected_hash` (optional) the hash of the bundle's files. If this does not match
+    ///        the actual hash, installation will fail. The bundle then gets checked before
+    ///        it is served.
     ///
     /// - `admin/ui/uninstall`
     ///     Uninstall and remove from the config a UI bundle by ID. This will also stop and remove
@@ -757,6 +1509,8 @@ impl ConductorApiBuilder {
     ///     - `port` Port to host the HTTP server on
     ///     - `bundle` UI bundle to serve on this port
     ///     - `dna_interface` DNA interface this UI can connect to (Optional)
+    ///     - `server` Object with the HTTP options of the server, i.e. `spa_fallback`,
+    ///       `compression`, `cache_max_age`, `cors` and `dna_interface_proxy` (Optional)
     ///
     /// - `admin/ui_interface/remove`
     ///     Remove an interface by ID
@@ -777,26 +1531,31 @@ impl ConductorApiBuilder {
     ///     - `id` ID of the UI interface to stop
     ///
     pub fn with_admin_ui_functions(mut self) -> Self {
-        self.io.add_method("admin/ui/install", move |params| {
+        self.add_method("admin/ui/install", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let root_dir = Self::get_as_string("root_dir", &params_map)?;
             let id = Self::get_as_string("id", &params_map)?;
+            let expected_hash = match params_map.get("expected_hash") {
+                Some(Value::Null) | None => None,
+                Some(_) => Some(Self::get_as_string("expected_hash", &params_map)?.into()),
+            };
             conductor_call!(|c| c.install_ui_bundle_from_file(
                 PathBuf::from(root_dir),
                 &id,
-                false
+                false,
+                expected_hash
             ))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/ui/uninstall", move |params| {
+        self.add_method("admin/ui/uninstall", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             conductor_call!(|c| c.uninstall_ui_bundle(&id))?;
             Ok(json!({"success": true}))
         });
 
-        self.io.add_method("admin/ui/list", move |_| {
+        self.add_method("admin/ui/list", move |_| {
             let ui_bundles = conductor_call!(
                 |c| Ok(c.config().ui_bundles) as Result<Vec<UiBundleConfiguration>, String>
             )?;
@@ -805,7 +1564,7 @@ impl ConductorApiBuilder {
             ))
         });
 
-        self.io.add_method("admin/ui_interface/add", move |params| {
+        self.add_method("admin/ui_interface/add", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let id = Self::get_as_string("id", &params_map)?;
             let port = u16::try_from(Self::get_as_int("port", &params_map)?).map_err(|_| {
@@ -815,41 +1574,48 @@ impl ConductorApiBuilder {
             })?;
             let bundle = Self::get_as_string("bundle", &params_map)?;
             let dna_interface = Self::get_as_string("dna_interface", &params_map).ok();
+            let server = match params_map.get("server") {
+                Some(Value::Null) | None => None,
+                Some(server) => Some(serde_json::from_value(server.clone()).map_err(|e| {
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "`server` is not a valid UI server configuration: {}",
+                        e
+                    ))
+                })?),
+            };
 
             conductor_call!(|c| c.add_ui_interface(UiInterfaceConfiguration {
                 id,
                 port,
                 bundle,
-                dna_interface
+                dna_interface,
+                server,
             }))?;
             Ok(json!({"success": true}))
         });
 
-        self.io
-            .add_method("admin/ui_interface/remove", move |params| {
-                let params_map = Self::unwrap_params_map(params)?;
-                let id = Self::get_as_string("id", &params_map)?;
-                conductor_call!(|c| c.remove_ui_interface(&id))?;
-                Ok(json!({"success": true}))
-            });
+        self.add_method("admin/ui_interface/remove", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            conductor_call!(|c| c.remove_ui_interface(&id))?;
+            Ok(json!({"success": true}))
+        });
 
-        self.io
-            .add_method("admin/ui_interface/start", move |params| {
-                let params_map = Self::unwrap_params_map(params)?;
-                let id = Self::get_as_string("id", &params_map)?;
-                conductor_call!(|c| c.start_ui_interface(&id))?;
-                Ok(json!({"success": true}))
-            });
+        self.add_method("admin/ui_interface/start", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            conductor_call!(|c| c.start_ui_interface(&id))?;
+            Ok(json!({"success": true}))
+        });
 
-        self.io
-            .add_method("admin/ui_interface/stop", move |params| {
-                let params_map = Self::unwrap_params_map(params)?;
-                let id = Self::get_as_string("id", &params_map)?;
-                conductor_call!(|c| c.stop_ui_interface(&id))?;
-                Ok(json!({"success": true}))
-            });
+        self.add_method("admin/ui_interface/stop", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            conductor_call!(|c| c.stop_ui_interface(&id))?;
+            Ok(json!({"success": true}))
+        });
 
-        self.io.add_method("admin/ui_interface/list", move |_| {
+        self.add_method("admin/ui_interface/list", move |_| {
             let ui_interfaces =
                 conductor_call!(|c| Ok(c.config().ui_interfaces)
                     as Result<Vec<UiInterfaceConfiguration>, String>)?;
@@ -864,51 +1630,43 @@ impl ConductorApiBuilder {
         self
     }
 
-    pub fn with_agent_signature_callback(mut self, keybundle: Arc<Mutex<KeyBundle>>) -> Self {
-        self.io.add_method("agent/sign", move |params| {
+    /// Answers `agent/sign` requests of the instance with the given signer.
+    pub fn with_signer(mut self, signer: Arc<Signer>) -> Self {
+        self.add_method("agent/sign", move |params| {
             let params_map = Self::unwrap_params_map(params)?;
             let payload = Self::get_as_string("payload", &params_map)?;
-            // Convert payload string into a SecBuf
-            let mut message = SecBuf::with_insecure_from_string(payload.clone());
-
-            // Get write lock on the key since we need a mutuble reference to lock the
-            // secure memory the key is in:
-            let mut message_signature = keybundle
-                .lock()
-                .unwrap()
-                .sign(&mut message)
-                .expect("Failed to sign with keybundle.");
-
-            let message_signature = message_signature.read_lock();
-            // Return as base64 encoded string
-            let signature = base64::encode(&**message_signature);
-
+            let signature = signer.sign(&payload).map_err(|error| {
+                let mut new = jsonrpc_core::Error::internal_error();
+                new.message = format!("Could not sign: {}", error);
+                new
+            })?;
             Ok(json!({"payload": payload, "signature": signature}))
         });
         self
     }
 
+    /// Signs with the given key, see [LocalSigner](../signers/struct.LocalSigner.html).
+    pub fn with_agent_signature_callback(self, keybundle: Arc<Mutex<KeyBundle>>) -> Self {
+        self.with_signer(Arc::new(LocalSigner::new(keybundle)))
+    }
+
+    /// Signs with the HTTP signing service at the given URI and verifies the signatures
+    /// against the agent's key, see [HttpSigner](../signers/struct.HttpSigner.html).
     pub fn with_outsource_signing_callback(
-        mut self,
+        self,
         agent_id: AgentId,
         signing_service_uri: String,
     ) -> Self {
-        let agent_id = agent_id.clone();
-        let signing_service_uri = signing_service_uri.clone();
-
-        self.io.add_method("agent/sign", move |params| {
-            let params_map = Self::unwrap_params_map(params)?;
-            let payload = Self::get_as_string("payload", &params_map)?;
-
-            let signature = request_signing_service(&agent_id, &payload, &signing_service_uri)
-                .map_err(|holochain_error| {
-                    println!("Error in signing hack: {:?}", holochain_error);
-                    jsonrpc_core::Error::internal_error()
-                })?;
-
-            Ok(json!({"payload": payload, "signature": signature}))
-        });
-        self
+        let signer = remote_signer_from_config(
+            &SignerConfiguration::Http {
+                url: signing_service_uri,
+                token: None,
+                timeout_ms: default_signer_timeout_ms(),
+            },
+            &agent_id.pub_sign_key,
+        )
+        .expect("HTTP signers are available on every platform");
+        self.with_signer(Arc::from(signer))
     }
 }
 
@@ -919,7 +1677,11 @@ pub trait Interface {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::{conductor::tests::test_conductor, config::Configuration};
+    use crate::{
+        conductor::{mount_conductor_from_config, tests::test_conductor},
+        config::{Configuration, InterfaceTokenConfiguration},
+        interface_auth::UNAUTHORIZED_ERROR_CODE,
+    };
 
     fn example_config_and_instances() -> (Configuration, InstanceMap) {
         let conductor = test_conductor();
@@ -1002,6 +1764,238 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_rpc_call_authorization() {
+        let (config, instances) = example_config_and_instances();
+        let authorization = Authorization::new(&[InterfaceTokenConfiguration {
+            token: Some("info-token".to_string()),
+            token_hash: None,
+            methods: Some(vec!["info/*".to_string()]),
+            instances: None,
+        }])
+        .unwrap();
+        let handler = ConductorApiBuilder::new()
+            .with_instances(instances.clone())
+            .with_instance_configs(config.instances)
+            .with_authorization(authorization)
+            .with_admin_dna_functions()
+            .spawn();
+
+        let response_str = handler
+            .handle_request_sync(&create_call_str("info/instances", None))
+            .expect("Invalid call to handler");
+        assert_eq!(
+            response_str,
+            r#"{"jsonrpc":"2.0","error":{"code":-32001,"message":"`auth_token` param not provided"},"id":"0"}"#
+        );
+
+        let response_str = handler
+            .handle_request_sync(&create_call_str(
+                "info/instances",
+                Some(json!({"auth_token": "info-token"})),
+            ))
+            .expect("Invalid call to handler");
+        assert_eq!(
+            unwrap_response_if_valid(&response_str),
+            r#"[{"id":"test-instance-1","dna":"bridge-callee","agent":"test-agent-1"}]"#
+        );
+
+        let response_str = handler
+            .handle_request_sync(&create_call_str(
+                "admin/dna/list",
+                Some(json!({"auth_token": "info-token"})),
+            ))
+            .expect("Invalid call to handler");
+        assert_eq!(
+            response_str,
+            r#"{"jsonrpc":"2.0","error":{"code":-32001,"message":"Token is not allowed to call admin/dna/list"},"id":"0"}"#
+        );
+    }
+
+    #[test]
+    fn test_interface_list_hides_tokens() {
+        let mut config = test_conductor().config();
+        config.interfaces[0].tokens = vec![
+            InterfaceTokenConfiguration {
+                token: Some("plaintext-secret".to_string()),
+                token_hash: None,
+                methods: Some(vec!["call".to_string()]),
+                instances: None,
+            },
+            InterfaceTokenConfiguration {
+                token: None,
+                token_hash: Some(hash_token("hashed-secret").unwrap()),
+                methods: None,
+                instances: Some(vec!["test-instance-1".to_string()]),
+            },
+        ];
+        mount_conductor_from_config(config);
+        let handler = ConductorApiBuilder::new()
+            .with_admin_dna_functions()
+            .spawn();
+
+        let response_str = handler
+            .handle_request_sync(&create_call_str("admin/interface/list", None))
+            .expect("Invalid call to handler");
+        assert!(!response_str.contains("plaintext-secret"));
+        assert!(!response_str.contains(&hash_token("plaintext-secret").unwrap()));
+        assert!(!response_str.contains(&hash_token("hashed-secret").unwrap()));
+        let interfaces: serde_json::Value =
+            serde_json::from_str(&unwrap_response_if_valid(&response_str)).unwrap();
+        assert_eq!(
+            interfaces[0]["tokens"],
+            json!([{"methods": ["call"]}, {"instances": ["test-instance-1"]}])
+        );
+    }
+
+    #[test]
+    fn test_issued_tokens_stay_within_scope() {
+        let authorization = Authorization::new(&[
+            InterfaceTokenConfiguration {
+                token: Some("interface-admin-token".to_string()),
+                token_hash: None,
+                methods: Some(vec!["admin/interface/*".to_string()]),
+                instances: None,
+            },
+            InterfaceTokenConfiguration {
+                token: Some("app-token".to_string()),
+                token_hash: None,
+                methods: None,
+                instances: Some(vec!["test-instance-1".to_string()]),
+            },
+        ])
+        .unwrap();
+        let handler = ConductorApiBuilder::new()
+            .with_authorization(authorization)
+            .with_admin_dna_functions()
+            .spawn();
+        let issue = |params: serde_json::Value| {
+            let response_str = handler
+                .handle_request_sync(&create_call_str(
+                    "admin/interface/issue_token",
+                    Some(params),
+                ))
+                .expect("Invalid call to handler");
+            serde_json::from_str::<serde_json::Value>(&response_str).unwrap()["error"].clone()
+        };
+        let unauthorized_code = json!(UNAUTHORIZED_ERROR_CODE);
+
+        for params in vec![
+            json!({"auth_token": "interface-admin-token", "interface_id": "app interface"}),
+            json!({
+                "auth_token": "interface-admin-token",
+                "interface_id": "app interface",
+                "methods": ["admin/*"],
+            }),
+            json!({
+                "auth_token": "app-token",
+                "interface_id": "app interface",
+                "methods": ["call"],
+            }),
+            json!({
+                "auth_token": "app-token",
+                "interface_id": "app interface",
+                "instances": ["test-instance-1", "test-instance-2"],
+            }),
+        ] {
+            assert_eq!(issue(params)["code"], unauthorized_code);
+        }
+
+        // Narrower tokens get past the authorization (and fail later on, as the interface
+        // does not exist)
+        for params in vec![
+            json!({
+                "auth_token": "interface-admin-token",
+                "interface_id": "app interface",
+                "methods": ["admin/interface/list"],
+            }),
+            json!({
+                "auth_token": "app-token",
+                "interface_id": "app interface",
+                "methods": ["call"],
+                "instances": ["test-instance-1"],
+            }),
+        ] {
+            let error = issue(params);
+            assert!(error.is_object());
+            assert_ne!(error["code"], unauthorized_code);
+        }
+    }
+
+    #[test]
+    fn test_zome_api_info() {
+        let (config, instances) = example_config_and_instances();
+        let handler = ConductorApiBuilder::new()
+            .with_instances(instances.clone())
+            .with_instance_configs(config.instances)
+            .spawn();
+        let call = |method: &str, params: serde_json::Value| {
+            let response_str = handler
+                .handle_request_sync(&create_call_str(method, Some(params)))
+                .expect("Invalid call to handler");
+            serde_json::from_str::<serde_json::Value>(&response_str).unwrap()
+        };
+
+        let zomes = call("info/zomes", json!({"instance_id": "test-instance-1"}))["result"].clone();
+        assert_eq!(zomes.as_array().unwrap().len(), 1);
+        assert_eq!(zomes[0]["name"], json!("greeter"));
+        assert_eq!(
+            zomes[0]["traits"],
+            json!({"hc_public": ["public_test_fn", "hello"]})
+        );
+        assert_eq!(
+            zomes[0]["entry_types"][0],
+            json!({
+                "name": "testEntryType",
+                "description": "",
+                "sharing": "public",
+                "links_to": [{"target_type": "testEntryTypeB", "tag": "test-tag"}],
+                "linked_from": [],
+            })
+        );
+
+        let functions = call(
+            "info/functions",
+            json!({"instance_id": "test-instance-1", "zome": "greeter"}),
+        )["result"]
+            .clone();
+        assert_eq!(
+            functions[1],
+            json!({
+                "zome": "greeter",
+                "name": "hello",
+                "inputs": [],
+                "outputs": [{"name": "greeting", "type": "String"}],
+                "traits": ["hc_public"],
+            })
+        );
+        assert_eq!(
+            call("info/functions", json!({"instance_id": "test-instance-1"}))["result"],
+            functions
+        );
+        assert_eq!(
+            call(
+                "info/functions",
+                json!({"instance_id": "test-instance-1", "zome": "nope"})
+            )["error"]["message"],
+            json!("DNA has no zome \"nope\"")
+        );
+        assert_eq!(
+            call("info/zomes", json!({"instance_id": "bad instance id"}))["error"]["message"],
+            json!("instance identifier invalid")
+        );
+
+        let document =
+            call("info/openrpc", json!({"instance_id": "test-instance-1"}))["result"].clone();
+        assert_eq!(document["methods"][0]["name"], json!("call"));
+        let inputs = &document["methods"][0]["params"][3]["schema"]["oneOf"];
+        assert_eq!(inputs[1]["title"], json!("greeter/hello"));
+        assert_eq!(
+            inputs[1]["description"],
+            json!("In hc_public. Returns greeting: String")
+        );
+    }
+
     #[test]
     fn test_rpc_call_method() {
         let (config, instances) = example_config_and_instances();
@@ -1131,5 +2125,84 @@ pub mod tests {
             response_str,
             r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Holochain Instance Error: Holochain instance is not active yet."},"id":"0"}"#
         );
+
+        let response_str = handler
+            .handle_request_sync(&create_call_str(
+                "call",
+                Some(json!({
+                    "instance_id" : "test-instance-1",
+                    "zome" : "greeter",
+                    "function" : "hello",
+                    "trace_context" : "not a span",
+                })),
+            ))
+            .expect("Invalid call to handler");
+        assert_eq!(
+            response_str,
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"invalid trace_context: invalid type: string \"not a span\", expected struct SpanContext"},"id":"0"}"#
+        );
+
+        // A valid trace context gets taken out of the params before calling
+        let response_str = handler
+            .handle_request_sync(&create_call_str(
+                "call",
+                Some(json!({
+                    "instance_id" : "test-instance-1",
+                    "zome" : "greeter",
+                    "function" : "hello",
+                    "trace_context" : {"trace_id" : "trace", "span_id" : "span"},
+                })),
+            ))
+            .expect("Invalid call to handler");
+        assert_eq!(
+            response_str,
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Holochain Instance Error: Holochain instance is not active yet."},"id":"0"}"#
+        );
+    }
+
+    #[test]
+    fn test_call_pool_is_bounded() {
+        let pool = CallPool::new();
+        let (release, blocked) = std::sync::mpsc::channel::<()>();
+        let blocked = Arc::new(Mutex::new(blocked));
+        let method: CallMethod = Arc::new(move |_| {
+            let _ = blocked.lock().unwrap().recv();
+            Ok(Value::Null)
+        });
+        let mut receivers = Vec::new();
+        for _ in 0..MAX_QUEUED_CALLS {
+            let (sender, receiver) = oneshot::channel();
+            pool.submit(PendingCall {
+                method: method.clone(),
+                params: Params::None,
+                sender,
+            })
+            .expect("Should accept calls until the queue is full");
+            receivers.push(receiver);
+        }
+        // Only one call can run at a time, so the others queue up until the queue is full
+        let mut accepted = receivers.len();
+        loop {
+            let (sender, receiver) = oneshot::channel();
+            let submitted = pool.submit(PendingCall {
+                method: method.clone(),
+                params: Params::None,
+                sender,
+            });
+            if submitted.is_err() {
+                break;
+            }
+            receivers.push(receiver);
+            accepted += 1;
+            assert!(accepted <= MAX_QUEUED_CALLS + MAX_CALL_THREADS);
+        }
+        assert!(pool.shared.0.lock().unwrap().threads <= MAX_CALL_THREADS);
+
+        for _ in 0..accepted {
+            release.send(()).unwrap();
+        }
+        for receiver in receivers {
+            assert_eq!(receiver.wait().unwrap(), Ok(Value::Null));
+        }
     }
 }
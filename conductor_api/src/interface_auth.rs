@@ -0,0 +1,523 @@
+//! Token based authorization for the JSON-RPC methods of interfaces.
+//!
+//! Clients pass their token as `auth_token` param with every call. Before a method gets
+//! dispatched, the token is checked against the tokens configured for the interface and
+//! their allow-lists of methods and instances. The token param is removed before the
+//! params get handed to the method.
+//!
+//! The instances a call acts on are taken from its params: `id` for the `admin/instance/*`,
+//! `admin/debug/*` and `admin/chain/*` methods, `caller_id` and `callee_id` for the
+//! `admin/bridge/*` methods and `instance_id` for `admin/interface/add_instance`,
+//! `admin/interface/remove_instance` and all non-admin methods.
+//! All of them have to be among the instances a token is restricted to. Such tokens can't
+//! call admin methods that don't name the instances they act on, except for the ones that
+//! check the token's scope themselves, like listings that only show the allowed instances.
+use base64;
+use config::InterfaceTokenConfiguration;
+use holochain_core_types::error::HolochainError;
+use holochain_sodium::{hash, secbuf::SecBuf};
+use jsonrpc_ws_server::jsonrpc_core::{self, types::params::Params, ErrorCode};
+
+/// Name of the param clients pass their token in
+pub const AUTH_TOKEN_PARAM: &str = "auth_token";
+
+/// JSON-RPC error code of calls that get rejected
+pub const UNAUTHORIZED_ERROR_CODE: i64 = -32001;
+
+/// Number of random bytes in generated tokens
+const TOKEN_BYTES: usize = 32;
+
+/// Returns the hex encoded SHA-256 hash of the given token as it is used in configs.
+pub fn hash_token(token: &str) -> Result<String, HolochainError> {
+    let mut input = SecBuf::with_insecure_from_string(token.to_string());
+    let mut output = SecBuf::with_insecure(hash::BYTES256);
+    hash::sha256(&mut input, &mut output)?;
+    let output = output.read_lock();
+    Ok(output.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Creates a new random token.
+pub fn generate_token() -> Result<String, HolochainError> {
+    let mut random = SecBuf::with_insecure(TOKEN_BYTES);
+    random.randomize();
+    let bytes = random.read_lock().to_vec();
+    Ok(base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD))
+}
+
+/// What a token grants access to
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenScope {
+    /// Allowed methods, entries ending with `*` match all methods with that prefix.
+    /// All methods if None.
+    pub methods: Option<Vec<String>>,
+    /// Allowed instances, all instances if None
+    pub instances: Option<Vec<String>>,
+}
+
+impl TokenScope {
+    pub fn allows_method(&self, method: &str) -> bool {
+        match self.methods {
+            None => true,
+            Some(ref methods) => methods
+                .iter()
+                .any(|pattern| pattern_covers(pattern, method)),
+        }
+    }
+
+    pub fn allows_instance(&self, instance: &str) -> bool {
+        match self.instances {
+            None => true,
+            Some(ref instances) => instances.iter().any(|allowed| allowed == instance),
+        }
+    }
+
+    /// Whether everything the other scope grants access to is also granted by this one
+    pub fn covers(&self, other: &TokenScope) -> bool {
+        let methods = match (&self.methods, &other.methods) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(mine), Some(theirs)) => theirs
+                .iter()
+                .all(|method| mine.iter().any(|pattern| pattern_covers(pattern, method))),
+        };
+        let instances = match (&self.instances, &other.instances) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(_), Some(theirs)) => theirs.iter().all(|id| self.allows_instance(id)),
+        };
+        methods && instances
+    }
+}
+
+/// Whether `pattern` matches `method`.
+/// `method` can be a pattern itself, it then only matches if `pattern` matches everything
+/// it matches.
+fn pattern_covers(pattern: &str, method: &str) -> bool {
+    if pattern.ends_with('*') {
+        method.starts_with(&pattern[..pattern.len() - 1])
+    } else {
+        pattern == method
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AuthorizedToken {
+    hash: String,
+    scope: TokenScope,
+}
+
+/// Decides which calls get dispatched.
+/// Without any tokens, every call is allowed.
+#[derive(Clone, Debug, Default)]
+pub struct Authorization {
+    tokens: Vec<AuthorizedToken>,
+}
+
+/// Names of the params that hold the IDs of the instances the given method acts on
+fn instance_params(method: &str) -> &'static [&'static str] {
+    if method.starts_with("admin/instance/")
+        || method.starts_with("admin/debug/")
+        || method.starts_with("admin/chain/")
+    {
+        &["id"]
+    } else if method.starts_with("admin/bridge/") {
+        &["caller_id", "callee_id"]
+    } else if method == "admin/interface/add_instance"
+        || method == "admin/interface/remove_instance"
+        || !method.starts_with("admin/")
+    {
+        &["instance_id"]
+    } else {
+        &[]
+    }
+}
+
+pub(crate) fn unauthorized(message: String) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: ErrorCode::ServerError(UNAUTHORIZED_ERROR_CODE),
+        message,
+        data: None,
+    }
+}
+
+impl Authorization {
+    pub fn new(tokens: &[InterfaceTokenConfiguration]) -> Result<Self, HolochainError> {
+        let tokens = tokens
+            .iter()
+            .map(|config| {
+                let hash = match (&config.token, &config.token_hash) {
+                    (Some(token), _) => hash_token(token)?,
+                    (None, Some(hash)) => hash.to_lowercase(),
+                    (None, None) => {
+                        return Err(HolochainError::ConfigError(String::from(
+                            "Interface token without token or token_hash",
+                        )));
+                    }
+                };
+                Ok(AuthorizedToken {
+                    hash,
+                    scope: TokenScope {
+                        methods: config.methods.clone(),
+                        instances: config.instances.clone(),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, HolochainError>>()?;
+        Ok(Authorization { tokens })
+    }
+
+    /// Checks if the token passed in `params` may call `method`.
+    /// The instance that gets called is either given as `instance` or taken from the
+    /// params (see [module docs](index.html)).
+    /// Returns the params without the token.
+    pub fn authorize(
+        &self,
+        method: &str,
+        instance: Option<&str>,
+        params: Params,
+    ) -> Result<Params, jsonrpc_core::Error> {
+        self.check(method, instance, params, false)
+            .map(|(params, _)| params)
+    }
+
+    /// Like [authorize](#method.authorize), for methods that restrict what they do to the
+    /// scope of the token themselves, e.g. listings that only show the allowed instances.
+    /// Also returns the scope of the token, None if there are no tokens.
+    pub fn authorize_scoped(
+        &self,
+        method: &str,
+        params: Params,
+    ) -> Result<(Params, Option<TokenScope>), jsonrpc_core::Error> {
+        self.check(method, None, params, true)
+            .map(|(params, token)| (params, token.map(|token| token.scope.clone())))
+    }
+
+    fn check(
+        &self,
+        method: &str,
+        instance: Option<&str>,
+        params: Params,
+        scoped: bool,
+    ) -> Result<(Params, Option<&AuthorizedToken>), jsonrpc_core::Error> {
+        let (token, params) = match params {
+            Params::Map(mut map) => {
+                let token = map.remove(AUTH_TOKEN_PARAM);
+                (token, Params::Map(map))
+            }
+            params => (None, params),
+        };
+        if self.tokens.is_empty() {
+            return Ok((params, None));
+        }
+
+        let token = token
+            .as_ref()
+            .and_then(|token| token.as_str())
+            .ok_or_else(|| unauthorized(format!("`{}` param not provided", AUTH_TOKEN_PARAM)))?;
+        let hash = hash_token(token).map_err(|e| unauthorized(e.to_string()))?;
+        let authorized = self
+            .tokens
+            .iter()
+            .find(|authorized| authorized.hash == hash)
+            .ok_or_else(|| unauthorized(format!("Invalid `{}`", AUTH_TOKEN_PARAM)))?;
+        let scope = &authorized.scope;
+
+        if !scope.allows_method(method) {
+            return Err(unauthorized(format!(
+                "Token is not allowed to call {}",
+                method
+            )));
+        }
+
+        let mut instances: Vec<String> = instance.map(String::from).into_iter().collect();
+        let mut missing_instance = instance.is_none() && instance_params(method).is_empty();
+        for name in instance_params(method) {
+            let value = match params {
+                Params::Map(ref map) => map.get(*name).and_then(|id| id.as_str()),
+                _ => None,
+            };
+            match value {
+                Some(id) => instances.push(id.to_string()),
+                None => missing_instance = true,
+            }
+        }
+        if let Some(instance) = instances.iter().find(|id| !scope.allows_instance(id)) {
+            return Err(unauthorized(format!(
+                "Token is not allowed to call instance {}",
+                instance
+            )));
+        }
+        if scope.instances.is_some() && missing_instance && method.starts_with("admin/") && !scoped
+        {
+            return Err(unauthorized(format!(
+                "Token is restricted to some instances and {} does not name the ones it acts on",
+                method
+            )));
+        }
+
+        Ok((params, Some(authorized)))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json::{Map, Value};
+
+    fn params(value: Value) -> Params {
+        match value {
+            Value::Object(map) => Params::Map(map),
+            _ => panic!("expected object"),
+        }
+    }
+
+    fn test_authorization() -> Authorization {
+        Authorization::new(&[
+            InterfaceTokenConfiguration {
+                token: Some("admin-token".to_string()),
+                token_hash: None,
+                methods: None,
+                instances: None,
+            },
+            InterfaceTokenConfiguration {
+                token: None,
+                token_hash: Some(hash_token("app-token").unwrap()),
+                methods: Some(vec!["call".to_string(), "info/*".to_string()]),
+                instances: Some(vec!["app".to_string()]),
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn allows_everything_without_tokens() {
+        let authorization = Authorization::default();
+        assert_eq!(
+            authorization.authorize(
+                "admin/dna/list",
+                None,
+                params(json!({"auth_token": "whatever"}))
+            ),
+            Ok(Params::Map(Map::new()))
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_tokens() {
+        let authorization = test_authorization();
+        let missing = authorization
+            .authorize("info/instances", None, Params::None)
+            .unwrap_err();
+        assert_eq!(
+            missing.code,
+            ErrorCode::ServerError(UNAUTHORIZED_ERROR_CODE)
+        );
+        assert!(authorization
+            .authorize(
+                "info/instances",
+                None,
+                params(json!({"auth_token": "wrong"}))
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn checks_allowed_methods_and_instances() {
+        let authorization = test_authorization();
+        let call = json!({"auth_token": "app-token", "instance_id": "app", "zome": "blog"});
+        assert_eq!(
+            authorization.authorize("call", None, params(call)),
+            Ok(params(json!({"instance_id": "app", "zome": "blog"})))
+        );
+        assert!(authorization
+            .authorize(
+                "info/instances",
+                None,
+                params(json!({"auth_token": "app-token"}))
+            )
+            .is_ok());
+        assert!(authorization
+            .authorize(
+                "admin/dna/list",
+                None,
+                params(json!({"auth_token": "app-token"}))
+            )
+            .is_err());
+        assert!(authorization
+            .authorize(
+                "call",
+                None,
+                params(json!({"auth_token": "app-token", "instance_id": "other"}))
+            )
+            .is_err());
+        assert!(authorization
+            .authorize(
+                "other/blog/create_post",
+                Some("other"),
+                params(json!({"auth_token": "app-token"}))
+            )
+            .is_err());
+        assert!(authorization
+            .authorize(
+                "admin/dna/list",
+                None,
+                params(json!({"auth_token": "admin-token"}))
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn checks_instance_of_admin_methods() {
+        let authorization = Authorization::new(&[InterfaceTokenConfiguration {
+            token: Some("app-admin-token".to_string()),
+            token_hash: None,
+            methods: Some(vec!["admin/*".to_string()]),
+            instances: Some(vec!["app".to_string()]),
+        }])
+        .unwrap();
+        for method in &[
+            "admin/instance/export",
+            "admin/instance/stop",
+            "admin/debug/chain",
+            "admin/chain/verify",
+        ] {
+            assert!(authorization
+                .authorize(
+                    method,
+                    None,
+                    params(json!({"auth_token": "app-admin-token", "id": "app"}))
+                )
+                .is_ok());
+            assert!(authorization
+                .authorize(
+                    method,
+                    None,
+                    params(json!({"auth_token": "app-admin-token", "id": "other"}))
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn checks_all_instances_admin_methods_act_on() {
+        let authorization = Authorization::new(&[InterfaceTokenConfiguration {
+            token: Some("app-admin-token".to_string()),
+            token_hash: None,
+            methods: Some(vec!["admin/*".to_string()]),
+            instances: Some(vec!["app".to_string(), "helper".to_string()]),
+        }])
+        .unwrap();
+        let authorize = |method: &str, params_value: Value| {
+            let mut params_value = params_value;
+            params_value[AUTH_TOKEN_PARAM] = json!("app-admin-token");
+            authorization.authorize(method, None, params(params_value))
+        };
+
+        assert!(authorize(
+            "admin/bridge/add",
+            json!({"caller_id": "app", "callee_id": "helper", "handle": "h"})
+        )
+        .is_ok());
+        assert!(authorize(
+            "admin/bridge/add",
+            json!({"caller_id": "app", "callee_id": "other", "handle": "h"})
+        )
+        .is_err());
+        assert!(authorize(
+            "admin/bridge/remove",
+            json!({"caller_id": "other", "callee_id": "app"})
+        )
+        .is_err());
+        assert!(authorize(
+            "admin/interface/add_instance",
+            json!({"interface_id": "websocket", "instance_id": "other"})
+        )
+        .is_err());
+
+        // Admin methods that don't name the instances they act on can't be called
+        for method in &[
+            "admin/instance/list",
+            "admin/instance/health",
+            "admin/bridge/list",
+            "admin/interface/list",
+            "admin/ui_interface/add",
+            "admin/agent/add",
+            "admin/dna/list",
+        ] {
+            assert!(authorize(method, json!({})).is_err());
+        }
+
+        // Unless they check the scope themselves
+        assert!(authorization
+            .authorize_scoped(
+                "admin/instance/list",
+                params(json!({"auth_token": "app-admin-token"}))
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn scoped_methods_get_the_token_scope() {
+        let authorization = test_authorization();
+        assert_eq!(
+            authorization
+                .authorize_scoped("info/instances", params(json!({"auth_token": "app-token"}))),
+            Ok((
+                Params::Map(Map::new()),
+                Some(TokenScope {
+                    methods: Some(vec!["call".to_string(), "info/*".to_string()]),
+                    instances: Some(vec!["app".to_string()]),
+                })
+            ))
+        );
+        assert_eq!(
+            authorization.authorize_scoped(
+                "info/instances",
+                params(json!({"auth_token": "admin-token"}))
+            ),
+            Ok((
+                Params::Map(Map::new()),
+                Some(TokenScope {
+                    methods: None,
+                    instances: None,
+                })
+            ))
+        );
+        assert_eq!(
+            Authorization::default().authorize_scoped("info/instances", Params::None),
+            Ok((Params::None, None))
+        );
+    }
+
+    #[test]
+    fn scopes_cover_narrower_ones() {
+        let scope = |methods: Option<Vec<&str>>, instances: Option<Vec<&str>>| TokenScope {
+            methods: methods.map(|methods| methods.into_iter().map(String::from).collect()),
+            instances: instances.map(|ids| ids.into_iter().map(String::from).collect()),
+        };
+        let everything = scope(None, None);
+        let interface_admin = scope(Some(vec!["admin/interface/*"]), None);
+        let app = scope(Some(vec!["call", "info/*"]), Some(vec!["app"]));
+
+        assert!(everything.covers(&interface_admin));
+        assert!(everything.covers(&app));
+        assert!(!interface_admin.covers(&everything));
+        assert!(!app.covers(&everything));
+        assert!(interface_admin.covers(&scope(
+            Some(vec!["admin/interface/list", "admin/interface/issue_*"]),
+            None
+        )));
+        assert!(!interface_admin.covers(&scope(Some(vec!["admin/*"]), None)));
+        assert!(app.covers(&scope(Some(vec!["info/instances"]), Some(vec!["app"]))));
+        assert!(!app.covers(&scope(Some(vec!["call"]), None)));
+        assert!(!app.covers(&scope(Some(vec!["call"]), Some(vec!["app", "other"]))));
+        assert!(!app.covers(&scope(Some(vec!["admin/dna/list"]), Some(vec!["app"]))));
+    }
+
+    #[test]
+    fn generated_tokens_differ() {
+        let token = generate_token().unwrap();
+        assert_ne!(token, generate_token().unwrap());
+        assert_eq!(hash_token(&token).unwrap().len(), 64);
+    }
+}
@@ -0,0 +1,242 @@
+use interface::Interface;
+use jsonrpc_ws_server::jsonrpc_core::IoHandler;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    os::unix::{
+        fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, TryRecvError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// How often connections and the accept loop check whether the interface stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Serves JSON-RPC over a Unix domain socket.
+/// Requests and responses are newline delimited JSON, one per line.
+///
+/// Access is controlled by the permissions of the socket file: only users with
+/// write access to the file can connect.
+pub struct DomainSocketInterface {
+    file: PathBuf,
+    permissions: u32,
+}
+
+impl DomainSocketInterface {
+    pub fn new(file: PathBuf, permissions: u32) -> Self {
+        DomainSocketInterface { file, permissions }
+    }
+
+    /// Binds the socket, replacing a stale socket file left behind by an earlier run.
+    /// Refuses to replace anything that is not a socket.
+    ///
+    /// The socket gets bound inside a directory only the conductor's user can access and
+    /// only moved to its configured path once it has its permissions, so nobody can connect
+    /// while it still has the permissions the umask gives it.
+    fn bind(&self) -> Result<UnixListener, String> {
+        if let Ok(metadata) = fs::symlink_metadata(&self.file) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!(
+                    "{} exists and is not a socket",
+                    self.file.display()
+                ));
+            }
+            fs::remove_file(&self.file).map_err(|e| e.to_string())?;
+        }
+        let file_name = self
+            .file
+            .file_name()
+            .ok_or_else(|| format!("{} is not a file path", self.file.display()))?
+            .to_string_lossy()
+            .to_string();
+        let private_dir = self
+            .file
+            .with_file_name(format!(".{}.{}", file_name, process::id()));
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&private_dir)
+            .map_err(|e| format!("Could not create {}: {}", private_dir.display(), e))?;
+        let private_file = private_dir.join(&file_name);
+        let result = UnixListener::bind(&private_file)
+            .and_then(|listener| {
+                fs::set_permissions(&private_file, fs::Permissions::from_mode(self.permissions))?;
+                fs::rename(&private_file, &self.file)?;
+                Ok(listener)
+            })
+            .map_err(|e| e.to_string());
+        let _ = fs::remove_file(&private_file);
+        let _ = fs::remove_dir(&private_dir);
+        result
+    }
+
+    /// Answers requests until the client disconnects or `stopped` gets set.
+    fn serve_connection(
+        stream: UnixStream,
+        handler: &IoHandler,
+        stopped: &AtomicBool,
+    ) -> Result<(), String> {
+        stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+        stream
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .map_err(|e| e.to_string())?;
+        let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(stream);
+        // Keeps what got read of a line when a read times out in the middle of it
+        let mut line = Vec::new();
+        while !stopped.load(Ordering::Relaxed) {
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(ref e) if is_timeout(e) => continue,
+                Err(e) => return Err(e.to_string()),
+            }
+            let request = String::from_utf8(line.split_off(0)).map_err(|e| e.to_string())?;
+            if request.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = handler.handle_request_sync(&request) {
+                writer
+                    .write_all(format!("{}\n", response).as_bytes())
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut
+}
+
+impl Interface for DomainSocketInterface {
+    /// Every connection gets served on its own thread, which ends when the client
+    /// disconnects or the interface stops.
+    fn run(&self, handler: IoHandler, kill_switch: Receiver<()>) -> Result<(), String> {
+        let listener = self.bind()?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let handler = Arc::new(handler);
+        let stopped = Arc::new(AtomicBool::new(false));
+        // Connection threads with flags they set when they end
+        let mut connections: Vec<(Arc<AtomicBool>, JoinHandle<()>)> = Vec::new();
+        let result = loop {
+            match kill_switch.try_recv() {
+                Err(TryRecvError::Empty) => (),
+                _ => break Ok(()),
+            }
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let handler = handler.clone();
+                    let stopped = stopped.clone();
+                    let finished = Arc::new(AtomicBool::new(false));
+                    let finished_child = finished.clone();
+                    connections.retain(|(finished, _)| !finished.load(Ordering::Relaxed));
+                    let spawned = thread::Builder::new()
+                        .name(format!("domain-socket-connection: {}", self.file.display()))
+                        .spawn(move || {
+                            let _ = Self::serve_connection(stream, &handler, &stopped);
+                            finished_child.store(true, Ordering::Relaxed);
+                        });
+                    if let Ok(connection) = spawned {
+                        connections.push((finished, connection));
+                    }
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => break Err(e.to_string()),
+            }
+        };
+        stopped.store(true, Ordering::Relaxed);
+        for (_, connection) in connections {
+            let _ = connection.join();
+        }
+        let _ = fs::remove_file(&self.file);
+        result
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+    use jsonrpc_ws_server::jsonrpc_core::Value;
+    use std::sync::mpsc::channel;
+
+    fn wait_for_socket(file: &PathBuf) -> UnixStream {
+        for _ in 0..100 {
+            if let Ok(stream) = UnixStream::connect(file) {
+                return stream;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("Domain socket interface did not come up");
+    }
+
+    #[test]
+    fn serves_json_rpc_and_sets_permissions() {
+        let dir = tempdir().expect("Could not create temp dir");
+        let file = dir.path().join("conductor.sock");
+
+        let mut handler = IoHandler::new();
+        handler.add_method("ping", |_| Ok(Value::String("pong".into())));
+
+        let (kill_switch, kill_receiver) = channel();
+        let interface = DomainSocketInterface::new(file.clone(), 0o600);
+        let server = thread::spawn(move || interface.run(handler, kill_receiver));
+
+        let mut stream = wait_for_socket(&file);
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        stream
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"ping\",\"id\":1}\n")
+            .unwrap();
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).unwrap();
+        assert_eq!(
+            response,
+            "{\"jsonrpc\":\"2.0\",\"result\":\"pong\",\"id\":1}\n"
+        );
+
+        // An idle connection must not keep the interface from stopping
+        let mut idle_stream = wait_for_socket(&file);
+        idle_stream
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"ping\",\"id\":2}\n")
+            .unwrap();
+        let mut idle_reader = BufReader::new(idle_stream);
+        let mut response = String::new();
+        idle_reader.read_line(&mut response).unwrap();
+        assert!(response.contains("pong"));
+
+        kill_switch.send(()).unwrap();
+        assert_eq!(server.join().unwrap(), Ok(()));
+        assert!(!file.exists());
+        // Nothing is left of the private directory the socket got bound in
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+        let mut rest = String::new();
+        idle_reader
+            .read_line(&mut rest)
+            .expect("Connection should be closed, not left open");
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn refuses_to_replace_regular_file() {
+        let dir = tempdir().expect("Could not create temp dir");
+        let file = dir.path().join("not-a-socket");
+        fs::write(&file, "important").unwrap();
+
+        let (_kill_switch, kill_receiver) = channel();
+        let interface = DomainSocketInterface::new(file.clone(), 0o600);
+        assert!(interface.run(IoHandler::new(), kill_receiver).is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "important");
+    }
+}
@@ -0,0 +1,308 @@
+//! TLS for the websocket and HTTP interfaces.
+//!
+//! The JSON-RPC servers we use can't do TLS themselves. So if TLS is configured, this
+//! module serves the interface's JSON-RPC handler directly on the TLS connections:
+//! HTTP through hyper and websockets through tungstenite.
+use config::{TlsConfiguration, DEFAULT_BIND_ADDRESS};
+use hyper::{
+    header::{self, HeaderValue},
+    server::conn::Http,
+    service::service_fn,
+    Body, Method, Request, Response, StatusCode,
+};
+use jsonrpc_ws_server::jsonrpc_core::IoHandler;
+use std::{
+    fs::File,
+    io::{self, BufReader, ErrorKind},
+    net::{SocketAddr, TcpListener as StdTcpListener, TcpStream as StdTcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, TryRecvError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tokio::{net::TcpListener, prelude::*, runtime::Runtime, spawn};
+use tokio_rustls::{
+    rustls::{
+        internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys},
+        AllowAnyAuthenticatedClient, Certificate, NoClientAuth, PrivateKey, RootCertStore,
+        ServerConfig, ServerSession, Session, StreamOwned,
+    },
+    TlsAcceptor,
+};
+use tungstenite::{self, handshake::HandshakeError, Error as WsError, Message};
+
+/// How often websocket connections and the accept loop check whether the interface stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type ResponseFuture = Box<Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+
+/// Resolves the address an interface should listen on.
+pub fn listen_address(bind_address: &Option<String>, port: u16) -> Result<SocketAddr, String> {
+    let host = bind_address
+        .as_ref()
+        .map(|address| address.as_str())
+        .unwrap_or(DEFAULT_BIND_ADDRESS);
+    (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Invalid bind address {}: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("Bind address {} did not resolve", host))
+}
+
+fn open(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("Could not open {}: {}", path, e))
+}
+
+fn load_certificates(path: &str) -> Result<Vec<Certificate>, String> {
+    let certificates = certs(&mut open(path)?)
+        .map_err(|_| format!("Could not read certificates from {}", path))?;
+    if certificates.is_empty() {
+        return Err(format!("No certificates found in {}", path));
+    }
+    Ok(certificates)
+}
+
+fn load_private_key(path: &str) -> Result<PrivateKey, String> {
+    let read_error = |_| format!("Could not read private key from {}", path);
+    let mut keys = pkcs8_private_keys(&mut open(path)?).map_err(read_error)?;
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut open(path)?).map_err(read_error)?;
+    }
+    keys.into_iter()
+        .next()
+        .ok_or_else(|| format!("No private key found in {}", path))
+}
+
+/// Creates the rustls server configuration, loading all referenced files.
+pub fn server_config(tls: &TlsConfiguration) -> Result<Arc<ServerConfig>, String> {
+    let verifier = match tls.client_ca {
+        Some(ref client_ca) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in load_certificates(client_ca)? {
+                roots.add(&certificate).map_err(|e| {
+                    format!("Invalid client CA certificate in {}: {:?}", client_ca, e)
+                })?;
+            }
+            AllowAnyAuthenticatedClient::new(roots)
+        }
+        None => NoClientAuth::new(),
+    };
+    let mut config = ServerConfig::new(verifier);
+    config
+        .set_single_cert(
+            load_certificates(&tls.certificate)?,
+            load_private_key(&tls.key)?,
+        )
+        .map_err(|e| format!("Invalid TLS certificate or key: {:?}", e))?;
+    Ok(Arc::new(config))
+}
+
+/// Serves JSON-RPC over HTTPS on `address` until the kill switch gets triggered.
+/// Requests have to be POSTed, like with the plain HTTP interface. Connections that fail
+/// the handshake (e.g. because the client did not present an acceptable certificate)
+/// get dropped.
+pub fn serve_http(
+    address: &SocketAddr,
+    handler: IoHandler,
+    config: Arc<ServerConfig>,
+    kill_switch: Receiver<()>,
+) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|e| e.to_string())?;
+    let acceptor = TlsAcceptor::from(config);
+    let handler = Arc::new(handler);
+    let http = Arc::new(Http::new());
+    let server = listener.incoming().for_each(move |stream| {
+        let handler = handler.clone();
+        let http = http.clone();
+        let connection = acceptor
+            .accept(stream)
+            .map_err(|_| ())
+            .and_then(move |tls_stream| {
+                http.serve_connection(
+                    tls_stream,
+                    service_fn(move |request| http_response(&handler, request)),
+                )
+                .map_err(|_| ())
+            });
+        spawn(connection);
+        Ok(())
+    });
+
+    let mut runtime = Runtime::new().map_err(|e| e.to_string())?;
+    runtime.spawn(server.map_err(|_| ()));
+    let _ = kill_switch.recv();
+    let _ = runtime.shutdown_now().wait();
+    Ok(())
+}
+
+fn http_response(handler: &Arc<IoHandler>, request: Request<Body>) -> ResponseFuture {
+    if request.method() != Method::POST {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+        return Box::new(future::ok(response));
+    }
+    let handler = handler.clone();
+    Box::new(request.into_body().concat2().and_then(move |body| {
+        let request = String::from_utf8_lossy(&body).into_owned();
+        handler.handle_request(&request).then(|result| {
+            let mut response = Response::new(Body::empty());
+            match result {
+                Ok(Some(json)) => {
+                    *response.body_mut() = Body::from(json);
+                    response.headers_mut().insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                }
+                // Notifications don't get a response
+                _ => *response.status_mut() = StatusCode::NO_CONTENT,
+            }
+            Ok::<_, hyper::Error>(response)
+        })
+    }))
+}
+
+/// Serves JSON-RPC over websockets with TLS on `address` until the kill switch gets
+/// triggered. Every connection gets served on its own thread, which ends when the
+/// client disconnects or the interface stops.
+pub fn serve_websocket(
+    address: &SocketAddr,
+    handler: IoHandler,
+    config: Arc<ServerConfig>,
+    kill_switch: Receiver<()>,
+) -> Result<(), String> {
+    let listener = StdTcpListener::bind(address).map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let handler = Arc::new(handler);
+    let stopped = Arc::new(AtomicBool::new(false));
+    // Connection threads with flags they set when they end
+    let mut connections: Vec<(Arc<AtomicBool>, JoinHandle<()>)> = Vec::new();
+    let result = loop {
+        match kill_switch.try_recv() {
+            Err(TryRecvError::Empty) => (),
+            _ => break Ok(()),
+        }
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let handler = handler.clone();
+                let config = config.clone();
+                let stopped = stopped.clone();
+                let finished = Arc::new(AtomicBool::new(false));
+                let finished_child = finished.clone();
+                connections.retain(|(finished, _)| !finished.load(Ordering::Relaxed));
+                let spawned = thread::Builder::new()
+                    .name(format!("wss-connection: {}", address))
+                    .spawn(move || {
+                        let _ = serve_websocket_connection(stream, config, &handler, &stopped);
+                        finished_child.store(true, Ordering::Relaxed);
+                    });
+                if let Ok(connection) = spawned {
+                    connections.push((finished, connection));
+                }
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => break Err(e.to_string()),
+        }
+    };
+    stopped.store(true, Ordering::Relaxed);
+    for (_, connection) in connections {
+        let _ = connection.join();
+    }
+    result
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut
+}
+
+fn serve_websocket_connection(
+    stream: StdTcpStream,
+    config: Arc<ServerConfig>,
+    handler: &IoHandler,
+    stopped: &AtomicBool,
+) -> Result<(), String> {
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    // Lets blocked reads return regularly so the connection notices when the interface stops
+    stream
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+    let mut tls_stream = StreamOwned::new(ServerSession::new(&config), stream);
+    while tls_stream.sess.is_handshaking() {
+        if stopped.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        match tls_stream.sess.complete_io(&mut tls_stream.sock) {
+            Ok(_) => (),
+            Err(ref e) if is_timeout(e) => (),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let mut handshake = tungstenite::accept(tls_stream);
+    let mut websocket = loop {
+        if stopped.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        handshake = match handshake {
+            Ok(websocket) => break websocket,
+            Err(HandshakeError::Interrupted(mid_handshake)) => mid_handshake.handshake(),
+            Err(HandshakeError::Failure(e)) => return Err(e.to_string()),
+        };
+    };
+
+    while !stopped.load(Ordering::Relaxed) {
+        let message = match websocket.read_message() {
+            Ok(message) => message,
+            Err(WsError::Io(ref e)) if is_timeout(e) => continue,
+            Err(WsError::ConnectionClosed(_)) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let request = match message {
+            Message::Text(text) => text,
+            Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            _ => continue,
+        };
+        if let Some(response) = handler.handle_request_sync(&request) {
+            websocket
+                .write_message(Message::Text(response))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let _ = websocket.close(None);
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn listen_address_defaults_to_all_interfaces() {
+        assert_eq!(listen_address(&None, 8888), Ok(([0, 0, 0, 0], 8888).into()));
+        assert_eq!(
+            listen_address(&Some("127.0.0.1".to_string()), 8888),
+            Ok(([127, 0, 0, 1], 8888).into())
+        );
+        assert!(listen_address(&Some("not an address".to_string()), 8888).is_err());
+    }
+
+    #[test]
+    fn server_config_fails_on_missing_files() {
+        let tls = TlsConfiguration {
+            certificate: "/does/not/exist/cert.pem".to_string(),
+            key: "/does/not/exist/key.pem".to_string(),
+            client_ca: None,
+        };
+        assert_eq!(
+            server_config(&tls)
+                .err()
+                .map(|e| e.starts_with("Could not open")),
+            Some(true)
+        );
+    }
+}
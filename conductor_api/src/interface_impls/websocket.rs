@@ -1,24 +1,50 @@
+use config::TlsConfiguration;
 use interface::Interface;
+use interface_impls::tls;
 use jsonrpc_ws_server::{jsonrpc_core::IoHandler, ServerBuilder};
 use std::sync::mpsc::Receiver;
 
 pub struct WebsocketInterface {
     port: u16,
+    bind_address: Option<String>,
+    tls: Option<TlsConfiguration>,
 }
 
 impl WebsocketInterface {
     pub fn new(port: u16) -> Self {
-        WebsocketInterface { port }
+        WebsocketInterface {
+            port,
+            bind_address: None,
+            tls: None,
+        }
+    }
+
+    pub fn with_bind_address(mut self, bind_address: Option<String>) -> Self {
+        self.bind_address = bind_address;
+        self
+    }
+
+    pub fn with_tls(mut self, tls: Option<TlsConfiguration>) -> Self {
+        self.tls = tls;
+        self
     }
 }
 
 impl Interface for WebsocketInterface {
     fn run(&self, handler: IoHandler, kill_switch: Receiver<()>) -> Result<(), String> {
-        let url = format!("0.0.0.0:{}", self.port);
-        let _server = ServerBuilder::new(handler)
-            .start(&url.parse().expect("Invalid URL!"))
-            .map_err(|e| e.to_string())?;
-        let _ = kill_switch.recv();
-        Ok(())
+        let address = tls::listen_address(&self.bind_address, self.port)?;
+        match self.tls {
+            None => {
+                let _server = ServerBuilder::new(handler)
+                    .start(&address)
+                    .map_err(|e| e.to_string())?;
+                let _ = kill_switch.recv();
+                Ok(())
+            }
+            Some(ref tls_config) => {
+                let server_config = tls::server_config(tls_config)?;
+                tls::serve_websocket(&address, handler, server_config, kill_switch)
+            }
+        }
     }
 }
@@ -0,0 +1,195 @@
+//! Agent keys managed by the conductor.
+//!
+//! The keystore is a directory in the conductor's persistence dir that holds one
+//! passphrase-encrypted `KeyBlob` per agent key, named after the key's public address.
+//! Key files only ever get written encrypted, so exporting them is safe. Decrypted key
+//! bundles only live in the conductor's memory, see `Conductor::unlock_agent_key`.
+use holochain_core_types::{agent::Base32, error::HolochainError};
+use holochain_dpki::{
+    key_blob::{Blobbable, KeyBlob},
+    key_bundle::KeyBundle,
+    password_encryption::PwHashConfig,
+    seed::SeedType,
+    SEED_SIZE,
+};
+use holochain_sodium::secbuf::SecBuf;
+use serde_json;
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+/// Hint stored with keys generated by the conductor
+const KEY_HINT: &str = "conductor keystore";
+
+fn passphrase_buf(passphrase: &str) -> SecBuf {
+    SecBuf::with_insecure_from_string(passphrase.to_string())
+}
+
+/// Reads an encrypted key file as written by `hc keygen` or the keystore.
+pub fn read_key_blob(path: &PathBuf) -> Result<KeyBlob, HolochainError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Decrypts a key blob with the given passphrase.
+pub fn unlock_key_blob(
+    blob: &KeyBlob,
+    passphrase: &str,
+    config: Option<PwHashConfig>,
+) -> Result<KeyBundle, HolochainError> {
+    KeyBundle::from_blob(blob, &mut passphrase_buf(passphrase), config).map_err(|_| {
+        HolochainError::ErrorGeneric(String::from(
+            "Could not decrypt key, wrong passphrase or corrupt key file",
+        ))
+    })
+}
+
+pub struct Keystore {
+    dir: PathBuf,
+    config: Option<PwHashConfig>,
+}
+
+impl Keystore {
+    /// Keystore in the given directory that encrypts with the default (sensitive)
+    /// password hashing settings, which makes its files loadable by `Conductor::load_key`.
+    pub fn new(dir: PathBuf) -> Self {
+        Keystore { dir, config: None }
+    }
+
+    /// Keystore with custom password hashing settings, e.g. for faster tests.
+    pub fn with_config(dir: PathBuf, config: Option<PwHashConfig>) -> Self {
+        Keystore { dir, config }
+    }
+
+    /// Path of the key file for the given public address
+    pub fn key_path(&self, public_address: &Base32) -> PathBuf {
+        self.dir.join(format!("{}.key", public_address))
+    }
+
+    fn write(&self, public_address: &Base32, blob: &KeyBlob) -> Result<PathBuf, HolochainError> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.key_path(public_address);
+        if path.exists() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Key for {} already exists in keystore",
+                public_address
+            )));
+        }
+        File::create(&path)?.write_all(serde_json::to_string(blob)?.as_bytes())?;
+        Ok(path)
+    }
+
+    /// Creates a new random agent key, stores it encrypted with `passphrase` and
+    /// returns its public address and the path of its key file.
+    pub fn generate(&self, passphrase: &str) -> Result<(Base32, PathBuf), HolochainError> {
+        let mut seed = SecBuf::with_secure(SEED_SIZE);
+        seed.randomize();
+        let mut keybundle = KeyBundle::new_from_seed_buf(&mut seed, SeedType::Mock)?;
+        let blob = keybundle.as_blob(
+            &mut passphrase_buf(passphrase),
+            KEY_HINT.to_string(),
+            self.config.clone(),
+        )?;
+        let public_address = keybundle.get_id();
+        let path = self.write(&public_address, &blob)?;
+        Ok((public_address, path))
+    }
+
+    /// Stores an existing encrypted key.
+    /// The passphrase is only used to check that the key can be decrypted and to read
+    /// its public address; the blob gets stored as it is.
+    pub fn import(
+        &self,
+        blob: &KeyBlob,
+        passphrase: &str,
+    ) -> Result<(Base32, PathBuf), HolochainError> {
+        let public_address = unlock_key_blob(blob, passphrase, self.config.clone())?.get_id();
+        let path = self.write(&public_address, blob)?;
+        Ok((public_address, path))
+    }
+
+    /// Returns the encrypted key for the given public address.
+    pub fn export(&self, public_address: &Base32) -> Result<KeyBlob, HolochainError> {
+        // Public addresses come from callers and become a file name, so they must not be
+        // able to point outside of the keystore (like "../../some/file")
+        let is_address =
+            !public_address.is_empty() && public_address.chars().all(|c| c.is_ascii_alphanumeric());
+        if !is_address {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Invalid public address: {}",
+                public_address
+            )));
+        }
+        let path = self.key_path(public_address);
+        if !path.exists() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "No key for {} in keystore",
+                public_address
+            )));
+        }
+        read_key_blob(&path)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+    use holochain_sodium::pwhash;
+
+    fn test_keystore(dir: PathBuf) -> Keystore {
+        Keystore::with_config(
+            dir,
+            Some(PwHashConfig(
+                pwhash::OPSLIMIT_INTERACTIVE,
+                pwhash::MEMLIMIT_INTERACTIVE,
+                pwhash::ALG_ARGON2ID13,
+            )),
+        )
+    }
+
+    #[test]
+    fn generated_keys_can_be_exported_and_imported() {
+        let dir = tempdir().unwrap();
+        let keystore = test_keystore(dir.path().join("keys"));
+
+        let (public_address, path) = keystore.generate("secret").unwrap();
+        assert_eq!(path, keystore.key_path(&public_address));
+
+        let blob = keystore.export(&public_address).unwrap();
+        let keybundle = unlock_key_blob(&blob, "secret", keystore.config.clone()).unwrap();
+        assert_eq!(keybundle.get_id(), public_address);
+        assert!(unlock_key_blob(&blob, "wrong", keystore.config.clone()).is_err());
+
+        // Same key can't be stored twice
+        assert!(keystore.import(&blob, "secret").is_err());
+
+        let other = test_keystore(dir.path().join("other"));
+        assert!(other.import(&blob, "wrong").is_err());
+        assert_eq!(
+            other.import(&blob, "secret").unwrap().0,
+            public_address.clone()
+        );
+        assert!(other.export(&String::from("unknown")).is_err());
+    }
+
+    #[test]
+    fn export_rejects_paths() {
+        let dir = tempdir().unwrap();
+        let keystore = test_keystore(dir.path().join("keys"));
+        fs::write(dir.path().join("secret.key"), "{}").unwrap();
+
+        let result = keystore.export(&String::from("../secret"));
+        assert_eq!(
+            result.err(),
+            Some(HolochainError::ErrorGeneric(String::from(
+                "Invalid public address: ../secret"
+            )))
+        );
+        assert!(keystore.export(&String::new()).is_err());
+    }
+}
@@ -118,13 +118,19 @@ extern crate toml;
 extern crate maplit;
 #[macro_use]
 extern crate lazy_static;
+extern crate brotli;
 extern crate directories;
+extern crate flate2;
 extern crate hyper;
-extern crate hyper_staticfile;
 extern crate json_patch;
+extern crate multihash;
+extern crate native_tls;
 // #[cfg(test)]
 // extern crate reqwest;
 extern crate tokio;
+extern crate tokio_rustls;
+extern crate tungstenite;
+extern crate url;
 #[cfg(test)]
 #[macro_use]
 extern crate pretty_assertions;
@@ -135,12 +141,16 @@ pub mod conductor;
 pub mod config;
 pub mod context_builder;
 pub mod error;
-pub mod holo_signing_service;
 pub mod holochain;
 pub mod interface;
+pub mod interface_auth;
 pub mod interface_impls;
 pub mod key_loaders;
+pub mod keystore;
 pub mod logger;
+pub mod metrics;
+pub mod signers;
 pub mod static_file_server;
+pub mod zome_api;
 
 pub use crate::holochain::Holochain;
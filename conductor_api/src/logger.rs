@@ -1,9 +1,18 @@
 use chrono::{DateTime, Local};
 use colored::*;
+use config::LoggerConfiguration;
 use holochain_core::logger::{ChannelLogger, Sender};
 use holochain_core_types::error::HolochainError;
 use regex::Regex;
-use std::thread;
+use serde_json;
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    thread,
+};
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct LogRule {
@@ -15,7 +24,15 @@ pub struct LogRule {
     pub color: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+impl PartialEq for LogRule {
+    fn eq(&self, other: &LogRule) -> bool {
+        self.pattern.as_str() == other.pattern.as_str()
+            && self.exclude == other.exclude
+            && self.color == other.color
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct LogRules {
     pub rules: Vec<LogRule>,
 }
@@ -80,28 +97,235 @@ impl LogRules {
     }
 }
 
+/// How log messages get written: colorized text for terminals
+/// or one JSON object per line for log aggregation.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> LogFormat {
+        LogFormat::Text
+    }
+}
+
+/// When a log file gets moved aside and a new one started.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LogRotation {
+    /// Rotate before the file would grow beyond `max_bytes`
+    Size {
+        max_bytes: u64,
+    },
+    /// Rotate at the first message of every hour
+    Hourly,
+    /// Rotate at the first message of every day
+    Daily,
+    Never,
+}
+
+impl Default for LogRotation {
+    fn default() -> LogRotation {
+        LogRotation::Size {
+            max_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl LogRotation {
+    /// Identifies the time period a message belongs to, so we know when to rotate.
+    fn period(&self, date: &DateTime<Local>) -> String {
+        match self {
+            LogRotation::Hourly => date.format("%Y-%m-%d %H").to_string(),
+            LogRotation::Daily => date.format("%Y-%m-%d").to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+fn default_log_file_format() -> LogFormat {
+    LogFormat::Json
+}
+
+fn default_log_file_keep() -> usize {
+    5
+}
+
+/// Configures the log file sink of the debug logger.
+/// Rotated files get numbered: `<path>.1` is the newest, `<path>.<keep>` the oldest.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct LogFileConfiguration {
+    pub path: String,
+    #[serde(default = "default_log_file_format")]
+    pub format: LogFormat,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    /// Number of rotated files to keep
+    #[serde(default = "default_log_file_keep")]
+    pub keep: usize,
+}
+
+/// Log file that rotates itself according to its configuration.
+pub struct LogFile {
+    config: LogFileConfiguration,
+    path: PathBuf,
+    file: File,
+    size: u64,
+    period: String,
+}
+
+impl LogFile {
+    pub fn open(config: LogFileConfiguration) -> Result<Self, HolochainError> {
+        let path = PathBuf::from(&config.path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        // Time based rotation has to continue where a previous run left off
+        let modified: DateTime<Local> = match metadata.modified() {
+            Ok(time) => time.into(),
+            Err(_) => Local::now(),
+        };
+        Ok(LogFile {
+            period: config.rotation.period(&modified),
+            config,
+            path,
+            file,
+            size: metadata.len(),
+        })
+    }
+
+    fn rotated_path(&self, number: usize) -> PathBuf {
+        PathBuf::from(format!("{}.{}", self.path.display(), number))
+    }
+
+    fn rotate(&mut self) -> Result<(), HolochainError> {
+        if self.config.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(self.config.keep);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for number in (1..self.config.keep).rev() {
+                let rotated = self.rotated_path(number);
+                if rotated.exists() {
+                    fs::rename(&rotated, self.rotated_path(number + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    pub fn write(&mut self, message: &LogMessage) -> Result<(), HolochainError> {
+        let line = match self.config.format {
+            LogFormat::Text => message.to_text(),
+            LogFormat::Json => message.to_json(),
+        } + "\n";
+        let period = self.config.rotation.period(&message.date);
+        let rotate = match self.config.rotation {
+            LogRotation::Size { max_bytes } => {
+                self.size > 0 && self.size + line.len() as u64 > max_bytes
+            }
+            LogRotation::Hourly | LogRotation::Daily => period != self.period,
+            LogRotation::Never => false,
+        };
+        if rotate {
+            self.rotate()?;
+        }
+        self.period = period;
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
 // The DebugLogger implements a receiver for the instance ChannelLogger
 // which allows for configurable colorization and filtering of log messages.
+// Messages can additionally be written to a rotating log file.
 pub struct DebugLogger {
     sender: Sender,
+    instance_rules: Arc<RwLock<HashMap<String, LogRules>>>,
 }
 
 impl DebugLogger {
     pub fn new(rules: LogRules) -> Self {
+        Self::with_sinks(rules, LogFormat::Text, None)
+    }
+
+    /// Sets up the logger as configured. If the log file can't be opened, this gets
+    /// reported and the logger only writes to stdout.
+    pub fn from_config(config: &LoggerConfiguration) -> Self {
+        let file =
+            config
+                .file
+                .as_ref()
+                .and_then(|file_config| match LogFile::open(file_config.clone()) {
+                    Ok(file) => Some(file),
+                    Err(error) => {
+                        eprintln!("Could not open log file {}: {}", file_config.path, error);
+                        None
+                    }
+                });
+        Self::with_sinks(config.rules.clone(), config.format.clone(), file)
+    }
+
+    fn with_sinks(rules: LogRules, format: LogFormat, mut file: Option<LogFile>) -> Self {
         let (tx, rx) = ChannelLogger::setup();
-        let logger = DebugLogger { sender: tx.clone() };
+        let instance_rules = Arc::new(RwLock::new(HashMap::new()));
+        let logger = DebugLogger {
+            sender: tx.clone(),
+            instance_rules: instance_rules.clone(),
+        };
 
         thread::spawn(move || loop {
             match rx.recv() {
-                Ok((id, msg)) => run(&rules, id, msg),
+                Ok((id, msg)) => {
+                    let message = match instance_rules.read().unwrap().get(&id) {
+                        Some(rules) => rules.run(id, msg),
+                        None => rules.run(id, msg),
+                    };
+                    if let Some(message) = message {
+                        if let Some(ref mut file) = file {
+                            if let Err(error) = file.write(&message) {
+                                eprintln!("Could not write to log file: {}", error);
+                            }
+                        }
+                        match format {
+                            LogFormat::Text => render(message),
+                            LogFormat::Json => println!("{}", message.to_json()),
+                        }
+                    }
+                }
                 Err(_) => break,
             }
         });
         logger
     }
+
     pub fn get_sender(&self) -> Sender {
         self.sender.clone()
     }
+
+    /// Makes messages of the given instance get filtered by their own rules instead of
+    /// the conductor wide ones. Passing `None` switches back to the conductor's rules.
+    pub fn set_instance_rules(&self, id: &str, rules: Option<LogRules>) {
+        let mut instance_rules = self.instance_rules.write().unwrap();
+        match rules {
+            Some(rules) => instance_rules.insert(id.to_string(), rules),
+            None => instance_rules.remove(id),
+        };
+    }
 }
 
 // run checks a message against the rules and renders it if it matches
@@ -147,11 +371,93 @@ pub struct LogMessage {
     color: Option<String>,
 }
 
+/// Log message as it gets written in the JSON format
+#[derive(Serialize)]
+struct JsonLogLine<'a> {
+    timestamp: String,
+    instance: &'a str,
+    level: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<&'a str>,
+    message: &'a str,
+}
+
+impl LogMessage {
+    /// Splits messages like "err/net/dht: some text" into level ("error"),
+    /// module ("net/dht") and message. Messages without a known level prefix
+    /// are treated as "info".
+    pub fn parts(&self) -> (&str, Option<&str>, &str) {
+        lazy_static! {
+            static ref PREFIX: Regex = Regex::new(r"^(\w+)(?:/([^:\s]+))?: ?").unwrap();
+        }
+        if let Some(captures) = PREFIX.captures(&self.msg) {
+            let level = match &captures[1] {
+                "err" | "error" => Some("error"),
+                "warn" | "warning" => Some("warn"),
+                "info" => Some("info"),
+                "debug" => Some("debug"),
+                "trace" => Some("trace"),
+                _ => None,
+            };
+            if let Some(level) = level {
+                let module = captures.get(2).map(|module| module.as_str());
+                let rest = &self.msg[captures.get(0).unwrap().end()..];
+                return (level, module, rest);
+            }
+        }
+        ("info", None, &self.msg)
+    }
+
+    /// Renders the message as single line JSON object
+    pub fn to_json(&self) -> String {
+        let (level, module, message) = self.parts();
+        serde_json::to_string(&JsonLogLine {
+            timestamp: self.date.to_rfc3339(),
+            instance: &self.id,
+            level,
+            module,
+            message,
+        })
+        .expect("log line must be serializable")
+    }
+
+    /// Renders the message like `render` does, just without colors
+    pub fn to_text(&self) -> String {
+        format!(
+            "{}:{}: {}",
+            self.date.format("%Y-%m-%d %H:%M:%S"),
+            self.id,
+            self.msg
+        )
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
     use super::*;
+    use std::io::Read;
     use toml;
 
+    fn test_message(msg: &str) -> LogMessage {
+        LogMessage {
+            date: Local::now(),
+            id: "app".to_string(),
+            msg: msg.to_string(),
+            color: None,
+        }
+    }
+
+    fn read_file(path: &PathBuf) -> String {
+        let mut contents = String::new();
+        File::open(path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
     #[test]
     fn test_log_rules() {
         let mut rules = LogRules::new();
@@ -210,4 +516,70 @@ color = "blue"
         assert!(rules1.rules[1].pattern.is_match("bar"));
         assert_eq!(rules1.rules[1].color, Some("blue".to_string()));
     }
+
+    #[test]
+    fn test_message_parts() {
+        assert_eq!(
+            test_message("err/net/dht: no entry").parts(),
+            ("error", Some("net/dht"), "no entry")
+        );
+        assert_eq!(
+            test_message("debug/dna: 'hello'").parts(),
+            ("debug", Some("dna"), "'hello'")
+        );
+        assert_eq!(test_message("debug: x").parts(), ("debug", None, "x"));
+        assert_eq!(
+            test_message("something/else: x").parts(),
+            ("info", None, "something/else: x")
+        );
+    }
+
+    #[test]
+    fn test_json_format() {
+        let message = test_message("err/net: \"quoted\"\nsecond line");
+        let json: serde_json::Value = serde_json::from_str(&message.to_json()).unwrap();
+        assert_eq!(json["instance"], "app");
+        assert_eq!(json["level"], "error");
+        assert_eq!(json["module"], "net");
+        assert_eq!(json["message"], "\"quoted\"\nsecond line");
+        assert_eq!(json["timestamp"], message.date.to_rfc3339());
+        assert!(!message.to_json().contains('\n'));
+    }
+
+    #[test]
+    fn test_log_file_rotates_by_size() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("logs").join("conductor.log");
+        let mut file = LogFile::open(LogFileConfiguration {
+            path: path.to_str().unwrap().to_string(),
+            format: LogFormat::Text,
+            rotation: LogRotation::Size { max_bytes: 100 },
+            keep: 2,
+        })
+        .unwrap();
+        for i in 0..4 {
+            // each line is longer than half of max_bytes
+            file.write(&test_message(&format!("{}{}", i, "x".repeat(50))))
+                .unwrap();
+        }
+        assert!(read_file(&path).contains(":app: 3x"));
+        assert!(read_file(&PathBuf::from(format!("{}.1", path.display()))).contains(":app: 2x"));
+        assert!(read_file(&PathBuf::from(format!("{}.2", path.display()))).contains(":app: 1x"));
+        assert!(!PathBuf::from(format!("{}.3", path.display())).exists());
+    }
+
+    #[test]
+    fn test_log_file_config_defaults() {
+        let config: LogFileConfiguration = toml::from_str("path = \"conductor.log\"").unwrap();
+        assert_eq!(config.format, LogFormat::Json);
+        assert_eq!(config.rotation, LogRotation::default());
+        assert_eq!(config.keep, 5);
+        let config: LogFileConfiguration = toml::from_str(
+            r#"path = "conductor.log"
+[rotation]
+type = "daily""#,
+        )
+        .unwrap();
+        assert_eq!(config.rotation, LogRotation::Daily);
+    }
 }
@@ -0,0 +1,446 @@
+//! Export of conductor and instance metrics in Prometheus text format.
+//!
+//! The metrics get collected by core (see `holochain_core::metrics`) and rendered on every
+//! request, so the endpoint always reflects the current state of the conductor.
+//! Only storage statistics, which take reading everything an instance stored, get collected
+//! periodically in the background (see `StorageStatsRefresher`).
+use config::MetricsConfiguration;
+use holochain_core::{
+    metrics::{MetricsSnapshot, LATENCY_BUCKETS},
+    storage_stats::StorageStats,
+};
+use hyper::{rt::Future, service::service_fn_ok, Body, Request, Response, Server, StatusCode};
+use interface::InstanceMap;
+use interface_impls::tls::listen_address;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+use tokio::runtime::Runtime;
+
+/// Path the metrics get served at
+pub const METRICS_ROUTE: &str = "/metrics";
+
+/// Everything we export about one instance
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstanceMetrics {
+    pub active: bool,
+    pub metrics: MetricsSnapshot,
+    pub storage: Option<StorageStats>,
+}
+
+/// Renders the response body of the metrics endpoint
+pub type MetricsSource = Arc<Box<Fn() -> String + Send + Sync>>;
+
+/// Latest storage statistics by instance ID
+pub type StorageStatsCache = Arc<RwLock<BTreeMap<String, StorageStats>>>;
+
+/// Returns the instances to collect storage statistics for
+pub type InstancesSource = Box<Fn() -> InstanceMap + Send>;
+
+/// How often the storage statistics get collected
+pub const STORAGE_STATS_INTERVAL: Duration = Duration::from_secs(60);
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// One metric family with its samples (label set and value)
+struct Family {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    samples: Vec<(String, String, f64)>,
+}
+
+impl Family {
+    fn new(name: &'static str, kind: &'static str, help: &'static str) -> Self {
+        Family {
+            name,
+            kind,
+            help,
+            samples: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, labels: String, value: f64) {
+        self.add_with_suffix("", labels, value);
+    }
+
+    fn add_with_suffix(&mut self, suffix: &str, labels: String, value: f64) {
+        self.samples.push((suffix.to_string(), labels, value));
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, self.kind);
+        for (suffix, labels, value) in self.samples.iter() {
+            let _ = writeln!(out, "{}{}{} {}", self.name, suffix, labels, value);
+        }
+    }
+}
+
+/// Renders the metrics of all instances (by instance ID) in Prometheus text format.
+pub fn render_prometheus(instances: &BTreeMap<String, InstanceMetrics>) -> String {
+    let mut active = Family::new(
+        "holochain_instance_active",
+        "gauge",
+        "Whether the instance is running (1) or stopped (0).",
+    );
+    let mut zome_calls = Family::new(
+        "holochain_zome_calls_total",
+        "counter",
+        "Number of finished zome function calls.",
+    );
+    let mut zome_call_errors = Family::new(
+        "holochain_zome_call_errors_total",
+        "counter",
+        "Number of zome function calls that returned an error.",
+    );
+    let mut zome_call_duration = Family::new(
+        "holochain_zome_call_duration_seconds",
+        "histogram",
+        "Duration of zome function calls.",
+    );
+    let mut calls_in_flight = Family::new(
+        "holochain_zome_calls_in_flight",
+        "gauge",
+        "Number of zome function calls that are currently running.",
+    );
+    let mut queue_depth = Family::new(
+        "holochain_action_queue_depth",
+        "gauge",
+        "Number of actions waiting to be processed by the instance's action loop.",
+    );
+    let mut validations = Family::new(
+        "holochain_validations_total",
+        "counter",
+        "Number of entry validations by result (passed, failed or postponed).",
+    );
+    let mut pending_validations = Family::new(
+        "holochain_pending_validations",
+        "gauge",
+        "Number of entries and links waiting for their dependencies to be validated.",
+    );
+    let mut network_messages = Family::new(
+        "holochain_network_messages_received_total",
+        "counter",
+        "Number of messages received from the network module by message type.",
+    );
+    let mut storage_bytes = Family::new(
+        "holochain_storage_bytes",
+        "gauge",
+        "Total size of the content stored by the instance.",
+    );
+    let mut chain_length = Family::new(
+        "holochain_chain_length",
+        "gauge",
+        "Number of headers in the instance's source chain.",
+    );
+
+    for (id, instance) in instances.iter() {
+        let id = id.as_str();
+        let instance_labels = labels(&[("instance", id)]);
+        let metrics = &instance.metrics;
+        active.add(
+            instance_labels.clone(),
+            if instance.active { 1.0 } else { 0.0 },
+        );
+
+        for (zome, functions) in metrics.zome_calls.iter() {
+            for (function, calls) in functions.iter() {
+                let call_labels = [
+                    ("instance", id),
+                    ("zome", zome.as_str()),
+                    ("function", function.as_str()),
+                ];
+                zome_calls.add(labels(&call_labels), calls.calls as f64);
+                zome_call_errors.add(labels(&call_labels), calls.errors as f64);
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(calls.buckets.iter()) {
+                    let bound = bound.to_string();
+                    let mut bucket_labels = call_labels.to_vec();
+                    bucket_labels.push(("le", bound.as_str()));
+                    zome_call_duration.add_with_suffix(
+                        "_bucket",
+                        labels(&bucket_labels),
+                        *count as f64,
+                    );
+                }
+                let mut inf_labels = call_labels.to_vec();
+                inf_labels.push(("le", "+Inf"));
+                zome_call_duration.add_with_suffix(
+                    "_bucket",
+                    labels(&inf_labels),
+                    calls.calls as f64,
+                );
+                zome_call_duration.add_with_suffix(
+                    "_sum",
+                    labels(&call_labels),
+                    calls.total_seconds,
+                );
+                zome_call_duration.add_with_suffix(
+                    "_count",
+                    labels(&call_labels),
+                    calls.calls as f64,
+                );
+            }
+        }
+
+        calls_in_flight.add(instance_labels.clone(), metrics.zome_calls_in_flight as f64);
+        queue_depth.add(instance_labels.clone(), metrics.action_queue_depth as f64);
+        for (result, count) in &[
+            ("passed", metrics.validations_passed),
+            ("failed", metrics.validations_failed),
+            ("postponed", metrics.validations_postponed),
+        ] {
+            validations.add(
+                labels(&[("instance", id), ("result", *result)]),
+                *count as f64,
+            );
+        }
+        pending_validations.add(instance_labels.clone(), metrics.pending_validations as f64);
+        for (method, count) in metrics.network_messages.iter() {
+            network_messages.add(
+                labels(&[("instance", id), ("method", method.as_str())]),
+                *count as f64,
+            );
+        }
+
+        if let Some(ref storage) = instance.storage {
+            storage_bytes.add(instance_labels.clone(), storage.total_bytes as f64);
+            chain_length.add(instance_labels, storage.chain_length as f64);
+        }
+    }
+
+    let mut out = String::new();
+    for family in &[
+        active,
+        zome_calls,
+        zome_call_errors,
+        zome_call_duration,
+        calls_in_flight,
+        queue_depth,
+        validations,
+        pending_validations,
+        network_messages,
+        storage_bytes,
+        chain_length,
+    ] {
+        family.render(&mut out);
+    }
+    out
+}
+
+fn metrics_response(request: &Request<Body>, source: &MetricsSource) -> Response<Body> {
+    if request.uri().path() != METRICS_ROUTE {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .expect("unable to build response");
+    }
+    Response::builder()
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(Body::from(source()))
+        .expect("unable to build response")
+}
+
+/// HTTP server that serves the metrics at `/metrics`
+pub struct MetricsServer {
+    config: MetricsConfiguration,
+    source: MetricsSource,
+    shutdown_signal: Option<Sender<()>>,
+}
+
+impl MetricsServer {
+    pub fn new(config: MetricsConfiguration, source: MetricsSource) -> Self {
+        MetricsServer {
+            config,
+            source,
+            shutdown_signal: None,
+        }
+    }
+
+    pub fn start(&mut self) -> Result<(), String> {
+        let address = listen_address(&self.config.bind_address, self.config.port)?;
+        let source = self.source.clone();
+        let server = Server::try_bind(&address)
+            .map_err(|e| format!("Could not bind metrics endpoint to {}: {}", address, e))?
+            .serve(move || {
+                let source = source.clone();
+                service_fn_ok(move |request| metrics_response(&request, &source))
+            })
+            .map_err(|_| ());
+
+        let (tx, rx) = channel::<()>();
+        self.shutdown_signal = Some(tx);
+        thread::Builder::new()
+            .name(String::from("metrics_server"))
+            .spawn(move || {
+                let mut runtime = Runtime::new().expect("Could not create runtime");
+                runtime.spawn(server);
+                let _ = rx.recv();
+                let _ = runtime.shutdown_now().wait();
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(shutdown_signal) = self.shutdown_signal.take() {
+            let _ = shutdown_signal.send(());
+        }
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Collects the storage statistics of all instances into a StorageStatsCache every
+/// STORAGE_STATS_INTERVAL on its own thread, so scrapes don't have to read the storages.
+pub struct StorageStatsRefresher {
+    stop_signal: Option<Sender<()>>,
+}
+
+impl StorageStatsRefresher {
+    /// `instances` gets called on the refresher's thread; the statistics get collected after
+    /// it returned, so it can briefly lock the conductor to get the instances.
+    pub fn start(cache: StorageStatsCache, instances: InstancesSource) -> Result<Self, String> {
+        let (tx, rx) = channel::<()>();
+        thread::Builder::new()
+            .name(String::from("storage_stats"))
+            .spawn(move || loop {
+                let stats = instances()
+                    .iter()
+                    .filter_map(|(id, instance)| {
+                        let stats = instance.read().unwrap().storage_stats().ok()?;
+                        Some((id.clone(), stats))
+                    })
+                    .collect();
+                *cache.write().unwrap() = stats;
+                match rx.recv_timeout(STORAGE_STATS_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(StorageStatsRefresher {
+            stop_signal: Some(tx),
+        })
+    }
+
+    /// Stops collecting without waiting for a collection that is in progress to finish
+    pub fn stop(&mut self) {
+        if let Some(stop_signal) = self.stop_signal.take() {
+            let _ = stop_signal.send(());
+        }
+    }
+}
+
+impl Drop for StorageStatsRefresher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use conductor::tests::test_conductor;
+    use holochain_core::metrics::Metrics;
+
+    #[test]
+    fn renders_instance_metrics() {
+        let metrics = Metrics::default();
+        metrics.record_zome_call("blog", "create_post", Duration::from_millis(20), false);
+        metrics.record_network_message("handleStoreEntry");
+        let mut instances = BTreeMap::new();
+        instances.insert(
+            String::from("app"),
+            InstanceMetrics {
+                active: true,
+                metrics: metrics.snapshot(),
+                storage: Some(StorageStats {
+                    chain_length: 3,
+                    total_bytes: 1024,
+                    ..Default::default()
+                }),
+            },
+        );
+
+        let text = render_prometheus(&instances);
+        let call_labels = r#"{instance="app",zome="blog",function="create_post""#;
+        assert!(text.contains("# TYPE holochain_zome_call_duration_seconds histogram\n"));
+        assert!(text.contains(&format!("holochain_zome_calls_total{}}} 1\n", call_labels)));
+        assert!(text.contains(&format!(
+            "holochain_zome_call_errors_total{}}} 1\n",
+            call_labels
+        )));
+        assert!(text.contains(&format!(
+            "holochain_zome_call_duration_seconds_bucket{},le=\"0.01\"}} 0\n",
+            call_labels
+        )));
+        assert!(text.contains(&format!(
+            "holochain_zome_call_duration_seconds_bucket{},le=\"0.025\"}} 1\n",
+            call_labels
+        )));
+        assert!(text.contains(&format!(
+            "holochain_zome_call_duration_seconds_bucket{},le=\"+Inf\"}} 1\n",
+            call_labels
+        )));
+        assert!(text.contains(
+            "holochain_network_messages_received_total{instance=\"app\",method=\"handleStoreEntry\"} 1\n"
+        ));
+        assert!(
+            text.contains("holochain_validations_total{instance=\"app\",result=\"passed\"} 0\n")
+        );
+        assert!(text.contains("holochain_instance_active{instance=\"app\"} 1\n"));
+        assert!(text.contains("holochain_storage_bytes{instance=\"app\"} 1024\n"));
+        assert!(text.contains("holochain_chain_length{instance=\"app\"} 3\n"));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(
+            labels(&[("instance", "a\"b\\c\nd")]),
+            r#"{instance="a\"b\\c\nd"}"#
+        );
+    }
+
+    #[test]
+    fn refresher_collects_storage_stats() {
+        let conductor = test_conductor();
+        let instances = conductor.instances().clone();
+        let cache: StorageStatsCache = Arc::new(RwLock::new(BTreeMap::new()));
+        let mut refresher =
+            StorageStatsRefresher::start(cache.clone(), Box::new(move || instances.clone()))
+                .expect("Could not start refresher");
+
+        let mut tries = 0;
+        while cache.read().unwrap().is_empty() && tries < 100 {
+            thread::sleep(Duration::from_millis(50));
+            tries += 1;
+        }
+        assert!(cache.read().unwrap().contains_key("test-instance-1"));
+        refresher.stop();
+    }
+}
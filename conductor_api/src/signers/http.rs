@@ -0,0 +1,58 @@
+use boolinator::Boolinator;
+use holochain_core_types::{agent::Base32, error::HolochainError};
+use signers::Signer;
+use std::time::Duration;
+
+/// Signer behind an HTTP endpoint, like the Holo signing service.
+/// The payload gets POSTed as `{"agent_id": ..., "payload": ...}` and the response body is
+/// the signature. If a token is configured it is sent as `Authorization: Bearer <token>`.
+pub struct HttpSigner {
+    url: String,
+    agent_id: Base32,
+    token: Option<String>,
+    timeout: Duration,
+}
+
+impl HttpSigner {
+    pub fn new(url: String, agent_id: Base32, token: Option<String>, timeout: Duration) -> Self {
+        HttpSigner {
+            url,
+            agent_id,
+            token,
+            timeout,
+        }
+    }
+}
+
+impl Signer for HttpSigner {
+    fn sign(&self, payload: &str) -> Result<String, HolochainError> {
+        let body_json = json!({"agent_id": self.agent_id, "payload": payload});
+        let body = serde_json::to_string(&body_json).unwrap();
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| {
+                HolochainError::ErrorGeneric(format!("Could not create HTTP client: {}", e))
+            })?;
+        let url = reqwest::Url::parse(&self.url).map_err(|_| {
+            HolochainError::ConfigError(format!("Can't parse signing service URI: '{}'", self.url))
+        })?;
+        let mut request = client.post(url).body(body);
+        if let Some(ref token) = self.token {
+            request = request.header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let mut response = request.send().map_err(|e| {
+            HolochainError::ErrorGeneric(format!("Error during signing request: {:?}", e))
+        })?;
+        response
+            .status()
+            .is_success()
+            .ok_or(HolochainError::new(&format!(
+                "Status of response from signing service is not success, but: {:?}",
+                response.status()
+            )))?;
+        response
+            .text()
+            .map_err(|_| HolochainError::new("Signing service response has no text"))
+    }
+}
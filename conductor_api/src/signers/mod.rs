@@ -0,0 +1,217 @@
+//! Where signatures of agents come from.
+//!
+//! Instances get their signatures through the conductor API's `agent/sign` method, which
+//! delegates to the `Signer` of the instance's agent. By default that is a `LocalSigner`
+//! with the agent's key from its key file or the conductor's keystore. Agents can instead be
+//! configured to use a remote signer, in which case the private key never enters the
+//! conductor's process (see [SignerConfiguration](../config/enum.SignerConfiguration.html)).
+//! Signatures of remote signers only get used after they were verified against the agent's
+//! public key.
+pub mod http;
+pub mod remote;
+#[cfg(any(test, feature = "test-signer"))]
+pub mod test_signer;
+
+pub use self::{http::*, remote::*};
+
+use config::SignerConfiguration;
+use holochain_core_types::{agent::Base32, error::HolochainError};
+use holochain_dpki::{key_bundle::KeyBundle, utils};
+use holochain_sodium::secbuf::SecBuf;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Size of an Ed25519 signature in bytes
+const SIGNATURE_SIZE: usize = 64;
+
+/// Produces signatures for one agent.
+/// Signatures are base64 encoded, as returned by `agent/sign`.
+pub trait Signer: Send + Sync {
+    fn sign(&self, payload: &str) -> Result<String, HolochainError>;
+}
+
+/// Signs with a key bundle in the conductor's memory, i.e. a key loaded from the agent's
+/// key file or unlocked from the conductor's keystore.
+pub struct LocalSigner {
+    keybundle: Arc<Mutex<KeyBundle>>,
+}
+
+impl LocalSigner {
+    pub fn new(keybundle: Arc<Mutex<KeyBundle>>) -> Self {
+        LocalSigner { keybundle }
+    }
+}
+
+impl Signer for LocalSigner {
+    fn sign(&self, payload: &str) -> Result<String, HolochainError> {
+        let mut message = SecBuf::with_insecure_from_string(payload.to_string());
+        // The key's secure memory has to be locked for signing, so we need a mutable reference
+        let mut signature = self.keybundle.lock().unwrap().sign(&mut message)?;
+        let signature = signature.read_lock();
+        Ok(base64::encode(&**signature))
+    }
+}
+
+/// Wraps another signer and only hands out signatures that are valid for the given
+/// public key, so a misconfigured or compromised signer can't make an instance publish
+/// entries with bogus signatures.
+pub struct VerifyingSigner {
+    signer: Box<Signer>,
+    public_key: Base32,
+}
+
+impl VerifyingSigner {
+    pub fn new(signer: Box<Signer>, public_key: Base32) -> Self {
+        VerifyingSigner { signer, public_key }
+    }
+}
+
+impl Signer for VerifyingSigner {
+    fn sign(&self, payload: &str) -> Result<String, HolochainError> {
+        let signature = self.signer.sign(payload)?;
+        verify_signature(&self.public_key, payload, &signature)?;
+        Ok(signature)
+    }
+}
+
+/// Checks that the given base64 encoded signature of `payload` was made with the private key
+/// that belongs to `public_key`.
+pub fn verify_signature(
+    public_key: &Base32,
+    payload: &str,
+    signature: &str,
+) -> Result<(), HolochainError> {
+    let signature_bytes = base64::decode(signature)?;
+    // libsodium reads a full signature, no matter how long the buffer is
+    if signature_bytes.len() != SIGNATURE_SIZE {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Signature has {} bytes instead of {}",
+            signature_bytes.len(),
+            SIGNATURE_SIZE
+        )));
+    }
+    let mut data = SecBuf::with_insecure_from_string(payload.to_string());
+    let mut signature_buf = SecBuf::with_insecure(SIGNATURE_SIZE);
+    signature_buf
+        .write(0, &signature_bytes)
+        .map_err(|e| HolochainError::ErrorGeneric(e.to_string()))?;
+    if utils::verify(public_key.clone(), &mut data, &mut signature_buf)? {
+        Ok(())
+    } else {
+        Err(HolochainError::ErrorGeneric(format!(
+            "Signature does not match the public key {}",
+            public_key
+        )))
+    }
+}
+
+/// Creates the remote signer described by the given config for the agent with the given
+/// public key. Its signatures get verified against that key.
+/// Domain socket signers are only available on Unix.
+pub fn remote_signer_from_config(
+    config: &SignerConfiguration,
+    public_key: &Base32,
+) -> Result<Box<Signer>, HolochainError> {
+    let signer: Box<Signer> = match config {
+        SignerConfiguration::Http {
+            url,
+            token,
+            timeout_ms,
+        } => Box::new(HttpSigner::new(
+            url.clone(),
+            public_key.clone(),
+            token.clone(),
+            Duration::from_millis(*timeout_ms),
+        )),
+        SignerConfiguration::Websocket {
+            url,
+            token,
+            timeout_ms,
+        } => Box::new(RemoteSigner::new(
+            RemoteSignerEndpoint::Websocket(url.clone()),
+            public_key.clone(),
+            token.clone(),
+            Duration::from_millis(*timeout_ms),
+        )),
+        #[cfg(unix)]
+        SignerConfiguration::DomainSocket {
+            file,
+            token,
+            timeout_ms,
+        } => Box::new(RemoteSigner::new(
+            RemoteSignerEndpoint::DomainSocket(PathBuf::from(file)),
+            public_key.clone(),
+            token.clone(),
+            Duration::from_millis(*timeout_ms),
+        )),
+        #[cfg(not(unix))]
+        SignerConfiguration::DomainSocket { .. } => {
+            return Err(HolochainError::ConfigError(String::from(
+                "Domain socket signers are only supported on Unix",
+            )));
+        }
+    };
+    Ok(Box::new(VerifyingSigner::new(signer, public_key.clone())))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use key_loaders::test_keybundle;
+
+    /// Signer that returns whatever it was given
+    struct FixedSigner(String);
+
+    impl Signer for FixedSigner {
+        fn sign(&self, _payload: &str) -> Result<String, HolochainError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn local_signatures_verify() {
+        let keybundle = test_keybundle(&String::from("alice"));
+        let public_key = keybundle.get_id();
+        let signer = LocalSigner::new(Arc::new(Mutex::new(keybundle)));
+        let signature = signer.sign("payload").unwrap();
+        assert_eq!(verify_signature(&public_key, "payload", &signature), Ok(()));
+        assert!(verify_signature(&public_key, "other payload", &signature).is_err());
+    }
+
+    #[test]
+    fn verifying_signer_rejects_foreign_and_malformed_signatures() {
+        let alice = test_keybundle(&String::from("alice"));
+        let alice_key = alice.get_id();
+        let bob_key = test_keybundle(&String::from("bob")).get_id();
+        let alice_signature = LocalSigner::new(Arc::new(Mutex::new(alice)))
+            .sign("payload")
+            .unwrap();
+
+        let signer = VerifyingSigner::new(
+            Box::new(FixedSigner(alice_signature.clone())),
+            alice_key.clone(),
+        );
+        assert_eq!(signer.sign("payload"), Ok(alice_signature.clone()));
+
+        let signer = VerifyingSigner::new(Box::new(FixedSigner(alice_signature)), bob_key);
+        assert!(signer.sign("payload").is_err());
+
+        let signer = VerifyingSigner::new(
+            Box::new(FixedSigner(base64::encode(&[1, 2, 3]))),
+            alice_key.clone(),
+        );
+        assert_eq!(
+            signer.sign("payload"),
+            Err(HolochainError::ErrorGeneric(String::from(
+                "Signature has 3 bytes instead of 64"
+            )))
+        );
+
+        let signer = VerifyingSigner::new(Box::new(FixedSigner(String::from("%%%"))), alice_key);
+        assert!(signer.sign("payload").is_err());
+    }
+}
@@ -0,0 +1,485 @@
+//! Signers running in a separate process.
+//!
+//! They get reached over a websocket or (on Unix) a domain socket and speak JSON-RPC: a request
+//! for the method `agent/sign` with the params `agent_id`, `payload` and (if configured)
+//! `token` is answered with `{"signature": "<base64>"}`, just like the conductor's own
+//! `agent/sign`. On domain sockets, requests and responses are newline delimited.
+//! Every signature request uses a new connection.
+use holochain_core_types::{agent::Base32, error::HolochainError};
+use native_tls::{self, TlsConnector};
+use serde_json::Value;
+use signers::Signer;
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{
+    io::{BufRead, BufReader},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+};
+use tungstenite::{self, handshake::HandshakeError, Message};
+use url::Url;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteSignerEndpoint {
+    /// `ws://` or `wss://` URL
+    Websocket(String),
+    #[cfg(unix)]
+    DomainSocket(PathBuf),
+}
+
+/// Sockets whose read and write timeouts can be set
+trait SetTimeouts {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()>;
+}
+
+impl SetTimeouts for TcpStream {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+#[cfg(unix)]
+impl SetTimeouts for UnixStream {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+/// Socket that times out at a fixed point in time instead of after every read or write
+/// taking too long, so a signer that answers slowly in small pieces can't stretch a request
+/// beyond its timeout.
+#[derive(Debug)]
+struct DeadlineStream<S> {
+    stream: S,
+    deadline: Instant,
+}
+
+impl<S: SetTimeouts> DeadlineStream<S> {
+    fn new(stream: S, deadline: Instant) -> Self {
+        DeadlineStream { stream, deadline }
+    }
+
+    /// Limits the next operation to the time that is left
+    fn arm(&self) -> io::Result<()> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "deadline of remote signer request passed",
+            ));
+        }
+        self.stream.set_timeouts(self.deadline - now)
+    }
+}
+
+impl<S: Read + SetTimeouts> Read for DeadlineStream<S> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.arm()?;
+        self.stream.read(buffer)
+    }
+}
+
+impl<S: Write + SetTimeouts> Write for DeadlineStream<S> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.arm()?;
+        self.stream.write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.arm()?;
+        self.stream.flush()
+    }
+}
+
+/// Remaining time until the deadline, or a timeout error if it passed
+fn time_left(deadline: Instant) -> Result<Duration, HolochainError> {
+    let now = Instant::now();
+    if now >= deadline {
+        Err(HolochainError::Timeout)
+    } else {
+        Ok(deadline - now)
+    }
+}
+
+pub struct RemoteSigner {
+    endpoint: RemoteSignerEndpoint,
+    agent_id: Base32,
+    token: Option<String>,
+    timeout: Duration,
+}
+
+impl RemoteSigner {
+    /// The timeout applies to the whole request, from connecting to receiving the signature.
+    pub fn new(
+        endpoint: RemoteSignerEndpoint,
+        agent_id: Base32,
+        token: Option<String>,
+        timeout: Duration,
+    ) -> Self {
+        RemoteSigner {
+            endpoint,
+            agent_id,
+            token,
+            timeout,
+        }
+    }
+
+    fn request(&self, payload: &str) -> String {
+        let mut params = json!({"agent_id": self.agent_id, "payload": payload});
+        if let Some(ref token) = self.token {
+            params["token"] = json!(token);
+        }
+        json!({"jsonrpc": "2.0", "id": 0, "method": "agent/sign", "params": params}).to_string()
+    }
+
+    fn io_error(&self, error: io::Error) -> HolochainError {
+        match error.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => HolochainError::Timeout,
+            _ => HolochainError::ErrorGeneric(format!(
+                "Could not reach remote signer at {:?}: {}",
+                self.endpoint, error
+            )),
+        }
+    }
+
+    fn websocket_error(&self, error: tungstenite::Error) -> HolochainError {
+        match error {
+            tungstenite::Error::Io(error) => self.io_error(error),
+            error => HolochainError::ErrorGeneric(format!(
+                "Websocket error while talking to remote signer: {}",
+                error
+            )),
+        }
+    }
+
+    #[cfg(unix)]
+    fn call_domain_socket(
+        &self,
+        file: &Path,
+        request: String,
+        deadline: Instant,
+    ) -> Result<String, HolochainError> {
+        let stream = UnixStream::connect(file).map_err(|e| self.io_error(e))?;
+        let mut stream = DeadlineStream::new(stream, deadline);
+        stream
+            .write_all(format!("{}\n", request).as_bytes())
+            .map_err(|e| self.io_error(e))?;
+        let mut response = String::new();
+        BufReader::new(stream)
+            .read_line(&mut response)
+            .map_err(|e| self.io_error(e))?;
+        Ok(response)
+    }
+
+    fn call_websocket(
+        &self,
+        url: &str,
+        request: String,
+        deadline: Instant,
+    ) -> Result<String, HolochainError> {
+        let url = Url::parse(url).map_err(|_| {
+            HolochainError::ConfigError(format!("Can't parse remote signer URL: '{}'", url))
+        })?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| {
+                HolochainError::ConfigError(format!("Remote signer URL {} has no host", url))
+            })?
+            .to_string();
+        let port = url.port_or_known_default().unwrap_or(80);
+        let address = (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|e| self.io_error(e))?
+            .next()
+            .ok_or_else(|| {
+                HolochainError::ErrorGeneric(format!("Could not resolve remote signer {}", host))
+            })?;
+        let stream = TcpStream::connect_timeout(&address, time_left(deadline)?)
+            .map_err(|e| self.io_error(e))?;
+        // TLS goes on top, so that its handshake and records are covered by the deadline too
+        let stream = DeadlineStream::new(stream, deadline);
+        match url.scheme() {
+            "ws" => self.exchange(url, stream, request),
+            "wss" => {
+                let connector =
+                    TlsConnector::new().map_err(|e| HolochainError::ErrorGeneric(e.to_string()))?;
+                let stream = match connector.connect(&host, stream) {
+                    Ok(stream) => stream,
+                    Err(native_tls::HandshakeError::Failure(error)) => {
+                        return Err(HolochainError::ErrorGeneric(format!(
+                            "TLS handshake with remote signer failed: {}",
+                            error
+                        )))
+                    }
+                    Err(native_tls::HandshakeError::WouldBlock(_)) => {
+                        return Err(HolochainError::Timeout)
+                    }
+                };
+                self.exchange(url, stream, request)
+            }
+            scheme => Err(HolochainError::ConfigError(format!(
+                "Remote signer URL has unsupported scheme \"{}\", expected ws or wss",
+                scheme
+            ))),
+        }
+    }
+
+    /// Sends the request as one text message and waits for the answer
+    fn exchange<S: Read + Write>(
+        &self,
+        url: Url,
+        stream: S,
+        request: String,
+    ) -> Result<String, HolochainError> {
+        let (mut socket, _) = tungstenite::client(url, stream).map_err(|e| match e {
+            HandshakeError::Failure(error) => self.websocket_error(error),
+            HandshakeError::Interrupted(_) => HolochainError::Timeout,
+        })?;
+        socket
+            .write_message(Message::Text(request))
+            .map_err(|e| self.websocket_error(e))?;
+        loop {
+            match socket.read_message().map_err(|e| self.websocket_error(e))? {
+                Message::Text(response) => {
+                    let _ = socket.close(None);
+                    return Ok(response);
+                }
+                Message::Close(_) => {
+                    return Err(HolochainError::ErrorGeneric(String::from(
+                        "Remote signer closed the connection without answering",
+                    )))
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Extracts the signature from a JSON-RPC response
+fn parse_response(response: &str) -> Result<String, HolochainError> {
+    let response: Value = serde_json::from_str(response)?;
+    if let Some(error) = response.get("error") {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Remote signer refused to sign: {}",
+            error
+        )));
+    }
+    response
+        .get("result")
+        .and_then(|result| result.get("signature"))
+        .and_then(|signature| signature.as_str())
+        .map(String::from)
+        .ok_or_else(|| {
+            HolochainError::ErrorGeneric(String::from(
+                "Response of remote signer contains no signature",
+            ))
+        })
+}
+
+impl Signer for RemoteSigner {
+    fn sign(&self, payload: &str) -> Result<String, HolochainError> {
+        let request = self.request(payload);
+        let deadline = Instant::now() + self.timeout;
+        let response = match self.endpoint {
+            RemoteSignerEndpoint::Websocket(ref url) => self.call_websocket(url, request, deadline),
+            #[cfg(unix)]
+            RemoteSignerEndpoint::DomainSocket(ref file) => {
+                self.call_domain_socket(file, request, deadline)
+            }
+        }?;
+        parse_response(&response)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    #[cfg(unix)]
+    extern crate tempfile;
+    #[cfg(unix)]
+    use self::tempfile::tempdir;
+    use super::*;
+    use interface::Interface;
+    #[cfg(unix)]
+    use interface_impls::DomainSocketInterface;
+    use interface_impls::WebsocketInterface;
+    use key_loaders::test_keybundle;
+    #[cfg(unix)]
+    use signers::VerifyingSigner;
+    use signers::{test_signer::test_signer_handler, verify_signature};
+    #[cfg(unix)]
+    use std::{os::unix::net::UnixListener, sync::mpsc::Sender};
+    use std::{sync::mpsc::channel, thread};
+
+    /// Runs the test signer for the key of the given name on a domain socket
+    #[cfg(unix)]
+    fn start_signer_on_socket(file: &PathBuf, key_name: &str, token: Option<&str>) -> Sender<()> {
+        let handler = test_signer_handler(
+            test_keybundle(&String::from(key_name)),
+            token.map(String::from),
+        );
+        let (kill_switch, kill_receiver) = channel();
+        let interface = DomainSocketInterface::new(file.clone(), 0o600);
+        thread::spawn(move || interface.run(handler, kill_receiver));
+        for _ in 0..100 {
+            if file.exists() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        kill_switch
+    }
+
+    #[cfg(unix)]
+    fn socket_signer(file: &PathBuf, agent_id: Base32, token: Option<&str>) -> RemoteSigner {
+        RemoteSigner::new(
+            RemoteSignerEndpoint::DomainSocket(file.clone()),
+            agent_id,
+            token.map(String::from),
+            Duration::from_secs(5),
+        )
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn signs_over_domain_socket() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("signer.sock");
+        let kill_switch = start_signer_on_socket(&file, "alice", Some("secret"));
+        let alice = test_keybundle(&String::from("alice")).get_id();
+
+        let signer = socket_signer(&file, alice.clone(), Some("secret"));
+        let signature = signer.sign("payload").unwrap();
+        assert_eq!(verify_signature(&alice, "payload", &signature), Ok(()));
+
+        let error = socket_signer(&file, alice.clone(), Some("wrong"))
+            .sign("payload")
+            .unwrap_err();
+        assert!(error.to_string().contains("Remote signer refused to sign"));
+        assert!(socket_signer(&file, alice, None).sign("payload").is_err());
+
+        kill_switch.send(()).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn signatures_of_the_wrong_key_get_rejected() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("signer.sock");
+        let kill_switch = start_signer_on_socket(&file, "mallory", None);
+        let alice = test_keybundle(&String::from("alice")).get_id();
+        let mallory = test_keybundle(&String::from("mallory")).get_id();
+
+        // The test signer only signs for its own agent
+        assert!(socket_signer(&file, alice.clone(), None)
+            .sign("payload")
+            .is_err());
+
+        // Signing as mallory works but doesn't produce valid signatures for alice
+        let signer = VerifyingSigner::new(Box::new(socket_signer(&file, mallory, None)), alice);
+        assert!(signer.sign("payload").is_err());
+
+        kill_switch.send(()).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn times_out_if_signer_does_not_answer() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("silent.sock");
+        let listener = UnixListener::bind(&file).unwrap();
+        let silent = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            thread::sleep(Duration::from_millis(500));
+            drop(stream);
+        });
+
+        let signer = RemoteSigner::new(
+            RemoteSignerEndpoint::DomainSocket(file.clone()),
+            String::from("agent"),
+            None,
+            Duration::from_millis(100),
+        );
+        assert_eq!(signer.sign("payload"), Err(HolochainError::Timeout));
+        silent.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn times_out_if_signer_answers_too_slowly() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("slow.sock");
+        let listener = UnixListener::bind(&file).unwrap();
+        let slow = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Every single byte arrives well within the timeout, the whole answer doesn't
+            for byte in b"{\"jsonrpc\":\"2.0\"".iter() {
+                thread::sleep(Duration::from_millis(50));
+                if stream.write_all(&[*byte]).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let signer = RemoteSigner::new(
+            RemoteSignerEndpoint::DomainSocket(file.clone()),
+            String::from("agent"),
+            None,
+            Duration::from_millis(200),
+        );
+        assert_eq!(signer.sign("payload"), Err(HolochainError::Timeout));
+        slow.join().unwrap();
+    }
+
+    #[test]
+    fn signs_over_websocket() {
+        let handler = test_signer_handler(
+            test_keybundle(&String::from("alice")),
+            Some(String::from("secret")),
+        );
+        let (kill_switch, kill_receiver) = channel();
+        let interface = WebsocketInterface::new(3019).with_bind_address(Some("127.0.0.1".into()));
+        thread::spawn(move || interface.run(handler, kill_receiver));
+        let alice = test_keybundle(&String::from("alice")).get_id();
+
+        let signer = RemoteSigner::new(
+            RemoteSignerEndpoint::Websocket(String::from("ws://127.0.0.1:3019")),
+            alice.clone(),
+            Some(String::from("secret")),
+            Duration::from_secs(5),
+        );
+        let mut result = signer.sign("payload");
+        for _ in 0..50 {
+            if result.is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+            result = signer.sign("payload");
+        }
+        assert_eq!(
+            verify_signature(&alice, "payload", &result.unwrap()),
+            Ok(())
+        );
+
+        kill_switch.send(()).unwrap();
+    }
+
+    #[test]
+    fn parses_responses() {
+        assert_eq!(
+            parse_response(r#"{"jsonrpc":"2.0","result":{"signature":"abc"},"id":0}"#),
+            Ok(String::from("abc"))
+        );
+        assert!(parse_response(r#"{"jsonrpc":"2.0","result":{},"id":0}"#).is_err());
+        assert!(parse_response(
+            r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"Unauthorized"},"id":0}"#
+        )
+        .is_err());
+        assert!(parse_response("not json").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+//! Stand-in for a remote signer, to be used in tests.
+//!
+//! The handler answers `agent/sign` requests for the one agent whose key it holds, as described
+//! in [remote](../remote/index.html). It can be served with any interface, e.g. a
+//! `DomainSocketInterface` or a `WebsocketInterface`. The `holochain_test_signer` binary of
+//! `test_bin` runs it as a separate process.
+//! Only compiled for tests and with the `test-signer` feature, since it signs whatever it gets.
+use holochain_dpki::key_bundle::KeyBundle;
+use jsonrpc_ws_server::jsonrpc_core::{self, IoHandler, Params, Value};
+use signers::{LocalSigner, Signer};
+use std::sync::{Arc, Mutex};
+
+/// Error code of requests without the right token
+pub const UNAUTHORIZED: i64 = -32001;
+
+fn param(params: &serde_json::Map<String, Value>, name: &str) -> Option<String> {
+    params
+        .get(name)
+        .and_then(|value| value.as_str())
+        .map(String::from)
+}
+
+/// JSON-RPC handler that signs with the given key.
+/// If a token is given, only requests that carry it get answered.
+pub fn test_signer_handler(keybundle: KeyBundle, token: Option<String>) -> IoHandler {
+    let agent_id = keybundle.get_id();
+    let signer = LocalSigner::new(Arc::new(Mutex::new(keybundle)));
+    let mut handler = IoHandler::new();
+    handler.add_method("agent/sign", move |params: Params| {
+        let params = match params {
+            Params::Map(map) => map,
+            _ => return Err(jsonrpc_core::Error::invalid_params("expected params map")),
+        };
+        if token.is_some() && param(&params, "token") != token {
+            return Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::ServerError(UNAUTHORIZED),
+                message: String::from("Unauthorized"),
+                data: None,
+            });
+        }
+        if param(&params, "agent_id") != Some(agent_id.clone()) {
+            return Err(jsonrpc_core::Error::invalid_params(format!(
+                "Can only sign for agent {}",
+                agent_id
+            )));
+        }
+        let payload = param(&params, "payload")
+            .ok_or_else(|| jsonrpc_core::Error::invalid_params("`payload` is missing"))?;
+        let signature = signer
+            .sign(&payload)
+            .map_err(|_| jsonrpc_core::Error::internal_error())?;
+        Ok(json!({"payload": payload, "signature": signature}))
+    });
+    handler
+}
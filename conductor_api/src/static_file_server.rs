@@ -1,70 +1,617 @@
+//! HTTP server of UI interfaces.
+//!
+//! Besides the bundle's files it serves `/_dna_connections.json`, which tells the UI where to
+//! find its DNA interface, and optionally forwards websocket connections on a configured path
+//! to that interface, so that browsers don't need to talk to a second port.
+use brotli::CompressorWriter;
 use conductor::base::notify;
-use config::{InterfaceConfiguration, UiBundleConfiguration, UiInterfaceConfiguration};
+use config::{
+    ui_bundle_hash, InterfaceConfiguration, InterfaceDriver, UiBundleConfiguration,
+    UiInterfaceConfiguration, UiServerConfiguration,
+};
 use error::HolochainResult;
-use holochain_core_types::error::HolochainError;
+use flate2::{write::GzEncoder, Compression};
+use holochain_core_types::{error::HolochainError, hash::HashString};
 use hyper::{
-    http::{response::Builder, uri},
+    header::{self, HeaderValue},
     rt::Future,
     server::Server,
-    Body, Request, Response,
+    upgrade::OnUpgrade,
+    Body, Method, Request, Response, StatusCode,
 };
-use hyper_staticfile::{Static, StaticFuture};
+use multihash::Hash;
 use std::{
-    io::Error,
-    sync::mpsc::{channel, Sender},
+    collections::HashMap,
+    fs,
+    io::{self, Error, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
     thread,
+    time::SystemTime,
 };
 use tokio::{
-    prelude::{future, Async, Poll},
+    net::TcpStream,
+    prelude::{
+        future::{self, Loop},
+        AsyncRead,
+    },
     runtime::Runtime,
 };
 
 const DNA_CONFIG_ROUTE: &str = "/_dna_connections.json";
 
-fn redirect_request_to_root<T>(req: &mut Request<T>) {
-    let mut original_parts: uri::Parts = req.uri().to_owned().into();
-    original_parts.path_and_query = Some("/".parse().unwrap());
-    *req.uri_mut() = uri::Uri::from_parts(original_parts).unwrap();
+/// Compressing smaller responses is not worth it
+const MIN_COMPRESSION_SIZE: usize = 1024;
+
+/// Upper limit for the response head of a proxied websocket handshake
+const MAX_PROXY_HEAD_SIZE: usize = 16 * 1024;
+
+type ResponseFuture = Box<Future<Item = Response<Body>, Error = Error> + Send>;
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
 }
 
-fn dna_connections_response(config: &Option<InterfaceConfiguration>) -> Response<Body> {
-    let interface = match config {
-        Some(config) => json!(config),
-        None => serde_json::Value::Null,
-    };
-    Builder::new()
-        .body(json!({ "dna_interface": interface }).to_string().into())
-        .expect("unable to build response")
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Encoding {
+    Brotli,
+    Gzip,
 }
 
-enum MainFuture {
-    Static(StaticFuture<Body>),
-    Config(Option<InterfaceConfiguration>),
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn compress(self, content: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut writer = CompressorWriter::new(Vec::new(), 4096, 9, 22);
+                writer.write_all(content)?;
+                Ok(writer.into_inner())
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+        }
+    }
 }
 
-impl Future for MainFuture {
-    type Item = Response<Body>;
-    type Error = Error;
+/// A version of a file (identified by its modification time and size) with the hash its ETag
+/// is made of and the compressed variants that got requested so far
+struct CachedFile {
+    modified: SystemTime,
+    len: u64,
+    content: Vec<u8>,
+    hash: HashString,
+    compressed: Mutex<HashMap<Encoding, Arc<Vec<u8>>>>,
+}
+
+impl CachedFile {
+    /// Compresses the content the first time an encoding is requested
+    fn compressed(&self, encoding: Encoding) -> io::Result<Arc<Vec<u8>>> {
+        let mut compressed = self.compressed.lock().unwrap();
+        if let Some(content) = compressed.get(&encoding) {
+            return Ok(content.clone());
+        }
+        let content = Arc::new(encoding.compress(&self.content)?);
+        compressed.insert(encoding, content.clone());
+        Ok(content)
+    }
+}
+
+/// Files served by a server, shared by all its connections, so that every version of a file
+/// only gets read, hashed and compressed once instead of on every request.
+#[derive(Clone, Default)]
+struct FileCache {
+    files: Arc<Mutex<HashMap<PathBuf, Arc<CachedFile>>>>,
+}
+
+impl FileCache {
+    /// Returns the cached file if it did not change since it got cached, reads it otherwise
+    fn load(&self, path: &Path) -> io::Result<Arc<CachedFile>> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?;
+        if let Some(file) = self.files.lock().unwrap().get(path) {
+            if file.modified == modified && file.len == metadata.len() {
+                return Ok(file.clone());
+            }
+        }
+        let content = fs::read(path)?;
+        let file = Arc::new(CachedFile {
+            modified,
+            len: metadata.len(),
+            hash: HashString::encode_from_bytes(&content, Hash::SHA2256),
+            content,
+            compressed: Mutex::new(HashMap::new()),
+        });
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), file.clone());
+        Ok(file)
+    }
+}
+
+/// Picks brotli over gzip if the client accepts both. Encodings with q=0 count as refused.
+fn preferred_encoding<T>(req: &Request<T>) -> Option<Encoding> {
+    let mut accepted = Vec::new();
+    for value in req.headers().get_all(header::ACCEPT_ENCODING).iter() {
+        for item in value.to_str().unwrap_or("").split(',') {
+            let mut parts = item.split(';');
+            let name = parts.next().unwrap_or("").trim().to_lowercase();
+            let refused = parts.any(|parameter| {
+                let parameter = parameter.trim();
+                parameter.starts_with("q=")
+                    && parameter[2..]
+                        .parse::<f32>()
+                        .map(|q| q <= 0.0)
+                        .unwrap_or(false)
+            });
+            if !refused {
+                accepted.push(name);
+            }
+        }
+    }
+    if accepted.iter().any(|name| name == "br") {
+        Some(Encoding::Brotli)
+    } else if accepted.iter().any(|name| name == "gzip") {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "application/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "ico" => "image/x-icon",
+        "webp" => "image/webp",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn is_compressible(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || content_type.starts_with("application/javascript")
+        || content_type.starts_with("application/json")
+        || content_type == "image/svg+xml"
+        || content_type == "application/wasm"
+}
+
+/// Paths whose last segment has a file extension are assumed to refer to files,
+/// all others to routes of a single page app.
+fn is_asset_path(request_path: &str) -> bool {
+    request_path
+        .rsplit('/')
+        .next()
+        .map(|segment| segment.contains('.'))
+        .unwrap_or(false)
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Maps the path of a request to a path inside the bundle.
+/// Returns None for paths that would leave the bundle.
+fn resolve_path(root: &Path, request_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(request_path)?;
+    let mut path = root.to_path_buf();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => return None,
+            segment if segment.contains('\\') || segment.contains(':') => return None,
+            segment => path.push(segment),
+        }
+    }
+    Some(path)
+}
+
+fn matches_etag<T>(req: &Request<T>, etag: &str) -> bool {
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        })
+}
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match *self {
-            MainFuture::Config(ref config) => Ok(Async::Ready(dna_connections_response(config))),
-            MainFuture::Static(ref mut future) => future.poll(),
+/// Address of the given DNA interface that websocket connections can be forwarded to.
+/// None for interfaces that are not websocket interfaces or that use TLS.
+fn proxy_backend(interface: &InterfaceConfiguration) -> Option<SocketAddr> {
+    match interface.driver {
+        InterfaceDriver::Websocket {
+            port,
+            ref bind_address,
+            tls: None,
+        } => {
+            let ip = match bind_address
+                .as_ref()
+                .and_then(|address| address.parse::<IpAddr>().ok())
+            {
+                Some(ip) if !ip.is_unspecified() => ip,
+                Some(IpAddr::V6(_)) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+                _ => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            };
+            Some(SocketAddr::new(ip, port))
         }
+        _ => None,
     }
 }
 
+fn proxy_request_head<T>(req: &Request<T>, backend: SocketAddr) -> Vec<u8> {
+    let mut head = format!("GET / HTTP/1.1\r\nHost: {}\r\n", backend);
+    for (name, value) in req.headers().iter() {
+        if name == header::HOST {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    head.push_str("\r\n");
+    head.into_bytes()
+}
+
+fn proxy_error(message: &str) -> Error {
+    Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads from the backend until the end of the response head.
+/// Returns everything read so far, which may include the first websocket frames.
+fn read_response_head(
+    stream: TcpStream,
+) -> impl Future<Item = (TcpStream, Vec<u8>), Error = Error> + Send {
+    future::loop_fn((stream, Vec::new()), |(stream, mut received)| {
+        tokio::io::read(stream, vec![0; 1024]).and_then(move |(stream, buffer, read)| {
+            if read == 0 {
+                return Err(Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed during handshake",
+                ));
+            }
+            received.extend_from_slice(&buffer[..read]);
+            if head_end(&received).is_some() {
+                Ok(Loop::Break((stream, received)))
+            } else if received.len() > MAX_PROXY_HEAD_SIZE {
+                Err(proxy_error("response head is too large"))
+            } else {
+                Ok(Loop::Continue((stream, received)))
+            }
+        })
+    })
+}
+
+fn head_end(received: &[u8]) -> Option<usize> {
+    received.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+/// Turns the backend's response head into a response for the client.
+/// Also returns the bytes that followed the head.
+fn parse_response_head(received: &[u8]) -> io::Result<(Response<Body>, Vec<u8>)> {
+    let end = head_end(received).ok_or_else(|| proxy_error("incomplete response head"))?;
+    let head = ::std::str::from_utf8(&received[..end])
+        .map_err(|_| proxy_error("response head is not valid UTF-8"))?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| proxy_error("invalid status line"))?;
+    let mut builder = Response::builder();
+    builder.status(status);
+    for line in lines {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        // The body (if any) does not get forwarded
+        if name.eq_ignore_ascii_case("content-length")
+            || name.eq_ignore_ascii_case("transfer-encoding")
+        {
+            continue;
+        }
+        builder.header(name, value);
+    }
+    let response = builder
+        .body(Body::empty())
+        .map_err(|e| proxy_error(&e.to_string()))?;
+    Ok((response, received[end + 4..].to_vec()))
+}
+
+/// Pipes data between the upgraded client connection and the backend until one side closes.
+fn pipe_upgraded(
+    on_upgrade: OnUpgrade,
+    backend: TcpStream,
+    rest: Vec<u8>,
+) -> impl Future<Item = (), Error = ()> + Send {
+    on_upgrade
+        .map_err(|e| notify(format!("Websocket upgrade failed: {}", e)))
+        .and_then(move |upgraded| tokio::io::write_all(upgraded, rest).map_err(|_| ()))
+        .and_then(move |(upgraded, _)| {
+            let (client_read, client_write) = upgraded.split();
+            let (backend_read, backend_write) = backend.split();
+            tokio::io::copy(client_read, backend_write)
+                .select2(tokio::io::copy(backend_read, client_write))
+                .map(|_| ())
+                .map_err(|_| ())
+        })
+}
+
+/// Forwards a websocket handshake to the backend and, if the backend accepts it,
+/// the whole connection after the upgrade.
+fn proxy_websocket(req: Request<Body>, backend: SocketAddr) -> ResponseFuture {
+    let is_websocket = req
+        .headers()
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+    if !is_websocket {
+        return Box::new(future::ok(empty_response(StatusCode::BAD_REQUEST)));
+    }
+    let head = proxy_request_head(&req, backend);
+    let on_upgrade = req.into_body().on_upgrade();
+    Box::new(
+        TcpStream::connect(&backend)
+            .and_then(move |stream| tokio::io::write_all(stream, head))
+            .and_then(|(stream, _)| read_response_head(stream))
+            .then(move |result| {
+                let response = match result.and_then(|(stream, received)| {
+                    parse_response_head(&received).map(|(response, rest)| (stream, response, rest))
+                }) {
+                    Ok((stream, response, rest)) => {
+                        if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+                            tokio::spawn(pipe_upgraded(on_upgrade, stream, rest));
+                        }
+                        response
+                    }
+                    Err(error) => {
+                        notify(format!(
+                            "Could not proxy websocket connection to {}: {}",
+                            backend, error
+                        ));
+                        empty_response(StatusCode::BAD_GATEWAY)
+                    }
+                };
+                Ok::<_, Error>(response)
+            }),
+    )
+}
+
 /// Hyper `Service` implementation that serves all requests.
 struct StaticService {
-    static_: Static,
+    root: PathBuf,
+    options: UiServerConfiguration,
     dna_interface_config: Option<InterfaceConfiguration>,
+    proxy_backend: Option<SocketAddr>,
+    files: FileCache,
 }
 
 impl StaticService {
-    fn new(path: &String, dna_interface_config: &Option<InterfaceConfiguration>) -> Self {
+    fn new(
+        root: &str,
+        options: &UiServerConfiguration,
+        dna_interface_config: &Option<InterfaceConfiguration>,
+        files: &FileCache,
+    ) -> Self {
         StaticService {
-            static_: Static::new(path),
+            root: PathBuf::from(root),
+            options: options.clone(),
             dna_interface_config: dna_interface_config.to_owned(),
+            proxy_backend: dna_interface_config.as_ref().and_then(proxy_backend),
+            files: files.clone(),
+        }
+    }
+
+    fn is_proxy_request<T>(&self, req: &Request<T>) -> bool {
+        self.options
+            .dna_interface_proxy
+            .as_ref()
+            .map(|path| path == req.uri().path())
+            .unwrap_or(false)
+    }
+
+    /// Responds to all requests that don't get proxied
+    fn respond<T>(&self, req: &Request<T>) -> Response<Body> {
+        let mut response = if req.method() == Method::OPTIONS {
+            let mut response = empty_response(StatusCode::NO_CONTENT);
+            response.headers_mut().insert(
+                header::ALLOW,
+                HeaderValue::from_static("GET, HEAD, OPTIONS"),
+            );
+            response
+        } else if req.method() != Method::GET && req.method() != Method::HEAD {
+            empty_response(StatusCode::METHOD_NOT_ALLOWED)
+        } else if req.uri().path() == DNA_CONFIG_ROUTE {
+            self.dna_connections_response()
+        } else {
+            self.file_response(req)
+        };
+        self.add_cors_headers(req, &mut response);
+        if req.method() == Method::HEAD {
+            *response.body_mut() = Body::empty();
+        }
+        response
+    }
+
+    fn dna_connections_response(&self) -> Response<Body> {
+        let interface = match self.dna_interface_config {
+            // Tokens are secrets of the conductor and must not be handed out to every browser
+            Some(ref config) => json!(InterfaceConfiguration {
+                tokens: Vec::new(),
+                ..config.clone()
+            }),
+            None => serde_json::Value::Null,
+        };
+        let mut connections = json!({ "dna_interface": interface });
+        if let Some(ref path) = self.options.dna_interface_proxy {
+            connections["dna_interface_proxy"] = json!(path);
+        }
+        let mut response = Response::new(Body::from(connections.to_string()));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        response
+    }
+
+    /// Finds the file for the given request, falling back to index.html for routes
+    /// of single page apps.
+    fn find_file(&self, request_path: &str) -> Option<PathBuf> {
+        let path = resolve_path(&self.root, request_path)?;
+        if path.is_file() {
+            Some(path)
+        } else if path.join("index.html").is_file() {
+            Some(path.join("index.html"))
+        } else if self.options.spa_fallback && !is_asset_path(request_path) {
+            Some(self.root.join("index.html"))
+        } else {
+            None
+        }
+    }
+
+    fn file_response<T>(&self, req: &Request<T>) -> Response<Body> {
+        let path = match self.find_file(req.uri().path()) {
+            Some(path) => path,
+            None => return empty_response(StatusCode::NOT_FOUND),
+        };
+        let file = match self.files.load(&path) {
+            Ok(file) => file,
+            Err(_) => return empty_response(StatusCode::NOT_FOUND),
+        };
+        let content_type = content_type(&path);
+        let encoding = if self.options.compression
+            && file.content.len() >= MIN_COMPRESSION_SIZE
+            && is_compressible(content_type)
+        {
+            preferred_encoding(req)
+        } else {
+            None
+        };
+
+        let etag = match encoding {
+            Some(encoding) => format!("\"{}-{}\"", file.hash, encoding.name()),
+            None => format!("\"{}\"", file.hash),
+        };
+        // HTML files have to be revalidated so that new asset URLs get picked up
+        let cache_control = if content_type.starts_with("text/html") {
+            String::from("no-cache")
+        } else {
+            format!("public, max-age={}", self.options.cache_max_age)
+        };
+
+        let mut builder = Response::builder();
+        builder
+            .header(header::ETAG, etag.as_str())
+            .header(header::CACHE_CONTROL, cache_control.as_str())
+            .header(header::VARY, "Accept-Encoding");
+        if matches_etag(req, &etag) {
+            return builder
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())
+                .expect("unable to build response");
+        }
+
+        let body = match encoding.map(|encoding| (encoding, file.compressed(encoding))) {
+            Some((encoding, Ok(compressed))) => {
+                builder.header(header::CONTENT_ENCODING, encoding.name());
+                compressed.to_vec()
+            }
+            _ => file.content.clone(),
+        };
+        builder
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, body.len().to_string().as_str())
+            .body(Body::from(body))
+            .expect("unable to build response")
+    }
+
+    fn add_cors_headers<T>(&self, req: &Request<T>, response: &mut Response<Body>) {
+        let cors = match self.options.cors {
+            Some(ref cors) => cors,
+            None => return,
+        };
+        let origin = match req
+            .headers()
+            .get(header::ORIGIN)
+            .and_then(|origin| origin.to_str().ok())
+        {
+            Some(origin) => origin,
+            None => return,
+        };
+        let any_origin = cors.allowed_origins.iter().any(|allowed| allowed == "*");
+        if !any_origin && !cors.allowed_origins.iter().any(|allowed| allowed == origin) {
+            return;
+        }
+
+        let headers = response.headers_mut();
+        let allowed_origin = if any_origin { "*" } else { origin };
+        if let Ok(value) = HeaderValue::from_str(allowed_origin) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+        }
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        if req.method() == Method::OPTIONS {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static("GET, HEAD, OPTIONS"),
+            );
+            if !cors.allowed_headers.is_empty() {
+                if let Ok(value) = HeaderValue::from_str(&cors.allowed_headers.join(", ")) {
+                    headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+                }
+            }
+            if let Some(max_age) = cors.max_age {
+                headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age));
+            }
         }
     }
 }
@@ -73,29 +620,15 @@ impl hyper::service::Service for StaticService {
     type ReqBody = Body;
     type ResBody = Body;
     type Error = Error;
-    type Future = MainFuture;
-
-    fn call(&mut self, mut req: Request<Body>) -> MainFuture {
-        match req.uri().path() {
-            DNA_CONFIG_ROUTE => MainFuture::Config(self.dna_interface_config.clone()),
-            _ => {
-                MainFuture::Static(
-                    hyper_staticfile::resolve(&self.static_.root, &req)
-                        .map(|result| {
-                            match result {
-                                hyper_staticfile::ResolveResult::NotFound => {
-                                    // redirect all not-found routes to the root
-                                    // this allows virtual routes on the front end
-                                    redirect_request_to_root(&mut req);
-                                    self.static_.serve(req)
-                                }
-                                _ => self.static_.serve(req),
-                            }
-                        })
-                        .wait()
-                        .unwrap(),
-                )
-            }
+    type Future = ResponseFuture;
+
+    fn call(&mut self, req: Request<Body>) -> ResponseFuture {
+        if !self.is_proxy_request(&req) {
+            return Box::new(future::ok(self.respond(&req)));
+        }
+        match self.proxy_backend {
+            Some(backend) => proxy_websocket(req, backend),
+            None => Box::new(future::ok(empty_response(StatusCode::BAD_GATEWAY))),
         }
     }
 }
@@ -123,13 +656,35 @@ impl StaticServer {
         }
     }
 
+    /// Starts serving the bundle, after checking its hash if the bundle config has one.
     pub fn start(&mut self) -> HolochainResult<()> {
+        if let Some(ref expected) = self.bundle_config.hash {
+            let actual = ui_bundle_hash(Path::new(&self.bundle_config.root_dir))?;
+            if actual != HashString::from(expected.clone()) {
+                return Err(HolochainError::ConfigError(format!(
+                    "Refusing to serve UI bundle \"{}\": its hash {} does not match the configured hash {}",
+                    self.bundle_config.id, actual, expected
+                ))
+                .into());
+            }
+        }
+
         let addr = ([127, 0, 0, 1], self.config.port).into();
 
         let (tx, rx) = channel::<()>();
         self.shutdown_signal = Some(tx);
         let static_path = self.bundle_config.root_dir.to_owned();
         let dna_interfaces = self.connected_dna_interface.to_owned();
+        let options = self.config.server.clone().unwrap_or_default();
+        let files = FileCache::default();
+        if options.dna_interface_proxy.is_some()
+            && dna_interfaces.as_ref().and_then(proxy_backend).is_none()
+        {
+            notify(format!(
+                "UI interface \"{}\" can't proxy its DNA interface, only websocket interfaces without TLS can be proxied",
+                self.config.id
+            ));
+        }
 
         notify(format!(
             "About to serve path \"{}\" at http://{}",
@@ -140,7 +695,12 @@ impl StaticServer {
         let _server = thread::spawn(move || {
             let server = Server::bind(&addr)
                 .serve(move || {
-                    future::ok::<_, Error>(StaticService::new(&static_path, &dna_interfaces))
+                    future::ok::<_, Error>(StaticService::new(
+                        &static_path,
+                        &options,
+                        &dna_interfaces,
+                        &files,
+                    ))
                 })
                 .map_err(|e| notify(format!("server error: {}", e)));
 
@@ -189,13 +749,19 @@ pub mod tests {
             bundle: "a bundle".to_string(),
             port: 3000,
             dna_interface: Some("interface".to_string()),
+            server: None,
         };
 
         let test_dna_interface = InterfaceConfiguration {
             id: "interface".to_string(),
             admin: true,
-            driver: InterfaceDriver::Http { port: 3000 },
+            driver: InterfaceDriver::Http {
+                port: 3000,
+                bind_address: None,
+                tls: None,
+            },
             instances: Vec::new(),
+            tokens: Vec::new(),
         };
 
         let mut static_server = StaticServer::from_configs(
@@ -218,3 +784,268 @@ pub mod tests {
         assert_eq!(static_server.running, false);
     }
 }
+
+#[cfg(test)]
+pub mod service_tests {
+    use super::*;
+    use config::{CorsConfiguration, TlsConfiguration};
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use tokio::prelude::Stream;
+    extern crate tempfile;
+    use self::tempfile::{tempdir, TempDir};
+
+    fn test_service(options: UiServerConfiguration) -> (TempDir, StaticService) {
+        let bundle = tempdir().unwrap();
+        fs::write(bundle.path().join("index.html"), "<html>index</html>").unwrap();
+        fs::write(
+            bundle.path().join("app.js"),
+            "console.log('app');\n".repeat(100),
+        )
+        .unwrap();
+        fs::create_dir(bundle.path().join("img")).unwrap();
+        fs::write(bundle.path().join("img").join("logo.png"), vec![1; 2048]).unwrap();
+        let service = StaticService::new(
+            &bundle.path().to_string_lossy().to_string(),
+            &options,
+            &None,
+            &FileCache::default(),
+        );
+        (bundle, service)
+    }
+
+    fn get(path: &str, headers: Vec<(&str, &str)>) -> Request<Body> {
+        let mut builder = Request::builder();
+        builder.uri(path);
+        for (name, value) in headers {
+            builder.header(name, value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn body(response: Response<Body>) -> Vec<u8> {
+        response.into_body().concat2().wait().unwrap().to_vec()
+    }
+
+    fn header<'a>(response: &'a Response<Body>, name: &str) -> Option<&'a str> {
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let root = Path::new("/bundle");
+        assert_eq!(
+            resolve_path(root, "/img/my%20logo.png"),
+            Some(PathBuf::from("/bundle/img/my logo.png"))
+        );
+        assert_eq!(
+            resolve_path(root, "/./app.js"),
+            Some(PathBuf::from("/bundle/app.js"))
+        );
+        assert_eq!(resolve_path(root, "/../secret"), None);
+        assert_eq!(resolve_path(root, "/img/%2e%2e/%2e%2e/secret"), None);
+        assert_eq!(resolve_path(root, "/%zz"), None);
+    }
+
+    #[test]
+    fn test_spa_fallback_only_for_routes() {
+        let (_bundle, service) = test_service(UiServerConfiguration::default());
+
+        let response = service.respond(&get("/some/route", vec![]));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response), b"<html>index</html>".to_vec());
+
+        let response = service.respond(&get("/missing.js", vec![]));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let (_bundle, service) = test_service(UiServerConfiguration {
+            spa_fallback: false,
+            ..Default::default()
+        });
+        let response = service.respond(&get("/some/route", vec![]));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = service.respond(&get("/", vec![]));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_etag_and_cache_control() {
+        let (_bundle, service) = test_service(UiServerConfiguration::default());
+
+        let response = service.respond(&get("/", vec![]));
+        assert_eq!(header(&response, "cache-control"), Some("no-cache"));
+        assert_eq!(
+            header(&response, "content-type"),
+            Some("text/html; charset=utf-8")
+        );
+
+        let response = service.respond(&get("/img/logo.png", vec![]));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            header(&response, "cache-control"),
+            Some("public, max-age=3600")
+        );
+        let etag = header(&response, "etag").unwrap().to_string();
+
+        let response = service.respond(&get(
+            "/img/logo.png",
+            vec![("if-none-match", etag.as_str())],
+        ));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(body(response).is_empty());
+
+        let response = service.respond(&get(
+            "/img/logo.png",
+            vec![("if-none-match", "\"something else\"")],
+        ));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_compression() {
+        let (bundle, service) = test_service(UiServerConfiguration::default());
+        let original = fs::read(bundle.path().join("app.js")).unwrap();
+
+        let response = service.respond(&get("/app.js", vec![("accept-encoding", "gzip, br")]));
+        assert_eq!(header(&response, "content-encoding"), Some("br"));
+        let mut decompressed = Vec::new();
+        brotli::Decompressor::new(&body(response)[..], 4096)
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, original);
+
+        let response = service.respond(&get(
+            "/app.js",
+            vec![("accept-encoding", "br;q=0, gzip;q=0.8")],
+        ));
+        assert_eq!(header(&response, "content-encoding"), Some("gzip"));
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&body(response)[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, original);
+
+        // Images are not worth compressing
+        let response = service.respond(&get("/img/logo.png", vec![("accept-encoding", "gzip")]));
+        assert_eq!(header(&response, "content-encoding"), None);
+
+        let (_bundle, service) = test_service(UiServerConfiguration {
+            compression: false,
+            ..Default::default()
+        });
+        let response = service.respond(&get("/app.js", vec![("accept-encoding", "gzip, br")]));
+        assert_eq!(header(&response, "content-encoding"), None);
+        assert_eq!(body(response), original);
+    }
+
+    #[test]
+    fn test_file_cache() {
+        let (bundle, service) = test_service(UiServerConfiguration::default());
+        let path = bundle.path().join("app.js");
+
+        let file = service.files.load(&path).unwrap();
+        assert!(Arc::ptr_eq(&file, &service.files.load(&path).unwrap()));
+        let compressed = file.compressed(Encoding::Gzip).unwrap();
+        assert!(Arc::ptr_eq(
+            &compressed,
+            &file.compressed(Encoding::Gzip).unwrap()
+        ));
+
+        // A changed file gets read again and gets a new ETag
+        let etag = header(&service.respond(&get("/app.js", vec![])), "etag")
+            .unwrap()
+            .to_string();
+        fs::write(&path, "console.log('changed');").unwrap();
+        let response = service.respond(&get("/app.js", vec![]));
+        assert_ne!(header(&response, "etag"), Some(etag.as_str()));
+        assert_eq!(body(response), b"console.log('changed');".to_vec());
+    }
+
+    #[test]
+    fn test_cors() {
+        let (_bundle, service) = test_service(UiServerConfiguration {
+            cors: Some(CorsConfiguration {
+                allowed_origins: vec![String::from("https://app.example.com")],
+                allowed_headers: vec![String::from("content-type")],
+                max_age: Some(600),
+            }),
+            ..Default::default()
+        });
+
+        let response = service.respond(&get("/", vec![("origin", "https://app.example.com")]));
+        assert_eq!(
+            header(&response, "access-control-allow-origin"),
+            Some("https://app.example.com")
+        );
+        let response = service.respond(&get("/", vec![("origin", "https://evil.example.com")]));
+        assert_eq!(header(&response, "access-control-allow-origin"), None);
+
+        let mut preflight = get("/app.js", vec![("origin", "https://app.example.com")]);
+        *preflight.method_mut() = Method::OPTIONS;
+        let response = service.respond(&preflight);
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            header(&response, "access-control-allow-headers"),
+            Some("content-type")
+        );
+        assert_eq!(header(&response, "access-control-max-age"), Some("600"));
+
+        let (_bundle, service) = test_service(UiServerConfiguration::default());
+        let response = service.respond(&get("/", vec![("origin", "https://app.example.com")]));
+        assert_eq!(header(&response, "access-control-allow-origin"), None);
+    }
+
+    #[test]
+    fn test_proxy_backend() {
+        let interface = |bind_address: Option<&str>, tls| InterfaceConfiguration {
+            id: "interface".to_string(),
+            admin: false,
+            driver: InterfaceDriver::Websocket {
+                port: 3000,
+                bind_address: bind_address.map(String::from),
+                tls,
+            },
+            instances: Vec::new(),
+            tokens: Vec::new(),
+        };
+        assert_eq!(
+            proxy_backend(&interface(None, None)),
+            Some("127.0.0.1:3000".parse().unwrap())
+        );
+        assert_eq!(
+            proxy_backend(&interface(Some("::"), None)),
+            Some("[::1]:3000".parse().unwrap())
+        );
+        assert_eq!(
+            proxy_backend(&interface(Some("10.0.0.2"), None)),
+            Some("10.0.0.2:3000".parse().unwrap())
+        );
+        assert_eq!(
+            proxy_backend(&interface(
+                None,
+                Some(TlsConfiguration {
+                    certificate: String::from("cert.pem"),
+                    key: String::from("key.pem"),
+                    client_ca: None,
+                })
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_response_head() {
+        let (response, rest) = parse_response_head(
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: abc\r\n\r\nframe",
+        )
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(header(&response, "upgrade"), Some("websocket"));
+        assert_eq!(header(&response, "sec-websocket-accept"), Some("abc"));
+        assert_eq!(rest, b"frame".to_vec());
+        assert!(parse_response_head(b"HTTP/1.1 101 Switching").is_err());
+    }
+}
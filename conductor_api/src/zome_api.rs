@@ -0,0 +1,417 @@
+//! Descriptions of the zome functions an instance exposes, so that clients can find out
+//! what they can call (`info/zomes`, `info/functions`) or generate client code
+//! (`info/openrpc`). Everything is derived from the instance's DNA.
+use holochain_core_types::dna::{
+    entry_types::{LinkedFrom, LinksTo, Sharing},
+    fn_declarations::FnParameter,
+    zome::Zome,
+    Dna,
+};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Version of the OpenRPC specification our documents follow
+const OPENRPC_VERSION: &str = "1.0.0-rc1";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ZomeInfo {
+    pub name: String,
+    pub description: String,
+    /// Names of the functions in each trait
+    pub traits: BTreeMap<String, Vec<String>>,
+    pub functions: Vec<FunctionInfo>,
+    pub entry_types: Vec<EntryTypeInfo>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FunctionInfo {
+    pub zome: String,
+    pub name: String,
+    pub inputs: Vec<FnParameter>,
+    pub outputs: Vec<FnParameter>,
+    /// The traits this function is part of, e.g. "hc_public"
+    pub traits: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EntryTypeInfo {
+    pub name: String,
+    pub description: String,
+    pub sharing: Sharing,
+    pub links_to: Vec<LinksTo>,
+    pub linked_from: Vec<LinkedFrom>,
+}
+
+fn functions_of_zome(zome_name: &str, zome: &Zome) -> Vec<FunctionInfo> {
+    zome.fn_declarations
+        .iter()
+        .map(|declaration| FunctionInfo {
+            zome: zome_name.to_string(),
+            name: declaration.name.clone(),
+            inputs: declaration.inputs.clone(),
+            outputs: declaration.outputs.clone(),
+            traits: zome
+                .traits
+                .iter()
+                .filter(|(_, trait_fns)| trait_fns.functions.contains(&declaration.name))
+                .map(|(trait_name, _)| trait_name.clone())
+                .collect(),
+        })
+        .collect()
+}
+
+fn zome_info(zome_name: &str, zome: &Zome) -> ZomeInfo {
+    ZomeInfo {
+        name: zome_name.to_string(),
+        description: zome.description.clone(),
+        traits: zome
+            .traits
+            .iter()
+            .map(|(trait_name, trait_fns)| (trait_name.clone(), trait_fns.functions.clone()))
+            .collect(),
+        functions: functions_of_zome(zome_name, zome),
+        entry_types: zome
+            .entry_types
+            .iter()
+            .map(|(entry_type, definition)| EntryTypeInfo {
+                name: entry_type.to_string(),
+                description: definition.description.clone(),
+                sharing: definition.sharing.clone(),
+                links_to: definition.links_to.clone(),
+                linked_from: definition.linked_from.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// All zomes of the DNA, ordered by name
+pub fn zomes(dna: &Dna) -> Vec<ZomeInfo> {
+    dna.zomes
+        .iter()
+        .map(|(zome_name, zome)| zome_info(zome_name, zome))
+        .collect()
+}
+
+/// The functions of the given zome, or of all zomes if none is given.
+/// Returns None if there is no zome of that name.
+pub fn functions(dna: &Dna, zome_name: Option<&str>) -> Option<Vec<FunctionInfo>> {
+    match zome_name {
+        Some(zome_name) => dna
+            .zomes
+            .get(zome_name)
+            .map(|zome| functions_of_zome(zome_name, zome)),
+        None => Some(
+            dna.zomes
+                .iter()
+                .flat_map(|(zome_name, zome)| functions_of_zome(zome_name, zome))
+                .collect(),
+        ),
+    }
+}
+
+/// Returns `T` if the given type is `<generic><T>`
+fn generic_argument<'a>(parameter_type: &'a str, generic: &str) -> Option<&'a str> {
+    if parameter_type.starts_with(generic)
+        && parameter_type[generic.len()..].starts_with('<')
+        && parameter_type.ends_with('>')
+    {
+        Some(&parameter_type[generic.len() + 1..parameter_type.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// JSON schema for a parameter type as it is written in DNAs, i.e. a Rust type.
+/// Types we don't know allow any value and name the Rust type in their description.
+fn schema_for_type(parameter_type: &str) -> Value {
+    let parameter_type = parameter_type.trim();
+    if let Some(item_type) = generic_argument(parameter_type, "Vec") {
+        return json!({"type": "array", "items": schema_for_type(item_type)});
+    }
+    if let Some(inner_type) = generic_argument(parameter_type, "Option") {
+        return json!({"oneOf": [schema_for_type(inner_type), {"type": "null"}]});
+    }
+    match parameter_type {
+        "String" | "&str" | "str" | "Address" | "HashString" => json!({"type": "string"}),
+        "bool" => json!({"type": "boolean"}),
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" => {
+            json!({"type": "integer"})
+        }
+        "f32" | "f64" => json!({"type": "number"}),
+        other => json!({ "description": format!("Rust type {}", other) }),
+    }
+}
+
+fn is_optional(parameter: &FnParameter) -> bool {
+    generic_argument(parameter.parameter_type.trim(), "Option").is_some()
+}
+
+/// OpenRPC document describing how to call the zome functions of an instance with the
+/// given id. It has a single `call` method since that is how zome functions are called.
+/// Its `params` param has a schema for the inputs of each function, titled
+/// `<zome>/<function>`. Calls return the function's result as a JSON encoded string.
+pub fn openrpc_document(dna: &Dna, instance_id: &str) -> Value {
+    let zomes = zomes(dna);
+    let zome_names: Vec<&String> = zomes.iter().map(|zome| &zome.name).collect();
+    let functions: Vec<FunctionInfo> = zomes
+        .iter()
+        .flat_map(|zome| zome.functions.clone())
+        .collect();
+    let mut function_names: Vec<&String> =
+        functions.iter().map(|function| &function.name).collect();
+    function_names.sort();
+    function_names.dedup();
+    let inputs: Vec<Value> = functions
+        .iter()
+        .map(|function| {
+            let properties: serde_json::Map<String, Value> = function
+                .inputs
+                .iter()
+                .map(|input| (input.name.clone(), schema_for_type(&input.parameter_type)))
+                .collect();
+            let required: Vec<&String> = function
+                .inputs
+                .iter()
+                .filter(|input| !is_optional(input))
+                .map(|input| &input.name)
+                .collect();
+            let outputs = function
+                .outputs
+                .iter()
+                .map(|output| format!("{}: {}", output.name, output.parameter_type))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut description = format!("Returns {}", outputs);
+            if !function.traits.is_empty() {
+                description = format!("In {}. {}", function.traits.join(", "), description);
+            }
+            json!({
+                "title": format!("{}/{}", function.zome, function.name),
+                "description": description,
+                "type": "object",
+                "properties": properties,
+                "required": required
+            })
+        })
+        .collect();
+    json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": {
+            "title": format!("{} ({})", dna.name, instance_id),
+            "description": dna.description,
+            "version": dna.version
+        },
+        "methods": [{
+            "name": "call",
+            "summary": "Calls a zome function",
+            "paramStructure": "by-name",
+            "params": [
+                {
+                    "name": "instance_id",
+                    "required": true,
+                    "schema": {"type": "string", "enum": [instance_id]}
+                },
+                {
+                    "name": "zome",
+                    "required": true,
+                    "schema": {"type": "string", "enum": zome_names}
+                },
+                {
+                    "name": "function",
+                    "required": true,
+                    "schema": {"type": "string", "enum": function_names}
+                },
+                {
+                    "name": "params",
+                    "required": true,
+                    "description": "Inputs of the function, see the <zome>/<function> schema",
+                    "schema": {"oneOf": inputs}
+                },
+                {
+                    "name": "token",
+                    "required": false,
+                    "description": "Capability token, the public token is used if omitted",
+                    "schema": {"type": "string"}
+                }
+            ],
+            "result": {
+                "name": "result",
+                "description": "Result of the function",
+                "schema": {"type": "string", "contentMediaType": "application/json"}
+            }
+        }]
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::{
+        dna::{
+            entry_types::EntryTypeDef,
+            fn_declarations::{FnDeclaration, TraitFns},
+        },
+        entry::entry_type::{AppEntryType, EntryType},
+    };
+
+    fn example_dna() -> Dna {
+        let mut zome = Zome::default();
+        zome.description = String::from("Posts");
+        zome.fn_declarations.push(FnDeclaration {
+            name: String::from("create_post"),
+            inputs: vec![
+                FnParameter::new("content", "String"),
+                FnParameter::new("tags", "Vec<String>"),
+                FnParameter::new("in_reply_to", "Option<Address>"),
+            ],
+            outputs: vec![FnParameter::new("result", "ZomeApiResult<Address>")],
+        });
+        zome.fn_declarations.push(FnDeclaration {
+            name: String::from("validate_post"),
+            inputs: vec![FnParameter::new("content", "String")],
+            outputs: vec![FnParameter::new("valid", "bool")],
+        });
+        let mut public = TraitFns::new();
+        public.functions.push(String::from("create_post"));
+        zome.traits.insert(String::from("hc_public"), public);
+        let mut entry_type_def = EntryTypeDef::new();
+        entry_type_def.description = String::from("A blog post");
+        entry_type_def.sharing = Sharing::Private;
+        zome.entry_types
+            .insert(EntryType::App(AppEntryType::from("post")), entry_type_def);
+
+        let mut dna = Dna::new();
+        dna.name = String::from("blog");
+        dna.version = String::from("0.1.0");
+        dna.zomes.insert(String::from("posts"), zome);
+        dna.zomes.insert(String::from("empty"), Zome::default());
+        dna
+    }
+
+    #[test]
+    fn describes_zomes() {
+        let zomes = zomes(&example_dna());
+        assert_eq!(
+            zomes
+                .iter()
+                .map(|zome| zome.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["empty", "posts"]
+        );
+        let posts = &zomes[1];
+        assert_eq!(posts.description, "Posts");
+        assert_eq!(
+            posts.traits.get("hc_public"),
+            Some(&vec![String::from("create_post")])
+        );
+        assert_eq!(
+            posts.entry_types,
+            vec![EntryTypeInfo {
+                name: String::from("post"),
+                description: String::from("A blog post"),
+                sharing: Sharing::Private,
+                links_to: Vec::new(),
+                linked_from: Vec::new()
+            }]
+        );
+        assert_eq!(
+            serde_json::to_value(&posts.functions[0]).unwrap(),
+            json!({
+                "zome": "posts",
+                "name": "create_post",
+                "inputs": [
+                    {"name": "content", "type": "String"},
+                    {"name": "tags", "type": "Vec<String>"},
+                    {"name": "in_reply_to", "type": "Option<Address>"}
+                ],
+                "outputs": [{"name": "result", "type": "ZomeApiResult<Address>"}],
+                "traits": ["hc_public"]
+            })
+        );
+        assert_eq!(posts.functions[1].traits, Vec::<String>::new());
+    }
+
+    #[test]
+    fn lists_functions_of_one_or_all_zomes() {
+        let dna = example_dna();
+        let names = |functions: Vec<FunctionInfo>| {
+            functions
+                .into_iter()
+                .map(|function| format!("{}/{}", function.zome, function.name))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            functions(&dna, None).map(names),
+            Some(vec![
+                String::from("posts/create_post"),
+                String::from("posts/validate_post")
+            ])
+        );
+        assert_eq!(functions(&dna, Some("empty")).map(names), Some(vec![]));
+        assert_eq!(functions(&dna, Some("comments")), None);
+    }
+
+    #[test]
+    fn maps_rust_types_to_schemas() {
+        assert_eq!(schema_for_type("String"), json!({"type": "string"}));
+        assert_eq!(schema_for_type(" u32 "), json!({"type": "integer"}));
+        assert_eq!(
+            schema_for_type("Vec<Option<bool>>"),
+            json!({"type": "array", "items": {"oneOf": [{"type": "boolean"}, {"type": "null"}]}})
+        );
+        assert_eq!(
+            schema_for_type("Post"),
+            json!({"description": "Rust type Post"})
+        );
+    }
+
+    #[test]
+    fn generates_openrpc_document() {
+        let document = openrpc_document(&example_dna(), "blog-instance");
+        assert_eq!(document["openrpc"], json!(OPENRPC_VERSION));
+        assert_eq!(document["info"]["title"], json!("blog (blog-instance)"));
+        assert_eq!(document["info"]["version"], json!("0.1.0"));
+        let methods = document["methods"].as_array().unwrap();
+        assert_eq!(methods.len(), 1);
+        assert_eq!(methods[0]["name"], json!("call"));
+        let params = methods[0]["params"].as_array().unwrap();
+        let names: Vec<_> = params.iter().map(|param| param["name"].clone()).collect();
+        assert_eq!(
+            names,
+            vec![
+                json!("instance_id"),
+                json!("zome"),
+                json!("function"),
+                json!("params"),
+                json!("token")
+            ]
+        );
+        assert_eq!(params[0]["schema"]["enum"], json!(["blog-instance"]));
+        assert_eq!(params[1]["schema"]["enum"], json!(["empty", "posts"]));
+        assert_eq!(
+            params[2]["schema"]["enum"],
+            json!(["create_post", "validate_post"])
+        );
+        let inputs = params[3]["schema"]["oneOf"].as_array().unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(
+            inputs[0],
+            json!({
+                "title": "posts/create_post",
+                "description": "In hc_public. Returns result: ZomeApiResult<Address>",
+                "type": "object",
+                "properties": {
+                    "content": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "in_reply_to": {"oneOf": [{"type": "string"}, {"type": "null"}]}
+                },
+                "required": ["content", "tags"]
+            })
+        );
+        assert_eq!(inputs[1]["title"], json!("posts/validate_post"));
+        assert_eq!(
+            methods[0]["result"]["schema"],
+            json!({"type": "string", "contentMediaType": "application/json"})
+        );
+    }
+}
@@ -118,6 +118,9 @@ pub enum Action {
     /// Create a network proxy instance from the given [NetworkSettings](struct.NetworkSettings.html)
     InitNetwork(NetworkSettings),
 
+    /// Untrack the DNA and drop the network proxy, used when the instance shuts down
+    ShutdownNetwork,
+
     /// Makes the network PUT the given entry to the DHT.
     /// Distinguishes between different entry types and does
     /// the right thing respectively.
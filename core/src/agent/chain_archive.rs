@@ -0,0 +1,390 @@
+//! Portable, versioned archive of an agent's source chain.
+//!
+//! A `ChainArchive` holds every header of the source chain together with its entry
+//! (starting with the DNA entry) and optionally the DHT shard the instance was holding.
+//! It is used by the conductor to back up a chain and restore it on a fresh instance
+//! of the same DNA and agent.
+use crate::{
+    agent::state::AgentStateSnapshot,
+    context::Context,
+    nucleus::{
+        state::NucleusStateSnapshot,
+        validation::{validate_header_address, validate_header_provenances},
+    },
+    state::State,
+};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    eav::{Attribute, EaviQuery, EntityAttributeValueIndex, IndexFilter},
+    entry::{entry_type::EntryType, Entry},
+    error::{HcResult, HolochainError},
+    json::*,
+};
+use std::{collections::HashMap, convert::TryFrom, sync::Arc};
+
+/// Version of the archive format produced by this code.
+/// Archives with a different version get rejected on import.
+pub const CHAIN_ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, DefaultJson)]
+pub struct ChainArchiveItem {
+    pub header: ChainHeader,
+    pub entry: Entry,
+}
+
+/// Content and meta data of the DHT shard an instance holds.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, DefaultJson)]
+pub struct DhtShard {
+    pub entries: Vec<Entry>,
+    pub headers: Vec<ChainHeader>,
+    pub meta: Vec<EntityAttributeValueIndex>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, DefaultJson)]
+pub struct ChainArchive {
+    pub version: u32,
+    pub dna_address: Address,
+    pub agent_address: Address,
+    /// Source chain items ordered from the DNA entry up to the top of the chain
+    pub chain: Vec<ChainArchiveItem>,
+    pub dht_shard: Option<DhtShard>,
+}
+
+fn archive_error<S: Into<String>>(reason: S) -> HolochainError {
+    HolochainError::ValidationFailed(format!("Invalid chain archive: {}", reason.into()))
+}
+
+impl ChainArchive {
+    /// Walks the source chain of the given state from the top header down to the DNA entry
+    /// and collects all headers and entries.
+    /// If `include_dht_shard` is true, all entries and meta data held in the DHT get added.
+    pub fn from_state(state: &State, include_dht_shard: bool) -> HcResult<Self> {
+        let agent = state.agent();
+        let cas = agent.chain_store().content_storage();
+        let mut chain = agent
+            .iter_chain()
+            .map(|header| {
+                let content = cas.read()?.fetch(header.entry_address())?.ok_or_else(|| {
+                    HolochainError::ErrorGeneric(format!(
+                        "Entry {} not found in chain storage",
+                        header.entry_address()
+                    ))
+                })?;
+                let entry = Entry::try_from_content(&content)?;
+                Ok(ChainArchiveItem { header, entry })
+            })
+            .collect::<HcResult<Vec<_>>>()?;
+        chain.reverse();
+
+        let dna_address = match chain.first() {
+            Some(ChainArchiveItem {
+                entry: Entry::Dna(_),
+                header,
+            }) => header.entry_address().clone(),
+            _ => {
+                return Err(HolochainError::ErrorGeneric(
+                    "Source chain does not start with a DNA entry".to_string(),
+                ))
+            }
+        };
+
+        let dht_shard = if include_dht_shard {
+            Some(Self::collect_dht_shard(state)?)
+        } else {
+            None
+        };
+
+        Ok(ChainArchive {
+            version: CHAIN_ARCHIVE_VERSION,
+            dna_address,
+            agent_address: agent.get_agent_address()?,
+            chain,
+            dht_shard,
+        })
+    }
+
+    fn collect_dht_shard(state: &State) -> HcResult<DhtShard> {
+        let dht = state.dht();
+        let meta = dht
+            .meta_storage()
+            .read()?
+            .fetch_eavi(&EaviQuery::new(
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                IndexFilter::Range(None, None),
+            ))?
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let cas = dht.content_storage();
+        let cas = cas.read()?;
+        let mut entries = Vec::new();
+        let mut headers = Vec::new();
+        for eavi in meta
+            .iter()
+            .filter(|eavi| eavi.attribute() == Attribute::EntryHeader)
+        {
+            if let Some(content) = cas.fetch(&eavi.entity())? {
+                let entry = Entry::try_from_content(&content)?;
+                if !entries.contains(&entry) {
+                    entries.push(entry);
+                }
+            }
+            if let Some(content) = cas.fetch(&eavi.value())? {
+                let header = ChainHeader::try_from_content(&content)?;
+                if !headers.contains(&header) {
+                    headers.push(header);
+                }
+            }
+        }
+
+        Ok(DhtShard {
+            entries,
+            headers,
+            meta,
+        })
+    }
+
+    pub fn top_chain_header(&self) -> Option<ChainHeader> {
+        self.chain.last().map(|item| item.header.clone())
+    }
+
+    /// Checks the archive for integrity:
+    /// * the chain starts with the DNA entry referenced by `dna_address`,
+    /// * every header links to its predecessor (and to the previous header of the same type),
+    /// * every entry matches the address and type given in its header,
+    /// * every header carries a valid signature of the archived agent.
+    /// Headers of the DHT shard get their signatures and entries checked as well.
+    pub fn verify(&self) -> HcResult<()> {
+        if self.version != CHAIN_ARCHIVE_VERSION {
+            return Err(archive_error(format!(
+                "unsupported version {} (expected {})",
+                self.version, CHAIN_ARCHIVE_VERSION
+            )));
+        }
+
+        match self.chain.first() {
+            Some(ChainArchiveItem {
+                entry: Entry::Dna(_),
+                header,
+            }) if *header.entry_address() == self.dna_address => (),
+            _ => return Err(archive_error("chain does not start with the DNA entry")),
+        }
+
+        let mut previous: Option<Address> = None;
+        let mut previous_of_type: HashMap<EntryType, Address> = HashMap::new();
+        for item in self.chain.iter() {
+            let header = &item.header;
+            if header.link() != previous {
+                return Err(archive_error(format!(
+                    "header {} does not link to its predecessor",
+                    header.address()
+                )));
+            }
+            if header.link_same_type() != previous_of_type.get(header.entry_type()).cloned() {
+                return Err(archive_error(format!(
+                    "header {} does not link to the previous header of type {}",
+                    header.address(),
+                    header.entry_type()
+                )));
+            }
+            if item.entry.entry_type() != *header.entry_type() {
+                return Err(archive_error(format!(
+                    "entry type of {} does not match its header",
+                    header.entry_address()
+                )));
+            }
+            validate_header_address(&item.entry, header)?;
+            if !header
+                .provenances()
+                .iter()
+                .any(|provenance| provenance.source() == self.agent_address)
+            {
+                return Err(archive_error(format!(
+                    "header {} is not signed by agent {}",
+                    header.address(),
+                    self.agent_address
+                )));
+            }
+            validate_header_provenances(header)?;
+
+            previous = Some(header.address());
+            previous_of_type.insert(header.entry_type().clone(), header.address());
+        }
+
+        let agent_entry_present = self.chain.iter().any(|item| match item.entry {
+            Entry::AgentId(_) => item.entry.address() == self.agent_address,
+            _ => false,
+        });
+        if !agent_entry_present {
+            return Err(archive_error("agent entry missing from chain"));
+        }
+
+        if let Some(ref shard) = self.dht_shard {
+            for header in shard.headers.iter() {
+                let entry = shard
+                    .entries
+                    .iter()
+                    .find(|entry| entry.address() == *header.entry_address())
+                    .ok_or_else(|| {
+                        archive_error(format!(
+                            "DHT shard holds header {} without its entry",
+                            header.address()
+                        ))
+                    })?;
+                validate_header_address(entry, header)?;
+                validate_header_provenances(header)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the archive and writes its content into the storages of the given context.
+    /// The instance's persisted state snapshot gets pointed to the archived top header, so
+    /// the restored chain becomes active when the instance is loaded from storage next.
+    ///
+    /// Only chains of the same DNA and agent can be restored and the target chain must not
+    /// contain anything but genesis entries, so no data gets overwritten.
+    pub fn restore(&self, context: &Arc<Context>) -> HcResult<()> {
+        self.verify()?;
+
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::ErrorGeneric("Context has no state".to_string()))?;
+
+        let dna_address = state
+            .nucleus()
+            .dna()
+            .map(|dna| dna.address())
+            .ok_or_else(|| HolochainError::ErrorGeneric("Instance has no DNA".to_string()))?;
+        if dna_address != self.dna_address {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Chain archive is for DNA {} but instance runs DNA {}",
+                self.dna_address, dna_address
+            )));
+        }
+        if context.agent_id.address() != self.agent_address {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Chain archive is for agent {} but instance runs as agent {}",
+                self.agent_address,
+                context.agent_id.address()
+            )));
+        }
+        let has_app_data = state
+            .agent()
+            .iter_chain()
+            .any(|header| match header.entry_type() {
+                EntryType::Dna | EntryType::AgentId | EntryType::CapTokenGrant => false,
+                _ => true,
+            });
+        if has_app_data {
+            return Err(HolochainError::ErrorGeneric(
+                "Instance already has data on its source chain".to_string(),
+            ));
+        }
+
+        {
+            let cas = state.agent().chain_store().content_storage();
+            let mut cas = cas.write()?;
+            for item in self.chain.iter() {
+                cas.add(&item.entry)?;
+                cas.add(&item.header)?;
+            }
+        }
+
+        if let Some(ref shard) = self.dht_shard {
+            let dht = state.dht();
+            {
+                let cas = dht.content_storage();
+                let mut cas = cas.write()?;
+                for entry in shard.entries.iter() {
+                    cas.add(entry)?;
+                }
+                for header in shard.headers.iter() {
+                    cas.add(header)?;
+                }
+            }
+            let eav = dht.meta_storage();
+            let mut eav = eav.write()?;
+            for eavi in shard.meta.iter() {
+                eav.add_eavi(eavi)?;
+            }
+        }
+
+        let mut persisted = context.dht_storage.write()?;
+        persisted.add(&AgentStateSnapshot::new(self.top_chain_header()))?;
+        persisted.add(&NucleusStateSnapshot::from(&*state))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{agent::state::AGENT_SNAPSHOT_ADDRESS, instance::tests::test_instance_and_context};
+    use holochain_core_types::entry::test_entry;
+
+    fn test_archive(netname: &str) -> (ChainArchive, Arc<Context>) {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, Some(netname)).expect("Could not create test instance");
+        let archive = ChainArchive::from_state(&*context.state().unwrap(), true)
+            .expect("Could not export chain");
+        (archive, context)
+    }
+
+    #[test]
+    fn export_contains_genesis_chain() {
+        let (archive, context) = test_archive("export_contains_genesis_chain");
+        assert_eq!(archive.version, CHAIN_ARCHIVE_VERSION);
+        assert_eq!(archive.agent_address, context.agent_id.address());
+        assert_eq!(archive.chain[0].entry.entry_type(), EntryType::Dna);
+        assert_eq!(
+            archive.top_chain_header(),
+            context.state().unwrap().agent().top_chain_header()
+        );
+        assert!(archive.dht_shard.is_some());
+        assert_eq!(archive.verify(), Ok(()));
+    }
+
+    #[test]
+    fn verify_rejects_tampered_archive() {
+        let (archive, _) = test_archive("verify_rejects_tampered_archive");
+
+        let mut wrong_entry = archive.clone();
+        wrong_entry.chain.last_mut().unwrap().entry = test_entry();
+        assert!(wrong_entry.verify().is_err());
+
+        let mut missing_link = archive.clone();
+        missing_link.chain.remove(1);
+        assert!(missing_link.verify().is_err());
+
+        let mut wrong_version = archive.clone();
+        wrong_version.version = CHAIN_ARCHIVE_VERSION + 1;
+        assert!(wrong_version.verify().is_err());
+    }
+
+    #[test]
+    fn restore_round_trip() {
+        let (archive, _) = test_archive("restore_round_trip_source");
+        let (_, context) = test_archive("restore_round_trip_target");
+
+        archive.restore(&context).expect("Could not restore chain");
+
+        let snapshot = context
+            .dht_storage
+            .read()
+            .unwrap()
+            .fetch(&Address::from(AGENT_SNAPSHOT_ADDRESS))
+            .unwrap()
+            .map(|content| AgentStateSnapshot::try_from_content(&content).unwrap())
+            .unwrap();
+        assert_eq!(
+            snapshot.top_chain_header(),
+            archive.top_chain_header().as_ref()
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Secondary index over a source chain, mapping entry types to the headers of that type.
+//!
+//! The index gets stored in the chain's CAS so it survives restarts. Headers of each type are
+//! kept in order of their position on the chain and grouped in pages of `PAGE_SIZE` headers.
+//! Full pages are immutable content addressed items, so adding a header only rewrites the
+//! (small) index root and the page that is being filled.
+//! The root remembers the top header it was built for. An index that does not match the chain
+//! it is used with is never consulted. Reads walk the chain instead and the index gets rebuilt
+//! from the chain when the next header is committed.
+use crate::agent::chain_store::ChainStoreIterator;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    error::{HcResult, HolochainError},
+    json::JsonString,
+};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    sync::{Arc, RwLock},
+};
+
+pub static CHAIN_INDEX_ADDRESS: &'static str = "ChainIndex";
+
+/// Number of headers per stored page
+const PAGE_SIZE: usize = 256;
+
+/// Address of a header together with its position on the chain (the first header has 0)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexedHeader {
+    pub position: usize,
+    pub address: Address,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+struct ChainIndexPage {
+    headers: Vec<IndexedHeader>,
+}
+
+impl AddressableContent for ChainIndexPage {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Self::try_from(content.to_owned())
+    }
+}
+
+/// All headers of one entry type, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct EntryTypeIndex {
+    count: usize,
+    /// Addresses of full pages
+    pages: Vec<Address>,
+    /// Headers that don't fill a page yet
+    tail: Vec<IndexedHeader>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct ChainIndex {
+    /// Address of the top header this index was built for
+    top: Option<Address>,
+    /// Number of headers on the indexed chain
+    length: usize,
+    /// Indexes per entry type, keyed by the entry type's string representation
+    types: BTreeMap<String, EntryTypeIndex>,
+}
+
+impl AddressableContent for ChainIndex {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Self::try_from(content.to_owned())
+    }
+
+    fn address(&self) -> Address {
+        CHAIN_INDEX_ADDRESS.into()
+    }
+}
+
+impl ChainIndex {
+    /// Builds the index for the chain ending at `top_chain_header` by walking the whole chain.
+    pub fn build(
+        storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+        top_chain_header: &Option<ChainHeader>,
+    ) -> HcResult<ChainIndex> {
+        let mut headers: Vec<ChainHeader> =
+            ChainStoreIterator::new(storage.clone(), top_chain_header.clone()).collect();
+        headers.reverse();
+        let mut index = ChainIndex::default();
+        for header in headers.iter() {
+            index.add_header(storage, header)?;
+        }
+        Ok(index)
+    }
+
+    /// Loads the index stored in the given CAS, if there is one.
+    pub fn load(
+        storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+    ) -> HcResult<Option<ChainIndex>> {
+        match storage.read()?.fetch(&Address::from(CHAIN_INDEX_ADDRESS))? {
+            Some(content) => Ok(Some(ChainIndex::try_from_content(&content)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self, storage: &Arc<RwLock<dyn ContentAddressableStorage>>) -> HcResult<()> {
+        storage.write()?.add(self)
+    }
+
+    pub fn top(&self) -> &Option<Address> {
+        &self.top
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Names of all entry types that have headers on the chain
+    pub fn entry_types(&self) -> Vec<String> {
+        self.types.keys().cloned().collect()
+    }
+
+    /// Number of headers of the given entry type
+    pub fn count(&self, entry_type: &str) -> usize {
+        self.types.get(entry_type).map(|t| t.count).unwrap_or(0)
+    }
+
+    /// Appends `header`, which has to be linked to the current top header, to the index.
+    pub fn add_header(
+        &mut self,
+        storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+        header: &ChainHeader,
+    ) -> HcResult<()> {
+        if header.link() != self.top {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Header {} is not linked to the top of the indexed chain",
+                header.address()
+            )));
+        }
+        let type_index = self
+            .types
+            .entry(header.entry_type().to_string())
+            .or_insert_with(EntryTypeIndex::default);
+        type_index.tail.push(IndexedHeader {
+            position: self.length,
+            address: header.address(),
+        });
+        if type_index.tail.len() >= PAGE_SIZE {
+            let page = ChainIndexPage {
+                headers: type_index.tail.drain(..).collect(),
+            };
+            storage.write()?.add(&page)?;
+            type_index.pages.push(page.address());
+        }
+        type_index.count += 1;
+        self.length += 1;
+        self.top = Some(header.address());
+        Ok(())
+    }
+
+    /// Returns up to `max` of the newest headers of the given entry type, newest first.
+    /// Only the pages needed to do so get loaded.
+    pub fn newest_of_type(
+        &self,
+        storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+        entry_type: &str,
+        max: usize,
+    ) -> HcResult<Vec<IndexedHeader>> {
+        let type_index = match self.types.get(entry_type) {
+            Some(type_index) => type_index,
+            None => return Ok(Vec::new()),
+        };
+        let mut headers: Vec<IndexedHeader> =
+            type_index.tail.iter().rev().take(max).cloned().collect();
+        for page_address in type_index.pages.iter().rev() {
+            if headers.len() >= max {
+                break;
+            }
+            let page = storage
+                .read()?
+                .fetch(page_address)?
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric(format!(
+                        "Chain index page {} not found",
+                        page_address
+                    ))
+                })
+                .and_then(|content| ChainIndexPage::try_from_content(&content))?;
+            let missing = max - headers.len();
+            headers.extend(page.headers.into_iter().rev().take(missing));
+        }
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::agent::chain_store::tests::test_chain_store;
+    use holochain_core_types::{
+        chain_header::test_provenances,
+        entry::{test_entry, test_entry_b},
+        time::test_iso_8601,
+    };
+
+    /// Stores a chain that alternates between the types of test_entry() and test_entry_b()
+    /// and returns its headers, oldest first.
+    fn store_test_chain(
+        storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+        length: usize,
+    ) -> Vec<ChainHeader> {
+        let mut headers: Vec<ChainHeader> = Vec::new();
+        for i in 0..length {
+            let entry = if i % 2 == 0 {
+                test_entry()
+            } else {
+                test_entry_b()
+            };
+            let link_same_type = if i >= 2 {
+                Some(headers[i - 2].address())
+            } else {
+                None
+            };
+            let header = ChainHeader::new(
+                &entry.entry_type(),
+                &entry.address(),
+                &test_provenances(&format!("sig{}", i)),
+                &headers.last().map(|header| header.address()),
+                &link_same_type,
+                &None,
+                &test_iso_8601(),
+            );
+            storage.write().unwrap().add(&header).unwrap();
+            headers.push(header);
+        }
+        headers
+    }
+
+    #[test]
+    fn build_and_page_through_index() {
+        let storage = test_chain_store().content_storage();
+        let headers = store_test_chain(&storage, PAGE_SIZE * 2 + 10);
+        let top = headers.last().cloned();
+
+        let index = ChainIndex::build(&storage, &top).expect("Could not build index");
+        assert_eq!(index.top(), &top.map(|header| header.address()));
+        assert_eq!(index.length(), headers.len());
+
+        let type_a = test_entry().entry_type().to_string();
+        assert_eq!(index.count(&type_a), PAGE_SIZE + 5);
+
+        let all_a = index
+            .newest_of_type(&storage, &type_a, usize::max_value())
+            .unwrap();
+        let expected: Vec<IndexedHeader> = headers
+            .iter()
+            .enumerate()
+            .filter(|(position, _)| position % 2 == 0)
+            .map(|(position, header)| IndexedHeader {
+                position,
+                address: header.address(),
+            })
+            .rev()
+            .collect();
+        assert_eq!(all_a, expected);
+
+        let newest_three = index.newest_of_type(&storage, &type_a, 3).unwrap();
+        assert_eq!(newest_three, expected[..3].to_vec());
+    }
+
+    #[test]
+    fn rejects_unlinked_header_and_round_trips_through_storage() {
+        let storage = test_chain_store().content_storage();
+        let headers = store_test_chain(&storage, 3);
+
+        let mut index = ChainIndex::default();
+        assert!(index.add_header(&storage, &headers[1]).is_err());
+        for header in headers.iter() {
+            index.add_header(&storage, header).unwrap();
+        }
+
+        index.save(&storage).unwrap();
+        assert_eq!(ChainIndex::load(&storage).unwrap(), Some(index));
+    }
+}
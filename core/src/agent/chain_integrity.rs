@@ -0,0 +1,329 @@
+//! Integrity checks for a persisted source chain.
+//!
+//! Walks the chain from a given top header down to the first header and checks that
+//! * every `link` and `link_same_type` pointer resolves to the right header,
+//! * every entry can be loaded and matches the address and type given in its header,
+//! * every provenance carries a valid signature,
+//! * timestamps never decrease from older to newer headers.
+use crate::{
+    agent::chain_store::ChainStore,
+    nucleus::validation::{validate_header_address, validate_header_provenances},
+};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
+};
+use std::{collections::HashMap, fmt};
+
+/// The first inconsistency found while walking the chain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BrokenChainLink {
+    /// Address of the header that is broken
+    pub header_address: Address,
+    /// Position of that header counted from the top of the chain (top header = 0)
+    pub position: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenChainLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "header {} (position {} from the top): {}",
+            self.header_address, self.position, self.reason
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChainIntegrityReport {
+    /// Number of headers that passed all checks
+    pub verified_headers: usize,
+    pub broken_link: Option<BrokenChainLink>,
+}
+
+impl ChainIntegrityReport {
+    pub fn is_valid(&self) -> bool {
+        self.broken_link.is_none()
+    }
+}
+
+fn broken(header: &ChainHeader, position: usize, reason: String) -> BrokenChainLink {
+    BrokenChainLink {
+        header_address: header.address(),
+        position,
+        reason,
+    }
+}
+
+fn fetch_header(
+    chain_store: &ChainStore,
+    address: &Address,
+) -> Result<Option<ChainHeader>, String> {
+    let storage = chain_store.content_storage();
+    let maybe_content = storage
+        .read()
+        .map_err(|_| "could not get lock on storage".to_string())?
+        .fetch(address)
+        .map_err(|e| e.to_string())?;
+    match maybe_content {
+        Some(content) => ChainHeader::try_from_content(&content)
+            .map(Some)
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+fn fetch_entry(chain_store: &ChainStore, address: &Address) -> Result<Entry, String> {
+    let storage = chain_store.content_storage();
+    let content = storage
+        .read()
+        .map_err(|_| "could not get lock on storage".to_string())?
+        .fetch(address)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("entry {} not found in storage", address))?;
+    Entry::try_from_content(&content)
+        .map_err(|e| format!("could not load entry {}: {}", address, e))
+}
+
+fn verify_header(
+    chain_store: &ChainStore,
+    header: &ChainHeader,
+    position: usize,
+) -> Result<(), BrokenChainLink> {
+    let entry = fetch_entry(chain_store, header.entry_address())
+        .map_err(|reason| broken(header, position, reason))?;
+    if entry.entry_type() != *header.entry_type() {
+        return Err(broken(
+            header,
+            position,
+            format!(
+                "entry has type {} but header says {}",
+                entry.entry_type(),
+                header.entry_type()
+            ),
+        ));
+    }
+
+    // Make sure the link resolves before the iterator tries to follow it
+    if let Some(link) = header.link() {
+        match fetch_header(chain_store, &link) {
+            Ok(Some(_)) => (),
+            Ok(None) => {
+                return Err(broken(
+                    header,
+                    position,
+                    format!("linked header {} not found in storage", link),
+                ))
+            }
+            Err(e) => {
+                return Err(broken(
+                    header,
+                    position,
+                    format!("linked header {} could not be loaded: {}", link, e),
+                ))
+            }
+        }
+    }
+
+    validate_header_address(&entry, header)
+        .and_then(|_| validate_header_provenances(header))
+        .map_err(|e| broken(header, position, HolochainError::from(e).to_string()))?;
+    Ok(())
+}
+
+/// Verifies the chain in the given ChainStore, starting at `top_chain_header`, and stops
+/// at the first broken link.
+pub fn verify_chain(
+    chain_store: &ChainStore,
+    top_chain_header: &Option<ChainHeader>,
+) -> ChainIntegrityReport {
+    // For every entry type, the `link_same_type` claim of the last (newer) header of that type
+    // that still has to be matched, together with the claiming header and its position.
+    let mut same_type_claims: HashMap<EntryType, (Option<Address>, ChainHeader, usize)> =
+        HashMap::new();
+    let mut newer: Option<ChainHeader> = None;
+    let mut position = 0;
+
+    let report = |verified_headers, broken_link| ChainIntegrityReport {
+        verified_headers,
+        broken_link,
+    };
+
+    for header in chain_store.iter(top_chain_header) {
+        if let Some(ref newer) = newer {
+            if header.timestamp() > newer.timestamp() {
+                return report(
+                    position,
+                    Some(broken(
+                        &header,
+                        position,
+                        format!(
+                            "timestamp {} is later than the one of the next header ({})",
+                            header.timestamp(),
+                            newer.timestamp()
+                        ),
+                    )),
+                );
+            }
+        }
+
+        if let Some((claim, claimer, claimer_position)) = same_type_claims.get(header.entry_type())
+        {
+            if *claim != Some(header.address()) {
+                return report(
+                    *claimer_position,
+                    Some(broken(
+                        claimer,
+                        *claimer_position,
+                        format!(
+                            "link_same_type does not point to the previous header of type {} ({})",
+                            header.entry_type(),
+                            header.address()
+                        ),
+                    )),
+                );
+            }
+        }
+
+        if let Err(broken_link) = verify_header(chain_store, &header, position) {
+            return report(position, Some(broken_link));
+        }
+
+        same_type_claims.insert(
+            header.entry_type().clone(),
+            (header.link_same_type(), header.clone(), position),
+        );
+        newer = Some(header);
+        position += 1;
+    }
+
+    // Claims left over point to headers that are not part of this chain
+    let dangling = same_type_claims
+        .into_iter()
+        .filter_map(|(_, (claim, claimer, claimer_position))| {
+            claim.map(|address| (address, claimer, claimer_position))
+        })
+        .min_by_key(|(_, _, claimer_position)| *claimer_position);
+    match dangling {
+        Some((address, claimer, claimer_position)) => report(
+            claimer_position,
+            Some(broken(
+                &claimer,
+                claimer_position,
+                format!(
+                    "link_same_type points to {} which is not a header of this chain",
+                    address
+                ),
+            )),
+        ),
+        None => report(position, None),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::tests::test_instance_and_context;
+    use holochain_core_types::{entry::test_entry, signature::Provenance, time::Iso8601};
+
+    fn test_chain() -> (ChainStore, ChainHeader) {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (instance, _) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+        let agent = instance.state().agent();
+        (agent.chain_store(), agent.top_chain_header().unwrap())
+    }
+
+    fn header_on_top(
+        top: &ChainHeader,
+        entry: &Entry,
+        provenances: &[Provenance],
+        timestamp: &Iso8601,
+    ) -> ChainHeader {
+        ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            provenances,
+            &Some(top.address()),
+            &None,
+            &None,
+            timestamp,
+        )
+    }
+
+    #[test]
+    fn verifies_intact_chain() {
+        let (chain_store, top) = test_chain();
+        let report = verify_chain(&chain_store, &Some(top));
+        assert!(report.is_valid());
+        assert!(report.verified_headers >= 2);
+    }
+
+    #[test]
+    fn reports_missing_entry() {
+        let (chain_store, top) = test_chain();
+        // the entry of this header never gets stored:
+        let header = header_on_top(&top, &test_entry(), top.provenances(), top.timestamp());
+        chain_store
+            .content_storage()
+            .write()
+            .unwrap()
+            .add(&header)
+            .unwrap();
+
+        let report = verify_chain(&chain_store, &Some(header.clone()));
+        assert_eq!(report.verified_headers, 0);
+        let broken_link = report.broken_link.unwrap();
+        assert_eq!(broken_link.header_address, header.address());
+        assert_eq!(broken_link.position, 0);
+    }
+
+    #[test]
+    fn reports_invalid_signature() {
+        let (chain_store, top) = test_chain();
+        let entry = test_entry();
+        // reuses the signature of the previous header, which was made for a different entry:
+        let header = header_on_top(&top, &entry, top.provenances(), top.timestamp());
+        {
+            let storage = chain_store.content_storage();
+            let mut storage = storage.write().unwrap();
+            storage.add(&entry).unwrap();
+            storage.add(&header).unwrap();
+        }
+
+        let report = verify_chain(&chain_store, &Some(header.clone()));
+        assert_eq!(
+            report
+                .broken_link
+                .map(|broken_link| broken_link.header_address),
+            Some(header.address())
+        );
+    }
+
+    #[test]
+    fn reports_unresolved_link() {
+        let (chain_store, top) = test_chain();
+        let entry = test_entry();
+        let header = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            top.provenances(),
+            &Some(Address::from("QmNotThere")),
+            &None,
+            &None,
+            top.timestamp(),
+        );
+        chain_store
+            .content_storage()
+            .write()
+            .unwrap()
+            .add(&entry)
+            .unwrap();
+
+        let report = verify_chain(&chain_store, &Some(header));
+        assert!(report.broken_link.unwrap().reason.contains("QmNotThere"));
+    }
+}
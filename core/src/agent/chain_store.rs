@@ -1,3 +1,4 @@
+use crate::agent::chain_index::ChainIndex;
 use globset::{GlobBuilder, GlobSetBuilder};
 use holochain_core_types::{
     cas::{
@@ -6,7 +7,10 @@ use holochain_core_types::{
     },
     chain_header::ChainHeader,
     entry::entry_type::EntryType,
-    error::RibosomeErrorCode::{self, *},
+    error::{
+        HcResult, HolochainError,
+        RibosomeErrorCode::{self, *},
+    },
 };
 use std::{
     str::FromStr,
@@ -17,6 +21,8 @@ use std::{
 pub struct ChainStore {
     // Storages holding local shard data
     content_storage: Arc<RwLock<dyn ContentAddressableStorage>>,
+    // Last used per-entry-type index, shared between clones of this ChainStore
+    index: Arc<RwLock<Option<ChainIndex>>>,
 }
 
 impl PartialEq for ChainStore {
@@ -44,7 +50,10 @@ pub enum ChainStoreQueryResult {
 
 impl ChainStore {
     pub fn new(content_storage: Arc<RwLock<dyn ContentAddressableStorage>>) -> Self {
-        ChainStore { content_storage }
+        ChainStore {
+            content_storage,
+            index: Arc::new(RwLock::new(None)),
+        }
     }
 
     pub fn content_storage(&self) -> Arc<RwLock<dyn ContentAddressableStorage>> {
@@ -55,19 +64,123 @@ impl ChainStore {
         ChainStoreIterator::new(self.content_storage.clone(), start_chain_header.clone())
     }
 
-    /// Scans the local chain for the first Entry of EntryType, and then creates a
-    /// ChainStoreTypeIter to return the sequence of all Entrys with the same EntryType. Requires a
-    /// single EntryType.
+    /// Finds the first Entry of EntryType (through the index, or by scanning the local chain if
+    /// there is no index for that chain), and then creates a ChainStoreTypeIter to return the
+    /// sequence of all Entrys with the same EntryType. Requires a single EntryType.
     pub fn iter_type(
         &self,
         start_chain_header: &Option<ChainHeader>,
         entry_type: &EntryType,
     ) -> ChainStoreTypeIterator {
-        ChainStoreTypeIterator::new(
-            self.content_storage.clone(),
-            self.iter(start_chain_header)
-                .find(|chain_header| chain_header.entry_type() == entry_type),
-        )
+        let first = self
+            .chain_index(start_chain_header)
+            .and_then(|index| {
+                index.ok_or_else(|| HolochainError::ErrorGeneric(String::from("No chain index")))
+            })
+            .and_then(|index| {
+                index.newest_of_type(&self.content_storage, &entry_type.to_string(), 1)
+            })
+            .and_then(|newest| match newest.first() {
+                Some(indexed) => self.fetch_header(&indexed.address).map(Some),
+                None => Ok(None),
+            })
+            .unwrap_or_else(|_| {
+                self.iter(start_chain_header)
+                    .find(|chain_header| chain_header.entry_type() == entry_type)
+            });
+        ChainStoreTypeIterator::new(self.content_storage.clone(), first)
+    }
+
+    fn fetch_header(&self, address: &Address) -> HcResult<ChainHeader> {
+        self.content_storage
+            .read()?
+            .fetch(address)?
+            .ok_or_else(|| HolochainError::ErrorGeneric(format!("Header {} not found", address)))
+            .and_then(|content| ChainHeader::try_from_content(&content))
+    }
+
+    /// Returns the per-entry-type index for the chain ending at `start_chain_header`, if the
+    /// cached or stored index was built for that chain. Never builds or stores an index, so
+    /// reading the chain does not write to its storage; only add_to_index() does that.
+    pub fn chain_index(
+        &self,
+        start_chain_header: &Option<ChainHeader>,
+    ) -> HcResult<Option<ChainIndex>> {
+        let top = start_chain_header.as_ref().map(|header| header.address());
+        if let Some(ref index) = *self.index.read()? {
+            if *index.top() == top {
+                return Ok(Some(index.clone()));
+            }
+        }
+        match ChainIndex::load(&self.content_storage)? {
+            Some(index) if *index.top() == top => {
+                *self.index.write()? = Some(index.clone());
+                Ok(Some(index))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Adds a newly committed header, which has to be linked to the current top header,
+    /// to the index and stores the updated index.
+    /// (Re)builds the index from the chain first if there is none for the previous top.
+    pub fn add_to_index(&self, chain_header: &ChainHeader) -> HcResult<()> {
+        let previous_top = match chain_header.link() {
+            Some(link) => Some(self.fetch_header(&link)?),
+            None => None,
+        };
+        let mut index = match self.chain_index(&previous_top)? {
+            Some(index) => index,
+            None => ChainIndex::build(&self.content_storage, &previous_top)?,
+        };
+        index.add_header(&self.content_storage, chain_header)?;
+        index.save(&self.content_storage)?;
+        *self.index.write()? = Some(index);
+        Ok(())
+    }
+
+    /// Uses the index to find the headers of all entry types for which `matches` is true,
+    /// newest first. Returns None if no index could be used, so the caller can fall back
+    /// to scanning the chain.
+    fn query_index<F>(
+        &self,
+        start_chain_header: &Option<ChainHeader>,
+        matches: F,
+        start: usize,
+        limit: usize,
+        headers: bool,
+    ) -> Option<ChainStoreQueryResult>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let index = self.chain_index(start_chain_header).ok()??;
+        let max = start.saturating_add(limit);
+        let mut indexed = Vec::new();
+        for entry_type in index.entry_types().iter().filter(|t| matches(t)) {
+            indexed.extend(
+                index
+                    .newest_of_type(&self.content_storage, entry_type, max)
+                    .ok()?,
+            );
+        }
+        indexed.sort_by(|a, b| b.position.cmp(&a.position));
+        let found: Vec<ChainHeader> = indexed
+            .iter()
+            .skip(start)
+            .take(limit)
+            .map(|indexed| self.fetch_header(&indexed.address))
+            .collect::<HcResult<_>>()
+            .ok()?;
+        Some(if headers {
+            ChainStoreQueryResult::Headers(found)
+        } else {
+            ChainStoreQueryResult::Addresses(
+                found
+                    .iter()
+                    .map(|header| header.entry_address().to_owned())
+                    .collect(),
+            )
+        })
     }
 
     // Supply a None for options to get defaults (all elements, no ChainHeaders just Addresses)
@@ -120,11 +233,22 @@ impl ChainStore {
                 }
             }
             [one] if !is_glob_str(one) => {
-                // Single EntryType without "glob" pattern; uses .iter_type()
+                // Single EntryType without "glob" pattern; uses the index, or .iter_type() if
+                // the index can't be used
                 let entry_type = match EntryType::from_str(&one) {
                     Ok(inner) => inner,
                     Err(..) => return Err(UnknownEntryType),
                 };
+                let entry_type_name = entry_type.to_string();
+                if let Some(result) = self.query_index(
+                    start_chain_header,
+                    |name| name == entry_type_name,
+                    start,
+                    limit,
+                    headers,
+                ) {
+                    return Ok(result);
+                }
                 if headers {
                     ChainStoreQueryResult::Headers(
                         self.iter_type(start_chain_header, &entry_type)
@@ -159,6 +283,15 @@ impl ChainStore {
                     );
                 }
                 let globset = builder.build().map_err(|_| UnknownEntryType)?;
+                if let Some(result) = self.query_index(
+                    start_chain_header,
+                    |name| globset.is_match(name),
+                    start,
+                    limit,
+                    headers,
+                ) {
+                    return Ok(result);
+                }
                 if headers {
                     ChainStoreQueryResult::Headers(
                         self.iter(start_chain_header)
@@ -299,7 +432,10 @@ impl Iterator for ChainStoreTypeIterator {
 #[cfg(test)]
 pub mod tests {
     use self::tempfile::tempdir;
-    use crate::agent::chain_store::{ChainStore, ChainStoreQueryOptions, ChainStoreQueryResult};
+    use crate::agent::{
+        chain_index::ChainIndex,
+        chain_store::{ChainStore, ChainStoreQueryOptions, ChainStoreQueryResult},
+    };
     use holochain_cas_implementations::cas::file::FilesystemStorage;
     use holochain_core_types::{
         cas::content::AddressableContent,
@@ -781,6 +917,108 @@ pub mod tests {
         }
     }
 
+    #[test]
+    /// show that headers added to the index are found by query() with start and limit
+    fn indexed_query_test() {
+        let chain_store = test_chain_store();
+        let storage = chain_store.content_storage();
+
+        let mut chain_headers: Vec<ChainHeader> = Vec::new();
+        for i in 0..10 {
+            let entry = if i % 2 == 0 {
+                test_entry()
+            } else {
+                test_entry_b()
+            };
+            let chain_header = ChainHeader::new(
+                &entry.entry_type(),
+                &entry.address(),
+                &test_provenances(&format!("sig{}", i)),
+                &chain_headers.last().map(|header| header.address()),
+                &None,
+                &None,
+                &test_iso_8601(),
+            );
+            storage.write().unwrap().add(&chain_header).unwrap();
+            chain_store
+                .add_to_index(&chain_header)
+                .expect("could not add header to index");
+            chain_headers.push(chain_header);
+        }
+        let top = chain_headers.last().cloned();
+
+        let index = chain_store.chain_index(&top).unwrap().unwrap();
+        assert_eq!(index.length(), 10);
+        assert_eq!(index.count(&test_entry_type_b().to_string()), 5);
+
+        // There is no index for an older part of the chain and reading must not build one
+        let older = Some(chain_headers[4].clone());
+        assert_eq!(chain_store.chain_index(&older).unwrap(), None);
+        let storage_size = storage.read().unwrap().addresses().unwrap().len();
+        let found = match chain_store
+            .query(
+                &older,
+                &vec![test_entry_type_b().to_string().as_ref()],
+                ChainStoreQueryOptions::default(),
+            )
+            .unwrap()
+        {
+            ChainStoreQueryResult::Addresses(addresses) => addresses,
+            other => panic!("Unexpected query value {:?}", other),
+        };
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            storage.read().unwrap().addresses().unwrap().len(),
+            storage_size
+        );
+        let stored = ChainIndex::load(&storage).unwrap().unwrap();
+        assert_eq!(*stored.top(), top.as_ref().map(|header| header.address()));
+
+        let found = match chain_store
+            .query(
+                &top,
+                &vec![test_entry_type_b().to_string().as_ref()],
+                ChainStoreQueryOptions {
+                    start: 1,
+                    limit: 2,
+                    headers: true,
+                },
+            )
+            .unwrap()
+        {
+            ChainStoreQueryResult::Headers(headers) => headers,
+            other => panic!("Unexpected query value {:?}", other),
+        };
+        assert_eq!(
+            found,
+            vec![chain_headers[7].clone(), chain_headers[5].clone()]
+        );
+
+        let found = match chain_store
+            .query(
+                &top,
+                &vec!["testEntryType*"],
+                ChainStoreQueryOptions {
+                    start: 2,
+                    limit: 3,
+                    headers: true,
+                },
+            )
+            .unwrap()
+        {
+            ChainStoreQueryResult::Headers(headers) => headers,
+            other => panic!("Unexpected query value {:?}", other),
+        };
+        assert_eq!(
+            found,
+            chain_headers[5..8]
+                .iter()
+                .rev()
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+    }
+
     use globset::{Glob, GlobBuilder, GlobSetBuilder};
 
     #[test]
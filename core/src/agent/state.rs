@@ -221,6 +221,14 @@ fn reduce_commit_entry(
             let storage = &state.chain_store.content_storage().clone();
             storage.write().unwrap().add(entry)?;
             storage.write().unwrap().add(&chain_header)?;
+            // The index only speeds up queries, which fall back to walking the chain,
+            // so failing to update it must not fail the commit
+            if let Err(error) = state.chain_store.add_to_index(&chain_header) {
+                context.log(format!(
+                    "err/agent/commit: could not add header to chain index: {}",
+                    error
+                ));
+            }
             Ok((chain_header, entry.address()))
         })
         .and_then(|(chain_header, address)| {
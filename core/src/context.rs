@@ -2,10 +2,12 @@ use crate::{
     action::ActionWrapper,
     instance::Observer,
     logger::Logger,
+    metrics::Metrics,
     nucleus::actions::get_entry::get_entry_from_cas,
     persister::Persister,
     signal::{Signal, SignalSender},
     state::State,
+    tracing::{SpanContext, Tracer},
 };
 use futures::{
     task::{noop_local_waker_ref, Poll},
@@ -56,6 +58,11 @@ pub struct Context {
     pub p2p_config: P2pConfig,
     pub conductor_api: Arc<RwLock<IoHandler>>,
     pub signal_tx: Option<SyncSender<Signal>>,
+    pub metrics: Arc<Metrics>,
+    pub tracer: Arc<Tracer>,
+    /// The span of the zome call (or received message) this context is used for,
+    /// see [with_span](#method.with_span)
+    pub span: Option<SpanContext>,
 }
 
 impl Context {
@@ -111,6 +118,9 @@ impl Context {
             eav_storage: eav,
             p2p_config,
             conductor_api: Self::test_check_conductor_api(conductor_api, agent_id),
+            metrics: Arc::new(Metrics::default()),
+            tracer: Arc::new(Tracer::default()),
+            span: None,
         }
     }
 
@@ -138,6 +148,9 @@ impl Context {
             eav_storage: eav,
             p2p_config,
             conductor_api: Self::test_check_conductor_api(None, agent_id),
+            metrics: Arc::new(Metrics::default()),
+            tracer: Arc::new(Tracer::default()),
+            span: None,
         })
     }
 
@@ -155,6 +168,20 @@ impl Context {
         self.state = Some(state);
     }
 
+    /// Returns a copy of this context that shares everything with it but has the given
+    /// span set, so that the zome API functions and workflows that get called with it
+    /// can record their work as child spans.
+    pub fn with_span(&self, span: SpanContext) -> Context {
+        let mut context = self.clone();
+        context.span = Some(span);
+        context
+    }
+
+    /// A new child span of this context's span, or a new trace if it has none
+    pub fn child_span(&self) -> SpanContext {
+        SpanContext::child_of(self.span.as_ref())
+    }
+
     pub fn state(&self) -> Option<RwLockReadGuard<State>> {
         match self.state {
             None => None,
@@ -0,0 +1,208 @@
+//! Liveness probes of a running instance.
+//!
+//! An instance is only useful while its action loop keeps reducing actions and its network
+//! worker keeps running. Both run on their own threads, so a panic in either of them
+//! would otherwise go unnoticed. The conductor's supervisor probes instances periodically
+//! and reports what it found (and did about it) as `InstanceHealth`.
+use crate::instance::Instance;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Liveness of an instance's action loop, updated by the loop's thread
+#[derive(Clone, Default)]
+pub struct ActionLoopLiveness {
+    running: Arc<AtomicBool>,
+    busy_since: Arc<Mutex<Option<Instant>>>,
+}
+
+/// Marks the action loop as not running when dropped, i.e. also when the loop panics
+pub(crate) struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+impl ActionLoopLiveness {
+    /// To be called by the action loop's thread, which has to keep the returned guard
+    pub(crate) fn started(&self) -> RunningGuard {
+        self.running.store(true, Ordering::Relaxed);
+        RunningGuard(self.running.clone())
+    }
+
+    pub(crate) fn set_busy(&self, busy: bool) {
+        *self.busy_since.lock().unwrap() = if busy { Some(Instant::now()) } else { None };
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// How long the loop has been processing the current action, if any
+    pub fn busy_for(&self) -> Option<Duration> {
+        self.busy_since
+            .lock()
+            .unwrap()
+            .map(|busy_since| busy_since.elapsed())
+    }
+}
+
+/// Result of probing one part of an instance
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Probe {
+    Alive,
+    /// Still running but busy with the same action for too long
+    Stuck {
+        busy_seconds: u64,
+    },
+    Dead,
+    /// Not running yet or any more on purpose, e.g. the network while the instance gets
+    /// initialized or after it got shut down
+    NotReady,
+    /// Could not be probed without blocking, e.g. because the state is being written
+    Unknown,
+}
+
+impl Probe {
+    pub fn is_failure(&self) -> bool {
+        match self {
+            Probe::Stuck { .. } | Probe::Dead => true,
+            Probe::Alive | Probe::NotReady | Probe::Unknown => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InstanceProbes {
+    pub action_loop: Probe,
+    pub network: Probe,
+}
+
+impl InstanceProbes {
+    pub fn is_alive(&self) -> bool {
+        !self.action_loop.is_failure() && !self.network.is_failure()
+    }
+}
+
+/// Probes the action loop and network worker of the given instance.
+/// The action loop counts as stuck if it has been reducing one action for longer than
+/// `stuck_after`. This never blocks on the instance's locks.
+pub fn probe_instance(instance: &Instance, stuck_after: Duration) -> InstanceProbes {
+    let liveness = instance.action_loop_liveness();
+    let action_loop = if !liveness.is_running() {
+        Probe::Dead
+    } else {
+        match liveness.busy_for() {
+            Some(busy) if busy >= stuck_after => Probe::Stuck {
+                busy_seconds: busy.as_secs(),
+            },
+            _ => Probe::Alive,
+        }
+    };
+
+    let network = match instance.try_state() {
+        Some(state) => match state.network().network {
+            Some(ref network) => match network.try_lock() {
+                Ok(network) => {
+                    if network.is_running() {
+                        Probe::Alive
+                    } else {
+                        Probe::Dead
+                    }
+                }
+                Err(_) => Probe::Unknown,
+            },
+            None => Probe::NotReady,
+        },
+        None => Probe::Unknown,
+    };
+
+    InstanceProbes {
+        action_loop,
+        network,
+    }
+}
+
+/// What the conductor's supervisor knows about an instance
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    /// A probe failed and the instance will be restarted after its backoff
+    Restarting,
+    /// A probe failed and the restart policy does not allow (more) restarts
+    Failed,
+    /// The instance is not active, so it does not get probed
+    Stopped,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InstanceHealth {
+    pub status: HealthStatus,
+    /// Results of the last probe, None if the instance was not probed yet
+    pub probes: Option<InstanceProbes>,
+    /// Number of times the supervisor restarted the instance
+    pub restarts: u32,
+    /// Error of the last failed restart attempt
+    pub last_error: Option<String>,
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::tests::test_instance_and_context;
+    use std::thread;
+
+    #[test]
+    fn running_instance_is_alive() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (instance, _) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+        let probes = probe_instance(&instance, Duration::from_secs(60));
+        assert_eq!(probes.action_loop, Probe::Alive);
+        assert!(probes.is_alive());
+    }
+
+    #[test]
+    fn shut_down_network_is_not_ready() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (instance, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+        instance
+            .shutdown(&context, Duration::from_secs(10))
+            .expect("Shutdown should not time out");
+        let probes = probe_instance(&instance, Duration::from_secs(60));
+        assert_eq!(probes.network, Probe::NotReady);
+        assert_eq!(probes.action_loop, Probe::Dead);
+    }
+
+    #[test]
+    fn liveness_detects_dead_and_stuck_loops() {
+        let liveness = ActionLoopLiveness::default();
+        assert!(!liveness.is_running());
+
+        let loop_liveness = liveness.clone();
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            loop_liveness.set_busy(true);
+            let _running = loop_liveness.started();
+            let _ = rx.recv();
+            panic!("reducer panicked");
+        });
+        while !liveness.is_running() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(liveness.busy_for().is_some());
+
+        tx.send(()).unwrap();
+        assert!(handle.join().is_err());
+        assert!(!liveness.is_running());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
-    action::ActionWrapper, context::Context, persister::Persister, scheduled_jobs, signal::Signal,
-    state::State, workflows::application,
+    action::{Action, ActionWrapper},
+    context::Context, health::ActionLoopLiveness, persister::Persister,
+    scheduled_jobs, signal::Signal, state::State, workflows::application,
 };
 #[cfg(test)]
 use crate::{
@@ -15,12 +16,14 @@ use holochain_core_types::{
     error::{error::HolochainError, HcResult},
 };
 use std::{
+    collections::VecDeque,
     sync::{
-        mpsc::{sync_channel, Receiver, Sender, SyncSender},
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender},
         Arc, Mutex, RwLock, RwLockReadGuard,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 pub const RECV_DEFAULT_TIMEOUT_MS: Duration = Duration::from_millis(10000);
@@ -35,6 +38,9 @@ pub struct Instance {
     observer_channel: Option<SyncSender<Observer>>,
     scheduler_handle: Option<Arc<ScheduleHandle>>,
     persister: Option<Arc<Mutex<Persister>>>,
+    action_loop_liveness: ActionLoopLiveness,
+    /// Set by `shutdown` to make the action loop return
+    kill_switch: Arc<AtomicBool>,
 }
 
 /// State Observer that executes a closure everytime the State changes.
@@ -46,6 +52,8 @@ pub static DISPATCH_WITHOUT_CHANNELS: &str = "dispatch called without channels o
 
 impl Instance {
     pub const DEFAULT_CHANNEL_BUF_SIZE: usize = 100;
+    /// How often an idle action loop checks whether it got killed
+    const KILL_SWITCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
     /// This is initializing and starting the redux action loop and adding channels to dispatch
     /// actions and observers to the context
@@ -163,16 +171,39 @@ impl Instance {
     ) {
         let sync_self = self.clone();
         let sub_context = self.initialize_context(context);
+        let liveness = self.action_loop_liveness.clone();
+        let kill_switch = self.kill_switch.clone();
 
         thread::spawn(move || {
+            let _running = liveness.started();
             let mut state_observers: Vec<Observer> = Vec::new();
-            for action_wrapper in rx_action {
+            // Actions get moved from the channel into this queue (up to the channel's size)
+            // so we know how many are waiting
+            let mut queue = VecDeque::new();
+            while !kill_switch.load(Ordering::Relaxed) {
+                if queue.is_empty() {
+                    match rx_action.recv_timeout(Self::KILL_SWITCH_POLL_INTERVAL) {
+                        Ok(action_wrapper) => queue.push_back(action_wrapper),
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                while queue.len() < Self::DEFAULT_CHANNEL_BUF_SIZE {
+                    match rx_action.try_recv() {
+                        Ok(action_wrapper) => queue.push_back(action_wrapper),
+                        Err(_) => break,
+                    }
+                }
+                let action_wrapper = queue.pop_front().expect("queue can't be empty here");
+                sub_context.metrics.set_action_queue_depth(queue.len());
+                liveness.set_busy(true);
                 state_observers = sync_self.process_action(
                     action_wrapper,
                     state_observers,
                     &rx_observer,
                     &sub_context,
                 );
+                liveness.set_busy(false);
             }
         });
     }
@@ -248,6 +279,8 @@ impl Instance {
             observer_channel: None,
             scheduler_handle: None,
             persister: None,
+            action_loop_liveness: ActionLoopLiveness::default(),
+            kill_switch: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -258,6 +291,8 @@ impl Instance {
             observer_channel: None,
             scheduler_handle: None,
             persister: None,
+            action_loop_liveness: ActionLoopLiveness::default(),
+            kill_switch: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -267,6 +302,89 @@ impl Instance {
             .expect("owners of the state RwLock shouldn't panic")
     }
 
+    /// Like `state()` but returns None instead of blocking if the state is being written
+    pub(crate) fn try_state(&self) -> Option<RwLockReadGuard<State>> {
+        self.state.try_read().ok()
+    }
+
+    /// Lets the conductor probe whether the action loop is still running, see `health`
+    pub fn action_loop_liveness(&self) -> &ActionLoopLiveness {
+        &self.action_loop_liveness
+    }
+
+    /// Shuts the instance down in an orderly way, giving up on waiting after `timeout`:
+    /// 1. waits for running zome calls to return,
+    /// 2. waits for the action loop to reduce all queued actions (e.g. publishes and
+    ///    validations triggered by those calls),
+    /// 3. untracks the DNA on the network and waits for that to be reduced, which stops
+    ///    the network worker (or stops the worker directly if the action loop is stuck),
+    /// 4. saves the state, including pending validations, so they resume after a restart,
+    /// 5. stops the action loop and waits for its thread to end.
+    ///
+    /// Callers need to make sure no new zome calls get started. The instance can't be used
+    /// anymore afterwards, so a new one can be loaded from the same storage.
+    /// The state gets saved and the action loop stopped even if waiting timed out,
+    /// but an error gets returned then.
+    pub fn shutdown(&self, context: &Arc<Context>, timeout: Duration) -> HcResult<()> {
+        let deadline = Instant::now() + timeout;
+        let mut timed_out = Vec::new();
+
+        if !wait_until(deadline, || context.metrics.zome_calls_in_flight() == 0) {
+            timed_out.push(format!(
+                "{} running zome calls",
+                context.metrics.zome_calls_in_flight()
+            ));
+        }
+        if !wait_until(deadline, || self.action_loop_is_idle(context)) {
+            timed_out.push(String::from("queued actions"));
+        }
+
+        let shutdown_dispatched = self
+            .action_channel()
+            .try_send(ActionWrapper::new(Action::ShutdownNetwork))
+            .is_ok();
+        if !shutdown_dispatched
+            || !wait_until(deadline, || {
+                self.try_state()
+                    .map(|state| state.network().network.is_none())
+                    .unwrap_or(false)
+            })
+        {
+            timed_out.push(String::from("untracking the DNA"));
+            if let Some(state) = self.try_state() {
+                if let Some(ref network) = state.network().network {
+                    if let Err(error) = network.lock().unwrap().stop() {
+                        context.log(format!(
+                            "err/instance/shutdown: could not stop network: {:?}",
+                            error
+                        ));
+                    }
+                }
+            }
+        }
+
+        let saved = self.save();
+        self.kill_switch.store(true, Ordering::Relaxed);
+        if !wait_until(deadline, || !self.action_loop_liveness.is_running()) {
+            timed_out.push(String::from("the action loop to stop"));
+        }
+        saved?;
+        if timed_out.is_empty() {
+            Ok(())
+        } else {
+            Err(HolochainError::ErrorGeneric(format!(
+                "Shutdown timed out waiting for: {}",
+                timed_out.join(", ")
+            )))
+        }
+    }
+
+    fn action_loop_is_idle(&self, context: &Arc<Context>) -> bool {
+        !self.action_loop_liveness.is_running()
+            || (self.action_loop_liveness.busy_for().is_none()
+                && context.metrics.snapshot().action_queue_depth == 0)
+    }
+
     pub fn save(&self) -> HcResult<()> {
         self.persister
             .as_ref()
@@ -285,6 +403,20 @@ impl Instance {
     }
 }*/
 
+/// Polls the condition until it is true or the deadline has passed.
+/// Returns whether the condition became true.
+fn wait_until<F: Fn() -> bool>(deadline: Instant, condition: F) -> bool {
+    loop {
+        if condition() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
 /// Send Action to Instance's Event Queue and block until it has been processed.
 ///
 /// # Panics
@@ -790,4 +922,28 @@ pub mod tests {
                 _ => false,
             });
     }
+
+    #[test]
+    fn shutdown_untracks_dna_and_saves_state() {
+        let netname = Some("shutdown_untracks_dna_and_saves_state");
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (instance, context) =
+            test_instance_and_context(dna, netname).expect("Could not create test instance");
+        let network = instance
+            .state()
+            .network()
+            .network
+            .clone()
+            .expect("Network should be initialized");
+
+        instance
+            .shutdown(&context, Duration::from_secs(10))
+            .expect("Shutdown should not time out");
+        assert!(instance.state().network().network.is_none());
+        assert!(!network.lock().unwrap().is_running());
+        assert!(!instance.action_loop_liveness().is_running());
+        assert_eq!(context.metrics.zome_calls_in_flight(), 0);
+        let saved = context.persister.lock().unwrap().load(context.clone());
+        assert!(saved.unwrap().is_some());
+    }
 }
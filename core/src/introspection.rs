@@ -0,0 +1,282 @@
+//! Read-only views into the state and storages of a running instance, for debugging.
+//!
+//! All of these work on a snapshot of the current state and are meant for operators
+//! and developers who need to find out why e.g. a validation is stuck.
+use crate::{
+    context::Context, network::direct_message::DirectMessage,
+    nucleus::actions::get_entry::get_entry_crud_meta_from_dht,
+    scheduled_jobs::pending_validations::ValidatingWorkflow, state::State,
+};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    crud_status::CrudStatus,
+    eav::{Attribute, EaviQuery, IndexFilter},
+    entry::Entry,
+    error::{HcResult, HolochainError},
+};
+use std::{collections::BTreeSet, sync::Arc};
+
+/// A source chain header together with its entry
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ChainItem {
+    pub header_address: Address,
+    pub header: ChainHeader,
+    pub entry: Option<Entry>,
+}
+
+/// An entry held for the DHT with its CRUD metadata
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HeldEntry {
+    pub address: Address,
+    pub entry_type: String,
+    pub crud_status: Option<CrudStatus>,
+    pub crud_link: Option<Address>,
+}
+
+/// One EAV item of the DHT's metadata storage
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EavItem {
+    pub entity: Address,
+    pub attribute: String,
+    pub value: Address,
+    pub index: i64,
+}
+
+/// An entry or link that can't be validated yet because it depends on entries we don't have
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PendingValidationItem {
+    pub address: Address,
+    pub entry_type: String,
+    pub workflow: ValidatingWorkflow,
+    pub dependencies: Vec<Address>,
+}
+
+/// A GET links request that is still waiting for the network
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PendingGetLinks {
+    pub base_address: Address,
+    pub tag: String,
+}
+
+/// Network requests that did not get a response yet
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct NetworkRequests {
+    pub get_entry: Vec<Address>,
+    pub get_links: Vec<PendingGetLinks>,
+    pub get_validation_package: Vec<Address>,
+    pub direct_messages: Vec<(String, DirectMessage)>,
+}
+
+fn state(context: &Context) -> HcResult<State> {
+    context
+        .state()
+        .map(|state| state.clone())
+        .ok_or_else(|| HolochainError::ErrorGeneric("Instance state not initialized".into()))
+}
+
+/// Returns `limit` items of the source chain, starting with the `start`th newest header.
+pub fn chain(context: &Context, start: usize, limit: usize) -> HcResult<Vec<ChainItem>> {
+    let agent = state(context)?.agent();
+    let chain_store = agent.chain_store();
+    let storage = chain_store.content_storage();
+    let storage = storage.read()?;
+    chain_store
+        .iter(&agent.top_chain_header())
+        .skip(start)
+        .take(limit)
+        .map(|header| {
+            let entry = match storage.fetch(header.entry_address())? {
+                Some(content) => Some(Entry::try_from_content(&content)?),
+                None => None,
+            };
+            Ok(ChainItem {
+                header_address: header.address(),
+                header,
+                entry,
+            })
+        })
+        .collect()
+}
+
+/// Returns `limit` of the entries held for the DHT (ordered by address), skipping `start`.
+///
+/// Only entries that got held together with a header count. Entries that are just in the
+/// local storage, like private entries of our own source chain, are not listed.
+pub fn held_entries(
+    context: &Arc<Context>,
+    start: usize,
+    limit: usize,
+) -> HcResult<Vec<HeldEntry>> {
+    let addresses: BTreeSet<Address> = context
+        .eav_storage
+        .read()?
+        .fetch_eavi(&EaviQuery::new(
+            Default::default(),
+            Some(Attribute::EntryHeader).into(),
+            Default::default(),
+            IndexFilter::Range(None, None),
+        ))?
+        .into_iter()
+        .map(|eavi| eavi.entity())
+        .collect();
+    let mut held = Vec::new();
+    for address in addresses.into_iter().skip(start).take(limit) {
+        let entry = match context.dht_storage.read()?.fetch(&address)? {
+            Some(content) => Entry::try_from_content(&content)?,
+            None => continue,
+        };
+        let crud_meta = get_entry_crud_meta_from_dht(context, address.clone())?;
+        held.push(HeldEntry {
+            address,
+            entry_type: entry.entry_type().to_string(),
+            crud_status: crud_meta.as_ref().map(|(status, _)| *status),
+            crud_link: crud_meta.and_then(|(_, link)| link),
+        });
+    }
+    Ok(held)
+}
+
+/// Returns all EAV items (of all indices) that have the given address as entity.
+pub fn eav_for_address(context: &Context, address: &Address) -> HcResult<Vec<EavItem>> {
+    let eavis = context.eav_storage.read()?.fetch_eavi(&EaviQuery::new(
+        Some(address.clone()).into(),
+        Default::default(),
+        Default::default(),
+        IndexFilter::Range(None, None),
+    ))?;
+    Ok(eavis
+        .into_iter()
+        .map(|eavi| EavItem {
+            entity: eavi.entity(),
+            attribute: eavi.attribute().to_string(),
+            value: eavi.value(),
+            index: eavi.index(),
+        })
+        .collect())
+}
+
+/// Returns all validations that wait for their dependencies.
+pub fn pending_validations(context: &Context) -> HcResult<Vec<PendingValidationItem>> {
+    let mut pending: Vec<_> = state(context)?
+        .nucleus()
+        .pending_validations
+        .values()
+        .map(|pending| PendingValidationItem {
+            address: pending.entry_with_header.entry.address(),
+            entry_type: pending.entry_with_header.entry.entry_type().to_string(),
+            workflow: pending.workflow.clone(),
+            dependencies: pending.dependencies.clone(),
+        })
+        .collect();
+    pending.sort_by(|a, b| a.address.cmp(&b.address));
+    Ok(pending)
+}
+
+/// Returns all network requests that are still waiting for a response.
+pub fn network_requests(context: &Context) -> HcResult<NetworkRequests> {
+    let network = state(context)?.network();
+    let mut requests = NetworkRequests::default();
+    for (key, result) in network.get_entry_with_meta_results.iter() {
+        if result.is_none() {
+            requests.get_entry.push(key.address.clone());
+        }
+    }
+    for (key, result) in network.get_links_results.iter() {
+        if result.is_none() {
+            requests.get_links.push(PendingGetLinks {
+                base_address: key.base_address.clone(),
+                tag: key.tag.clone(),
+            });
+        }
+    }
+    for (address, result) in network.get_validation_package_results.iter() {
+        if result.is_none() {
+            requests.get_validation_package.push(address.clone());
+        }
+    }
+    for (id, message) in network.direct_message_connections.iter() {
+        requests.direct_messages.push((id.clone(), message.clone()));
+    }
+    Ok(requests)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        dht::actions::hold::hold_entry, instance::tests::test_instance_and_context,
+        network::entry_with_header::EntryWithHeader,
+    };
+    use holochain_core_types::{
+        chain_header::test_chain_header,
+        entry::{entry_type::EntryType, test_entry},
+    };
+    use std::{thread, time};
+
+    #[test]
+    fn can_page_through_chain_and_inspect_genesis() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+
+        let all = chain(&context, 0, 100).expect("Could not read chain");
+        assert!(all.len() >= 2);
+        // Newest first, so genesis (the DNA entry) comes last
+        let genesis = all.last().unwrap();
+        assert_eq!(genesis.header.entry_type(), &EntryType::Dna);
+        assert_eq!(
+            genesis.entry.as_ref().map(|entry| entry.address()),
+            Some(genesis.header.entry_address().clone())
+        );
+
+        let page = chain(&context, 1, 1).expect("Could not read chain");
+        assert_eq!(page, vec![all[1].clone()]);
+
+        let held = held_entries(&context, 0, 100).expect("Could not read DHT");
+        assert!(held_entries(&context, held.len(), 100).unwrap().is_empty());
+
+        assert!(pending_validations(&context)
+            .expect("Could not read pending validations")
+            .is_empty());
+        assert_eq!(
+            eav_for_address(&context, &Address::from("unknown")).unwrap(),
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn held_entries_only_lists_entries_held_for_the_dht() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+        // The DNA is committed to the source chain but never published
+        let dna_address = chain(&context, 0, 100)
+            .expect("Could not read chain")
+            .last()
+            .map(|genesis| genesis.header.entry_address().clone())
+            .unwrap();
+
+        let entry_with_header = EntryWithHeader {
+            entry: test_entry(),
+            header: test_chain_header(),
+        };
+        context
+            .block_on(hold_entry(&entry_with_header, context.clone()))
+            .expect("Could not hold entry");
+
+        let mut held = Vec::new();
+        let mut tries = 0;
+        while held.is_empty() && tries < 50 {
+            tries = tries + 1;
+            held = held_entries(&context, 0, 100).expect("Could not read DHT");
+            if held.is_empty() {
+                thread::sleep(time::Duration::from_millis(100));
+            }
+        }
+        let addresses: Vec<_> = held.iter().map(|entry| entry.address.clone()).collect();
+        assert_eq!(addresses, vec![test_entry().address()]);
+        assert!(!addresses.contains(&dna_address));
+        assert_eq!(held[0].crud_status, Some(CrudStatus::Live));
+    }
+}
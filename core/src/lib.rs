@@ -24,14 +24,19 @@ pub mod action;
 pub mod agent;
 pub mod context;
 pub mod dht;
+pub mod health;
 pub mod instance;
 #[cfg(test)]
 pub mod link_tests;
 pub mod logger;
+pub mod metrics;
 pub mod network;
 pub mod nucleus;
 pub mod persister;
 pub mod scheduled_jobs;
 pub mod signal;
 pub mod state;
+pub mod introspection;
+pub mod storage_stats;
+pub mod tracing;
 pub mod workflows;
@@ -0,0 +1,209 @@
+//! Counters and timings of an instance, collected while it runs.
+//!
+//! Every `Context` holds a shared `Metrics` object that gets updated by the zome call,
+//! validation and network code. The conductor reads them through `metrics()` to export
+//! them (e.g. in Prometheus text format).
+use crate::{
+    context::Context,
+    nucleus::validation::{ValidationError, ValidationResult},
+};
+use holochain_core_types::error::{HcResult, HolochainError};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Upper bounds (in seconds) of the zome call latency histogram buckets
+pub const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Counts and latency histogram of one zome function
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZomeCallMetrics {
+    /// Number of finished calls
+    pub calls: u64,
+    /// Number of calls that returned an error
+    pub errors: u64,
+    /// Sum of the durations of all calls in seconds
+    pub total_seconds: f64,
+    /// Number of calls per entry of `LATENCY_BUCKETS` that took at most that long
+    /// (cumulative, like Prometheus histograms)
+    pub buckets: Vec<u64>,
+}
+
+impl ZomeCallMetrics {
+    fn record(&mut self, duration: Duration, success: bool) {
+        let seconds = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+        if self.buckets.len() != LATENCY_BUCKETS.len() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS.iter()) {
+            if seconds <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.calls += 1;
+        if !success {
+            self.errors += 1;
+        }
+        self.total_seconds += seconds;
+    }
+}
+
+/// Point in time copy of an instance's metrics
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// Zome call metrics per zome and function name
+    pub zome_calls: BTreeMap<String, BTreeMap<String, ZomeCallMetrics>>,
+    /// Number of zome calls that are currently running
+    pub zome_calls_in_flight: usize,
+    /// Number of actions waiting to be reduced by the action loop
+    pub action_queue_depth: usize,
+    /// Validations that found the entry valid
+    pub validations_passed: u64,
+    /// Validations that found the entry invalid or failed with an error
+    pub validations_failed: u64,
+    /// Validations that had to be postponed because of missing dependencies
+    pub validations_postponed: u64,
+    /// Entries and links currently waiting for their dependencies
+    pub pending_validations: usize,
+    /// Received network messages per `JsonProtocol` method
+    pub network_messages: BTreeMap<String, u64>,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    data: Mutex<MetricsSnapshot>,
+    action_queue_depth: AtomicUsize,
+    zome_calls_in_flight: AtomicUsize,
+}
+
+/// Counts a zome call as running while it is alive
+pub struct ZomeCallInFlight<'a>(&'a Metrics);
+
+impl<'a> Drop for ZomeCallInFlight<'a> {
+    fn drop(&mut self) {
+        self.0.zome_calls_in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Metrics {
+    pub fn record_zome_call(&self, zome: &str, function: &str, duration: Duration, success: bool) {
+        self.data
+            .lock()
+            .unwrap()
+            .zome_calls
+            .entry(zome.to_string())
+            .or_insert_with(BTreeMap::new)
+            .entry(function.to_string())
+            .or_insert_with(ZomeCallMetrics::default)
+            .record(duration, success);
+    }
+
+    /// Returns a guard that counts the call as running until it gets dropped
+    pub fn zome_call_started(&self) -> ZomeCallInFlight {
+        self.zome_calls_in_flight.fetch_add(1, Ordering::SeqCst);
+        ZomeCallInFlight(self)
+    }
+
+    pub fn zome_calls_in_flight(&self) -> usize {
+        self.zome_calls_in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn record_validation(&self, result: &ValidationResult) {
+        let mut data = self.data.lock().unwrap();
+        match result {
+            Ok(()) => data.validations_passed += 1,
+            Err(ValidationError::UnresolvedDependencies(_)) => data.validations_postponed += 1,
+            Err(_) => data.validations_failed += 1,
+        }
+    }
+
+    pub fn record_network_message(&self, method: &str) {
+        *self
+            .data
+            .lock()
+            .unwrap()
+            .network_messages
+            .entry(method.to_string())
+            .or_insert(0) += 1;
+    }
+
+    pub fn set_action_queue_depth(&self, depth: usize) {
+        self.action_queue_depth.store(depth, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut snapshot = self.data.lock().unwrap().clone();
+        snapshot.action_queue_depth = self.action_queue_depth.load(Ordering::Relaxed);
+        snapshot.zome_calls_in_flight = self.zome_calls_in_flight();
+        snapshot
+    }
+}
+
+/// Returns the metrics of the given context together with gauges that are read from
+/// the current state.
+pub fn metrics(context: &Context) -> HcResult<MetricsSnapshot> {
+    let mut snapshot = context.metrics.snapshot();
+    snapshot.pending_validations = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("Instance state not initialized".into()))?
+        .nucleus()
+        .pending_validations
+        .len();
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::cas::content::Address;
+
+    #[test]
+    fn records_zome_calls_in_cumulative_buckets() {
+        let metrics = Metrics::default();
+        metrics.record_zome_call("blog", "create_post", Duration::from_millis(20), true);
+        metrics.record_zome_call("blog", "create_post", Duration::from_millis(300), false);
+        metrics.record_zome_call("blog", "get_post", Duration::from_secs(20), true);
+
+        let snapshot = metrics.snapshot();
+        let create_post = &snapshot.zome_calls["blog"]["create_post"];
+        assert_eq!(create_post.calls, 2);
+        assert_eq!(create_post.errors, 1);
+        assert!((create_post.total_seconds - 0.32).abs() < 1e-9);
+        assert_eq!(create_post.buckets, vec![0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2]);
+        assert_eq!(
+            snapshot.zome_calls["blog"]["get_post"].buckets,
+            vec![0; LATENCY_BUCKETS.len()]
+        );
+    }
+
+    #[test]
+    fn counts_validations_and_network_messages() {
+        let metrics = Metrics::default();
+        metrics.record_validation(&Ok(()));
+        metrics.record_validation(&Err(ValidationError::Fail("invalid".into())));
+        metrics.record_validation(&Err(ValidationError::UnresolvedDependencies(vec![
+            Address::from("base"),
+        ])));
+        metrics.record_network_message("handleStoreEntry");
+        metrics.record_network_message("handleStoreEntry");
+        metrics.set_action_queue_depth(3);
+        let in_flight = metrics.zome_call_started();
+        assert_eq!(metrics.snapshot().zome_calls_in_flight, 1);
+        drop(in_flight);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.validations_passed, 1);
+        assert_eq!(snapshot.validations_failed, 1);
+        assert_eq!(snapshot.validations_postponed, 1);
+        assert_eq!(snapshot.network_messages["handleStoreEntry"], 2);
+        assert_eq!(snapshot.action_queue_depth, 3);
+        assert_eq!(snapshot.zome_calls_in_flight, 0);
+    }
+}
@@ -3,6 +3,7 @@ use crate::{
     context::Context,
     instance::dispatch_action,
     network::direct_message::{CustomDirectMessage, DirectMessage},
+    tracing::SpanKind,
 };
 use futures::{
     future::Future,
@@ -22,7 +23,14 @@ pub async fn custom_send(
     context: Arc<Context>,
 ) -> Result<String, HolochainError> {
     let id = ProcessUniqueId::new().to_string();
-    let direct_message = DirectMessage::Custom(custom_direct_message);
+    let span = context.child_span();
+    let started = context
+        .tracer
+        .start(&span, SpanKind::SendDirectMessage, to_agent.to_string());
+    let direct_message = DirectMessage::Custom(CustomDirectMessage {
+        span: Some(span),
+        ..custom_direct_message
+    });
     let direct_message_data = DirectMessageData {
         address: to_agent,
         message: direct_message,
@@ -39,10 +47,12 @@ pub async fn custom_send(
         dispatch_action(context_inner.action_channel(), action_wrapper.clone());
     });
 
-    await!(SendResponseFuture {
+    let result = await!(SendResponseFuture {
         context: context.clone(),
         id,
-    })
+    });
+    started.finish(&context.tracer, &result);
+    result
 }
 
 /// SendResponseFuture waits for a result to show up in NetworkState::custom_direct_message_replys
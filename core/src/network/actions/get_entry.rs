@@ -2,6 +2,7 @@ use crate::{
     action::{Action, ActionWrapper, GetEntryKey},
     context::Context,
     instance::dispatch_action,
+    tracing::SpanKind,
 };
 use futures::{
     future::Future,
@@ -22,9 +23,14 @@ pub async fn get_entry(
     address: Address,
     timeout: Timeout,
 ) -> HcResult<Option<EntryWithMeta>> {
+    // The span id doubles as id of the network request
+    let span = context.child_span();
+    let started = context
+        .tracer
+        .start(&span, SpanKind::GetEntry, address.to_string());
     let key = GetEntryKey {
         address: address,
-        id: snowflake::ProcessUniqueId::new().to_string(),
+        id: span.span_id.clone(),
     };
 
     let action_wrapper = ActionWrapper::new(Action::FetchEntry(key.clone()));
@@ -38,10 +44,12 @@ pub async fn get_entry(
         dispatch_action(context_inner.action_channel(), action_wrapper.clone());
     });
 
-    await!(GetEntryFuture {
+    let result = await!(GetEntryFuture {
         context: context.clone(),
         key
-    })
+    });
+    started.finish(&context.tracer, &result);
+    result
 }
 
 /// GetEntryFuture resolves to a HcResult<Entry>.
@@ -2,13 +2,13 @@ use crate::{
     action::{Action, ActionWrapper, GetLinksKey},
     context::Context,
     instance::dispatch_action,
+    tracing::SpanKind,
 };
 use futures::{
     future::Future,
     task::{LocalWaker, Poll},
 };
 use holochain_core_types::{cas::content::Address, error::HcResult, time::Timeout};
-use snowflake::ProcessUniqueId;
 use std::{pin::Pin, sync::Arc, thread};
 
 /// GetLinks Action Creator
@@ -20,10 +20,17 @@ pub async fn get_links(
     tag: String,
     timeout: Timeout,
 ) -> HcResult<Vec<Address>> {
+    // The span id doubles as id of the network request
+    let span = context.child_span();
+    let started = context.tracer.start(
+        &span,
+        SpanKind::GetLinks,
+        format!("{} tagged \"{}\"", address, tag),
+    );
     let key = GetLinksKey {
         base_address: address.clone(),
         tag: tag.clone(),
-        id: ProcessUniqueId::new().to_string(),
+        id: span.span_id.clone(),
     };
     let action_wrapper = ActionWrapper::new(Action::GetLinks(key.clone()));
     dispatch_action(context.action_channel(), action_wrapper.clone());
@@ -36,10 +43,12 @@ pub async fn get_links(
         dispatch_action(context_inner.action_channel(), action_wrapper.clone());
     });
 
-    await!(GetLinksFuture {
+    let result = await!(GetLinksFuture {
         context: context.clone(),
         key
-    })
+    });
+    started.finish(&context.tracer, &result);
+    result
 }
 
 /// GetLinksFuture resolves to a HcResult<Vec<Address>>.
@@ -2,6 +2,7 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
+    tracing::SpanKind,
 };
 use futures::{
     future::Future,
@@ -24,12 +25,19 @@ pub async fn get_validation_package(
     context: &Arc<Context>,
 ) -> HcResult<Option<ValidationPackage>> {
     let entry_address = header.entry_address().clone();
+    let started = context.tracer.start(
+        &context.child_span(),
+        SpanKind::GetValidationPackage,
+        entry_address.to_string(),
+    );
     let action_wrapper = ActionWrapper::new(Action::GetValidationPackage(header));
     dispatch_action(context.action_channel(), action_wrapper.clone());
-    await!(GetValidationPackageFuture {
+    let result = await!(GetValidationPackageFuture {
         context: context.clone(),
         address: entry_address,
-    })
+    });
+    started.finish(&context.tracer, &result);
+    result
 }
 
 /// GetValidationPackageFuture resolves to an Option<ValidationPackage>
@@ -1,3 +1,4 @@
+use crate::tracing::SpanContext;
 use holochain_core_types::{
     cas::content::Address, error::HolochainError, json::JsonString, validation::ValidationPackage,
 };
@@ -12,6 +13,12 @@ pub struct CustomDirectMessage {
     /// The payload that the zome sends.
     /// This is a result to enable the receive handler to return an error
     pub payload: Result<String, String>,
+
+    /// The span of the sending call, so that handling the message on the receiving
+    /// node becomes part of the sender's trace.
+    /// Not set in responses and by nodes that don't trace.
+    #[serde(default)]
+    pub span: Option<SpanContext>,
 }
 
 /// These are the different kinds of (low-level, i.e. non-app)
@@ -63,7 +63,9 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
         if let Err(_) = maybe_json_msg {
             return Ok(());
         }
-        match maybe_json_msg.unwrap() {
+        let json_msg = maybe_json_msg.unwrap();
+        context.metrics.record_network_message(json_msg.method());
+        match json_msg {
             JsonProtocol::FailureResult(failure_data) => {
                 if !is_my_dna(&context, &failure_data.dna_address) {
                     return Ok(());
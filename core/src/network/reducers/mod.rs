@@ -11,6 +11,7 @@ pub mod resolve_direct_connection;
 pub mod respond_get;
 pub mod respond_get_links;
 pub mod send_direct_message;
+pub mod shutdown;
 
 use crate::{
     action::{Action, ActionWrapper, NetworkReduceFn},
@@ -31,6 +32,7 @@ use crate::{
             respond_get::reduce_respond_fetch_data,
             respond_get_links::reduce_respond_get_links,
             send_direct_message::{reduce_send_direct_message, reduce_send_direct_message_timeout},
+            shutdown::reduce_shutdown,
         },
         state::NetworkState,
     },
@@ -62,6 +64,7 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
         Action::RespondGetLinks(_) => Some(reduce_respond_get_links),
         Action::SendDirectMessage(_) => Some(reduce_send_direct_message),
         Action::SendDirectMessageTimeout(_) => Some(reduce_send_direct_message_timeout),
+        Action::ShutdownNetwork => Some(reduce_shutdown),
         _ => None,
     }
 }
@@ -104,6 +104,7 @@ mod tests {
         let custom_direct_message = DirectMessage::Custom(CustomDirectMessage {
             zome: String::from("test"),
             payload: Ok(String::from("test")),
+            span: None,
         });
         let msg_id = String::from("any");
         let direct_message_data = DirectMessageData {
@@ -0,0 +1,41 @@
+use crate::{
+    action::ActionWrapper,
+    context::Context,
+    network::{reducers::send, state::NetworkState},
+};
+use holochain_net::connection::json_protocol::{JsonProtocol, TrackDnaData};
+use std::sync::Arc;
+
+/// Untracks the DNA so the network stops routing requests to us, then stops the network
+/// worker and drops the network proxy.
+/// Network actions reduced after this fail with "Network not initialized".
+pub fn reduce_shutdown(
+    context: Arc<Context>,
+    state: &mut NetworkState,
+    _action_wrapper: &ActionWrapper,
+) {
+    if let (Some(dna_address), Some(agent_id)) = (state.dna_address.clone(), state.agent_id.clone())
+    {
+        let json = JsonProtocol::UntrackDna(TrackDnaData {
+            dna_address,
+            agent_id,
+        });
+        if let Err(error) = send(state, json) {
+            context.log(format!(
+                "err/net/shutdown: could not untrack DNA: {:?}",
+                error
+            ));
+        }
+    }
+    if let Some(network) = state.network.take() {
+        // The state this one got reduced from still shares the network, so it can't be
+        // moved out of its Arc here. Stopping it through the lock joins the worker thread
+        // and closes its transport connection all the same.
+        if let Err(error) = network.lock().unwrap().stop() {
+            context.log(format!(
+                "err/net/shutdown: could not stop network: {:?}",
+                error
+            ));
+        }
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
         ribosome::{self, capabilities::CapabilityRequest, WasmCallData},
         ZomeFnCall, ZomeFnResult,
     },
+    tracing::SpanKind,
 };
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
@@ -26,7 +27,7 @@ use futures::{
     future::Future,
     task::{LocalWaker, Poll},
 };
-use std::{pin::Pin, sync::Arc, thread};
+use std::{pin::Pin, sync::Arc, thread, time::Instant};
 
 #[derive(Clone, Debug, PartialEq, Hash)]
 pub struct ExecuteZomeFnResponse {
@@ -72,16 +73,34 @@ pub async fn call_zome_function(
         zome_call
     ));
 
+    let span = context.tracer.start(
+        &zome_call.span,
+        SpanKind::ZomeCall,
+        format!("{}/{}", zome_call.zome_name, zome_call.fn_name),
+    );
+
     // 1. Validate the call (a number of things could go wrong)
-    let (dna_name, wasm) = validate_call(context.clone(), &zome_call)?;
+    let (dna_name, wasm) = match validate_call(context.clone(), &zome_call) {
+        Ok(dna_and_wasm) => dna_and_wasm,
+        Err(error) => {
+            let result = Err(error);
+            span.finish(&context.tracer, &result);
+            return result;
+        }
+    };
 
     context.log(format!(
         "debug/actions/call_zome_fn: executing call: {:?}",
         zome_call
     ));
 
-    // Clone context and call data for the Ribosome thread
-    let context_clone = context.clone();
+    let started = Instant::now();
+    let _in_flight = context.metrics.zome_call_started();
+
+    // Clone context and call data for the Ribosome thread.
+    // The zome function runs with the call's span so that everything it does becomes
+    // part of the call's trace.
+    let context_clone = Arc::new(context.with_span(zome_call.span.clone()));
     let zome_call_clone = zome_call.clone();
 
     // Signal (currently mainly to the nodejs_waiter) that we are about to start a zome function:
@@ -114,10 +133,17 @@ pub async fn call_zome_function(
             .expect("action channel to be open in reducer");
     });
 
-    await!(CallResultFuture {
+    let zome_name = zome_call.zome_name.clone();
+    let fn_name = zome_call.fn_name.clone();
+    let result = await!(CallResultFuture {
         context: context.clone(),
         zome_call,
-    })
+    });
+    context
+        .metrics
+        .record_zome_call(&zome_name, &fn_name, started.elapsed(), result.is_ok());
+    span.finish(&context.tracer, &result);
+    result
 }
 
 /// validates that a given zome function call specifies a correct zome function and capability grant
@@ -15,6 +15,7 @@ pub use crate::{
         reducers::reduce,
         ribosome::capabilities::CapabilityRequest,
     },
+    tracing::SpanContext,
 };
 use holochain_core_types::{cas::content::Address, error::HcResult, json::JsonString};
 
@@ -29,6 +30,8 @@ pub struct ZomeFnCall {
     pub cap: CapabilityRequest,
     pub fn_name: String,
     pub parameters: JsonString,
+    /// Span of this call, a new trace unless set with [with_span](#method.with_span)
+    pub span: SpanContext,
 }
 
 impl ZomeFnCall {
@@ -46,9 +49,16 @@ impl ZomeFnCall {
             cap: cap,
             fn_name: function.to_string(),
             parameters: parameters.into(),
+            span: SpanContext::root(),
         }
     }
 
+    /// Makes this call part of a trace, e.g. as child of the span of the calling code
+    pub fn with_span(mut self, span: SpanContext) -> Self {
+        self.span = span;
+        self
+    }
+
     pub fn create<J: Into<JsonString>>(
         context: Arc<Context>,
         zome: &str,
@@ -57,12 +67,14 @@ impl ZomeFnCall {
         parameters: J,
     ) -> Self {
         let params = parameters.into();
+        let span = context.child_span();
         ZomeFnCall::new(
             zome,
             make_cap_request_for_call(context, token, function, params.clone()),
             function,
             params,
         )
+        .with_span(span)
     }
 
     pub fn same_fn_as(&self, fn_call: &ZomeFnCall) -> bool {
@@ -5,6 +5,7 @@ use crate::{
         ribosome::{api::ZomeApiResult, Runtime},
         ZomeFnCall,
     },
+    tracing::{SpanContext, SpanKind, TRACE_CONTEXT_PARAM},
 };
 use holochain_core_types::{error::HolochainError, json::JsonString};
 use holochain_wasm_utils::api_serialization::{ZomeFnCallArgs, THIS_INSTANCE};
@@ -25,6 +26,7 @@ impl ZomeFnCall {
             args.fn_args.clone(),
         );
         ZomeFnCall::new(&args.zome_name, cap_call, &args.fn_name, args.fn_args)
+            .with_span(context.child_span())
     }
 }
 
@@ -85,19 +87,47 @@ fn bridge_call(runtime: &mut Runtime, input: ZomeFnCallArgs) -> Result<JsonStrin
             "expecting zome call data in bridge call not null call".to_string(),
         )
     })?;
-    let conductor_api = context.conductor_api.clone();
 
     let method = format!(
         "{}/{}/{}",
         input.instance_handle, input.zome_name, input.fn_name
     );
 
+    let span = context.child_span();
+    let started = context.tracer.start(&span, SpanKind::BridgeCall, method);
+    let result =
+        bridge_call_params(&input, &span).and_then(|params| send_bridge_request(&context, &params));
+    started.finish(&context.tracer, &result);
+    result
+}
+
+/// Builds the params of the conductor's `call` method for a bridge call.
+/// The span of the bridge call goes next to the function's params so that the call in
+/// the bridged instance becomes part of the same trace without changing its arguments.
+fn bridge_call_params(
+    input: &ZomeFnCallArgs,
+    span: &SpanContext,
+) -> Result<serde_json::Value, HolochainError> {
+    let mut params = serde_json::Map::new();
+    params.insert("instance_id".into(), input.instance_handle.clone().into());
+    params.insert("zome".into(), input.zome_name.clone().into());
+    params.insert("function".into(), input.fn_name.clone().into());
+    params.insert("params".into(), serde_json::from_str(&input.fn_args)?);
+    params.insert(TRACE_CONTEXT_PARAM.into(), serde_json::to_value(span)?);
+    Ok(serde_json::Value::Object(params))
+}
+
+fn send_bridge_request(
+    context: &Arc<Context>,
+    params: &serde_json::Value,
+) -> Result<JsonString, HolochainError> {
+    let conductor_api = context.conductor_api.clone();
     let handler = conductor_api.write().unwrap();
 
     let id = ProcessUniqueId::new();
     let request = format!(
-        r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": "{}"}}"#,
-        method, input.fn_args, id
+        r#"{{"jsonrpc": "2.0", "method": "call", "params": {}, "id": "{}"}}"#,
+        params, id
     );
 
     let response = handler
@@ -498,4 +528,31 @@ pub mod tests {
         assert!(check_capability(context.clone(), &zome_call));
     }
 
+    #[test]
+    fn test_bridge_call_params_carry_trace_context() {
+        let span = SpanContext::root().child();
+        let input = ZomeFnCallArgs {
+            instance_handle: "other-instance".to_string(),
+            zome_name: "blog".to_string(),
+            cap_token: Address::from("token"),
+            fn_name: "create_post".to_string(),
+            fn_args: r#"{"post":"hello"}"#.to_string(),
+        };
+        let params = bridge_call_params(&input, &span).unwrap();
+        assert_eq!(params["instance_id"], "other-instance");
+        assert_eq!(params["zome"], "blog");
+        assert_eq!(params["function"], "create_post");
+        // The function's arguments stay as they are
+        assert_eq!(params["params"]["post"], "hello");
+        assert_eq!(
+            serde_json::from_value::<SpanContext>(params[TRACE_CONTEXT_PARAM].clone()).unwrap(),
+            span
+        );
+
+        let input = ZomeFnCallArgs {
+            fn_args: "not json".to_string(),
+            ..input
+        };
+        assert!(bridge_call_params(&input, &span).is_err());
+    }
 }
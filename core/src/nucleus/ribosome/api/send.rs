@@ -22,6 +22,7 @@ pub fn invoke_send(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
     let message = CustomDirectMessage {
         payload: Ok(args.payload),
         zome: call_data.zome_name.clone(),
+        span: None,
     };
 
     let result = call_data.context.block_on(custom_send(
@@ -12,6 +12,10 @@ mod header_address;
 mod link_entry;
 mod provenances;
 
+pub(crate) use self::{
+    header_address::validate_header_address, provenances::validate_header_provenances,
+};
+
 #[derive(Clone, Debug, PartialEq)]
 /// A failed validation.
 pub enum ValidationError {
@@ -70,6 +74,16 @@ pub async fn validate_entry(
     entry: Entry,
     validation_data: ValidationData,
     context: &Arc<Context>,
+) -> ValidationResult {
+    let result = await!(validate_entry_by_type(entry, validation_data, context));
+    context.metrics.record_validation(&result);
+    result
+}
+
+async fn validate_entry_by_type(
+    entry: Entry,
+    validation_data: ValidationData,
+    context: &Arc<Context>,
 ) -> ValidationResult {
     //check_entry_type(entry.entry_type(), context)?;
     header_address::validate_header_address(&entry, &validation_data.package.chain_header)?;
@@ -1,11 +1,16 @@
 use crate::nucleus::validation::{ValidationError, ValidationResult};
 use boolinator::Boolinator;
-use holochain_core_types::validation::ValidationData;
+use holochain_core_types::{chain_header::ChainHeader, validation::ValidationData};
 use holochain_dpki;
 use holochain_sodium::secbuf::SecBuf;
 
 pub fn validate_provenances(validation_data: &ValidationData) -> ValidationResult {
-    let header = &validation_data.package.chain_header;
+    validate_header_provenances(&validation_data.package.chain_header)
+}
+
+/// Checks every provenance of the given header to hold a valid signature of the
+/// header's entry address by its source.
+pub fn validate_header_provenances(header: &ChainHeader) -> ValidationResult {
     header
         .provenances()
         .iter()
@@ -1,4 +1,4 @@
-use crate::action::ActionWrapper;
+use crate::{action::ActionWrapper, health::InstanceHealth};
 use std::{
     sync::mpsc::{channel, sync_channel, Receiver, SyncSender},
     thread,
@@ -8,6 +8,8 @@ use std::{
 pub enum Signal {
     Internal(ActionWrapper),
     User,
+    /// Sent by the conductor's supervisor when the health status of an instance changes
+    InstanceHealth(String, InstanceHealth),
 }
 
 pub type SignalSender = SyncSender<Signal>;
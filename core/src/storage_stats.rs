@@ -0,0 +1,190 @@
+//! Statistics about the data an instance holds in its storages.
+//!
+//! Content gets classified by its shape as an entry (of some type) or a chain header.
+//! Everything else (like the state snapshots of the persister) is counted as other content.
+use crate::context::Context;
+use holochain_core_types::{
+    cas::{content::Address, storage::ContentAddressableStorage},
+    eav::{EaviQuery, IndexFilter},
+    entry::entry_type::EntryType,
+    error::{HcResult, HolochainError},
+};
+use serde::de::IgnoredAny;
+use serde_json;
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+/// Just enough of a serialized entry to tell its type.
+/// Skips over the entry's content instead of deserializing it, which matters for DNA entries
+/// that contain the WASM of all zomes.
+#[derive(Deserialize)]
+enum EntryKind {
+    App(String, IgnoredAny),
+    Dna(IgnoredAny),
+    AgentId(IgnoredAny),
+    Deletion(IgnoredAny),
+    LinkAdd(IgnoredAny),
+    LinkRemove(IgnoredAny),
+    LinkList(IgnoredAny),
+    ChainHeader(IgnoredAny),
+    ChainMigrate(IgnoredAny),
+    CapToken(IgnoredAny),
+    CapTokenGrant(IgnoredAny),
+}
+
+impl EntryKind {
+    fn entry_type_name(self) -> String {
+        let entry_type = match self {
+            EntryKind::App(name, _) => return name,
+            EntryKind::Dna(_) => EntryType::Dna,
+            EntryKind::AgentId(_) => EntryType::AgentId,
+            EntryKind::Deletion(_) => EntryType::Deletion,
+            EntryKind::LinkAdd(_) => EntryType::LinkAdd,
+            EntryKind::LinkRemove(_) => EntryType::LinkRemove,
+            EntryKind::LinkList(_) => EntryType::LinkList,
+            EntryKind::ChainHeader(_) => EntryType::ChainHeader,
+            EntryKind::ChainMigrate(_) => EntryType::ChainMigrate,
+            EntryKind::CapToken(_) => EntryType::CapToken,
+            EntryKind::CapTokenGrant(_) => EntryType::CapTokenGrant,
+        };
+        entry_type.to_string()
+    }
+}
+
+/// The fields every serialized chain header has, without their values
+#[derive(Deserialize)]
+struct HeaderKind {
+    #[serde(rename = "entry_type")]
+    _entry_type: IgnoredAny,
+    #[serde(rename = "entry_address")]
+    _entry_address: IgnoredAny,
+    #[serde(rename = "provenances")]
+    _provenances: IgnoredAny,
+    #[serde(rename = "timestamp")]
+    _timestamp: IgnoredAny,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageStats {
+    /// Number of headers in the agent's source chain
+    pub chain_length: usize,
+    /// Number of stored entries per entry type, including entries held for the DHT
+    pub entries_per_type: BTreeMap<String, usize>,
+    /// Number of stored chain headers, including headers held for the DHT
+    pub headers: usize,
+    /// Number of stored items that are neither entries nor headers
+    pub other_content: usize,
+    /// Total size of all stored content in bytes
+    pub total_bytes: usize,
+    /// Number of EAV items per attribute (links are counted per tag)
+    pub eav_per_attribute: BTreeMap<String, usize>,
+}
+
+impl StorageStats {
+    fn add_content(
+        &mut self,
+        storage: &Arc<RwLock<ContentAddressableStorage>>,
+        seen: &mut HashSet<Address>,
+    ) -> HcResult<()> {
+        let storage = storage.read()?;
+        for address in storage.addresses()? {
+            if !seen.insert(address.clone()) {
+                continue;
+            }
+            let content = match storage.fetch(&address)? {
+                Some(content) => content,
+                None => continue,
+            };
+            let content = content.to_string();
+            self.total_bytes += content.len();
+            if let Ok(entry_kind) = serde_json::from_str::<EntryKind>(&content) {
+                *self
+                    .entries_per_type
+                    .entry(entry_kind.entry_type_name())
+                    .or_insert(0) += 1;
+            } else if serde_json::from_str::<HeaderKind>(&content).is_ok() {
+                self.headers += 1;
+            } else {
+                self.other_content += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collects statistics over the chain, DHT and EAV storages of the given context.
+/// This reads everything that is stored and is meant for occasional use by operators.
+pub fn storage_stats(context: &Context) -> HcResult<StorageStats> {
+    let mut stats = StorageStats::default();
+
+    stats.chain_length = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("Instance state not initialized".into()))?
+        .agent()
+        .iter_chain()
+        .count();
+
+    let mut seen = HashSet::new();
+    stats.add_content(&context.chain_storage, &mut seen)?;
+    stats.add_content(&context.dht_storage, &mut seen)?;
+
+    let all_eavis = context.eav_storage.read()?.fetch_eavi(&EaviQuery::new(
+        Default::default(),
+        Default::default(),
+        Default::default(),
+        IndexFilter::Range(None, None),
+    ))?;
+    for eavi in all_eavis {
+        *stats
+            .eav_per_attribute
+            .entry(eavi.attribute().to_string())
+            .or_insert(0) += 1;
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::tests::test_instance_and_context;
+    use holochain_core_types::entry::entry_type::EntryType;
+
+    #[test]
+    fn counts_genesis_entries() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+
+        let stats = storage_stats(&context).expect("Could not collect storage stats");
+        assert!(stats.chain_length >= 2);
+        assert_eq!(
+            stats.entries_per_type.get(&EntryType::Dna.to_string()),
+            Some(&1)
+        );
+        assert_eq!(
+            stats.entries_per_type.get(&EntryType::AgentId.to_string()),
+            Some(&1)
+        );
+        assert!(stats.headers >= stats.chain_length);
+        assert!(stats.total_bytes > 0);
+    }
+
+    #[test]
+    fn classifies_content_by_shape() {
+        use holochain_core_types::{
+            cas::content::AddressableContent, chain_header::test_chain_header, entry::test_entry,
+        };
+
+        let entry = test_entry();
+        let entry_kind: EntryKind =
+            serde_json::from_str(&entry.content().to_string()).expect("Entry should be recognized");
+        assert_eq!(entry_kind.entry_type_name(), entry.entry_type().to_string());
+
+        let header = test_chain_header().content().to_string();
+        assert!(serde_json::from_str::<EntryKind>(&header).is_err());
+        assert!(serde_json::from_str::<HeaderKind>(&header).is_ok());
+    }
+}
@@ -0,0 +1,360 @@
+//! Spans of the work a zome call causes, collected while an instance runs.
+//!
+//! Every zome call gets a `SpanContext` (a trace id shared by everything the call causes
+//! and a span id of its own). Nested zome calls, bridge calls, direct messages and network
+//! look-ups started by that call become child spans of the same trace, also across
+//! instances (the span context gets passed as `trace_context` param next to the `params`
+//! of bridge calls) and nodes (it is part of custom direct messages).
+//!
+//! Finished spans get recorded with their duration and outcome in the `Tracer` of the
+//! instance's `Context`, which keeps the most recent ones so that slow calls can be
+//! looked at afterwards.
+use chrono::{offset::Utc, DateTime};
+use snowflake::ProcessUniqueId;
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+    sync::Mutex,
+    time::Instant,
+};
+
+/// Number of spans a tracer keeps by default before dropping the oldest ones
+pub const DEFAULT_SPAN_CAPACITY: usize = 1000;
+
+/// Name of the param that carries the caller's `SpanContext` in zome function calls
+/// over the conductor API (i.e. bridge calls)
+pub const TRACE_CONTEXT_PARAM: &str = "trace_context";
+
+/// Identifies a span and the trace it belongs to.
+/// This is what gets passed on to the code the span's work calls into.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpanContext {
+    pub trace_id: String,
+    pub span_id: String,
+    #[serde(default)]
+    pub parent_span_id: Option<String>,
+}
+
+/// Ids have to be unique across nodes since traces span several of them, so the
+/// process unique id gets prefixed with the current time.
+fn new_id() -> String {
+    format!(
+        "{:x}-{}",
+        Utc::now().timestamp_nanos(),
+        ProcessUniqueId::new()
+    )
+}
+
+impl SpanContext {
+    /// The first span of a new trace
+    pub fn root() -> Self {
+        let id = new_id();
+        SpanContext {
+            trace_id: id.clone(),
+            span_id: id,
+            parent_span_id: None,
+        }
+    }
+
+    /// A new span of the same trace that has this span as parent
+    pub fn child(&self) -> Self {
+        SpanContext {
+            trace_id: self.trace_id.clone(),
+            span_id: new_id(),
+            parent_span_id: Some(self.span_id.clone()),
+        }
+    }
+
+    /// A child of the given span, or a new trace if there is none
+    pub fn child_of(parent: Option<&SpanContext>) -> Self {
+        parent
+            .map(SpanContext::child)
+            .unwrap_or_else(SpanContext::root)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpanKind {
+    /// Execution of a zome function, including capability checks
+    ZomeCall,
+    /// A call into a bridged instance, as seen by the caller
+    BridgeCall,
+    /// Sending a custom direct message and waiting for the response
+    SendDirectMessage,
+    /// Handling a received custom direct message in the zome's receive callback
+    ReceiveDirectMessage,
+    /// Looking up an entry on the network
+    GetEntry,
+    /// Looking up links on the network
+    GetLinks,
+    /// Requesting a validation package from an entry's author
+    GetValidationPackage,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpanOutcome {
+    Ok,
+    Error(String),
+}
+
+/// A finished span
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub kind: SpanKind,
+    /// What the span did, e.g. "<zome>/<function>" or the address that got looked up
+    pub name: String,
+    /// Start time as RFC 3339 timestamp
+    pub started_at: String,
+    pub duration_ms: f64,
+    pub outcome: SpanOutcome,
+}
+
+/// A span that is running. It gets recorded once it is finished.
+pub struct StartedSpan {
+    context: SpanContext,
+    kind: SpanKind,
+    name: String,
+    started_at: DateTime<Utc>,
+    started: Instant,
+}
+
+impl StartedSpan {
+    pub fn new<S: Into<String>>(context: &SpanContext, kind: SpanKind, name: S) -> Self {
+        StartedSpan {
+            context: context.clone(),
+            kind,
+            name: name.into(),
+            started_at: Utc::now(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Records the span in the given tracer with the outcome of the given result
+    pub fn finish<T, E: Display>(self, tracer: &Tracer, result: &Result<T, E>) {
+        let elapsed = self.started.elapsed();
+        let outcome = match result {
+            Ok(_) => SpanOutcome::Ok,
+            Err(error) => SpanOutcome::Error(error.to_string()),
+        };
+        tracer.record(Span {
+            trace_id: self.context.trace_id,
+            span_id: self.context.span_id,
+            parent_span_id: self.context.parent_span_id,
+            kind: self.kind,
+            name: self.name,
+            started_at: self.started_at.to_rfc3339(),
+            duration_ms: elapsed.as_secs() as f64 * 1e3 + f64::from(elapsed.subsec_nanos()) / 1e6,
+            outcome,
+        });
+    }
+}
+
+/// Keeps the most recent finished spans of an instance
+pub struct Tracer {
+    spans: Mutex<VecDeque<Span>>,
+    capacity: usize,
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Tracer::with_capacity(DEFAULT_SPAN_CAPACITY)
+    }
+}
+
+impl Tracer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Tracer {
+            spans: Mutex::new(VecDeque::new()),
+            capacity,
+        }
+    }
+
+    /// Starts a span of the given kind. Call `finish` on it to record it here.
+    pub fn start<S: Into<String>>(
+        &self,
+        context: &SpanContext,
+        kind: SpanKind,
+        name: S,
+    ) -> StartedSpan {
+        StartedSpan::new(context, kind, name)
+    }
+
+    pub fn record(&self, span: Span) {
+        let mut spans = self.spans.lock().unwrap();
+        if spans.len() >= self.capacity {
+            spans.pop_front();
+        }
+        spans.push_back(span);
+    }
+
+    /// All recorded spans, in the order they finished
+    pub fn spans(&self) -> Vec<Span> {
+        self.spans.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// The recorded spans of the given trace, in the order they started
+    pub fn trace(&self, trace_id: &str) -> Vec<Span> {
+        let mut spans: Vec<Span> = self
+            .spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|span| span.trace_id == trace_id)
+            .cloned()
+            .collect();
+        sort_by_start(&mut spans);
+        spans
+    }
+
+    /// The outermost recorded span of the most recent traces, newest first
+    pub fn recent_traces(&self, limit: usize) -> Vec<Span> {
+        recent_traces(&self.spans(), limit)
+    }
+}
+
+/// Sorts spans by their start time.
+/// Spans from other nodes can have timestamps with other offsets or precision, so they
+/// get compared as points in time, not as strings. Unreadable ones come first.
+pub fn sort_by_start(spans: &mut Vec<Span>) {
+    spans.sort_by_key(|span| DateTime::parse_from_rfc3339(&span.started_at).ok());
+}
+
+/// The outermost span of each trace among the given spans (the one whose parent is
+/// not among them), newest first.
+/// Spans of a trace that began on another node or in a client are therefore listed
+/// with the span that entered the given ones.
+pub fn recent_traces(spans: &[Span], limit: usize) -> Vec<Span> {
+    let span_ids: HashSet<&String> = spans.iter().map(|span| &span.span_id).collect();
+    let mut roots: Vec<Span> = spans
+        .iter()
+        .filter(|span| match &span.parent_span_id {
+            Some(parent) => !span_ids.contains(parent),
+            None => true,
+        })
+        .cloned()
+        .collect();
+    sort_by_start(&mut roots);
+    roots.into_iter().rev().take(limit).collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::error::HolochainError;
+
+    fn finish(tracer: &Tracer, context: &SpanContext, kind: SpanKind, ok: bool) {
+        let result: Result<(), HolochainError> = if ok {
+            Ok(())
+        } else {
+            Err(HolochainError::Timeout)
+        };
+        tracer.start(context, kind, "test").finish(tracer, &result);
+    }
+
+    #[test]
+    fn children_share_the_trace() {
+        let root = SpanContext::root();
+        assert_eq!(root.trace_id, root.span_id);
+        assert_eq!(root.parent_span_id, None);
+
+        let child = root.child();
+        assert_eq!(child.trace_id, root.trace_id);
+        assert_ne!(child.span_id, root.span_id);
+        assert_eq!(child.parent_span_id, Some(root.span_id.clone()));
+
+        assert_eq!(SpanContext::child_of(Some(&child)).trace_id, root.trace_id);
+        assert_ne!(SpanContext::child_of(None).trace_id, root.trace_id);
+    }
+
+    #[test]
+    fn records_spans_per_trace() {
+        let tracer = Tracer::default();
+        let call = SpanContext::root();
+        let lookup = call.child();
+        let other_call = SpanContext::root();
+        finish(&tracer, &lookup, SpanKind::GetEntry, false);
+        finish(&tracer, &call, SpanKind::ZomeCall, true);
+        finish(&tracer, &other_call, SpanKind::ZomeCall, true);
+
+        let trace = tracer.trace(&call.trace_id);
+        assert_eq!(trace.len(), 2);
+        let lookup_span = trace
+            .iter()
+            .find(|span| span.span_id == lookup.span_id)
+            .unwrap();
+        assert_eq!(lookup_span.kind, SpanKind::GetEntry);
+        assert_eq!(lookup_span.parent_span_id, Some(call.span_id.clone()));
+        assert_eq!(
+            lookup_span.outcome,
+            SpanOutcome::Error(HolochainError::Timeout.to_string())
+        );
+
+        let roots = tracer.recent_traces(10);
+        assert_eq!(
+            roots
+                .iter()
+                .map(|span| span.span_id.clone())
+                .collect::<Vec<_>>(),
+            vec![other_call.span_id.clone(), call.span_id.clone()]
+        );
+        assert_eq!(tracer.recent_traces(1).len(), 1);
+    }
+
+    #[test]
+    fn drops_oldest_spans_when_full() {
+        let tracer = Tracer::with_capacity(2);
+        let first = SpanContext::root();
+        finish(&tracer, &first, SpanKind::ZomeCall, true);
+        finish(&tracer, &SpanContext::root(), SpanKind::ZomeCall, true);
+        finish(&tracer, &SpanContext::root(), SpanKind::ZomeCall, true);
+        let spans = tracer.spans();
+        assert_eq!(spans.len(), 2);
+        assert!(spans.iter().all(|span| span.span_id != first.span_id));
+    }
+
+    #[test]
+    fn sorts_spans_by_point_in_time() {
+        let span = |started_at: &str| Span {
+            trace_id: "trace".into(),
+            span_id: started_at.into(),
+            parent_span_id: None,
+            kind: SpanKind::ZomeCall,
+            name: "test".into(),
+            started_at: started_at.into(),
+            duration_ms: 0.0,
+            outcome: SpanOutcome::Ok,
+        };
+        let mut spans = vec![
+            span("2019-05-01T10:00:00+00:00"),
+            span("2019-05-01T11:30:00+02:00"),
+            span("2019-05-01T10:00:00.500+00:00"),
+            span("not a timestamp"),
+        ];
+        sort_by_start(&mut spans);
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| span.started_at.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "not a timestamp",
+                "2019-05-01T11:30:00+02:00",
+                "2019-05-01T10:00:00+00:00",
+                "2019-05-01T10:00:00.500+00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn span_context_serializes_for_bridge_calls() {
+        let span = SpanContext::root().child();
+        let json = serde_json::to_string(&span).unwrap();
+        assert_eq!(serde_json::from_str::<SpanContext>(&json).unwrap(), span);
+        let without_parent: SpanContext =
+            serde_json::from_str(r#"{"trace_id": "t", "span_id": "s"}"#).unwrap();
+        assert_eq!(without_parent.parent_span_id, None);
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     instance::dispatch_action,
     network::direct_message::{CustomDirectMessage, DirectMessage},
     nucleus::ribosome::callback::{receive::receive, CallbackParams, CallbackResult},
+    tracing::{SpanContext, SpanKind},
 };
 
 use holochain_core_types::{cas::content::Address, error::HolochainError};
@@ -20,15 +21,26 @@ pub async fn handle_custom_direct_message(
         .payload
         .map_err(|error| format!("Got error in initial custom direct message: {}", error))?;
 
-    let result = receive(context.clone(), &zome, &CallbackParams::Receive(payload));
+    // The receive callback continues the sender's trace
+    let span = SpanContext::child_of(custom_direct_message.span.as_ref());
+    let started = context
+        .tracer
+        .start(&span, SpanKind::ReceiveDirectMessage, zome.clone());
+    let result = receive(
+        Arc::new(context.with_span(span)),
+        &zome,
+        &CallbackParams::Receive(payload),
+    );
     let response = match result {
         CallbackResult::ReceiveResult(response) => Ok(response),
         _ => Err("Error calling receive callback".to_string()),
     };
+    started.finish(&context.tracer, &response);
 
     let custom_direct_message = CustomDirectMessage {
         zome,
         payload: response,
+        span: None,
     };
     let direct_message = DirectMessage::Custom(custom_direct_message);
     let direct_message_data = DirectMessageData {
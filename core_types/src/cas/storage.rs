@@ -37,6 +37,14 @@ pub trait ContentAddressableStorage: objekt::Clone + Send + Sync + Debug {
     /// AddressableContent::from_content() can be used to allow the compiler to infer the type
     /// @see the fetch implementation for ExampleCas in the cas module tests
     fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError>;
+    /// returns the Addresses of all Content in the Store
+    /// this is meant for introspection (e.g. storage statistics) and may be slow
+    /// implementations that can't enumerate their content keep the default, which errors
+    fn addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        Err(HolochainError::ErrorGeneric(
+            "this storage can not list its content".to_string(),
+        ))
+    }
     //needed to find a way to compare two different CAS for partialord derives.
     //easiest solution was to just compare two ids which are based on uuids
     fn get_id(&self) -> Uuid;
@@ -86,6 +94,10 @@ impl ContentAddressableStorage for ExampleContentAddressableStorage {
         Ok(self.content.read()?.unthreadable_fetch(address)?)
     }
 
+    fn addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        Ok(self.content.read()?.storage.keys().cloned().collect())
+    }
+
     fn get_id(&self) -> Uuid {
         Uuid::new_v4()
     }
@@ -192,6 +204,9 @@ where
                 Ok(Some(other_content.clone())),
                 cas.fetch(&other_content.address())
             );
+            let addresses = cas.addresses().expect("could not list addresses");
+            assert!(addresses.contains(&content.address()));
+            assert!(addresses.contains(&other_content.address()));
         }
 
         // show consistent view on data across threads
@@ -1,4 +1,7 @@
-use crate::{cas::content::Address, dna::fn_declarations::Trait};
+use crate::{
+    cas::content::{Address, AddressableContent},
+    dna::{fn_declarations::Trait, Dna},
+};
 use std::collections::BTreeMap;
 
 /// A bridge is the definition of a connection to another DNA that runs under the same agency,
@@ -50,6 +53,82 @@ pub enum BridgeReference {
     Trait { traits: BTreeMap<String, Trait> },
 }
 
+impl BridgeReference {
+    /// Checks if the given DNA can be bridged to with this reference, i.e. if it has the
+    /// referenced address or if, for every referenced trait, one of its zomes exposes that
+    /// trait with all of the trait's functions declared with the same inputs and outputs.
+    /// The error describes the first mismatch found.
+    pub fn check_callee(&self, callee: &Dna) -> Result<(), String> {
+        match self {
+            BridgeReference::Address { dna_address } => {
+                let callee_address = callee.address();
+                if callee_address == *dna_address {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "DNA hash {} does not match the referenced DNA address {}",
+                        callee_address, dna_address
+                    ))
+                }
+            }
+            BridgeReference::Trait { traits } => {
+                for (trait_name, bridge_trait) in traits.iter() {
+                    check_trait(callee, trait_name, bridge_trait)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn check_trait(callee: &Dna, trait_name: &str, bridge_trait: &Trait) -> Result<(), String> {
+    let mut mismatches = Vec::new();
+    for (zome_name, zome) in callee.zomes.iter() {
+        let trait_fns = match zome.traits.get(trait_name) {
+            Some(trait_fns) => trait_fns,
+            None => continue,
+        };
+        let mismatch = bridge_trait.functions.iter().find_map(|function| {
+            if !trait_fns.functions.contains(&function.name) {
+                return Some(format!(
+                    "function \"{}\" is not part of the trait",
+                    function.name
+                ));
+            }
+            match zome.get_function(&function.name) {
+                None => Some(format!("function \"{}\" is not declared", function.name)),
+                Some(declaration)
+                    if declaration.inputs != function.inputs
+                        || declaration.outputs != function.outputs =>
+                {
+                    Some(format!(
+                        "function \"{}\" has inputs {:?} and outputs {:?} instead of {:?} and {:?}",
+                        function.name,
+                        declaration.inputs,
+                        declaration.outputs,
+                        function.inputs,
+                        function.outputs
+                    ))
+                }
+                Some(_) => None,
+            }
+        });
+        match mismatch {
+            None => return Ok(()),
+            Some(mismatch) => mismatches.push(format!("zome \"{}\": {}", zome_name, mismatch)),
+        }
+    }
+    if mismatches.is_empty() {
+        Err(format!("No zome exposes trait \"{}\"", trait_name))
+    } else {
+        Err(format!(
+            "No zome implements trait \"{}\" ({})",
+            trait_name,
+            mismatches.join("; ")
+        ))
+    }
+}
+
 /// Required or optional
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
 #[serde(rename_all = "lowercase")]
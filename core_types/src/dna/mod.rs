@@ -38,11 +38,11 @@ pub mod tests {
     use super::*;
     extern crate base64;
     use crate::{
-        cas::content::Address,
+        cas::content::{Address, AddressableContent},
         dna::{
             bridges::{Bridge, BridgePresence, BridgeReference},
             entry_types::EntryTypeDef,
-            fn_declarations::{FnDeclaration, FnParameter, Trait},
+            fn_declarations::{FnDeclaration, FnParameter, Trait, TraitFns},
             zome::tests::test_zome,
         },
         entry::entry_type::{AppEntryType, EntryType},
@@ -546,4 +546,61 @@ pub mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_bridge_reference_check_callee() {
+        let get_happs = FnDeclaration {
+            name: String::from("get_happs"),
+            inputs: vec![],
+            outputs: vec![FnParameter::new("happs", "json")],
+        };
+        let mut zome = test_zome();
+        zome.fn_declarations.push(get_happs.clone());
+        zome.traits.insert(
+            String::from("happ_directory"),
+            TraitFns {
+                functions: vec![String::from("get_happs")],
+            },
+        );
+        let mut callee = test_dna();
+        callee.zomes.insert(String::from("directory"), zome);
+
+        let by_trait = |trait_name: &str, function: FnDeclaration| BridgeReference::Trait {
+            traits: btreemap! {
+                String::from(trait_name) => Trait { functions: vec![function] }
+            },
+        };
+        assert_eq!(
+            by_trait("happ_directory", get_happs.clone()).check_callee(&callee),
+            Ok(())
+        );
+
+        let mut other_signature = get_happs.clone();
+        other_signature.inputs = vec![FnParameter::new("category", "string")];
+        assert!(by_trait("happ_directory", other_signature)
+            .check_callee(&callee)
+            .unwrap_err()
+            .starts_with(
+                "No zome implements trait \"happ_directory\" (zome \"directory\": function \"get_happs\" has inputs []"
+            ));
+
+        let mut other_function = get_happs.clone();
+        other_function.name = String::from("get_happ");
+        assert_eq!(
+            by_trait("happ_directory", other_function).check_callee(&callee),
+            Err(String::from(
+                "No zome implements trait \"happ_directory\" (zome \"directory\": function \"get_happ\" is not part of the trait)"
+            ))
+        );
+        assert_eq!(
+            by_trait("persona_management", get_happs).check_callee(&callee),
+            Err(String::from("No zome exposes trait \"persona_management\""))
+        );
+
+        let by_address = |dna_address: Address| BridgeReference::Address { dna_address };
+        assert_eq!(by_address(callee.address()).check_callee(&callee), Ok(()));
+        assert!(by_address(Address::from("Qmabcdef1234567890"))
+            .check_callee(&callee)
+            .is_err());
+    }
 }
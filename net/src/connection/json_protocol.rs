@@ -429,6 +429,56 @@ pub enum JsonProtocol {
     HandleGetHoldingMetaListResult(MetaListData),
 }
 
+impl JsonProtocol {
+    /// Name of the message type as it is sent over the wire (the `method` field),
+    /// e.g. "handleStoreEntry"
+    pub fn method(&self) -> &'static str {
+        match self {
+            JsonProtocol::SuccessResult(_) => "successResult",
+            JsonProtocol::FailureResult(_) => "failureResult",
+            JsonProtocol::TrackDna(_) => "trackDna",
+            JsonProtocol::UntrackDna(_) => "untrackDna",
+            JsonProtocol::Connect(_) => "connect",
+            JsonProtocol::PeerConnected(_) => "peerConnected",
+            JsonProtocol::GetState => "requestState",
+            JsonProtocol::GetStateResult(_) => "state",
+            JsonProtocol::GetDefaultConfig => "requestDefaultConfig",
+            JsonProtocol::GetDefaultConfigResult(_) => "defaultConfig",
+            JsonProtocol::SetConfig(_) => "setConfig",
+            JsonProtocol::SendMessage(_) => "sendMessage",
+            JsonProtocol::SendMessageResult(_) => "sendMessageResult",
+            JsonProtocol::HandleSendMessage(_) => "handleSendMessage",
+            JsonProtocol::HandleSendMessageResult(_) => "handleSendMessageResult",
+            JsonProtocol::FetchEntry(_) => "fetchEntry",
+            JsonProtocol::FetchEntryResult(_) => "fetchEntryResult",
+            JsonProtocol::HandleFetchEntry(_) => "handleFetchEntry",
+            JsonProtocol::HandleFetchEntryResult(_) => "handleFetchEntryResult",
+            JsonProtocol::PublishEntry(_) => "publishEntry",
+            JsonProtocol::HandleStoreEntry(_) => "handleStoreEntry",
+            JsonProtocol::HandleDropEntry(_) => "handleDropEntry",
+            JsonProtocol::FetchMeta(_) => "fetchMeta",
+            JsonProtocol::FetchMetaResult(_) => "fetchMetaResult",
+            JsonProtocol::HandleFetchMeta(_) => "handleFetchMeta",
+            JsonProtocol::HandleFetchMetaResult(_) => "handleFetchMetaResult",
+            JsonProtocol::PublishMeta(_) => "publishMeta",
+            JsonProtocol::HandleStoreMeta(_) => "handleStoreMeta",
+            JsonProtocol::HandleDropMeta(_) => "handleDropData",
+            JsonProtocol::HandleGetPublishingEntryList(_) => "handleGetPublishingEntryList",
+            JsonProtocol::HandleGetPublishingEntryListResult(_) => {
+                "handleGetPublishingEntryListResult"
+            }
+            JsonProtocol::HandleGetHoldingEntryList(_) => "handleGetHoldingEntryList",
+            JsonProtocol::HandleGetHoldingEntryListResult(_) => "handleGetHoldingEntryListResult",
+            JsonProtocol::HandleGetPublishingMetaList(_) => "handleGetPublishingMetaList",
+            JsonProtocol::HandleGetPublishingMetaListResult(_) => {
+                "handleGetPublishingMetaListResult"
+            }
+            JsonProtocol::HandleGetHoldingMetaList(_) => "handleGetHoldingMetaList",
+            JsonProtocol::HandleGetHoldingMetaListResult(_) => "handleGetHoldingMetaListResult",
+        }
+    }
+}
+
 /// Conversions
 impl<'a> TryFrom<&'a Protocol> for JsonProtocol {
     type Error = Error;
@@ -491,6 +541,22 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn it_names_methods_like_on_the_wire() {
+        let messages = vec![
+            JsonProtocol::GetState,
+            JsonProtocol::GetStateResult(StateData {
+                state: "test_state".to_string(),
+                id: "test_id".to_string(),
+                bindings: vec![],
+            }),
+        ];
+        for message in messages {
+            let json = serde_json::to_value(&message).unwrap();
+            assert_eq!(json["method"], message.method());
+        }
+    }
+
     #[test]
     fn it_can_convert_funky_state() {
         let w = JsonProtocol::try_from(JsonString::from(
@@ -11,12 +11,21 @@ use std::{
     thread, time,
 };
 
+struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
 /// Struct for holding a network connection running on a separate thread.
 /// It is itself a NetSend, and spawns a NetWorker.
 pub struct NetConnectionThread {
     can_keep_running: Arc<AtomicBool>,
+    is_running: Arc<AtomicBool>,
     send_channel: mpsc::Sender<Protocol>,
-    thread: thread::JoinHandle<()>,
+    thread: Option<thread::JoinHandle<()>>,
     done: NetShutdown,
     pub endpoint: String,
 }
@@ -41,12 +50,16 @@ impl NetConnectionThread {
         // Create shared bool between self and spawned thread
         let can_keep_running = Arc::new(AtomicBool::new(true));
         let can_keep_running_child = can_keep_running.clone();
+        let is_running = Arc::new(AtomicBool::new(true));
+        let is_running_child = is_running.clone();
         // Create channels between self and spawned thread
         let (send_channel, recv_channel) = mpsc::channel();
         let (send_endpoint, recv_endpoint) = mpsc::channel();
 
         // Spawn worker thread
         let thread = thread::spawn(move || {
+            // Clears is_running when the thread ends, even if the worker panics
+            let _running = RunningGuard(is_running_child);
             // Create worker
             let mut worker = worker_factory(handler).unwrap_or_else(|e| panic!("{:?}", e));
             // Get endpoint and send it to owner (NetConnectionThread)
@@ -115,28 +128,46 @@ impl NetConnectionThread {
         // Done
         Ok(NetConnectionThread {
             can_keep_running,
+            is_running,
             send_channel,
-            thread,
+            thread: Some(thread),
             done,
             endpoint,
         })
     }
 
+    /// Is the worker thread still running?
+    /// This is false after the worker got stopped or if it panicked.
+    pub fn is_running(&self) -> bool {
+        self.is_running.load(Ordering::Relaxed)
+    }
+
     /// stop the worker thread (join)
-    pub fn stop(self) -> NetResult<()> {
+    /// Stopping an already stopped connection does nothing.
+    pub fn stop(&mut self) -> NetResult<()> {
         // tell child thread to stop running
         self.can_keep_running.store(false, Ordering::Relaxed);
-        if self.thread.join().is_err() {
-            bail!("NetConnectionThread failed to join on stop() call");
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                bail!("NetConnectionThread failed to join on stop() call");
+            }
         }
         // Call shutdown closure if any
-        if let Some(done) = self.done {
+        if let Some(done) = self.done.take() {
             done();
         }
         Ok(())
     }
 }
 
+impl Drop for NetConnectionThread {
+    /// Makes sure the worker thread does not outlive its owner
+    fn drop(&mut self) {
+        self.stop()
+            .unwrap_or_else(|e| eprintln!("Error occured in p2p network module on drop: {:?}", e));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{super::net_connection::NetWorker, *};
@@ -155,9 +186,35 @@ mod tests {
         .unwrap();
 
         con.send("test".into()).unwrap();
+        assert!(con.is_running());
         con.stop().unwrap();
     }
 
+    struct PanickingWorker;
+
+    impl NetWorker for PanickingWorker {
+        fn tick(&mut self) -> NetResult<bool> {
+            panic!("worker died")
+        }
+    }
+
+    #[test]
+    fn it_reports_dead_worker() {
+        let con = NetConnectionThread::new(
+            Box::new(move |_r| Ok(())),
+            Box::new(|_h| Ok(Box::new(PanickingWorker) as Box<NetWorker>)),
+            None,
+        )
+        .unwrap();
+
+        let mut tries = 0;
+        while con.is_running() && tries < 100 {
+            thread::sleep(time::Duration::from_millis(10));
+            tries += 1;
+        }
+        assert!(!con.is_running());
+    }
+
     struct SimpleWorker {
         handler: NetHandler,
     }
@@ -210,7 +267,7 @@ mod tests {
     fn it_can_tick() {
         let (sender, receiver) = mpsc::channel();
 
-        let con = NetConnectionThread::new(
+        let mut con = NetConnectionThread::new(
             Box::new(move |r| {
                 sender.send(r?)?;
                 Ok(())
@@ -62,10 +62,15 @@ impl P2pNetwork {
     }
 
     /// Stop the network connection (disconnect any sockets, join any threads, etc)
-    pub fn stop(self) -> NetResult<()> {
+    pub fn stop(&mut self) -> NetResult<()> {
         self.connection.stop()
     }
 
+    /// Is the connection's worker thread still running?
+    pub fn is_running(&self) -> bool {
+        self.connection.is_running()
+    }
+
     /// Getter of the endpoint of its connection
     pub fn endpoint(&self) -> String {
         self.connection.endpoint.clone()
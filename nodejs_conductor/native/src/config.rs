@@ -50,6 +50,8 @@ pub fn js_make_config(mut cx: FunctionContext) -> JsResult<JsValue> {
         LoggerConfiguration {
             logger_type: "debug".into(),
             rules,
+            format: Default::default(),
+            file: None,
         }
     };
     let config = make_config(instances, logger);
@@ -71,6 +73,7 @@ fn make_config(instance_data: Vec<InstanceData>, logger: LoggerConfiguration) ->
                 public_address: keybundle.get_id(),
                 key_file: agent_name.clone(),
                 holo_remote_key: None,
+                signer: None,
             };
             config
         });
@@ -85,6 +88,8 @@ fn make_config(instance_data: Vec<InstanceData>, logger: LoggerConfiguration) ->
             agent: agent_id,
             dna: dna_id,
             storage: StorageConfiguration::Memory,
+            log_rules: None,
+            restart_policy: None,
         };
         instance_configs.push(instance);
     }
@@ -105,6 +110,8 @@ fn make_dna_config(dna: DnaData) -> Result<DnaConfiguration, String> {
         id: dna.name.clone(),
         file: path,
         hash: None,
+        uuid: None,
+        properties: None,
     })
     // eventually can get actual file content to calculate hash and stuff,
     // but for now it doesn't matter so don't care...
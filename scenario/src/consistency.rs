@@ -0,0 +1,110 @@
+//! When the instances of a scenario agree on the DHT.
+//!
+//! The instances of a scenario are consistent once
+//! * every entry that got published from any of their source chains is held by all of them,
+//! * every link (and link removal) is stored at its base by all of them and
+//! * none of them has a validation that waits for missing dependencies.
+//!
+//! With the in-memory network every node gets every entry, so this is what a test can
+//! expect after publishing, no matter which agent it asks.
+use holochain_core::{context::Context, introspection};
+use holochain_core_types::{
+    cas::content::Address, eav::Attribute, entry::Entry, error::HcResult, link::Link,
+};
+use std::sync::Arc;
+
+/// What an instance has to store in order to be consistent with an author's chain
+enum Published {
+    Entry(Address),
+    Link(Link),
+    LinkRemoval(Link),
+}
+
+/// Everything the instance with the given context published so far
+fn published(context: &Context) -> HcResult<Vec<Published>> {
+    let mut published = Vec::new();
+    for item in introspection::chain(context, 0, usize::max_value())? {
+        if !item.header.entry_type().can_publish() {
+            continue;
+        }
+        published.push(Published::Entry(item.header.entry_address().clone()));
+        match item.entry {
+            Some(Entry::LinkAdd(link_data)) => {
+                published.push(Published::Link(link_data.link().clone()))
+            }
+            Some(Entry::LinkRemove(link_data)) => {
+                published.push(Published::LinkRemoval(link_data.link().clone()))
+            }
+            _ => (),
+        }
+    }
+    Ok(published)
+}
+
+fn has_link_meta(context: &Context, link: &Link, attribute: Attribute) -> HcResult<bool> {
+    let attribute = attribute.to_string();
+    Ok(introspection::eav_for_address(context, link.base())?
+        .iter()
+        .any(|item| item.attribute == attribute && &item.value == link.target()))
+}
+
+fn is_held_by(published: &Published, context: &Context) -> HcResult<bool> {
+    match published {
+        Published::Entry(address) => context.dht_storage.read()?.contains(address),
+        Published::Link(link) => {
+            has_link_meta(context, link, Attribute::LinkTag(link.tag().clone()))
+        }
+        Published::LinkRemoval(link) => {
+            has_link_meta(context, link, Attribute::RemovedLink(link.tag().clone()))
+        }
+    }
+}
+
+fn describe(published: &Published) -> String {
+    match published {
+        Published::Entry(address) => format!("entry {}", address),
+        Published::Link(link) => format!(
+            "link from {} to {} tagged \"{}\"",
+            link.base(),
+            link.target(),
+            link.tag()
+        ),
+        Published::LinkRemoval(link) => format!(
+            "removal of link from {} to {} tagged \"{}\"",
+            link.base(),
+            link.target(),
+            link.tag()
+        ),
+    }
+}
+
+/// Returns why the given instances (by their ids) are not consistent yet,
+/// or None if they are.
+pub fn inconsistency(instances: &[(String, Arc<Context>)]) -> HcResult<Option<String>> {
+    for (id, context) in instances {
+        let pending = introspection::pending_validations(context)?;
+        if !pending.is_empty() {
+            return Ok(Some(format!(
+                "{} has {} pending validation(s), the first one of {}",
+                id,
+                pending.len(),
+                pending[0].address
+            )));
+        }
+    }
+    for (author, author_context) in instances {
+        for published in published(author_context)? {
+            for (holder, context) in instances {
+                if !is_held_by(&published, context)? {
+                    return Ok(Some(format!(
+                        "{} does not hold the {} published by {}",
+                        holder,
+                        describe(&published),
+                        author
+                    )));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,22 @@
+//! Multi-agent tests of DNAs without Node.js.
+//!
+//! A [Scenario](scenario/struct.Scenario.html) runs one DNA for several agents, spread over
+//! several conductors in the current process that share an in-memory network.
+//! Tests call zome functions as any of the agents and wait until everything that got
+//! published reached every instance before checking what the other agents see:
+//!
+//! ```rust,ignore
+//! let scenario = ScenarioBuilder::new(dna)
+//!     .with_conductors(2)
+//!     .with_agents(&["alice", "bob"])
+//!     .build()?;
+//! let address = scenario.call("alice", "blog", "create_post", r#"{"content": "hi"}"#)?;
+//! scenario.await_consistency(Duration::from_secs(5))?;
+//! let post = scenario.call("bob", "blog", "get_post", &format!(r#"{{"address": {}}}"#, address))?;
+//! ```
+#![warn(unused_extern_crates)]
+
+pub mod consistency;
+pub mod scenario;
+
+pub use crate::scenario::{Scenario, ScenarioBuilder, DEFAULT_CONSISTENCY_TIMEOUT};
@@ -0,0 +1,446 @@
+use crate::consistency::inconsistency;
+use holochain_conductor_api::{
+    conductor::{base::DnaLoader, Conductor},
+    config::{
+        AgentConfiguration, Configuration, DnaConfiguration, InstanceConfiguration,
+        LoggerConfiguration, StorageConfiguration,
+    },
+    error::{HolochainInstanceError, HolochainResult},
+    key_loaders::{test_keybundle, test_keybundle_loader},
+    logger::LogRules,
+    Holochain,
+};
+use holochain_core::{
+    context::Context, nucleus::actions::call_zome_function::make_cap_request_for_call,
+};
+use holochain_core_types::{
+    dna::Dna,
+    error::{HcResult, HolochainError},
+    json::JsonString,
+};
+use holochain_net::p2p_config::P2pConfig;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long `build()` waits for the agents to see each other
+pub const DEFAULT_CONSISTENCY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often `await_consistency()` looks at the instances' states
+const CONSISTENCY_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long dropping a scenario waits for the instances of a conductor to shut down
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The DNA every instance of a scenario runs, as it appears in the conductor configs
+const DNA_ID: &str = "scenario-dna";
+
+/// Serves the scenario's DNA for every DNA file, so that no file needs to be written
+fn dna_loader(dna: Dna) -> DnaLoader {
+    let loader = Box::new(move |_: &PathBuf| Ok(dna.clone()))
+        as Box<dyn FnMut(&PathBuf) -> Result<Dna, HolochainError> + Send + Sync>;
+    Arc::new(loader)
+}
+
+fn quiet_logger() -> LoggerConfiguration {
+    let mut rules = LogRules::new();
+    rules.add_rule(".*", true, None).expect("rule is valid");
+    LoggerConfiguration {
+        logger_type: "debug".into(),
+        rules,
+        format: Default::default(),
+        file: None,
+    }
+}
+
+/// Conductor config with one instance (named like the agent) per agent
+fn make_config(agents: &[String], logger: LoggerConfiguration) -> Configuration {
+    Configuration {
+        agents: agents
+            .iter()
+            .map(|name| AgentConfiguration {
+                id: name.clone(),
+                name: name.clone(),
+                public_address: test_keybundle(name).get_id(),
+                key_file: name.clone(),
+                holo_remote_key: None,
+                signer: None,
+            })
+            .collect(),
+        dnas: vec![DnaConfiguration {
+            id: DNA_ID.into(),
+            file: format!("{}.dna.json", DNA_ID),
+            hash: None,
+            uuid: None,
+            properties: None,
+        }],
+        instances: agents
+            .iter()
+            .map(|name| InstanceConfiguration {
+                id: name.clone(),
+                agent: name.clone(),
+                dna: DNA_ID.into(),
+                storage: StorageConfiguration::Memory,
+                log_rules: None,
+                restart_policy: None,
+            })
+            .collect(),
+        logger,
+        ..Default::default()
+    }
+}
+
+/// Sets up a [Scenario](struct.Scenario.html).
+/// The agents get spread over the conductors round-robin, so with two conductors the first,
+/// third, fifth... agent share the first conductor.
+/// Agents get the deterministic test keys of their names
+/// (see `holochain_conductor_api::key_loaders::test_keybundle`).
+pub struct ScenarioBuilder {
+    dna: Dna,
+    conductors: usize,
+    agents: Vec<String>,
+    debug_log: bool,
+    consistency_timeout: Duration,
+}
+
+impl ScenarioBuilder {
+    pub fn new(dna: Dna) -> Self {
+        ScenarioBuilder {
+            dna,
+            conductors: 1,
+            agents: Vec::new(),
+            debug_log: false,
+            consistency_timeout: DEFAULT_CONSISTENCY_TIMEOUT,
+        }
+    }
+
+    pub fn with_conductors(mut self, conductors: usize) -> Self {
+        self.conductors = conductors;
+        self
+    }
+
+    pub fn with_agents(mut self, names: &[&str]) -> Self {
+        self.agents = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Print the debug log of all instances (off by default)
+    pub fn with_debug_log(mut self, debug_log: bool) -> Self {
+        self.debug_log = debug_log;
+        self
+    }
+
+    /// How long `build()` waits for the agents' genesis entries to reach everybody
+    pub fn with_consistency_timeout(mut self, timeout: Duration) -> Self {
+        self.consistency_timeout = timeout;
+        self
+    }
+
+    /// Starts the conductors and returns once every agent holds every agent's genesis
+    /// entries.
+    pub fn build(self) -> Result<Scenario, HolochainError> {
+        if self.conductors == 0 {
+            return Err(HolochainError::ConfigError(
+                "A scenario needs at least one conductor".into(),
+            ));
+        }
+        if self.agents.is_empty() {
+            return Err(HolochainError::ConfigError(
+                "A scenario needs at least one agent".into(),
+            ));
+        }
+        let mut names = HashSet::new();
+        if let Some(name) = self.agents.iter().find(|name| !names.insert(*name)) {
+            return Err(HolochainError::ConfigError(format!(
+                "Agent \"{}\" is part of the scenario more than once",
+                name
+            )));
+        }
+
+        let network = P2pConfig::new_with_unique_memory_backend();
+        let mut conductors = Vec::new();
+        let mut conductor_of_agent = HashMap::new();
+        for index in 0..self.conductors {
+            let agents: Vec<String> = self
+                .agents
+                .iter()
+                .skip(index)
+                .step_by(self.conductors)
+                .cloned()
+                .collect();
+            let logger = if self.debug_log {
+                Default::default()
+            } else {
+                quiet_logger()
+            };
+            let mut conductor = Conductor::from_config(make_config(&agents, logger))
+                .with_p2p_config(network.clone());
+            conductor.key_loader = test_keybundle_loader();
+            conductor.dna_loader = dna_loader(self.dna.clone());
+            conductor
+                .load_config()
+                .map_err(HolochainError::ErrorGeneric)?;
+            conductor
+                .start_all_instances()
+                .map_err(|error| HolochainError::ErrorGeneric(error.to_string()))?;
+            for agent in agents {
+                conductor_of_agent.insert(agent, index);
+            }
+            conductors.push(conductor);
+        }
+
+        let scenario = Scenario {
+            conductors,
+            agents: self.agents,
+            conductor_of_agent,
+        };
+        scenario.await_consistency(self.consistency_timeout)?;
+        Ok(scenario)
+    }
+}
+
+/// Several conductors in this process that share one in-memory network and run one DNA
+/// for a set of agents. Every agent has one instance, which has the agent's name as id.
+/// The conductors get shut down when the scenario gets dropped.
+pub struct Scenario {
+    conductors: Vec<Conductor>,
+    agents: Vec<String>,
+    conductor_of_agent: HashMap<String, usize>,
+}
+
+impl Scenario {
+    /// The names of all agents, in the order they were given to the builder
+    pub fn agents(&self) -> &[String] {
+        &self.agents
+    }
+
+    /// The conductor that runs the given agent's instance
+    pub fn conductor(&self, agent: &str) -> Option<&Conductor> {
+        self.conductor_of_agent
+            .get(agent)
+            .map(|index| &self.conductors[*index])
+    }
+
+    pub fn instance(&self, agent: &str) -> HolochainResult<Arc<RwLock<Holochain>>> {
+        self.conductor(agent)
+            .and_then(|conductor| conductor.instances().get(agent))
+            .cloned()
+            .ok_or(HolochainInstanceError::NoSuchInstance)
+    }
+
+    /// Calls a zome function as the given agent, with the agent's public capability token.
+    pub fn call(
+        &self,
+        agent: &str,
+        zome: &str,
+        function: &str,
+        params: &str,
+    ) -> HolochainResult<JsonString> {
+        // Don't keep the instance locked while the call runs, so that calls of
+        // different threads can run concurrently
+        let context = self.instance(agent)?.read().unwrap().call_context()?;
+        let token = context.get_public_token().ok_or_else(|| {
+            HolochainInstanceError::from(HolochainError::ErrorGeneric(format!(
+                "Agent \"{}\" has no public capability token",
+                agent
+            )))
+        })?;
+        let cap = make_cap_request_for_call(context.clone(), token, function, params.to_string());
+        Holochain::call_in_context(&context, zome, cap, function, params)
+    }
+
+    fn contexts(&self) -> Vec<(String, Arc<Context>)> {
+        self.agents
+            .iter()
+            .filter_map(|agent| {
+                self.instance(agent)
+                    .ok()
+                    .map(|instance| (agent.clone(), instance.read().unwrap().context().clone()))
+            })
+            .collect()
+    }
+
+    /// Returns why the agents are not consistent yet, or None if they are
+    /// (see [consistency](../consistency/index.html)).
+    pub fn inconsistency(&self) -> HcResult<Option<String>> {
+        inconsistency(&self.contexts())
+    }
+
+    /// Blocks until every agent holds everything any agent published and no validations
+    /// are pending, or fails with the reason why they are not consistent after `timeout`.
+    pub fn await_consistency(&self, timeout: Duration) -> Result<(), HolochainError> {
+        let start = Instant::now();
+        loop {
+            let reason = match self.inconsistency()? {
+                None => return Ok(()),
+                Some(reason) => reason,
+            };
+            if start.elapsed() >= timeout {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Scenario did not become consistent within {:?}: {}",
+                    timeout, reason
+                )));
+            }
+            thread::sleep(CONSISTENCY_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for Scenario {
+    /// Shuts every instance down for good, including the ones that got stopped through
+    /// `instance()`, so that no action loops or network threads outlive the scenario.
+    fn drop(&mut self) {
+        for conductor in self.conductors.iter_mut() {
+            let _ = conductor.shutdown_gracefully(SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core::health::Probe;
+    use holochain_core_types::cas::content::Address;
+    use holochain_wasm_utils::wasm_target_dir;
+    use test_utils::{
+        create_test_defs_with_fn_name, create_test_dna_with_defs, create_wasm_from_file,
+    };
+
+    /// DNA with the `commit_test` function of conductor_api's test zome, which commits an
+    /// entry of type "testEntryType"
+    fn test_dna(uuid: &str) -> Dna {
+        let wasm = create_wasm_from_file(&format!(
+            "{}/wasm32-unknown-unknown/release/example_api_wasm.wasm",
+            wasm_target_dir("scenario/", "../conductor_api/wasm-test/"),
+        ));
+        let defs = create_test_defs_with_fn_name("commit_test");
+        let mut dna = create_test_dna_with_defs("test_zome", defs, &wasm);
+        dna.uuid = uuid.into();
+        dna
+    }
+
+    fn holds(scenario: &Scenario, agent: &str, address: &Address) -> bool {
+        let instance = scenario.instance(agent).unwrap();
+        let instance = instance.read().unwrap();
+        let storage = instance.context().dht_storage.clone();
+        let holds = storage.read().unwrap().contains(address).unwrap();
+        holds
+    }
+
+    #[test]
+    fn spreads_agents_over_conductors() {
+        let scenario = ScenarioBuilder::new(test_dna("spreads_agents_over_conductors"))
+            .with_conductors(2)
+            .with_agents(&["alice", "bob", "carol"])
+            .build()
+            .unwrap();
+        assert_eq!(scenario.agents(), &["alice", "bob", "carol"]);
+        let instances = |agent| {
+            let mut ids: Vec<String> = scenario
+                .conductor(agent)
+                .unwrap()
+                .instances()
+                .keys()
+                .cloned()
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(instances("alice"), vec!["alice", "carol"]);
+        assert_eq!(instances("bob"), vec!["bob"]);
+        assert!(scenario.conductor("mallory").is_none());
+        assert_eq!(scenario.inconsistency(), Ok(None));
+    }
+
+    #[test]
+    fn entries_reach_agents_on_other_conductors() {
+        let scenario = ScenarioBuilder::new(test_dna("entries_reach_agents_on_other_conductors"))
+            .with_conductors(2)
+            .with_agents(&["alice", "bob", "carol"])
+            .build()
+            .unwrap();
+
+        let result = scenario
+            .call("alice", "test_zome", "commit_test", "{}")
+            .unwrap();
+        let address = Address::from(
+            serde_json::from_str::<serde_json::Value>(&String::from(result)).unwrap()["Ok"]
+                .as_str()
+                .unwrap(),
+        );
+
+        scenario
+            .await_consistency(DEFAULT_CONSISTENCY_TIMEOUT)
+            .unwrap();
+        for agent in &["alice", "bob", "carol"] {
+            assert!(
+                holds(&scenario, agent, &address),
+                "{} holds the entry",
+                agent
+            );
+        }
+    }
+
+    #[test]
+    fn dropping_shuts_down_all_instances() {
+        let scenario = ScenarioBuilder::new(test_dna("dropping_shuts_down_all_instances"))
+            .with_agents(&["alice", "bob"])
+            .build()
+            .unwrap();
+        let alice = scenario.instance("alice").unwrap();
+        let bob = scenario.instance("bob").unwrap();
+        alice.write().unwrap().stop().unwrap();
+
+        drop(scenario);
+        for instance in vec![alice, bob] {
+            let probes = instance.read().unwrap().probe(Duration::from_secs(60));
+            assert_eq!(probes.action_loop, Probe::Dead);
+            assert_eq!(probes.network, Probe::NotReady);
+        }
+    }
+
+    #[test]
+    fn unknown_agents_cannot_be_called() {
+        let scenario = ScenarioBuilder::new(test_dna("unknown_agents_cannot_be_called"))
+            .with_agents(&["alice"])
+            .build()
+            .unwrap();
+        assert_eq!(
+            scenario.call("mallory", "test_zome", "commit_test", "{}"),
+            Err(HolochainInstanceError::NoSuchInstance)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_setups() {
+        let build = |conductors, agents: &[&str]| {
+            ScenarioBuilder::new(Dna::new())
+                .with_conductors(conductors)
+                .with_agents(agents)
+                .build()
+                .err()
+        };
+        assert_eq!(
+            build(0, &["alice"]),
+            Some(HolochainError::ConfigError(
+                "A scenario needs at least one conductor".into()
+            ))
+        );
+        assert_eq!(
+            build(1, &[]),
+            Some(HolochainError::ConfigError(
+                "A scenario needs at least one agent".into()
+            ))
+        );
+        assert_eq!(
+            build(2, &["alice", "bob", "alice"]),
+            Some(HolochainError::ConfigError(
+                "Agent \"alice\" is part of the scenario more than once".into()
+            ))
+        );
+    }
+}
@@ -750,7 +750,7 @@ impl P2pNode {
 
     // Stop node
     #[cfg_attr(tarpaulin, skip)]
-    pub fn stop(self) {
+    pub fn stop(mut self) {
         self.p2p_connection
             .stop()
             .expect("Failed to stop p2p connection properly");
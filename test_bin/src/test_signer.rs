@@ -0,0 +1,60 @@
+//! Stand-in remote signer for tests of conductors with `[agents.signer]` configs.
+//!
+//! Signs for the deterministic test key of the given name (see
+//! `holochain_conductor_api::key_loaders::test_keybundle`) on a Unix domain socket (only on
+//! Unix) or a websocket port until it gets killed:
+//!
+//!     holochain_test_signer <key name> (--socket <file> | --port <port>) [--token <token>]
+extern crate holochain_conductor_api;
+
+#[cfg(unix)]
+use holochain_conductor_api::interface_impls::DomainSocketInterface;
+use holochain_conductor_api::{
+    interface::Interface, interface_impls::WebsocketInterface, key_loaders::test_keybundle,
+    signers::test_signer::test_signer_handler,
+};
+use std::{env, path::PathBuf, process, sync::mpsc::channel};
+
+const USAGE: &str =
+    "Usage: holochain_test_signer <key name> (--socket <file> | --port <port>) [--token <token>]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2)
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let key_name = args.next().unwrap_or_else(|| usage());
+    let mut socket = None;
+    let mut port = None;
+    let mut token = None;
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--socket" => socket = Some(PathBuf::from(value)),
+            "--port" => port = Some(value.parse::<u16>().unwrap_or_else(|_| usage())),
+            "--token" => token = Some(value),
+            _ => usage(),
+        }
+    }
+
+    let keybundle = test_keybundle(&key_name);
+    println!("Signing for agent {}", keybundle.get_id());
+    let handler = test_signer_handler(keybundle, token);
+    let interface: Box<Interface> = match (socket, port) {
+        #[cfg(unix)]
+        (Some(file), None) => Box::new(DomainSocketInterface::new(file, 0o600)),
+        (None, Some(port)) => Box::new(
+            WebsocketInterface::new(port).with_bind_address(Some(String::from("127.0.0.1"))),
+        ),
+        _ => usage(),
+    };
+
+    // Never gets a kill signal, the process runs until it gets terminated
+    let (_kill_switch, kill_receiver) = channel();
+    if let Err(error) = interface.run(handler, kill_receiver) {
+        eprintln!("Test signer failed: {}", error);
+        process::exit(1);
+    }
+}